@@ -0,0 +1,6 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|src: &str| {
+    let _ = graphix_compiler::expr::parser::parse_str(src);
+});
@@ -1,9 +1,9 @@
 use crate::{
-    expr::{ModPath, Sandbox},
+    expr::{Annotation, ModPath, Sandbox},
     typ::{TVar, Type},
     BindId, Scope,
 };
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use arcstr::ArcStr;
 use compact_str::CompactString;
 use fxhash::{FxHashMap, FxHashSet};
@@ -18,10 +18,20 @@ pub struct Bind {
     pub export: bool,
     pub typ: Type,
     pub doc: Option<ArcStr>,
+    /// the `#[key = value]` annotations written on the let that created
+    /// this binding
+    pub annotations: Arc<[Annotation]>,
     pub scope: ModPath,
     pub name: CompactString,
 }
 
+impl Bind {
+    /// the annotation on this binding with the specified key, if any
+    pub fn annotation(&self, key: &str) -> Option<&Annotation> {
+        self.annotations.iter().find(|a| a.key == key)
+    }
+}
+
 impl fmt::Debug for Bind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Bind {{ id: {:?}, export: {} }}", self.id, self.export,)
@@ -35,6 +45,7 @@ impl Clone for Bind {
             scope: self.scope.clone(),
             name: self.name.clone(),
             doc: self.doc.clone(),
+            annotations: self.annotations.clone(),
             export: self.export,
             typ: self.typ.clone(),
         }
@@ -43,11 +54,95 @@ impl Clone for Bind {
 
 #[derive(Debug, Clone)]
 pub struct TypeDef {
-    pub params: Arc<[(TVar, Option<Type>)]>,
+    /// the type parameters, each with an optional constraint and an
+    /// optional default
+    pub params: Arc<[(TVar, Option<Type>, Option<Type>)]>,
     pub typ: Type,
     pub doc: Option<ArcStr>,
 }
 
+impl TypeDef {
+    /// check that a reference to the type named name supplies n type
+    /// parameters. Parameters with defaults may be omitted.
+    pub(crate) fn check_arity(&self, name: &ModPath, n: usize) -> Result<()> {
+        let max = self.params.len();
+        let min = self.params.iter().take_while(|(_, _, def)| def.is_none()).count();
+        if min == max && n != max {
+            bail!("{name} expects {max} type parameters")
+        } else if n < min || n > max {
+            bail!("{name} expects between {min} and {max} type parameters")
+        }
+        Ok(())
+    }
+}
+
+/// resolve the type references in the defaults of typedef params relative to
+/// scope, the same way the body of the typedef is
+pub(crate) fn scope_params(
+    params: &[(TVar, Option<Type>, Option<Type>)],
+    scope: &ModPath,
+) -> Arc<[(TVar, Option<Type>, Option<Type>)]> {
+    Arc::from_iter(params.iter().map(|(tv, tc, def)| {
+        (tv.clone(), tc.clone(), def.as_ref().map(|t| t.scope_refs(scope)))
+    }))
+}
+
+/// What changed between two environments, see `Env::diff`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvDelta {
+    /// bind ids added
+    pub added_ids: Vec<BindId>,
+    /// bind ids removed
+    pub removed_ids: Vec<BindId>,
+    /// names bound, by scope, along with the id they are bound to
+    pub added_names: Vec<(ModPath, CompactString, BindId)>,
+    /// names no longer bound, by scope, along with the id they were bound
+    /// to. A name that was rebound appears in both added and removed.
+    pub removed_names: Vec<(ModPath, CompactString, BindId)>,
+    /// types defined, by scope
+    pub added_typedefs: Vec<(ModPath, CompactString)>,
+    /// types no longer defined, by scope
+    pub removed_typedefs: Vec<(ModPath, CompactString)>,
+}
+
+impl EnvDelta {
+    pub fn is_empty(&self) -> bool {
+        let Self {
+            added_ids,
+            removed_ids,
+            added_names,
+            removed_names,
+            added_typedefs,
+            removed_typedefs,
+        } = self;
+        added_ids.is_empty()
+            && removed_ids.is_empty()
+            && added_names.is_empty()
+            && removed_names.is_empty()
+            && added_typedefs.is_empty()
+            && removed_typedefs.is_empty()
+    }
+}
+
+/// the entries of the scoped map a that are not in b, or that changed
+/// according to changed
+fn scoped_diff<V: Clone>(
+    a: &Map<ModPath, Map<CompactString, V>>,
+    b: &Map<ModPath, Map<CompactString, V>>,
+    changed: impl Fn(&V, &V) -> bool,
+    mut f: impl FnMut(&ModPath, &CompactString, &V),
+) {
+    let d = a.diff(b, |_, va, vb| {
+        let d = va.diff(vb, |_, va, vb| changed(va, vb).then(|| va.clone()));
+        (d.len() > 0).then_some(d)
+    });
+    for (scope, m) in d.into_iter() {
+        for (name, v) in m.into_iter() {
+            f(scope, name, v)
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Env {
     pub by_id: Map<BindId, Bind>,
@@ -98,6 +193,47 @@ impl Env {
         }
     }
 
+    /// compute what changed in this env relative to the earlier snapshot
+    /// prev. The underlying maps share structure, so this only visits the
+    /// parts of the env that actually changed. This is intended to be used
+    /// for IDEs and interactive shells, and is not used by the compiler.
+    pub fn diff(&self, prev: &Env) -> EnvDelta {
+        let mut delta = EnvDelta::default();
+        let ids =
+            |a: &Map<BindId, Bind>, b: &Map<BindId, Bind>, res: &mut Vec<BindId>| {
+                for (id, _) in a.diff(b, |_, _, _| None).into_iter() {
+                    res.push(*id)
+                }
+            };
+        ids(&self.by_id, &prev.by_id, &mut delta.added_ids);
+        ids(&prev.by_id, &self.by_id, &mut delta.removed_ids);
+        type Binds = Map<ModPath, Map<CompactString, BindId>>;
+        type TypeDefs = Map<ModPath, Map<CompactString, TypeDef>>;
+        let names =
+            |a: &Binds, b: &Binds, res: &mut Vec<(ModPath, CompactString, BindId)>| {
+                scoped_diff(
+                    a,
+                    b,
+                    |ia, ib| ia != ib,
+                    |scope, name, id| res.push((scope.clone(), name.clone(), *id)),
+                )
+            };
+        names(&self.binds, &prev.binds, &mut delta.added_names);
+        names(&prev.binds, &self.binds, &mut delta.removed_names);
+        let typedefs =
+            |a: &TypeDefs, b: &TypeDefs, res: &mut Vec<(ModPath, CompactString)>| {
+                let changed = |ta: &TypeDef, tb: &TypeDef| {
+                    !Arc::ptr_eq(&ta.params, &tb.params) || ta.typ != tb.typ
+                };
+                scoped_diff(a, b, changed, |scope, name, _| {
+                    res.push((scope.clone(), name.clone()))
+                })
+            };
+        typedefs(&self.typedefs, &prev.typedefs, &mut delta.added_typedefs);
+        typedefs(&prev.typedefs, &self.typedefs, &mut delta.removed_typedefs);
+        delta
+    }
+
     pub fn apply_sandbox(&self, spec: &Sandbox) -> Result<Self> {
         fn get_bind_name(n: &ModPath) -> Result<(&str, &str)> {
             let dir = Path::dirname(&**n).ok_or_else(|| anyhow!("unknown module {n}"))?;
@@ -233,8 +369,25 @@ impl Env {
     }
 
     pub fn lookup_typedef(&self, scope: &ModPath, name: &ModPath) -> Option<&TypeDef> {
+        let mut buf = CompactString::from("");
         self.find_visible(scope, name, |scope, name| {
-            self.typedefs.get(scope).and_then(|m| m.get(name))
+            let defs = |scope: &str| self.typedefs.get(scope).and_then(|m| m.get(name));
+            defs(scope).or_else(|| {
+                // types defined in the modules a module uses are visible
+                // through it, but a used module's own uses are not followed
+                let (scope, used) = self.used.get_full(scope)?;
+                used.iter().find_map(|m| {
+                    self.find_visible(scope, m, |scope, m| {
+                        buf.clear();
+                        buf.push_str(scope);
+                        if buf.chars().next_back() != Some(Path::SEP) {
+                            buf.push(Path::SEP);
+                        }
+                        buf.push_str(m);
+                        defs(buf.as_str())
+                    })
+                })
+            })
         })
     }
 
@@ -309,7 +462,7 @@ impl Env {
         &mut self,
         scope: &ModPath,
         name: &str,
-        params: Arc<[(TVar, Option<Type>)]>,
+        params: Arc<[(TVar, Option<Type>, Option<Type>)]>,
         typ: Type,
         doc: Option<ArcStr>,
     ) -> Result<()> {
@@ -319,28 +472,46 @@ impl Env {
         } else {
             let mut known: LPooled<FxHashMap<ArcStr, TVar>> = LPooled::take();
             let mut declared: LPooled<FxHashSet<ArcStr>> = LPooled::take();
-            for (tv, tc) in params.iter() {
+            for (tv, tc, _) in params.iter() {
                 Type::TVar(tv.clone()).alias_tvars(&mut known);
                 if let Some(tc) = tc {
                     tc.alias_tvars(&mut known);
                 }
             }
             typ.alias_tvars(&mut known);
-            for (tv, _) in params.iter() {
+            for (tv, _, _) in params.iter() {
                 if !declared.insert(tv.name.clone()) {
                     bail!("duplicate type variable {tv} in definition of {name}");
                 }
             }
-            for (_, t) in params.iter() {
+            for (_, t, _) in params.iter() {
                 if let Some(t) = t {
                     t.check_tvars_declared(&mut declared)?;
                 }
             }
+            // defaults must come last, and can't depend on the other parameters
+            let mut has_default = false;
+            for (tv, _, def) in params.iter() {
+                match def {
+                    None if has_default => {
+                        bail!(
+                            "type parameter {tv} in definition of {name} needs a default"
+                        )
+                    }
+                    None => (),
+                    Some(def) => {
+                        has_default = true;
+                        def.check_tvars_declared(&FxHashSet::default())
+                            .with_context(|| format!("in the default for {tv}"))?;
+                    }
+                }
+            }
             for dec in declared.iter() {
                 if !known.contains_key(dec) {
                     bail!("unused type parameter {dec} in definition of {name}")
                 }
             }
+            let params = scope_params(&params, scope);
             defs.insert_cow(name.into(), TypeDef { params, typ, doc });
             Ok(())
         }
@@ -372,6 +543,7 @@ impl Env {
             id: *id,
             scope: scope.clone(),
             doc: None,
+            annotations: Arc::from_iter([]),
             name: CompactString::from(name),
             typ,
         })
@@ -413,3 +585,31 @@ impl Env {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_bind() {
+        let mut env = Env::default();
+        let root = ModPath::root();
+        env.bind_variable(&root, "x", Type::Bottom);
+        env.deftype(&root, "T", Arc::from_iter([]), Type::Bottom, None).unwrap();
+        let prev = env.clone();
+        assert!(env.diff(&prev).is_empty());
+        let id = env.bind_variable(&root, "y", Type::Bottom).id;
+        let added = EnvDelta {
+            added_ids: vec![id],
+            added_names: vec![(root.clone(), "y".into(), id)],
+            ..EnvDelta::default()
+        };
+        assert_eq!(env.diff(&prev), added);
+        let removed = EnvDelta {
+            removed_ids: vec![id],
+            removed_names: vec![(root.clone(), "y".into(), id)],
+            ..EnvDelta::default()
+        };
+        assert_eq!(prev.diff(&env), removed);
+    }
+}
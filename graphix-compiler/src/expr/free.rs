@@ -0,0 +1,179 @@
+//! Walk an expression keeping track of the names in scope, and find the
+//! free variables of an expression
+use crate::expr::{Expr, ExprKind, ModPath, ModuleKind};
+use fxhash::FxHashSet;
+use netidx::{path::Path, utils::Either};
+
+/// Callbacks for [Expr::walk_scoped]
+pub trait ScopeVisitor<'a> {
+    /// e is about to be visited, before any of its sub expressions
+    fn enter(&mut self, _e: &'a Expr) {}
+
+    /// e and all of its sub expressions have been visited
+    fn exit(&mut self, _e: &'a Expr) {}
+
+    /// e references, or connects to, name. binder is the expression that
+    /// binds name in the current scope, or None if name is bound outside
+    /// of the walked expression.
+    fn reference(&mut self, e: &'a Expr, name: &'a ModPath, binder: Option<&'a Expr>);
+}
+
+struct Scoped<'a, 'b, V> {
+    /// names in scope and the expressions that bind them, innermost last
+    bound: Vec<(&'a str, &'a Expr)>,
+    visitor: &'b mut V,
+}
+
+impl<'a, 'b, V: ScopeVisitor<'a>> Scoped<'a, 'b, V> {
+    fn reference(&mut self, e: &'a Expr, name: &'a ModPath) {
+        // a reference to m::x is bound if m is a module defined in the
+        // expression
+        let binder = Path::parts(&name.0)
+            .next()
+            .and_then(|n| self.bound.iter().rev().find(|(b, _)| *b == n))
+            .map(|(_, e)| *e);
+        self.visitor.reference(e, name, binder)
+    }
+
+    /// names bound in the block are visible to the rest of the block
+    fn block(&mut self, exprs: &'a [Expr]) {
+        let n = self.bound.len();
+        for e in exprs {
+            self.expr(e)
+        }
+        self.bound.truncate(n);
+    }
+
+    fn expr(&mut self, e: &'a Expr) {
+        self.visitor.enter(e);
+        match &e.kind {
+            ExprKind::Ref { name } => self.reference(e, name),
+            ExprKind::Connect { name, value, .. } => {
+                self.reference(e, name);
+                self.expr(value)
+            }
+            ExprKind::Bind(b) => {
+                if !b.rec {
+                    self.expr(&b.value)
+                }
+                b.pattern.with_names(&mut |n| self.bound.push((n, e)));
+                if b.rec {
+                    self.expr(&b.value)
+                }
+            }
+            ExprKind::Module { name, value } => {
+                match value {
+                    ModuleKind::Resolved { exprs, .. } => self.block(exprs),
+                    ModuleKind::Dynamic { source, .. } => self.expr(source),
+                    ModuleKind::Unresolved { .. } => (),
+                }
+                self.bound.push((name, e))
+            }
+            ExprKind::Do { exprs } => self.block(exprs),
+            ExprKind::Lambda(l) => {
+                // default values can't refer to the arguments
+                for a in l.args.iter() {
+                    if let Some(Some(d)) = &a.labeled {
+                        self.expr(d)
+                    }
+                }
+                if let Either::Left(body) = &l.body {
+                    let n = self.bound.len();
+                    for a in l.args.iter() {
+                        a.pattern.with_names(&mut |n| self.bound.push((n, e)))
+                    }
+                    self.expr(body);
+                    self.bound.truncate(n);
+                }
+            }
+            ExprKind::Select(s) => {
+                self.expr(&s.arg);
+                for (pat, arm) in s.arms.iter() {
+                    let n = self.bound.len();
+                    pat.structure_predicate.with_names(&mut |n| self.bound.push((n, e)));
+                    if let Some(g) = &pat.guard {
+                        self.expr(g)
+                    }
+                    self.expr(arm);
+                    self.bound.truncate(n);
+                }
+            }
+            ExprKind::TryCatch(tc) => {
+                self.block(&tc.exprs);
+                let n = self.bound.len();
+                self.bound.push((&tc.bind, e));
+                self.expr(&tc.handler);
+                self.bound.truncate(n);
+            }
+            _ => e.with_children(&mut |c| self.expr(c)),
+        }
+        self.visitor.exit(e);
+    }
+}
+
+#[derive(Default)]
+struct Free<'a> {
+    seen: FxHashSet<&'a ModPath>,
+    free: Vec<ModPath>,
+}
+
+impl<'a> ScopeVisitor<'a> for Free<'a> {
+    fn reference(&mut self, _e: &'a Expr, name: &'a ModPath, binder: Option<&'a Expr>) {
+        if binder.is_none() && self.seen.insert(name) {
+            self.free.push(name.clone())
+        }
+    }
+}
+
+impl Expr {
+    /// Walk self and all of its sub expressions, calling visitor for each
+    /// one. Names bound by lets, lambda arguments, select arms, catch,
+    /// and modules defined in the expression are only bound in their
+    /// scope, so a reference to a shadowed name outside the scope of the
+    /// shadowing binding resolves to the outer binding. `use` is not
+    /// taken into account.
+    pub fn walk_scoped<'a, V: ScopeVisitor<'a>>(&'a self, visitor: &mut V) {
+        Scoped { bound: vec![], visitor }.expr(self)
+    }
+
+    /// Return the names that self references, or connects to, but doesn't
+    /// bind, in the order they first appear. See
+    /// [walk_scoped](Expr::walk_scoped) for how scopes are treated.
+    pub fn free_variables(&self) -> Vec<ModPath> {
+        let mut free = Free::default();
+        self.walk_scoped(&mut free);
+        free.free
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::expr::parser::parse_one;
+
+    fn free(s: &str) -> Vec<ModPath> {
+        parse_one(s).unwrap().free_variables()
+    }
+
+    #[test]
+    fn free_outer() {
+        let res = free("{ let f = |x| x + y; let z = f(sys::time::now()); z + y }");
+        assert_eq!(
+            res,
+            vec![ModPath::from(["y"]), ModPath::from(["sys", "time", "now"])]
+        );
+        // the last x is outside the scope of the lambda argument and the let
+        let res = free("{ let g = |x| { let x = x + 1; x }; g(x) }");
+        assert_eq!(res, vec![ModPath::from(["x"])]);
+        let res = free("select a { `A(b) => b, c => d }");
+        assert_eq!(res, vec![ModPath::from(["a"]), ModPath::from(["d"])]);
+    }
+
+    #[test]
+    fn free_none() {
+        let res = free("{ let y = 1; let f = |x| x + y; let z = 0; z <- f(y); z }");
+        assert!(res.is_empty());
+        let res = free("{ let rec f = |x| select x { 0 => 0, x => f(x - 1) }; f(3) }");
+        assert!(res.is_empty());
+    }
+}
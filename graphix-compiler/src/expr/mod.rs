@@ -6,11 +6,13 @@ use crate::{
 use anyhow::Result;
 use arcstr::{literal, ArcStr};
 use combine::stream::position::SourcePosition;
+pub use free::ScopeVisitor;
+use fxhash::FxHashMap;
 pub use modpath::ModPath;
 use netidx::{path::Path, subscriber::Value, utils::Either};
 pub use pattern::{Pattern, StructurePattern};
 use regex::Regex;
-pub use resolver::ModuleResolver;
+pub use resolver::{ModuleCache, ModuleResolver};
 use serde::{
     de::{self, Visitor},
     Deserialize, Deserializer, Serialize, Serializer,
@@ -27,11 +29,13 @@ use std::{
 };
 use triomphe::Arc;
 
+mod free;
 mod modpath;
 pub mod parser;
 mod pattern;
 pub mod print;
 mod resolver;
+mod ser;
 #[cfg(test)]
 mod test;
 
@@ -66,30 +70,33 @@ impl fmt::Display for CouldNotResolve {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Arg {
     pub labeled: Option<Option<Expr>>,
     pub pattern: StructurePattern,
     pub constraint: Option<Type>,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Doc(pub Option<ArcStr>);
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct TypeDefExpr {
     pub name: ArcStr,
-    pub params: Arc<[(TVar, Option<Type>)]>,
+    /// the type parameters, each with an optional constraint and an
+    /// optional default
+    #[serde(with = "ser::arc_slice")]
+    pub params: Arc<[(TVar, Option<Type>, Option<Type>)]>,
     pub typ: Type,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct BindSig {
     pub name: ArcStr,
     pub typ: Type,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum SigKind {
     TypeDef(TypeDefExpr),
     Bind(BindSig),
@@ -97,14 +104,15 @@ pub enum SigKind {
     Use(ModPath),
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct SigItem {
     pub doc: Doc,
     pub kind: SigKind,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Sig {
+    #[serde(with = "ser::arc_slice")]
     pub items: Arc<[SigItem]>,
     pub toplevel: bool,
 }
@@ -117,96 +125,178 @@ impl Deref for Sig {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum Sandbox {
     Unrestricted,
-    Blacklist(Arc<[ModPath]>),
-    Whitelist(Arc<[ModPath]>),
+    Blacklist(#[serde(with = "ser::arc_slice")] Arc<[ModPath]>),
+    Whitelist(#[serde(with = "ser::arc_slice")] Arc<[ModPath]>),
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum ModuleKind {
-    Dynamic { sandbox: Sandbox, sig: Sig, source: Arc<Expr> },
-    Resolved { exprs: Arc<[Expr]>, sig: Option<Sig>, from_interface: bool },
-    Unresolved { from_interface: bool },
+    Dynamic {
+        sandbox: Sandbox,
+        sig: Sig,
+        source: Arc<Expr>,
+    },
+    Resolved {
+        #[serde(with = "ser::arc_slice")]
+        exprs: Arc<[Expr]>,
+        sig: Option<Sig>,
+        from_interface: bool,
+    },
+    Unresolved {
+        from_interface: bool,
+    },
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+/// Tooling metadata attached to a binding, written `#[key]` or
+/// `#[key = value]` on the lines before the `let`. Annotations have no
+/// effect at runtime.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Annotation {
+    pub key: ArcStr,
+    pub value: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct BindExpr {
+    #[serde(with = "ser::arc_slice")]
+    pub annotations: Arc<[Annotation]>,
     pub rec: bool,
     pub pattern: StructurePattern,
     pub typ: Option<Type>,
     pub value: Expr,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct LambdaExpr {
+    #[serde(with = "ser::arc_slice")]
     pub args: Arc<[Arg]>,
     pub vargs: Option<Option<Type>>,
     pub rtype: Option<Type>,
+    #[serde(with = "ser::arc_slice")]
     pub constraints: Arc<[(TVar, Type)]>,
     pub throws: Option<Type>,
+    #[serde(with = "ser::either")]
     pub body: Either<Expr, ArcStr>,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct TryCatchExpr {
     pub bind: ArcStr,
     pub constraint: Option<Type>,
     pub handler: Arc<Expr>,
+    #[serde(with = "ser::arc_slice")]
     pub exprs: Arc<[Expr]>,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct StructWithExpr {
     pub source: Arc<Expr>,
+    #[serde(with = "ser::arc_slice")]
     pub replace: Arc<[(ArcStr, Expr)]>,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct StructExpr {
+    #[serde(with = "ser::arc_slice")]
     pub args: Arc<[(ArcStr, Expr)]>,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct ApplyExpr {
+    #[serde(with = "ser::arc_slice")]
     pub args: Arc<[(Option<ArcStr>, Expr)]>,
     pub function: Arc<Expr>,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct SelectExpr {
     pub arg: Arc<Expr>,
+    #[serde(with = "ser::arc_slice")]
     pub arms: Arc<[(Pattern, Expr)]>,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum ExprKind {
     NoOp,
     Constant(Value),
-    Module { name: ArcStr, value: ModuleKind },
+    Module {
+        name: ArcStr,
+        value: ModuleKind,
+    },
     ExplicitParens(Arc<Expr>),
-    Do { exprs: Arc<[Expr]> },
-    Use { name: ModPath },
+    Do {
+        #[serde(with = "ser::arc_slice")]
+        exprs: Arc<[Expr]>,
+    },
+    Use {
+        name: ModPath,
+    },
     Bind(Arc<BindExpr>),
-    Ref { name: ModPath },
-    Connect { name: ModPath, value: Arc<Expr>, deref: bool },
-    StringInterpolate { args: Arc<[Expr]> },
-    StructRef { source: Arc<Expr>, field: ArcStr },
-    TupleRef { source: Arc<Expr>, field: usize },
-    ArrayRef { source: Arc<Expr>, i: Arc<Expr> },
-    ArraySlice { source: Arc<Expr>, start: Option<Arc<Expr>>, end: Option<Arc<Expr>> },
-    MapRef { source: Arc<Expr>, key: Arc<Expr> },
+    Ref {
+        name: ModPath,
+    },
+    Connect {
+        name: ModPath,
+        value: Arc<Expr>,
+        deref: bool,
+    },
+    StringInterpolate {
+        #[serde(with = "ser::arc_slice")]
+        args: Arc<[Expr]>,
+    },
+    StructRef {
+        source: Arc<Expr>,
+        field: ArcStr,
+    },
+    TupleRef {
+        source: Arc<Expr>,
+        field: usize,
+    },
+    ArrayRef {
+        source: Arc<Expr>,
+        i: Arc<Expr>,
+    },
+    ArraySlice {
+        source: Arc<Expr>,
+        start: Option<Arc<Expr>>,
+        end: Option<Arc<Expr>>,
+    },
+    MapRef {
+        source: Arc<Expr>,
+        key: Arc<Expr>,
+    },
     StructWith(StructWithExpr),
     Lambda(Arc<LambdaExpr>),
     TypeDef(TypeDefExpr),
-    TypeCast { expr: Arc<Expr>, typ: Type },
+    TypeCast {
+        expr: Arc<Expr>,
+        typ: Type,
+    },
     Apply(ApplyExpr),
-    Any { args: Arc<[Expr]> },
-    Array { args: Arc<[Expr]> },
-    Map { args: Arc<[(Expr, Expr)]> },
-    Tuple { args: Arc<[Expr]> },
-    Variant { tag: ArcStr, args: Arc<[Expr]> },
+    Any {
+        #[serde(with = "ser::arc_slice")]
+        args: Arc<[Expr]>,
+    },
+    Array {
+        #[serde(with = "ser::arc_slice")]
+        args: Arc<[Expr]>,
+    },
+    Map {
+        #[serde(with = "ser::arc_slice")]
+        args: Arc<[(Expr, Expr)]>,
+    },
+    Tuple {
+        #[serde(with = "ser::arc_slice")]
+        args: Arc<[Expr]>,
+    },
+    Variant {
+        tag: ArcStr,
+        #[serde(with = "ser::arc_slice")]
+        args: Arc<[Expr]>,
+    },
     Struct(StructExpr),
     Select(SelectExpr),
     Qop(Arc<Expr>),
@@ -214,40 +304,158 @@ pub enum ExprKind {
     TryCatch(Arc<TryCatchExpr>),
     ByRef(Arc<Expr>),
     Deref(Arc<Expr>),
-    Eq { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Ne { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Lt { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Gt { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Lte { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Gte { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    And { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Or { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Not { expr: Arc<Expr> },
-    Add { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    CheckedAdd { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Sub { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    CheckedSub { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Mul { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    CheckedMul { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Div { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    CheckedDiv { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Mod { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    CheckedMod { lhs: Arc<Expr>, rhs: Arc<Expr> },
-    Sample { lhs: Arc<Expr>, rhs: Arc<Expr> },
+    Eq {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Ne {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Lt {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Gt {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Lte {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Gte {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    And {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Or {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Not {
+        expr: Arc<Expr>,
+    },
+    Add {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    CheckedAdd {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Sub {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    CheckedSub {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Mul {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    CheckedMul {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Div {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    CheckedDiv {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Mod {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    CheckedMod {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
+    Sample {
+        lhs: Arc<Expr>,
+        rhs: Arc<Expr>,
+    },
 }
 
 impl ExprKind {
     pub fn to_expr(self, pos: SourcePosition) -> Expr {
-        Expr { id: ExprId::new(), ori: get_origin(), pos, kind: self }
+        Expr { id: ExprId::new(), ori: get_origin(), pos, end: pos, kind: self }
     }
 
     /// does not provide any position information or comment
     pub fn to_expr_nopos(self) -> Expr {
-        Expr { id: ExprId::new(), ori: get_origin(), pos: Default::default(), kind: self }
+        let pos = Default::default();
+        Expr { id: ExprId::new(), ori: get_origin(), pos, end: pos, kind: self }
+    }
+
+    /// the name of the kind of expression, e.g. "Apply" or "Bind"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NoOp => "NoOp",
+            Self::Constant(_) => "Constant",
+            Self::Module { .. } => "Module",
+            Self::ExplicitParens(_) => "ExplicitParens",
+            Self::Do { .. } => "Do",
+            Self::Use { .. } => "Use",
+            Self::Bind(_) => "Bind",
+            Self::Ref { .. } => "Ref",
+            Self::Connect { .. } => "Connect",
+            Self::StringInterpolate { .. } => "StringInterpolate",
+            Self::StructRef { .. } => "StructRef",
+            Self::TupleRef { .. } => "TupleRef",
+            Self::ArrayRef { .. } => "ArrayRef",
+            Self::ArraySlice { .. } => "ArraySlice",
+            Self::MapRef { .. } => "MapRef",
+            Self::StructWith(_) => "StructWith",
+            Self::Lambda(_) => "Lambda",
+            Self::TypeDef(_) => "TypeDef",
+            Self::TypeCast { .. } => "TypeCast",
+            Self::Apply(_) => "Apply",
+            Self::Any { .. } => "Any",
+            Self::Array { .. } => "Array",
+            Self::Map { .. } => "Map",
+            Self::Tuple { .. } => "Tuple",
+            Self::Variant { .. } => "Variant",
+            Self::Struct(_) => "Struct",
+            Self::Select(_) => "Select",
+            Self::Qop(_) => "Qop",
+            Self::OrNever(_) => "OrNever",
+            Self::TryCatch(_) => "TryCatch",
+            Self::ByRef(_) => "ByRef",
+            Self::Deref(_) => "Deref",
+            Self::Eq { .. } => "Eq",
+            Self::Ne { .. } => "Ne",
+            Self::Lt { .. } => "Lt",
+            Self::Gt { .. } => "Gt",
+            Self::Lte { .. } => "Lte",
+            Self::Gte { .. } => "Gte",
+            Self::And { .. } => "And",
+            Self::Or { .. } => "Or",
+            Self::Not { .. } => "Not",
+            Self::Add { .. } => "Add",
+            Self::CheckedAdd { .. } => "CheckedAdd",
+            Self::Sub { .. } => "Sub",
+            Self::CheckedSub { .. } => "CheckedSub",
+            Self::Mul { .. } => "Mul",
+            Self::CheckedMul { .. } => "CheckedMul",
+            Self::Div { .. } => "Div",
+            Self::CheckedDiv { .. } => "CheckedDiv",
+            Self::Mod { .. } => "Mod",
+            Self::CheckedMod { .. } => "CheckedMod",
+            Self::Sample { .. } => "Sample",
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
 pub enum Source {
     File(PathBuf),
     Netidx(Path),
@@ -295,6 +503,9 @@ impl Source {
     }
 }
 
+/// The start and end position of an expression in its source
+pub type Span = (SourcePosition, SourcePosition);
+
 // hallowed are the ori
 #[derive(Debug, Clone, PartialEq, PartialOrd, Default)]
 pub struct Origin {
@@ -365,6 +576,9 @@ pub struct Expr {
     pub id: ExprId,
     pub ori: Arc<Origin>,
     pub pos: SourcePosition,
+    /// the position just after the end of the expression in the source,
+    /// or pos if it isn't known
+    pub end: SourcePosition,
     pub kind: ExprKind,
 }
 
@@ -400,12 +614,35 @@ impl PartialEq for Expr {
 
 impl Eq for Expr {}
 
+#[derive(Serialize, Deserialize)]
+struct ExprSer<K> {
+    line: i32,
+    column: i32,
+    end_line: i32,
+    end_column: i32,
+    kind: K,
+}
+
+/// Expressions serialize as their source text, and deserialize by parsing
+/// it. Wrap an expression in an [Ast] to serialize it structurally
+/// instead.
 impl Serialize for Expr {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        if ser::structural() {
+            ExprSer {
+                line: self.pos.line,
+                column: self.pos.column,
+                end_line: self.end.line,
+                end_column: self.end.column,
+                kind: &self.kind,
+            }
+            .serialize(serializer)
+        } else {
+            serializer.serialize_str(&self.to_string())
+        }
     }
 }
 
@@ -460,13 +697,230 @@ impl<'de> Deserialize<'de> for Expr {
     where
         D: Deserializer<'de>,
     {
-        de.deserialize_str(ExprVisitor)
+        if ser::structural() {
+            let ExprSer { line, column, end_line, end_column, kind } =
+                ExprSer::<ExprKind>::deserialize(de)?;
+            let mut e = kind.to_expr(SourcePosition { line, column });
+            e.end = SourcePosition { line: end_line, column: end_column };
+            Ok(e)
+        } else {
+            de.deserialize_str(ExprVisitor)
+        }
+    }
+}
+
+/// The version of the structural format written by [Ast]. It changes
+/// whenever the structure of [ExprKind], or anything it contains, changes
+/// in a way that breaks the format.
+pub const AST_VERSION: u32 = 2;
+
+/// An expression that serializes structurally, so it can be loaded
+/// without parsing, e.g. to cache parsed programs on disk.
+///
+/// The format is the version, [AST_VERSION], followed by the expression.
+/// Unlike the source text written by [Expr]'s own serde impls, it is tied
+/// to the version of the compiler, and loading a different version is an
+/// error. Source positions round trip, but ids are regenerated when an
+/// expression is loaded, and the origin is the current origin, just as it
+/// would be if the expression were parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ast(pub Expr);
+
+impl Serialize for Ast {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        ser::with_structural(|| (AST_VERSION, &self.0).serialize(serializer))
+    }
+}
+
+struct AstVisitor;
+
+impl<'de> Visitor<'de> for AstVisitor {
+    type Value = Ast;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "expected a version and an expression")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        match seq.next_element::<u32>()? {
+            None => Err(de::Error::invalid_length(0, &self)),
+            Some(v) if v != AST_VERSION => Err(de::Error::custom(format_args!(
+                "expression format version {v}, expected {AST_VERSION}"
+            ))),
+            Some(_) => match seq.next_element::<Expr>()? {
+                None => Err(de::Error::invalid_length(1, &self)),
+                Some(e) => Ok(Ast(e)),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Ast {
+    fn deserialize<D>(de: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ser::with_structural(|| de.deserialize_tuple(2, AstVisitor))
     }
 }
 
 impl Expr {
     pub fn new(kind: ExprKind, pos: SourcePosition) -> Self {
-        Expr { id: ExprId::new(), ori: get_origin(), pos, kind }
+        Expr { id: ExprId::new(), ori: get_origin(), pos, end: pos, kind }
+    }
+
+    /// The source span, start and end position, of self and every sub
+    /// expression of self, by id. The end is the position just after the
+    /// last non whitespace character of the expression. Positions are
+    /// relative to the origin of each expression, sub expressions from a
+    /// different origin, e.g. a resolved module, don't extend the span of
+    /// their parent.
+    pub fn spans(&self) -> FxHashMap<ExprId, Span> {
+        type Ends = FxHashMap<*const Origin, Vec<SourcePosition>>;
+        // the position after every non whitespace character in text
+        fn token_ends(text: &str) -> Vec<SourcePosition> {
+            let mut pos = SourcePosition { line: 1, column: 1 };
+            let mut res = vec![];
+            for c in text.chars() {
+                if c == '\n' {
+                    pos.line += 1;
+                    pos.column = 1;
+                } else {
+                    pos.column += 1;
+                }
+                if !c.is_whitespace() {
+                    res.push(pos)
+                }
+            }
+            res
+        }
+        fn go(
+            e: &Expr,
+            ends: &mut Ends,
+            spans: &mut FxHashMap<ExprId, Span>,
+        ) -> SourcePosition {
+            let mut end = e.end.max(e.pos);
+            e.with_children(&mut |c| {
+                let cend = go(c, ends, spans);
+                if Arc::ptr_eq(&c.ori, &e.ori) {
+                    end = end.max(cend)
+                }
+            });
+            // the parser may have consumed whitespace after the expression
+            let tends = ends
+                .entry(Arc::as_ptr(&e.ori))
+                .or_insert_with(|| token_ends(&e.ori.text));
+            let end = match tends.binary_search(&end) {
+                Ok(_) => end,
+                Err(0) => e.pos,
+                Err(i) => tends[i - 1].max(e.pos),
+            };
+            spans.insert(e.id, (e.pos, end));
+            end
+        }
+        let mut spans = FxHashMap::default();
+        go(self, &mut Ends::default(), &mut spans);
+        spans
+    }
+
+    /// call f with each of the direct sub expressions of self
+    pub fn with_children<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        match &self.kind {
+            ExprKind::Constant(_)
+            | ExprKind::NoOp
+            | ExprKind::Use { .. }
+            | ExprKind::Ref { .. }
+            | ExprKind::TypeDef { .. }
+            | ExprKind::Module { value: ModuleKind::Unresolved { .. }, .. } => (),
+            ExprKind::ExplicitParens(e)
+            | ExprKind::StructRef { source: e, .. }
+            | ExprKind::TupleRef { source: e, .. }
+            | ExprKind::TypeCast { expr: e, .. }
+            | ExprKind::Connect { value: e, .. }
+            | ExprKind::Module { value: ModuleKind::Dynamic { source: e, .. }, .. }
+            | ExprKind::Qop(e)
+            | ExprKind::OrNever(e)
+            | ExprKind::ByRef(e)
+            | ExprKind::Deref(e)
+            | ExprKind::Not { expr: e } => f(e),
+            ExprKind::Bind(b) => f(&b.value),
+            ExprKind::Map { args } => {
+                for (k, v) in args.iter() {
+                    f(k);
+                    f(v)
+                }
+            }
+            ExprKind::Module { value: ModuleKind::Resolved { exprs, .. }, .. }
+            | ExprKind::Do { exprs }
+            | ExprKind::Any { args: exprs }
+            | ExprKind::Array { args: exprs }
+            | ExprKind::Tuple { args: exprs }
+            | ExprKind::Variant { args: exprs, .. }
+            | ExprKind::StringInterpolate { args: exprs } => exprs.iter().for_each(f),
+            ExprKind::StructWith(StructWithExpr { source, replace }) => {
+                f(source);
+                replace.iter().for_each(|(_, e)| f(e))
+            }
+            ExprKind::Lambda(l) => match &l.body {
+                Either::Left(e) => f(e),
+                Either::Right(_) => (),
+            },
+            ExprKind::Apply(ApplyExpr { args, function }) => {
+                f(function);
+                args.iter().for_each(|(_, e)| f(e))
+            }
+            ExprKind::Struct(StructExpr { args }) => args.iter().for_each(|(_, e)| f(e)),
+            ExprKind::Select(SelectExpr { arg, arms }) => {
+                f(arg);
+                for (p, e) in arms.iter() {
+                    if let Some(g) = p.guard.as_ref() {
+                        f(g)
+                    }
+                    f(e)
+                }
+            }
+            ExprKind::TryCatch(tc) => {
+                tc.exprs.iter().for_each(&mut *f);
+                f(&tc.handler)
+            }
+            ExprKind::ArraySlice { source, start, end } => {
+                f(source);
+                start.iter().chain(end.iter()).for_each(|e| f(e))
+            }
+            ExprKind::ArrayRef { source, i: rhs }
+            | ExprKind::MapRef { source, key: rhs } => {
+                f(source);
+                f(rhs)
+            }
+            ExprKind::Add { lhs, rhs }
+            | ExprKind::CheckedAdd { lhs, rhs }
+            | ExprKind::Sub { lhs, rhs }
+            | ExprKind::CheckedSub { lhs, rhs }
+            | ExprKind::Mul { lhs, rhs }
+            | ExprKind::CheckedMul { lhs, rhs }
+            | ExprKind::Div { lhs, rhs }
+            | ExprKind::CheckedDiv { lhs, rhs }
+            | ExprKind::Mod { lhs, rhs }
+            | ExprKind::CheckedMod { lhs, rhs }
+            | ExprKind::And { lhs, rhs }
+            | ExprKind::Or { lhs, rhs }
+            | ExprKind::Eq { lhs, rhs }
+            | ExprKind::Ne { lhs, rhs }
+            | ExprKind::Gt { lhs, rhs }
+            | ExprKind::Lt { lhs, rhs }
+            | ExprKind::Gte { lhs, rhs }
+            | ExprKind::Lte { lhs, rhs }
+            | ExprKind::Sample { lhs, rhs } => {
+                f(lhs);
+                f(rhs)
+            }
+        }
     }
 
     /// fold over self and all of self's sub expressions
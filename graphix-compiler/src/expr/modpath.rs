@@ -2,7 +2,7 @@ use super::parser;
 use netidx::path::Path;
 use std::{borrow::Borrow, fmt, ops::Deref, result, str::FromStr};
 
-#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ModPath(pub Path);
 
 impl FromStr for ModPath {
@@ -1,8 +1,8 @@
 use crate::expr::{
     parser::{
         any, apply, array, arrayref, cast, do_block, interpolated, literal, map, mapref,
-        qop, raw_string, reference, select, spaces, sptoken, structref, structure,
-        structwith, tuple, tupleref, variant,
+        nested, qop, raw_string, reference, select, spaces, spanned, sptoken, structref,
+        structure, structwith, tuple, tupleref, variant,
     },
     Expr, ExprKind,
 };
@@ -40,8 +40,8 @@ parser! {
     pub(crate) fn arith_term[I]()(I) -> Expr
     where [I: RangeStream<Token = char, Position = SourcePosition>, I::Range: Range]
     {
-        spaces()
-            .with(choice((
+        nested(spaces()
+            .with(spanned(choice((
                 (position(), token('!').with(arith_term()))
                     .map(|(pos, expr)| ExprKind::Not { expr: Arc::new(expr) }.to_expr(pos)),
                 raw_string(),
@@ -70,16 +70,19 @@ parser! {
                 })),
                 attempt(literal()),
                 qop(reference()),
-            )))
-            .skip(spaces())
+            ))))
+            .skip(spaces()))
     }
 }
 
 fn mke(lhs: Expr, op: &'static str, rhs: Expr) -> Expr {
     macro_rules! mk {
         ($ctor:ident) => {{
-            let pos = lhs.pos;
-            ExprKind::$ctor { lhs: Arc::new(lhs), rhs: Arc::new(rhs) }.to_expr(pos)
+            let (pos, end) = (lhs.pos, rhs.end);
+            let mut e =
+                ExprKind::$ctor { lhs: Arc::new(lhs), rhs: Arc::new(rhs) }.to_expr(pos);
+            e.end = end;
+            e
         }};
     }
     match op {
@@ -155,7 +158,7 @@ parser! {
     pub(crate) fn arith[I]()(I) -> Expr
     where [I: RangeStream<Token = char, Position = SourcePosition>, I::Range: Range]
     {
-        (
+        nested((
             arith_term(),
             many((
                 attempt(spaces().with(choice((
@@ -187,6 +190,6 @@ parser! {
             } else {
                 shunting_yard(e, exprs)
             }
-        })
+        }))
     }
 }
@@ -1,4 +1,4 @@
-use super::{expr, sptoken, GRAPHIX_ESC, GRAPHIX_MUST_ESC};
+use super::{expr, nested, sptoken, GRAPHIX_ESC, GRAPHIX_MUST_ESC};
 use crate::expr::{get_origin, Expr, ExprId, ExprKind};
 use combine::{
     attempt, between, choice, many, position,
@@ -26,13 +26,14 @@ parser! {
                         id: ExprId::new(),
                         ori: get_origin(),
                         pos,
+                        end: pos,
                         kind: ExprKind::Constant(Value::from(s)),
                     },
                     Intp::Expr(s) => s,
                 }
             }
         }
-        (
+        nested((
             position(),
             between(
                 token('"'),
@@ -93,6 +94,6 @@ parser! {
                         }
                     })
                     .unwrap_or_else(|| ExprKind::Constant(Value::from("")).to_expr(pos))
-            })
+            }))
     }
 }
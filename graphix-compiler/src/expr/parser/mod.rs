@@ -1,7 +1,7 @@
 use crate::{
     expr::{
-        set_origin, BindExpr, Doc, Expr, ExprKind, ModPath, Origin, Pattern, SelectExpr,
-        Sig, SigItem, StructExpr, StructWithExpr, TryCatchExpr,
+        set_origin, Annotation, BindExpr, Doc, Expr, ExprKind, ModPath, Origin, Pattern,
+        SelectExpr, Sig, SigItem, Source, StructExpr, StructWithExpr, TryCatchExpr,
     },
     typ::{FnType, Type},
 };
@@ -29,7 +29,7 @@ use netidx_value::parser::{
     VAL_ESC, VAL_MUST_ESC,
 };
 use poolshark::local::LPooled;
-use std::sync::LazyLock;
+use std::{cell::Cell, sync::LazyLock};
 use triomphe::Arc;
 
 mod interpolateexp;
@@ -115,12 +115,32 @@ where
     I::Range: Range,
 {
     combine::parser::char::spaces().with(skip_many(
-        attempt(string("//").with(not_followed_by(token('/'))))
-            .with(skip_many(none_of(['\n'])))
-            .with(combine::parser::char::spaces()),
+        choice((
+            attempt(string("//").with(not_followed_by(token('/'))))
+                .with(skip_many(none_of(['\n']))),
+            block_comment(),
+        ))
+        .with(combine::parser::char::spaces()),
     ))
 }
 
+parser! {
+    // a /* */ comment, which may contain nested block comments
+    fn block_comment[I]()(I) -> ()
+    where [I: RangeStream<Token = char>, I::Range: Range]
+    {
+        nested(attempt(string("/*"))
+            .with(skip_many(choice((
+                block_comment(),
+                attempt(token('*').skip(not_followed_by(token('/')))).map(|_| ()),
+                attempt(token('/').skip(not_followed_by(token('*')))).map(|_| ()),
+                none_of(['*', '/']).map(|_| ()),
+            ))))
+            .with(string("*/").message("unterminated block comment"))
+            .map(|_| ()))
+    }
+}
+
 fn spaces1<I>() -> impl Parser<I, Output = ()>
 where
     I: RangeStream<Token = char>,
@@ -136,19 +156,67 @@ where
     I::Error: ParseError<I::Token, I::Range, I::Position>,
     I::Range: Range,
 {
-    combine::parser::char::spaces()
-        .with(many(
-            string("///")
-                .with(many(none_of(['\n'])))
-                .skip(combine::parser::char::spaces()),
-        ))
-        .map(|lines: LPooled<Vec<String>>| {
+    spaces().with(many(string("///").with(many(none_of(['\n']))).skip(spaces()))).map(
+        |lines: LPooled<Vec<String>>| {
             if lines.len() == 0 {
                 Doc(None)
             } else {
                 Doc(Some(ArcStr::from(lines.join("\n"))))
             }
-        })
+        },
+    )
+}
+
+// record the position just after the expression p parses as it's end,
+// unless a more precise end was already recorded by an inner parser
+fn spanned<I, P>(p: P) -> impl Parser<I, Output = Expr>
+where
+    I: RangeStream<Token = char, Position = SourcePosition>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+    I::Range: Range,
+    P: Parser<I, Output = Expr>,
+{
+    (p, position()).map(|(mut e, end): (Expr, _)| {
+        if e.end == e.pos {
+            e.end = end;
+        }
+        e
+    })
+}
+
+thread_local! {
+    // how deep the recursive parsers are, and whether the parse in progress
+    // has gone past MAX_PARSE_NESTING
+    static DEPTH: Cell<(usize, bool)> = Cell::new((0, false));
+}
+
+// run p one level deeper in the parser's recursion, failing instead of
+// recursing past MAX_PARSE_NESTING. Every recursive parser goes through
+// parser!, so wrapping each of them bounds the stack no matter which
+// syntax the input nests. Once the limit is hit everything fails until the
+// outermost parser returns, otherwise backtracking would retry each
+// alternative all the way back down to the limit.
+pub(super) fn nested<I, P>(mut p: P) -> impl Parser<I, Output = P::Output>
+where
+    I: RangeStream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+    I::Range: Range,
+    P: Parser<I>,
+{
+    combine::parser(move |input: &mut I| {
+        let (depth, deep) = DEPTH.get();
+        if deep || depth >= MAX_PARSE_NESTING {
+            DEPTH.set((depth, depth > 0));
+            return unexpected_any("expression is nested too deeply")
+                .parse_stream(input)
+                .into_result();
+        }
+        DEPTH.set((depth + 1, false));
+        let res = p.parse_stream(input).into_result();
+        let (_, deep) = DEPTH.get();
+        DEPTH.set((depth, deep && depth > 0));
+        res
+    })
 }
 
 fn spstring<'a, I>(s: &'static str) -> impl Parser<I, Output = &'a str>
@@ -352,6 +420,23 @@ where
         })
 }
 
+fn annotation<I>() -> impl Parser<I, Output = Annotation>
+where
+    I: RangeStream<Token = char>,
+    I::Error: ParseError<I::Token, I::Range, I::Position>,
+    I::Range: Range,
+{
+    attempt(string("#["))
+        .with((
+            spfname(),
+            optional(
+                sptoken('=').with(spaces()).with(parse_value(&VAL_MUST_ESC, &VAL_ESC)),
+            ),
+        ))
+        .skip(sptoken(']'))
+        .map(|(key, value)| Annotation { key, value })
+}
+
 fn letbind<I>() -> impl Parser<I, Output = Expr>
 where
     I: RangeStream<Token = char, Position = SourcePosition>,
@@ -360,6 +445,8 @@ where
 {
     (
         position(),
+        many(annotation().skip(spaces()))
+            .map(|mut a: LPooled<Vec<Annotation>>| Arc::from_iter(a.drain(..))),
         attempt(string("let").skip(spaces1()))
             .with((
                 optional(attempt(string("rec").with(spaces1()))),
@@ -369,9 +456,10 @@ where
             .skip(sptoken('=')),
         expr(),
     )
-        .map(|(pos, (rec, pattern, typ), value)| {
+        .map(|(pos, annotations, (rec, pattern, typ), value)| {
             let rec = rec.is_some();
-            ExprKind::Bind(Arc::new(BindExpr { rec, pattern, typ, value })).to_expr(pos)
+            ExprKind::Bind(Arc::new(BindExpr { annotations, rec, pattern, typ, value }))
+                .to_expr(pos)
         })
 }
 
@@ -719,7 +807,7 @@ parser! {
     fn expr[I]()(I) -> Expr
     where [I: RangeStream<Token = char, Position = SourcePosition>, I::Range: Range]
     {
-        spaces().with(choice((
+        nested(spaces().with(spanned(choice((
             module(),
             use_module(),
             try_catch(),
@@ -735,7 +823,7 @@ parser! {
             })),
             attempt(literal()),
             qop(reference())
-        )))
+        )))))
     }
 }
 
@@ -756,6 +844,27 @@ pub fn parse(ori: Origin) -> anyhow::Result<Arc<[Expr]>> {
     Ok(Arc::from_iter(r.drain(..)))
 }
 
+/// The longest input `parse_str` will accept, in bytes
+pub const MAX_PARSE_LEN: usize = 1 << 20;
+
+/// The deepest the recursive parsers will nest before failing
+pub const MAX_PARSE_NESTING: usize = 1024;
+
+/// Parse one or more expressions from a string
+///
+/// This is `parse` with a throwaway origin, for callers such as fuzz
+/// harnesses that don't have one. Input longer than `MAX_PARSE_LEN` is
+/// rejected up front, and like every parse, input that nests deeper than
+/// `MAX_PARSE_NESTING` is a parse error rather than a stack overflow.
+pub fn parse_str(src: &str) -> anyhow::Result<Vec<Expr>> {
+    if src.len() > MAX_PARSE_LEN {
+        anyhow::bail!("input is longer than {MAX_PARSE_LEN} bytes")
+    }
+    let ori =
+        Origin { parent: None, source: Source::Unspecified, text: ArcStr::from(src) };
+    Ok(parse(ori)?.to_vec())
+}
+
 /// Parse one or more signature expressions
 ///
 /// followed by (optional) whitespace and then eof. At least one
@@ -1,6 +1,6 @@
 use super::{
-    csep, doc_comment, expr, modpath, sep_by1_tok, spaces, spfname, spmodpath, spstring,
-    sptoken, typ, typedef,
+    csep, doc_comment, expr, modpath, nested, sep_by1_tok, spaces, spfname, spmodpath,
+    spstring, sptoken, typ, typedef,
 };
 use crate::expr::{
     parser::{semisep, spaces1},
@@ -20,7 +20,7 @@ parser! {
     pub(super) fn sig_item[I]()(I) -> SigItem
     where [I: RangeStream<Token = char, Position = SourcePosition>, I::Range: Range]
     {
-        doc_comment().skip(spaces()).then(|doc| {
+        nested(doc_comment().skip(spaces()).then(|doc| {
             choice((
                 typedef().map({
                     let doc = doc.clone();
@@ -45,7 +45,7 @@ parser! {
                     move |n: ArcStr| SigItem { doc: doc.clone(), kind: SigKind::Module(n) }
                 })
             ))
-        })
+        }))
     }
 }
 
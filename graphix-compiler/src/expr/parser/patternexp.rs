@@ -1,8 +1,8 @@
 use crate::{
     expr::{
         parser::{
-            csep, expr, fname, sep_by1_tok, sep_by_tok, spaces, spaces1, spstring,
-            sptoken, typ, typname,
+            csep, expr, fname, nested, sep_by1_tok, sep_by_tok, spaces, spaces1,
+            spstring, sptoken, typ, typname,
         },
         Expr, Pattern, StructurePattern,
     },
@@ -161,7 +161,10 @@ where
         sptoken('}'),
         spaces().with(sep_by1_tok(
             choice((
-                string("..").map(|_| (literal!(""), StructurePattern::Ignore, false)),
+                string("..").with(optional(fname())).map(|rest| match rest {
+                    None => (literal!(""), StructurePattern::Ignore, false),
+                    Some(n) => (n.clone(), StructurePattern::Bind(n), false),
+                }),
                 fname()
                     .skip(spaces())
                     .then(|name| {
@@ -182,19 +185,28 @@ where
     )
     .then(move |mut binds: LPooled<Vec<(ArcStr, StructurePattern, bool)>>| {
         let mut exhaustive = true;
-        binds.retain(|(_, _, ex)| {
+        let mut rest: LPooled<Vec<ArcStr>> = LPooled::take();
+        binds.retain(|(name, pat, ex)| {
             exhaustive &= *ex;
+            if let StructurePattern::Bind(_) = pat {
+                if !*ex {
+                    rest.push(name.clone())
+                }
+            }
             *ex
         });
         binds.sort_by_key(|(s, _, _)| s.clone());
         let s = binds.iter().map(|(s, _, _)| s).collect::<LPooled<FxHashSet<_>>>();
         if s.len() < binds.len() {
             unexpected_any("struct fields must be unique").left()
+        } else if rest.len() > 1 {
+            unexpected_any("struct patterns may have only one rest binding").left()
         } else {
             drop(s);
             let all = all.clone();
+            let rest = rest.pop();
             let binds = Arc::from_iter(binds.drain(..).map(|(s, p, _)| (s, p)));
-            value(StructurePattern::Struct { all, exhaustive, binds }).right()
+            value(StructurePattern::Struct { all, exhaustive, binds, rest }).right()
         }
     })
 }
@@ -257,7 +269,7 @@ parser! {
     pub(crate) fn structure_pattern[I]()(I) -> StructurePattern
     where [I: RangeStream<Token = char, Position = SourcePosition>, I::Range: Range]
     {
-        spaces().with(optional(attempt(all_pattern()))).then(|all| choice((
+        nested(spaces().with(optional(attempt(all_pattern()))).then(|all| choice((
             slice_pattern(all.clone()),
             tuple_pattern(all.clone()),
             struct_pattern(all.clone()),
@@ -265,7 +277,7 @@ parser! {
             underbar_pattern(all.is_some()),
             literal_pattern(all.is_some()),
             bind_pattern(all.is_some()),
-        )))
+        ))))
     }
 }
 
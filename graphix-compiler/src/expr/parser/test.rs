@@ -218,6 +218,7 @@ fn var_ref() {
 fn letbind() {
     assert_eq!(
         ExprKind::Bind(Arc::new(BindExpr {
+            annotations: Arc::from_iter([]),
             rec: false,
             typ: None,
             pattern: StructurePattern::Bind(literal!("foo")),
@@ -232,6 +233,7 @@ fn letbind() {
 fn letrecbind() {
     assert_eq!(
         ExprKind::Bind(Arc::new(BindExpr {
+            annotations: Arc::from_iter([]),
             rec: true,
             typ: None,
             pattern: StructurePattern::Bind(literal!("foo")),
@@ -263,6 +265,7 @@ fn doc() {
 fn typed_letbind() {
     assert_eq!(
         ExprKind::Bind(Arc::new(BindExpr {
+            annotations: Arc::from_iter([]),
             rec: false,
             typ: Some(Type::Primitive(Typ::I64.into())),
             pattern: StructurePattern::Bind(literal!("foo")),
@@ -646,6 +649,7 @@ fn select1() {
                         (literal!("foo"), StructurePattern::Literal(Value::I64(42))),
                         (literal!("foobar"), StructurePattern::Bind(literal!("a"))),
                     ]),
+                    rest: None,
                 },
                 guard: None,
             },
@@ -767,6 +771,7 @@ fn doexpr() {
     let exp = ExprKind::Do {
         exprs: Arc::from_iter([
             ExprKind::Bind(Arc::new(BindExpr {
+                annotations: Arc::from_iter([]),
                 rec: false,
                 typ: None,
                 pattern: StructurePattern::Bind(literal!("baz")),
@@ -930,6 +935,7 @@ fn apply_typed_lambda() {
 #[test]
 fn typed_array() {
     let e = ExprKind::Bind(Arc::new(BindExpr {
+        annotations: Arc::from_iter([]),
         rec: false,
         pattern: StructurePattern::Bind(literal!("f")),
         typ: None,
@@ -958,6 +964,7 @@ fn typed_array() {
 #[test]
 fn labeled_argument_lambda() {
     let e = ExprKind::Bind(Arc::new(BindExpr {
+        annotations: Arc::from_iter([]),
         rec: false,
         pattern: StructurePattern::Bind(literal!("a")),
         typ: Some(Type::Fn(Arc::new(FnType {
@@ -1137,6 +1144,7 @@ fn tuple0() {
 #[test]
 fn tuple1() {
     let e = ExprKind::Bind(Arc::new(BindExpr {
+        annotations: Arc::from_iter([]),
         rec: false,
         pattern: StructurePattern::Tuple {
             all: None,
@@ -1174,6 +1182,7 @@ fn tuple1() {
 #[test]
 fn struct0() {
     let e = ExprKind::Bind(Arc::new(BindExpr {
+        annotations: Arc::from_iter([]),
         rec: false,
         pattern: StructurePattern::Bind(literal!("a")),
         typ: None,
@@ -1208,6 +1217,7 @@ fn struct0() {
 #[test]
 fn bindstruct() {
     let e = ExprKind::Bind(Arc::new(BindExpr {
+        annotations: Arc::from_iter([]),
         rec: false,
         pattern: StructurePattern::Struct {
             all: None,
@@ -1217,6 +1227,7 @@ fn bindstruct() {
                 (literal!("baz"), StructurePattern::Bind(literal!("zam"))),
                 (literal!("foo"), StructurePattern::Bind(literal!("foo"))),
             ]),
+            rest: None,
         },
         typ: None,
         value: ExprKind::Struct(StructExpr {
@@ -1246,6 +1257,30 @@ fn bindstruct() {
     assert_eq!(e, pe)
 }
 
+#[test]
+fn bindstruct_rest() {
+    let e = ExprKind::Bind(Arc::new(BindExpr {
+        annotations: Arc::from_iter([]),
+        rec: false,
+        pattern: StructurePattern::Struct {
+            all: None,
+            exhaustive: false,
+            binds: Arc::from_iter([(
+                literal!("foo"),
+                StructurePattern::Bind(literal!("foo")),
+            )]),
+            rest: Some(literal!("rest")),
+        },
+        typ: None,
+        value: ExprKind::Ref { name: ["a"].into() }.to_expr_nopos(),
+    }))
+    .to_expr_nopos();
+    let s = "let { foo, ..rest } = a";
+    let pe = parse_one(s).unwrap();
+    assert_eq!(e, pe);
+    assert!(parse_one("let { foo, ..r0, ..r1 } = a").is_err())
+}
+
 #[test]
 fn structref() {
     let e = ExprKind::StructRef {
@@ -1386,3 +1421,178 @@ fn checked_associativity() {
     .to_expr_nopos();
     assert_eq!(e, parse_one("1 -? 2 -? 3").unwrap());
 }
+
+#[test]
+fn fn_type_display_round_trip() {
+    for s in [
+        "fn() -> i64",
+        "fn(i64, string) -> bool",
+        "fn(#x: i64, ?#y: f64, string) -> f64",
+        "fn<'a: Number>('a, 'a) -> 'a",
+        "fn<'a: Number, 'b: [string, i64]>(#f: fn('a) -> 'b, @args: 'a) -> Array<'b>",
+        "fn(i64) -> (fn(i64) -> i64)",
+        "fn(&i64) -> &string",
+        "fn(string) -> i64 throws `ParseError(string)",
+        "fn('a) -> 'a throws _",
+        "fn(fn(i64) -> i64 throws string, i64) -> [i64, Error<string>]",
+    ] {
+        let ft = parse_fn_type(s).unwrap();
+        let printed = format!("{ft}");
+        let reparsed = parse_fn_type(&printed)
+            .unwrap_or_else(|e| panic!("failed to reparse {printed}: {e}"));
+        assert_eq!(ft, reparsed, "{s} printed as {printed}");
+        assert_eq!(ft.explicit_throws, reparsed.explicit_throws, "{printed}");
+        assert_eq!(printed, format!("{reparsed}"));
+    }
+}
+
+#[test]
+fn parse_str_malformed() {
+    for s in [
+        "",
+        "\"",
+        "\"[",
+        "\"[]\"",
+        "\"[1\"",
+        "[",
+        "]",
+        "|",
+        "|x|",
+        "`",
+        "'",
+        "r'",
+        "let",
+        "let x =",
+        "a[",
+        "a[-9223372036854775808]",
+        "a[99999999999999999999]",
+        "select x {",
+        "[.., ..]",
+        "{a: 1, a: 2",
+        "1 + + 2",
+        "fn(",
+        "type T<'a = > = 'a",
+        "\u{0}",
+        "\u{10ffff}",
+    ] {
+        // must not panic, the result doesn't matter
+        let _ = parse_str(s);
+    }
+    // nesting that doesn't use brackets must be an error, not a stack
+    // overflow
+    let n = MAX_PARSE_NESTING + 1;
+    for s in [
+        format!("{}x", "!".repeat(n)),
+        format!("{}x", "|x| ".repeat(n)),
+        format!("let x: {}i64 = 1", "&".repeat(n)),
+        format!("let x: {}i64 = 1", "Array<".repeat(n)),
+        format!("{}1{}", "/*".repeat(n), "*/".repeat(n)),
+        format!("select x {{ {}_ => 1 }}", "(".repeat(n)),
+        format!("{}1", "(".repeat(n)),
+    ] {
+        assert!(parse_str(&s).is_err(), "{}", &s[..16]);
+    }
+}
+
+#[test]
+fn parse_str_bounded() {
+    let deep = "(".repeat(MAX_PARSE_NESTING + 1);
+    assert!(parse_str(&deep).is_err());
+    let deep = format!("{}1{}", "[".repeat(100), "]".repeat(100));
+    assert!(parse_str(&deep).is_ok());
+    let long = "1;".repeat(MAX_PARSE_LEN / 2 + 1);
+    assert!(parse_str(&long).is_err());
+    assert_eq!(parse_str("1; 2").unwrap().len(), 2);
+    // brackets in strings and comments aren't nesting
+    let parens = "(".repeat(MAX_PARSE_NESTING + 1);
+    assert!(parse_str(&format!("\"{parens}\"")).is_ok());
+    assert!(parse_str(&format!("r'{parens}'")).is_ok());
+    assert!(parse_str(&format!("/* {parens} */ 1")).is_ok());
+    assert!(parse_str(&format!("// {parens}\n1")).is_ok());
+}
+
+#[test]
+fn block_comment() {
+    assert_eq!(parse_one("a + 1").unwrap(), parse_one("a /* one\ntwo */ + 1").unwrap());
+    assert_eq!(parse_one("a * 2").unwrap(), parse_one("/* * / */ a * 2 /**/").unwrap());
+    assert_eq!(parse_str("let x = 1; /* let y = 2; */ x").unwrap().len(), 2);
+}
+
+#[test]
+fn nested_block_comment() {
+    assert_eq!(
+        parse_one("a / 2").unwrap(),
+        parse_one("a /* outer /* inner */ still outer */ / 2").unwrap()
+    );
+    assert!(parse_one("a /* outer /* inner */ + 1").is_err());
+}
+
+#[test]
+fn unterminated_block_comment() {
+    let e = parse_str("let x = 1; /* never closed").unwrap_err();
+    assert!(e.to_string().contains("unterminated block comment"), "{e}");
+}
+
+#[test]
+fn doc_block_comment() {
+    assert_eq!(
+        Doc(Some(literal!(" one\n two"))),
+        parse_doc("/* before */\n/// one\n/* between */\n/// two\n/* after */").unwrap()
+    );
+}
+
+#[test]
+fn annotated_letbind() {
+    let annotations = Arc::from_iter([
+        Annotation { key: literal!("deprecated"), value: None },
+        Annotation { key: literal!("unit"), value: Some(Value::from("m/s")) },
+    ]);
+    assert_eq!(
+        ExprKind::Bind(Arc::new(BindExpr {
+            annotations,
+            rec: false,
+            typ: None,
+            pattern: StructurePattern::Bind(literal!("speed")),
+            value: ExprKind::Constant(Value::I64(42)).to_expr_nopos()
+        }))
+        .to_expr_nopos(),
+        parse_one("#[deprecated]\n#[ unit = \"m/s\" ]\nlet speed = 42").unwrap()
+    );
+    let e = parse_one("#[unit = \"m/s\"] let speed = 42").unwrap();
+    assert_eq!(e, parse_one(&e.to_string()).unwrap());
+    assert!(parse_one("#[deprecated] 42").is_err());
+}
+
+#[test]
+fn annotation_attaches_to_its_bind() {
+    let exprs = parse_str("let a = 1;\n#[unit = \"s\"]\nlet b = 2;\nlet c = 3").unwrap();
+    let annotations = exprs
+        .iter()
+        .map(|e| match &e.kind {
+            ExprKind::Bind(b) => b.annotations.len(),
+            _ => panic!("expected a bind"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(annotations, vec![0, 1, 0]);
+}
+
+#[test]
+fn spans() {
+    let p = |line, column| SourcePosition { line, column };
+    let exprs = parse_str("let y = x +  42 ;\n[1, f(y)]").unwrap();
+    let spans = exprs[0].spans();
+    assert_eq!(spans[&exprs[0].id], (p(1, 1), p(1, 16)));
+    match &exprs[0].kind {
+        ExprKind::Bind(b) => assert_eq!(spans[&b.value.id], (p(1, 9), p(1, 16))),
+        _ => panic!("expected a bind"),
+    }
+    let spans = exprs[1].spans();
+    assert_eq!(spans[&exprs[1].id], (p(2, 1), p(2, 10)));
+    match &exprs[1].kind {
+        ExprKind::Array { args } => {
+            assert_eq!(spans[&args[0].id], (p(2, 2), p(2, 3)));
+            assert_eq!(spans[&args[1].id], (p(2, 5), p(2, 9)));
+        }
+        _ => panic!("expected an array"),
+    }
+}
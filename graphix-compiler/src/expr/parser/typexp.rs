@@ -1,6 +1,6 @@
 use super::{
-    csep, fname, ident, not_prefix, sep_by1_tok, sep_by_tok, spaces, spaces1, spfname,
-    spstring, sptoken, typname,
+    csep, fname, ident, nested, not_prefix, sep_by1_tok, sep_by_tok, spaces, spaces1,
+    spfname, spstring, sptoken, typname,
 };
 use crate::{
     expr::{Expr, ExprKind, ModPath, TypeDefExpr},
@@ -287,7 +287,7 @@ parser! {
     pub(super) fn typ[I]()(I) -> Type
     where [I: RangeStream<Token = char, Position = SourcePosition>, I::Range: Range]
     {
-        spaces().with(choice((
+        nested(spaces().with(choice((
             token('&').with(typ()).map(|t| Type::ByRef(Arc::new(t))),
             token('_').map(|_| Type::Bottom),
             between(token('['), sptoken(']'), sep_by_tok(typ(), csep(), token(']')))
@@ -308,7 +308,7 @@ parser! {
             attempt(typeprim()).map(|typ| Type::Primitive(typ.into())),
             tvar().map(|tv| Type::TVar(tv)),
             typref(),
-        )))
+        ))))
     }
 }
 
@@ -328,6 +328,7 @@ where
                 (
                     spaces().with(tvar()),
                     spaces().then(|_| optional(token(':').with(typ()))),
+                    spaces().then(|_| optional(token('=').with(typ()))),
                 ),
                 csep(),
                 token('>'),
@@ -337,15 +338,17 @@ where
     )
         .map(|(pos, name, params, typ)| {
             let params = params
-                .map(|mut ps: LPooled<Vec<(TVar, Option<Type>)>>| {
+                .map(|mut ps: LPooled<Vec<(TVar, Option<Type>, Option<Type>)>>| {
                     Arc::from_iter(ps.drain(..))
                 })
-                .unwrap_or_else(|| Arc::<[(TVar, Option<Type>)]>::from(Vec::new()));
+                .unwrap_or_else(|| {
+                    Arc::<[(TVar, Option<Type>, Option<Type>)]>::from(Vec::new())
+                });
             let typ = match typ {
                 Some(typ) => typ,
                 None => {
                     let params = Arc::from_iter(
-                        params.iter().map(|(tv, _)| Type::TVar(tv.clone())),
+                        params.iter().map(|(tv, _, _)| Type::TVar(tv.clone())),
                     );
                     Type::Abstract { id: AbstractId::new(), params }
                 }
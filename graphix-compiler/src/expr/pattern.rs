@@ -7,38 +7,45 @@ use smallvec::{smallvec, SmallVec};
 use std::fmt;
 use triomphe::Arc;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum StructurePattern {
     Ignore,
     Literal(Value),
     Bind(ArcStr),
     Slice {
         all: Option<ArcStr>,
+        #[serde(with = "super::ser::arc_slice")]
         binds: Arc<[StructurePattern]>,
     },
     SlicePrefix {
         all: Option<ArcStr>,
+        #[serde(with = "super::ser::arc_slice")]
         prefix: Arc<[StructurePattern]>,
         tail: Option<ArcStr>,
     },
     SliceSuffix {
         all: Option<ArcStr>,
         head: Option<ArcStr>,
+        #[serde(with = "super::ser::arc_slice")]
         suffix: Arc<[StructurePattern]>,
     },
     Tuple {
         all: Option<ArcStr>,
+        #[serde(with = "super::ser::arc_slice")]
         binds: Arc<[StructurePattern]>,
     },
     Variant {
         all: Option<ArcStr>,
         tag: ArcStr,
+        #[serde(with = "super::ser::arc_slice")]
         binds: Arc<[StructurePattern]>,
     },
     Struct {
         exhaustive: bool,
         all: Option<ArcStr>,
+        #[serde(with = "super::ser::arc_slice")]
         binds: Arc<[(ArcStr, StructurePattern)]>,
+        rest: Option<ArcStr>,
     },
 }
 
@@ -107,13 +114,16 @@ impl StructurePattern {
                     t.with_names(f)
                 }
             }
-            Self::Struct { exhaustive: _, all, binds } => {
+            Self::Struct { exhaustive: _, all, binds, rest } => {
                 if let Some(n) = all {
                     f(n)
                 }
                 for (_, t) in binds.iter() {
                     t.with_names(f)
                 }
+                if let Some(n) = rest {
+                    f(n)
+                }
             }
         }
     }
@@ -159,7 +169,7 @@ impl StructurePattern {
                 };
                 Ok(Type::Array(Arc::new(t)))
             }
-            Self::Struct { all: _, exhaustive: _, binds } => {
+            Self::Struct { all: _, exhaustive: _, binds, rest: _ } => {
                 let mut typs = binds
                     .iter()
                     .map(|(n, p)| Ok((n.clone(), p.infer_type_predicate(env)?)))
@@ -242,7 +252,7 @@ impl fmt::Display for StructurePattern {
                 with_sep!(binds);
                 write!(f, ")")
             }
-            StructurePattern::Struct { exhaustive, all, binds } => {
+            StructurePattern::Struct { exhaustive, all, binds, rest } => {
                 if let Some(all) = all {
                     write!(f, "{all}@ ")?
                 }
@@ -254,7 +264,10 @@ impl fmt::Display for StructurePattern {
                     }
                 }
                 if !exhaustive {
-                    write!(f, "..")?
+                    write!(f, "..")?;
+                    if let Some(rest) = rest {
+                        write!(f, "{rest}")?
+                    }
                 }
                 write!(f, "}}")
             }
@@ -262,7 +275,7 @@ impl fmt::Display for StructurePattern {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Pattern {
     pub type_predicate: Option<Type>,
     pub structure_predicate: StructurePattern,
@@ -1,9 +1,9 @@
 use super::Sig;
 use crate::{
     expr::{
-        parser, ApplyExpr, BindExpr, BindSig, Doc, Expr, ExprKind, LambdaExpr,
-        ModuleKind, Sandbox, SelectExpr, SigItem, SigKind, StructExpr, StructWithExpr,
-        TypeDefExpr,
+        parser, Annotation, ApplyExpr, BindExpr, BindSig, Doc, Expr, ExprKind,
+        LambdaExpr, ModuleKind, Sandbox, SelectExpr, SigItem, SigKind, StructExpr,
+        StructWithExpr, TypeDefExpr,
     },
     typ::Type,
 };
@@ -168,11 +168,14 @@ impl TypeDefExpr {
         write!(f, "type {}", self.name)?;
         if !self.params.is_empty() {
             write!(f, "<")?;
-            for (i, (tv, ct)) in self.params.iter().enumerate() {
+            for (i, (tv, ct, def)) in self.params.iter().enumerate() {
                 write!(f, "{tv}")?;
                 if let Some(ct) = ct {
                     write!(f, ": {ct}")?;
                 }
+                if let Some(def) = def {
+                    write!(f, " = {def}")?;
+                }
                 if i < self.params.len() - 1 {
                     write!(f, ", ")?;
                 }
@@ -332,9 +335,25 @@ impl PrettyDisplay for Sig {
     }
 }
 
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            None => write!(f, "#[{}]", self.key),
+            Some(v) => {
+                write!(f, "#[{} = ", self.key)?;
+                v.fmt_ext(f, &VAL_ESC, true)?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
 impl fmt::Display for BindExpr {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let BindExpr { rec, pattern, typ, value } = self;
+        let BindExpr { annotations, rec, pattern, typ, value } = self;
+        for a in annotations.iter() {
+            write!(f, "{a} ")?
+        }
         let rec = if *rec { " rec" } else { "" };
         match typ {
             None => write!(f, "let{} {pattern} = {value}", rec),
@@ -345,7 +364,10 @@ impl fmt::Display for BindExpr {
 
 impl PrettyDisplay for BindExpr {
     fn fmt_pretty_inner(&self, buf: &mut PrettyBuf) -> fmt::Result {
-        let BindExpr { rec, pattern, typ, value } = self;
+        let BindExpr { annotations, rec, pattern, typ, value } = self;
+        for a in annotations.iter() {
+            writeln!(buf, "{a}")?
+        }
         let rec = if *rec { " rec" } else { "" };
         match typ {
             None => writeln!(buf, "let{} {pattern} = ", rec)?,
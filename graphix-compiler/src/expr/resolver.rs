@@ -1,8 +1,9 @@
 use crate::{
     expr::{
-        parser, ApplyExpr, BindExpr, CouldNotResolve, Expr, ExprId, ExprKind, LambdaExpr,
-        ModPath, ModuleKind, Origin, Pattern, SelectExpr, Sig, SigItem, SigKind, Source,
-        StructExpr, StructWithExpr, StructurePattern, TryCatchExpr, TypeDefExpr,
+        parser, ApplyExpr, Arg, BindExpr, CouldNotResolve, Expr, ExprId, ExprKind,
+        LambdaExpr, ModPath, ModuleKind, Origin, Pattern, SelectExpr, Sig, SigItem,
+        SigKind, Source, StructExpr, StructWithExpr, StructurePattern, TryCatchExpr,
+        TypeDefExpr,
     },
     format_with_flags, PrintFlag,
 };
@@ -20,8 +21,16 @@ use netidx::{
     utils::Either,
 };
 use netidx_value::Value;
+use parking_lot::Mutex;
 use poolshark::local::LPooled;
-use std::{hash::Hash, path::PathBuf, pin::Pin, str::FromStr, time::Duration};
+use std::{
+    hash::Hash,
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 use tokio::{join, task, time::Instant, try_join};
 use triomphe::Arc;
 
@@ -63,6 +72,240 @@ impl ModuleResolver {
     }
 }
 
+/// The parsed text of each module source. An entry is used as long as
+/// the text being loaded is the text it was parsed from, and is replaced
+/// when the text changes, so loading an unchanged module again doesn't
+/// reparse it.
+pub(super) struct ParseCache<T> {
+    entries: Mutex<FxHashMap<Source, (ArcStr, T)>>,
+    parses: AtomicUsize,
+}
+
+impl<T> ParseCache<T> {
+    pub(super) fn new() -> Self {
+        Self { entries: Mutex::new(FxHashMap::default()), parses: AtomicUsize::new(0) }
+    }
+
+    /// return the cached parse of ori, copied for this load by reuse, or
+    /// parse it with f and cache the result if the text changed or was
+    /// never parsed
+    pub(super) fn parse(
+        &self,
+        ori: Origin,
+        f: impl FnOnce(Origin) -> Result<T>,
+        reuse: impl FnOnce(&T, Origin) -> T,
+    ) -> Result<T>
+    where
+        T: Clone,
+    {
+        {
+            let entries = self.entries.lock();
+            if let Some((text, t)) = entries.get(&ori.source)
+                && *text == ori.text
+            {
+                return Ok(reuse(t, ori));
+            }
+        }
+        // don't hold the lock while parsing
+        let (source, text) = (ori.source.clone(), ori.text.clone());
+        self.parses.fetch_add(1, Ordering::Relaxed);
+        let t = f(ori)?;
+        self.entries.lock().insert(source, (text, t.clone()));
+        Ok(t)
+    }
+
+    /// the number of times the cache has called the parser
+    #[cfg(test)]
+    pub(super) fn parses(&self) -> usize {
+        self.parses.load(Ordering::Relaxed)
+    }
+}
+
+/// A copy of e with a fresh id for e and every sub expression, and ori as
+/// their origin. A cached module is reused this way so that each load of
+/// it gets its own ids, and errors are reported relative to the module
+/// that loaded it this time.
+pub(super) fn refresh(e: &Expr, ori: &Arc<Origin>) -> Expr {
+    let r = |e: &Expr| refresh(e, ori);
+    let ra = |e: &Arc<Expr>| Arc::new(refresh(e, ori));
+    let rs = |es: &Arc<[Expr]>| Arc::from_iter(es.iter().map(|e| refresh(e, ori)));
+    let kind = match &e.kind {
+        k @ (ExprKind::Constant(_)
+        | ExprKind::NoOp
+        | ExprKind::Use { .. }
+        | ExprKind::Ref { .. }
+        | ExprKind::TypeDef { .. }
+        | ExprKind::Module { value: ModuleKind::Unresolved { .. }, .. }) => k.clone(),
+        ExprKind::Module {
+            name,
+            value: ModuleKind::Dynamic { sandbox, sig, source },
+        } => ExprKind::Module {
+            name: name.clone(),
+            value: ModuleKind::Dynamic {
+                sandbox: sandbox.clone(),
+                sig: sig.clone(),
+                source: ra(source),
+            },
+        },
+        ExprKind::Module {
+            name,
+            value: ModuleKind::Resolved { exprs, sig, from_interface },
+        } => ExprKind::Module {
+            name: name.clone(),
+            value: ModuleKind::Resolved {
+                exprs: rs(exprs),
+                sig: sig.clone(),
+                from_interface: *from_interface,
+            },
+        },
+        ExprKind::ExplicitParens(e) => ExprKind::ExplicitParens(ra(e)),
+        ExprKind::Do { exprs } => ExprKind::Do { exprs: rs(exprs) },
+        ExprKind::Bind(b) => {
+            ExprKind::Bind(Arc::new(BindExpr { value: r(&b.value), ..(**b).clone() }))
+        }
+        ExprKind::Connect { name, value, deref } => {
+            ExprKind::Connect { name: name.clone(), value: ra(value), deref: *deref }
+        }
+        ExprKind::StringInterpolate { args } => {
+            ExprKind::StringInterpolate { args: rs(args) }
+        }
+        ExprKind::StructRef { source, field } => {
+            ExprKind::StructRef { source: ra(source), field: field.clone() }
+        }
+        ExprKind::TupleRef { source, field } => {
+            ExprKind::TupleRef { source: ra(source), field: *field }
+        }
+        ExprKind::ArrayRef { source, i } => {
+            ExprKind::ArrayRef { source: ra(source), i: ra(i) }
+        }
+        ExprKind::ArraySlice { source, start, end } => ExprKind::ArraySlice {
+            source: ra(source),
+            start: start.as_ref().map(ra),
+            end: end.as_ref().map(ra),
+        },
+        ExprKind::MapRef { source, key } => {
+            ExprKind::MapRef { source: ra(source), key: ra(key) }
+        }
+        ExprKind::StructWith(StructWithExpr { source, replace }) => {
+            ExprKind::StructWith(StructWithExpr {
+                source: ra(source),
+                replace: Arc::from_iter(replace.iter().map(|(n, e)| (n.clone(), r(e)))),
+            })
+        }
+        ExprKind::Lambda(l) => {
+            let args = Arc::from_iter(l.args.iter().map(|a| Arg {
+                labeled: a.labeled.as_ref().map(|d| d.as_ref().map(r)),
+                ..a.clone()
+            }));
+            let body = match &l.body {
+                Either::Left(e) => Either::Left(r(e)),
+                Either::Right(s) => Either::Right(s.clone()),
+            };
+            ExprKind::Lambda(Arc::new(LambdaExpr { args, body, ..(**l).clone() }))
+        }
+        ExprKind::TypeCast { expr, typ } => {
+            ExprKind::TypeCast { expr: ra(expr), typ: typ.clone() }
+        }
+        ExprKind::Apply(ApplyExpr { args, function }) => ExprKind::Apply(ApplyExpr {
+            args: Arc::from_iter(args.iter().map(|(n, e)| (n.clone(), r(e)))),
+            function: ra(function),
+        }),
+        ExprKind::Any { args } => ExprKind::Any { args: rs(args) },
+        ExprKind::Array { args } => ExprKind::Array { args: rs(args) },
+        ExprKind::Map { args } => {
+            ExprKind::Map { args: Arc::from_iter(args.iter().map(|(k, v)| (r(k), r(v)))) }
+        }
+        ExprKind::Tuple { args } => ExprKind::Tuple { args: rs(args) },
+        ExprKind::Variant { tag, args } => {
+            ExprKind::Variant { tag: tag.clone(), args: rs(args) }
+        }
+        ExprKind::Struct(StructExpr { args }) => ExprKind::Struct(StructExpr {
+            args: Arc::from_iter(args.iter().map(|(n, e)| (n.clone(), r(e)))),
+        }),
+        ExprKind::Select(SelectExpr { arg, arms }) => ExprKind::Select(SelectExpr {
+            arg: ra(arg),
+            arms: Arc::from_iter(arms.iter().map(|(p, e)| {
+                let p = Pattern { guard: p.guard.as_ref().map(r), ..p.clone() };
+                (p, r(e))
+            })),
+        }),
+        ExprKind::Qop(e) => ExprKind::Qop(ra(e)),
+        ExprKind::OrNever(e) => ExprKind::OrNever(ra(e)),
+        ExprKind::TryCatch(tc) => ExprKind::TryCatch(Arc::new(TryCatchExpr {
+            handler: ra(&tc.handler),
+            exprs: rs(&tc.exprs),
+            ..(**tc).clone()
+        })),
+        ExprKind::ByRef(e) => ExprKind::ByRef(ra(e)),
+        ExprKind::Deref(e) => ExprKind::Deref(ra(e)),
+        ExprKind::Not { expr } => ExprKind::Not { expr: ra(expr) },
+        ExprKind::Add { lhs, rhs } => ExprKind::Add { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::CheckedAdd { lhs, rhs } => {
+            ExprKind::CheckedAdd { lhs: ra(lhs), rhs: ra(rhs) }
+        }
+        ExprKind::Sub { lhs, rhs } => ExprKind::Sub { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::CheckedSub { lhs, rhs } => {
+            ExprKind::CheckedSub { lhs: ra(lhs), rhs: ra(rhs) }
+        }
+        ExprKind::Mul { lhs, rhs } => ExprKind::Mul { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::CheckedMul { lhs, rhs } => {
+            ExprKind::CheckedMul { lhs: ra(lhs), rhs: ra(rhs) }
+        }
+        ExprKind::Div { lhs, rhs } => ExprKind::Div { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::CheckedDiv { lhs, rhs } => {
+            ExprKind::CheckedDiv { lhs: ra(lhs), rhs: ra(rhs) }
+        }
+        ExprKind::Mod { lhs, rhs } => ExprKind::Mod { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::CheckedMod { lhs, rhs } => {
+            ExprKind::CheckedMod { lhs: ra(lhs), rhs: ra(rhs) }
+        }
+        ExprKind::And { lhs, rhs } => ExprKind::And { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::Or { lhs, rhs } => ExprKind::Or { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::Eq { lhs, rhs } => ExprKind::Eq { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::Ne { lhs, rhs } => ExprKind::Ne { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::Gt { lhs, rhs } => ExprKind::Gt { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::Lt { lhs, rhs } => ExprKind::Lt { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::Gte { lhs, rhs } => ExprKind::Gte { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::Lte { lhs, rhs } => ExprKind::Lte { lhs: ra(lhs), rhs: ra(rhs) },
+        ExprKind::Sample { lhs, rhs } => ExprKind::Sample { lhs: ra(lhs), rhs: ra(rhs) },
+    };
+    Expr { id: ExprId::new(), ori: ori.clone(), pos: e.pos, end: e.end, kind }
+}
+
+/// Parsed modules and interfaces, by source, so that loading a module
+/// whose text hasn't changed since it was last loaded doesn't parse it
+/// again. Each runtime owns one, it is shared by everything the runtime
+/// loads, and dropped with the runtime. There is one entry per source,
+/// replaced whenever the source's text changes, so the cache never holds
+/// more than the latest text of each module the runtime has loaded.
+#[derive(Clone)]
+pub struct ModuleCache(Arc<ModuleCacheInner>);
+
+struct ModuleCacheInner {
+    modules: ParseCache<Arc<[Expr]>>,
+    interfaces: ParseCache<Sig>,
+}
+
+impl ModuleCache {
+    pub fn new() -> Self {
+        Self(Arc::new(ModuleCacheInner {
+            modules: ParseCache::new(),
+            interfaces: ParseCache::new(),
+        }))
+    }
+
+    fn parse(&self, ori: Origin) -> Result<Arc<[Expr]>> {
+        self.0.modules.parse(ori, parser::parse, |exprs, ori| {
+            let ori = Arc::new(ori);
+            Arc::from_iter(exprs.iter().map(|e| refresh(e, &ori)))
+        })
+    }
+
+    fn parse_sig(&self, ori: Origin) -> Result<Sig> {
+        self.0.interfaces.parse(ori, parser::parse_sig, |sig, _| sig.clone())
+    }
+}
+
 enum Resolution {
     Resolved { interface: Option<Origin>, implementation: Origin },
     TryNextMethod,
@@ -356,6 +599,7 @@ async fn resolve(
     scope: ModPath,
     prepend: Option<Arc<ModuleResolver>>,
     resolvers: Arc<[ModuleResolver]>,
+    cache: ModuleCache,
     id: ExprId,
     parent: Arc<Origin>,
     pos: SourcePosition,
@@ -398,13 +642,15 @@ async fn resolve(
         };
         let exprs = task::spawn_blocking({
             let ori = implementation.clone();
-            move || parser::parse(ori)
+            let cache = cache.clone();
+            move || cache.parse(ori)
         });
         let sig = match &interface {
             None => None,
             Some(ori) => {
                 let ori = ori.clone();
-                let sig = task::spawn_blocking(move || parser::parse_sig(ori))
+                let cache = cache.clone();
+                let sig = task::spawn_blocking(move || cache.parse_sig(ori))
                     .await?
                     .with_context(|| format!("parsing file {interface:?}"))?;
                 Some(sig)
@@ -424,7 +670,7 @@ async fn resolve(
                 ts.elapsed()
             )
         });
-        return Ok(Expr { id, ori: Arc::new(implementation), pos, kind });
+        return Ok(Expr { id, ori: Arc::new(implementation), pos, end: pos, kind });
     }
     bail!("module {name} could not be found {errors:?}")
 }
@@ -442,12 +688,14 @@ impl Expr {
     /// Resolve external modules referenced in the expression using
     /// the resolvers list. Each resolver will be tried in order,
     /// until one succeeds. If no resolver succeeds then an error will
-    /// be returned.
+    /// be returned. Modules that are already in the cache with the
+    /// same text are not parsed again.
     pub async fn resolve_modules<'a>(
         &'a self,
         resolvers: &'a Arc<[ModuleResolver]>,
+        cache: &'a ModuleCache,
     ) -> Result<Expr> {
-        self.resolve_modules_int(&ModPath::root(), &None, resolvers).await
+        self.resolve_modules_int(&ModPath::root(), &None, resolvers, cache).await
     }
 
     async fn resolve_modules_int<'a>(
@@ -455,9 +703,10 @@ impl Expr {
         scope: &ModPath,
         prepend: &'a Option<Arc<ModuleResolver>>,
         resolvers: &'a Arc<[ModuleResolver]>,
+        cache: &'a ModuleCache,
     ) -> Result<Expr> {
         if self.has_unresolved_modules() {
-            self.resolve_modules_inner(scope, prepend, resolvers).await
+            self.resolve_modules_inner(scope, prepend, resolvers, cache).await
         } else {
             Ok(self.clone())
         }
@@ -468,11 +717,12 @@ impl Expr {
         scope: &'a ModPath,
         prepend: &'a Option<Arc<ModuleResolver>>,
         resolvers: &'a Arc<[ModuleResolver]>,
+        cache: &'a ModuleCache,
     ) -> Pin<Box<dyn Future<Output = Result<Expr>> + Send + Sync + 'a>> {
         macro_rules! subexprs {
             ($args:expr) => {{
                 try_join_all($args.iter().map(|e| async {
-                    e.resolve_modules_int(scope, prepend, resolvers).await
+                    e.resolve_modules_int(scope, prepend, resolvers, cache).await
                 }))
                 .await?
             }};
@@ -482,7 +732,7 @@ impl Expr {
                 try_join_all($args.iter().map(|(k, e)| async {
                     Ok::<_, anyhow::Error>((
                         k.clone(),
-                        e.resolve_modules_int(scope, prepend, resolvers).await?,
+                        e.resolve_modules_int(scope, prepend, resolvers, cache).await?,
                     ))
                 }))
                 .await?
@@ -494,6 +744,7 @@ impl Expr {
                     id: self.id,
                     ori: self.ori.clone(),
                     pos: self.pos,
+                    end: self.end,
                     kind: $kind,
                 })
             };
@@ -510,8 +761,8 @@ impl Expr {
             ($kind:ident, $lhs:expr, $rhs:expr) => {
                 Box::pin(async move {
                     let (lhs, rhs) = try_join!(
-                        $lhs.resolve_modules_int(scope, prepend, resolvers),
-                        $rhs.resolve_modules_int(scope, prepend, resolvers)
+                        $lhs.resolve_modules_int(scope, prepend, resolvers, cache),
+                        $rhs.resolve_modules_int(scope, prepend, resolvers, cache)
                     )?;
                     expr!(ExprKind::$kind { lhs: Arc::from(lhs), rhs: Arc::from(rhs) })
                 })
@@ -532,13 +783,14 @@ impl Expr {
                 value: ModuleKind::Unresolved { from_interface },
                 name,
             } => {
-                let (id, pos, prepend, resolvers) =
-                    (self.id, self.pos, prepend.clone(), Arc::clone(resolvers));
+                let (id, pos, end, prepend, resolvers) =
+                    (self.id, self.pos, self.end, prepend.clone(), Arc::clone(resolvers));
                 Box::pin(async move {
-                    let e = resolve(
+                    let mut e = resolve(
                         scope.clone(),
                         prepend.clone(),
                         resolvers.clone(),
+                        cache.clone(),
                         id,
                         self.ori.clone(),
                         pos,
@@ -547,8 +799,9 @@ impl Expr {
                     )
                     .await
                     .with_context(|| CouldNotResolve(name.clone()))?;
+                    e.end = end;
                     let scope = ModPath(scope.append(&*name));
-                    e.resolve_modules_int(&scope, &prepend, &resolvers).await
+                    e.resolve_modules_int(&scope, &prepend, &resolvers, cache).await
                 })
             }
             ExprKind::Module {
@@ -572,7 +825,7 @@ impl Expr {
                     }),
                 };
                 let exprs = try_join_all(exprs.iter().map(|e| async {
-                    e.resolve_modules_int(&scope, &prepend, resolvers).await
+                    e.resolve_modules_int(&scope, &prepend, resolvers, cache).await
                 }))
                 .await?;
                 expr!(ExprKind::Module {
@@ -589,7 +842,7 @@ impl Expr {
                 value: ModuleKind::Dynamic { sandbox, sig, source },
             } => Box::pin(async move {
                 let source = Arc::new(
-                    source.resolve_modules_int(scope, prepend, resolvers).await?,
+                    source.resolve_modules_int(scope, prepend, resolvers, cache).await?,
                 );
                 expr!(ExprKind::Module {
                     name,
@@ -597,7 +850,7 @@ impl Expr {
                 })
             }),
             ExprKind::ExplicitParens(e) => Box::pin(async move {
-                let e = e.resolve_modules_int(scope, prepend, resolvers).await?;
+                let e = e.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                 expr!(ExprKind::ExplicitParens(Arc::new(e)))
             }),
             ExprKind::Do { exprs } => Box::pin(async move {
@@ -605,9 +858,11 @@ impl Expr {
                 expr!(ExprKind::Do { exprs })
             }),
             ExprKind::Bind(b) => Box::pin(async move {
-                let BindExpr { rec, pattern, typ, value } = &*b;
-                let value = value.resolve_modules_int(scope, prepend, resolvers).await?;
+                let BindExpr { annotations, rec, pattern, typ, value } = &*b;
+                let value =
+                    value.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                 expr!(ExprKind::Bind(Arc::new(BindExpr {
+                    annotations: annotations.clone(),
                     rec: *rec,
                     pattern: pattern.clone(),
                     typ: typ.clone(),
@@ -618,14 +873,17 @@ impl Expr {
                 Box::pin(async move {
                     expr!(ExprKind::StructWith(StructWithExpr {
                         source: Arc::new(
-                            source.resolve_modules_int(scope, prepend, resolvers).await?,
+                            source
+                                .resolve_modules_int(scope, prepend, resolvers, cache)
+                                .await?,
                         ),
                         replace: Arc::from(subtuples!(replace)),
                     }))
                 })
             }
             ExprKind::Connect { name, value, deref } => Box::pin(async move {
-                let value = value.resolve_modules_int(scope, prepend, resolvers).await?;
+                let value =
+                    value.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                 expr!(ExprKind::Connect { name, value: Arc::new(value), deref })
             }),
             ExprKind::Lambda(l) => Box::pin(async move {
@@ -633,7 +891,7 @@ impl Expr {
                 let body = match body {
                     Either::Right(s) => Either::Right(s.clone()),
                     Either::Left(e) => Either::Left(
-                        e.resolve_modules_int(scope, prepend, resolvers).await?,
+                        e.resolve_modules_int(scope, prepend, resolvers, cache).await?,
                     ),
                 };
                 let l = LambdaExpr {
@@ -647,7 +905,8 @@ impl Expr {
                 expr!(ExprKind::Lambda(Arc::new(l)))
             }),
             ExprKind::TypeCast { expr, typ } => Box::pin(async move {
-                let expr = expr.resolve_modules_int(scope, prepend, resolvers).await?;
+                let expr =
+                    expr.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                 expr!(ExprKind::TypeCast { expr: Arc::new(expr), typ })
             }),
             ExprKind::Apply(ApplyExpr { args, function }) => Box::pin(async move {
@@ -664,10 +923,11 @@ impl Expr {
             }),
             ExprKind::MapRef { source, key } => Box::pin(async move {
                 let source = Arc::new(
-                    source.resolve_modules_int(scope, prepend, resolvers).await?,
+                    source.resolve_modules_int(scope, prepend, resolvers, cache).await?,
+                );
+                let key = Arc::new(
+                    key.resolve_modules_inner(scope, prepend, resolvers, cache).await?,
                 );
-                let key =
-                    Arc::new(key.resolve_modules_inner(scope, prepend, resolvers).await?);
                 expr!(ExprKind::MapRef { source, key })
             }),
             ExprKind::Tuple { args } => only_args!(Tuple, args),
@@ -678,25 +938,27 @@ impl Expr {
             }),
             ExprKind::ArrayRef { source, i } => Box::pin(async move {
                 let source = Arc::new(
-                    source.resolve_modules_int(scope, prepend, resolvers).await?,
+                    source.resolve_modules_int(scope, prepend, resolvers, cache).await?,
+                );
+                let i = Arc::new(
+                    i.resolve_modules_int(scope, prepend, resolvers, cache).await?,
                 );
-                let i = Arc::new(i.resolve_modules_int(scope, prepend, resolvers).await?);
                 expr!(ExprKind::ArrayRef { source, i })
             }),
             ExprKind::ArraySlice { source, start, end } => Box::pin(async move {
                 let source = Arc::new(
-                    source.resolve_modules_int(scope, prepend, resolvers).await?,
+                    source.resolve_modules_int(scope, prepend, resolvers, cache).await?,
                 );
                 let start = match start {
                     None => None,
                     Some(e) => Some(Arc::new(
-                        e.resolve_modules_int(scope, prepend, resolvers).await?,
+                        e.resolve_modules_int(scope, prepend, resolvers, cache).await?,
                     )),
                 };
                 let end = match end {
                     None => None,
                     Some(e) => Some(Arc::new(
-                        e.resolve_modules_int(scope, prepend, resolvers).await?,
+                        e.resolve_modules_int(scope, prepend, resolvers, cache).await?,
                     )),
                 };
                 expr!(ExprKind::ArraySlice { source, start, end })
@@ -706,14 +968,16 @@ impl Expr {
                 expr!(ExprKind::Variant { tag, args })
             }),
             ExprKind::Select(SelectExpr { arg, arms }) => Box::pin(async move {
-                let arg =
-                    Arc::new(arg.resolve_modules_int(scope, prepend, resolvers).await?);
+                let arg = Arc::new(
+                    arg.resolve_modules_int(scope, prepend, resolvers, cache).await?,
+                );
                 let arms = try_join_all(arms.iter().map(|(p, e)| async {
                     let p = match &p.guard {
                         None => p.clone(),
                         Some(e) => {
-                            let e =
-                                e.resolve_modules_int(scope, prepend, resolvers).await?;
+                            let e = e
+                                .resolve_modules_int(scope, prepend, resolvers, cache)
+                                .await?;
                             Pattern {
                                 guard: Some(e),
                                 type_predicate: p.type_predicate.clone(),
@@ -721,27 +985,30 @@ impl Expr {
                             }
                         }
                     };
-                    let e = e.resolve_modules_int(scope, prepend, resolvers).await?;
+                    let e =
+                        e.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                     Ok::<_, anyhow::Error>((p, e))
                 }))
                 .await?;
                 expr!(ExprKind::Select(SelectExpr { arg, arms: Arc::from(arms) }))
             }),
             ExprKind::Qop(e) => Box::pin(async move {
-                let e = e.resolve_modules_int(scope, prepend, resolvers).await?;
+                let e = e.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                 expr!(ExprKind::Qop(Arc::new(e)))
             }),
             ExprKind::OrNever(e) => Box::pin(async move {
-                let e = e.resolve_modules_int(scope, prepend, resolvers).await?;
+                let e = e.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                 expr!(ExprKind::OrNever(Arc::new(e)))
             }),
             ExprKind::TryCatch(tc) => Box::pin(async move {
                 let exprs = try_join_all(tc.exprs.iter().map(|e| async {
-                    e.resolve_modules_int(&scope, &prepend, resolvers).await
+                    e.resolve_modules_int(&scope, &prepend, resolvers, cache).await
                 }))
                 .await?;
-                let handler =
-                    tc.handler.resolve_modules_int(scope, prepend, resolvers).await?;
+                let handler = tc
+                    .handler
+                    .resolve_modules_int(scope, prepend, resolvers, cache)
+                    .await?;
                 expr!(ExprKind::TryCatch(Arc::new(TryCatchExpr {
                     bind: tc.bind.clone(),
                     constraint: tc.constraint.clone(),
@@ -750,15 +1017,15 @@ impl Expr {
                 })))
             }),
             ExprKind::ByRef(e) => Box::pin(async move {
-                let e = e.resolve_modules_int(scope, prepend, resolvers).await?;
+                let e = e.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                 expr!(ExprKind::ByRef(Arc::new(e)))
             }),
             ExprKind::Deref(e) => Box::pin(async move {
-                let e = e.resolve_modules_int(scope, prepend, resolvers).await?;
+                let e = e.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                 expr!(ExprKind::Deref(Arc::new(e)))
             }),
             ExprKind::Not { expr: e } => Box::pin(async move {
-                let e = e.resolve_modules_int(scope, prepend, resolvers).await?;
+                let e = e.resolve_modules_int(scope, prepend, resolvers, cache).await?;
                 expr!(ExprKind::Not { expr: Arc::new(e) })
             }),
             ExprKind::Add { lhs, rhs } => bin_op!(Add, lhs, rhs),
@@ -0,0 +1,79 @@
+//! serde helpers for expression fields that can't derive it
+use netidx::utils::Either;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::Cell;
+use triomphe::Arc;
+
+thread_local! {
+    static STRUCTURAL: Cell<bool> = const { Cell::new(false) };
+}
+
+/// true if expressions are (de)serialized structurally on this thread
+pub(super) fn structural() -> bool {
+    STRUCTURAL.get()
+}
+
+/// For the duration of the closure F (de)serialize expressions
+/// structurally (on this thread only)
+pub(super) fn with_structural<R, F: FnOnce() -> R>(f: F) -> R {
+    let prev = STRUCTURAL.replace(true);
+    let res = f();
+    STRUCTURAL.set(prev);
+    res
+}
+
+/// serialize `Arc<[T]>` as a sequence
+pub(super) mod arc_slice {
+    use super::*;
+
+    pub(crate) fn serialize<S, T>(a: &Arc<[T]>, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize,
+    {
+        s.collect_seq(a.iter())
+    }
+
+    pub(crate) fn deserialize<'de, D, T>(d: D) -> Result<Arc<[T]>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        Ok(Arc::from(Vec::<T>::deserialize(d)?))
+    }
+}
+
+/// serialize `Either<L, R>` as an enum with `Left` and `Right` variants
+pub(super) mod either {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    enum E<L, R> {
+        Left(L),
+        Right(R),
+    }
+
+    pub(crate) fn serialize<S, L, R>(e: &Either<L, R>, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        L: Serialize,
+        R: Serialize,
+    {
+        match e {
+            Either::Left(l) => E::<&L, &R>::Left(l).serialize(s),
+            Either::Right(r) => E::<&L, &R>::Right(r).serialize(s),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D, L, R>(d: D) -> Result<Either<L, R>, D::Error>
+    where
+        D: Deserializer<'de>,
+        L: Deserialize<'de>,
+        R: Deserialize<'de>,
+    {
+        Ok(match E::deserialize(d)? {
+            E::Left(l) => Either::Left(l),
+            E::Right(r) => Either::Right(r),
+        })
+    }
+}
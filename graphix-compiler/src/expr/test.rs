@@ -329,12 +329,15 @@ fn structure_pattern() -> impl Strategy<Value = StructurePattern> {
             (
                 option::of(random_fname()),
                 collection::vec((random_fname(), inner.clone()), (1, 10)),
-                any::<bool>()
+                any::<bool>(),
+                option::of(random_fname())
             )
-                .prop_map(|(all, mut b, exhaustive)| {
+                .prop_map(|(all, mut b, exhaustive, rest)| {
                     b.sort_by_key(|(f, _)| f.clone());
                     b.dedup_by_key(|(f, _)| f.clone());
-                    StructurePattern::Struct { all, exhaustive, binds: Arc::from_iter(b) }
+                    let rest = if exhaustive { None } else { rest };
+                    let binds = Arc::from_iter(b);
+                    StructurePattern::Struct { all, exhaustive, binds, rest }
                 }),
             (
                 option::of(random_fname()),
@@ -379,17 +382,25 @@ fn build_pattern(arg: Expr, arms: Vec<(Option<Expr>, Pattern, Expr)>) -> Expr {
         .to_expr_nopos()
 }
 
+fn annotation() -> impl Strategy<Value = Annotation> {
+    (valid_fname(), option::of(any::<i64>().prop_map(Value::I64)))
+        .prop_map(|(key, value)| Annotation { key, value })
+}
+
 fn usestmt() -> impl Strategy<Value = Expr> {
     modpath().prop_map(|name| ExprKind::Use { name }.to_expr_nopos())
 }
 
 fn typedef() -> impl Strategy<Value = Expr> {
-    (typart(), collection::vec((tvar(), option::of(typexp())), 0..4), typexp()).prop_map(
-        |(name, params, typ)| {
+    (
+        typart(),
+        collection::vec((tvar(), option::of(typexp()), option::of(typexp())), 0..4),
+        typexp(),
+    )
+        .prop_map(|(name, params, typ)| {
             let params = Arc::from_iter(params.into_iter());
             ExprKind::TypeDef(TypeDefExpr { name, params, typ }).to_expr_nopos()
-        },
-    )
+        })
 }
 
 macro_rules! structref {
@@ -410,12 +421,24 @@ macro_rules! tupleref {
 
 macro_rules! bind {
     ($inner:expr) => {
-        ($inner, any::<bool>(), structure_pattern(), option::of(typexp())).prop_map(
-            |(value, rec, p, typ)| {
-                ExprKind::Bind(Arc::new(BindExpr { rec, pattern: p, value, typ }))
-                    .to_expr_nopos()
-            },
+        (
+            $inner,
+            collection::vec(annotation(), 0..3),
+            any::<bool>(),
+            structure_pattern(),
+            option::of(typexp()),
         )
+            .prop_map(|(value, annotations, rec, p, typ)| {
+                let annotations = Arc::from_iter(annotations);
+                ExprKind::Bind(Arc::new(BindExpr {
+                    annotations,
+                    rec,
+                    pattern: p,
+                    value,
+                    typ,
+                }))
+                .to_expr_nopos()
+            })
     };
 }
 
@@ -1083,11 +1106,12 @@ fn check_structure_pattern(pat0: &StructurePattern, pat1: &StructurePattern) ->
                     .all(|(p0, p1)| check_structure_pattern(p0, p1))
         }
         (
-            StructurePattern::Struct { exhaustive: e0, all: a0, binds: p0 },
-            StructurePattern::Struct { exhaustive: e1, all: a1, binds: p1 },
+            StructurePattern::Struct { exhaustive: e0, all: a0, binds: p0, rest: r0 },
+            StructurePattern::Struct { exhaustive: e1, all: a1, binds: p1, rest: r1 },
         ) => {
             e0 == e1
                 && a0 == a1
+                && r0 == r1
                 && p0.len() == p1.len()
                 && p0.iter().zip(p1.iter()).all(|((f0, p0), (f1, p1))| {
                     f0 == f1 && check_structure_pattern(p0, p1)
@@ -1133,13 +1157,14 @@ fn check_typedef(td0: &TypeDefExpr, td1: &TypeDefExpr) -> bool {
     dbg!(name0 == name1)
         && dbg!(
             p0.len() == p1.len()
-                && p0.iter().zip(p1.iter()).all(|((t0, c0), (t1, c1))| {
-                    t0 == t1
-                        && match (c0.as_ref(), c1.as_ref()) {
-                            (Some(c0), Some(c1)) => check_type(c0, c1),
-                            (None, None) => true,
-                            _ => false,
-                        }
+                && p0.iter().zip(p1.iter()).all(|((t0, c0, d0), (t1, c1, d1))| {
+                    let check_opt = |t0: &Option<Type>, t1: &Option<Type>| match (t0, t1)
+                    {
+                        (Some(t0), Some(t1)) => check_type(t0, t1),
+                        (None, None) => true,
+                        _ => false,
+                    };
+                    t0 == t1 && check_opt(c0, c1) && check_opt(d0, d1)
                 })
         )
         && dbg!(check_type(&typ0, &typ1))
@@ -1369,10 +1394,23 @@ fn check(s0: &Expr, s1: &Expr) -> bool {
             dbg!(name0 == name1)
         }
         (ExprKind::Bind(b0), ExprKind::Bind(b1)) => {
-            let BindExpr { rec: r0, pattern: p0, value: value0, typ: typ0 } = &**b0;
-            let BindExpr { rec: r1, pattern: p1, value: value1, typ: typ1 } = &**b1;
+            let BindExpr {
+                annotations: a0,
+                rec: r0,
+                pattern: p0,
+                value: value0,
+                typ: typ0,
+            } = &**b0;
+            let BindExpr {
+                annotations: a1,
+                rec: r1,
+                pattern: p1,
+                value: value1,
+                typ: typ1,
+            } = &**b1;
             dbg!(
-                dbg!(r0 == r1)
+                dbg!(a0 == a1)
+                    && dbg!(r0 == r1)
                     && dbg!(check_structure_pattern(p0, p1))
                     && dbg!(check_type_opt(typ0, typ1))
                     && dbg!(check(value0, value1))
@@ -1629,3 +1667,94 @@ proptest! {
         assert!(check(&s, &e))
     }
 }
+
+#[test]
+fn expr_serde_round_trip() {
+    let s = r#"{
+  type T<'a> = [`A('a), `B(string)];
+  let f = |#x: i64 = 1, y: T<i64>| -> i64 select y {
+    `A(v) if v > x => v,
+    _ => x
+  };
+  let g = |a, @args| 'a;
+  cast<i64>(f(#x: 2, `A(3)))?
+}"#;
+    let e = parse_one(s).unwrap();
+    // by default an expression serializes as its source text
+    let js = serde_json::to_string(&e).unwrap();
+    assert!(js.starts_with('"'), "{js}");
+    let e2: Expr = serde_json::from_str(&js).unwrap();
+    assert_eq!(e, e2);
+    let js = serde_json::to_string(&Ast(e.clone())).unwrap();
+    assert!(js.starts_with(&format!("[{AST_VERSION},")), "{js}");
+    let Ast(e2) = serde_json::from_str(&js).unwrap();
+    assert_eq!(e, e2);
+    assert_eq!(e.pos, e2.pos);
+    assert_ne!(e.id, e2.id);
+    assert_eq!(
+        format_with_flags(BitFlags::empty(), || e.to_string()),
+        format_with_flags(BitFlags::empty(), || e2.to_string())
+    );
+    let mut pos0 = vec![];
+    e.fold((), &mut |(), e| pos0.push((e.pos, e.end)));
+    let mut pos1 = vec![];
+    e2.fold((), &mut |(), e| pos1.push((e.pos, e.end)));
+    assert_eq!(pos0, pos1)
+}
+
+#[test]
+fn parse_cache() {
+    let cache = resolver::ParseCache::new();
+    let ori = |text: &str| Origin {
+        parent: None,
+        source: Source::Internal(ArcStr::from("m")),
+        text: ArcStr::from(text),
+    };
+    let reuse = |exprs: &Arc<[Expr]>, ori: Origin| -> Arc<[Expr]> {
+        let ori = Arc::new(ori);
+        Arc::from_iter(exprs.iter().map(|e| resolver::refresh(e, &ori)))
+    };
+    let ids = |exprs: &Arc<[Expr]>| {
+        exprs.iter().fold(vec![], |acc, e| {
+            e.fold(acc, &mut |mut acc, e| {
+                acc.push(e.id);
+                acc
+            })
+        })
+    };
+    let e0 = cache.parse(ori("let x = 1 + 2"), parser::parse, reuse).unwrap();
+    assert_eq!(cache.parses(), 1);
+    // the same text is not parsed again
+    let parent = Arc::new(ori("mod m"));
+    let mut o = ori("let x = 1 + 2");
+    o.parent = Some(parent.clone());
+    let e1 = cache.parse(o, parser::parse, reuse).unwrap();
+    assert_eq!(cache.parses(), 1);
+    assert_eq!(e0, e1);
+    // but every load gets fresh ids, and its own origin
+    let (ids0, ids1) = (ids(&e0), ids(&e1));
+    assert_eq!(ids0.len(), ids1.len());
+    assert!(ids0.iter().all(|id| !ids1.contains(id)));
+    e1[0].fold((), &mut |(), e| {
+        assert!(Arc::ptr_eq(e.ori.parent.as_ref().unwrap(), &parent))
+    });
+    // changed text bypasses the cache
+    let e2 = cache.parse(ori("let x = 2"), parser::parse, reuse).unwrap();
+    assert_eq!(cache.parses(), 2);
+    assert_ne!(e0, e2);
+    // and replaces the cached parse
+    cache.parse(ori("let x = 2"), parser::parse, reuse).unwrap();
+    assert_eq!(cache.parses(), 2);
+    cache.parse(ori("let x = 1 + 2"), parser::parse, reuse).unwrap();
+    assert_eq!(cache.parses(), 3);
+}
+
+#[test]
+fn expr_serde_version() {
+    let e = parse_one("1 + 2").unwrap();
+    let js = serde_json::to_string(&Ast(e)).unwrap();
+    let js =
+        js.replacen(&format!("[{AST_VERSION},"), &format!("[{},", AST_VERSION + 1), 1);
+    let err = serde_json::from_str::<Ast>(&js).unwrap_err();
+    assert!(err.to_string().contains("expression format version"), "{err}");
+}
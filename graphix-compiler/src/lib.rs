@@ -26,10 +26,10 @@ use enumflags2::{bitflags, BitFlags};
 use expr::Expr;
 use futures::channel::mpsc;
 use fxhash::{FxHashMap, FxHashSet};
-use log::info;
+use log::{Level, LevelFilter};
 use netidx::{
     path::Path,
-    publisher::{Id, Val, WriteRequest},
+    publisher::{Id, WriteRequest},
     subscriber::{self, Dval, SubId, UpdatesFlags, Value},
 };
 use netidx_protocols::rpc::server::{ArgSpec, RpcCall};
@@ -49,6 +49,7 @@ use std::{
     },
     fmt::Debug,
     mem,
+    ops::{Deref, DerefMut},
     sync::{
         self,
         atomic::{AtomicBool, Ordering},
@@ -67,6 +68,10 @@ pub enum CFlag {
     WarnUnhandled,
     WarnUnused,
     WarningsAreErrors,
+    /// inline small lambdas at their only call site
+    InlineLambdas,
+    /// float arithmetic that produces NaN or infinity is an arithmetic error
+    FloatErrors,
 }
 
 #[allow(dead_code)]
@@ -120,6 +125,17 @@ macro_rules! tdbg {
     };
 }
 
+/// Log like `log::log!`, unless the runtime's log level, set by
+/// `ExecCtx::set_log_level`, filters the message out
+#[macro_export]
+macro_rules! rtlog {
+    ($ctx:expr, $lvl:expr, $($arg:tt)+) => {
+        if $lvl <= $ctx.log_level() {
+            ::log::log!($lvl, $($arg)+)
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! err {
     ($tag:expr, $err:literal) => {{
@@ -219,6 +235,24 @@ pub enum PrintFlag {
 
 thread_local! {
     static PRINT_FLAGS: Cell<BitFlags<PrintFlag>> = Cell::new(PrintFlag::ReplacePrims.into());
+    static MAX_DEPTH: Cell<usize> = Cell::new(DEFAULT_MAX_DEPTH);
+}
+
+/// The default maximum nesting depth of compilation and type expansion
+pub const DEFAULT_MAX_DEPTH: usize = 512;
+
+/// return the maximum type expansion depth in effect on this thread
+pub(crate) fn max_depth() -> usize {
+    MAX_DEPTH.get()
+}
+
+/// For the duration of the closure F set the maximum type expansion
+/// depth (on this thread only).
+fn with_max_depth<R, F: FnOnce() -> R>(depth: usize, f: F) -> R {
+    let prev = MAX_DEPTH.replace(depth);
+    let res = f();
+    MAX_DEPTH.set(prev);
+    res
 }
 
 /// global pool of channel watch batches
@@ -277,6 +311,42 @@ impl<E: UserEvent> Event<E> {
         writes.clear();
         user.clear();
     }
+
+    /// Set init until the returned guard is dropped, after which the
+    /// previous value is restored, even on an early return or a
+    /// panic. The guard derefs to the event, e.g.
+    /// `node.update(ctx, &mut event.init_scope(true))`.
+    pub fn init_scope(&mut self, init: bool) -> InitScope<'_, E> {
+        let prev = mem::replace(&mut self.init, init);
+        InitScope { event: self, prev }
+    }
+}
+
+/// A guard that restores `Event::init` when it is dropped. See
+/// `Event::init_scope`.
+pub struct InitScope<'a, E: UserEvent> {
+    event: &'a mut Event<E>,
+    prev: bool,
+}
+
+impl<'a, E: UserEvent> Deref for InitScope<'a, E> {
+    type Target = Event<E>;
+
+    fn deref(&self) -> &Self::Target {
+        self.event
+    }
+}
+
+impl<'a, E: UserEvent> DerefMut for InitScope<'a, E> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.event
+    }
+}
+
+impl<'a, E: UserEvent> Drop for InitScope<'a, E> {
+    fn drop(&mut self) {
+        self.event.init = self.prev
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -454,7 +524,10 @@ pub trait Rt: Debug + Any {
     /// Subscribe to the specified netidx path
     ///
     /// When the subscription updates you are expected to deliver
-    /// Netidx events to the expression specified by ref_by.
+    /// Netidx events to the expression specified by ref_by. If the path
+    /// is already subscribed the existing subscription is shared, and if
+    /// flags contains `BEGIN_WITH_LAST` its last value is delivered to
+    /// ref_by alone.
     fn subscribe(&mut self, flags: UpdatesFlags, path: Path, ref_by: ExprId) -> Dval;
 
     /// Called when a subscription is no longer needed
@@ -470,20 +543,35 @@ pub trait Rt: Debug + Any {
     /// change
     fn list_table(&mut self, id: BindId, path: Path);
 
-    /// list or table will no longer be called on this BindId, and
+    /// List the paths matching the netidx glob pattern, return
+    /// Value::Null if the set of matching paths did not change.
+    fn glob(&mut self, id: BindId, pattern: Path);
+
+    /// list, table, or glob will no longer be called on this BindId, and
     /// related resources can be cleaned up.
     fn stop_list(&mut self, id: BindId);
 
     /// Publish the specified value, returning it's Id, which must be
     /// used to update the value and unpublish it. If the path is
-    /// already published, return an error.
-    fn publish(&mut self, path: Path, value: Value, ref_by: ExprId) -> Result<Val>;
+    /// already published, return an error, unless replace is true, in
+    /// which case the existing value is unpublished first. If typ is
+    /// specified then writes of values that are not a typ should be
+    /// rejected.
+    fn publish(
+        &mut self,
+        path: Path,
+        value: Value,
+        typ: Option<Type>,
+        replace: bool,
+        ref_by: ExprId,
+    ) -> Result<Id>;
 
-    /// Update the specified value
-    fn update(&mut self, id: &Val, value: Value);
+    /// Update the specified value. If the id is no longer published,
+    /// e.g. because it was replaced, do nothing.
+    fn update(&mut self, id: Id, value: Value);
 
     /// Stop publishing the specified id
-    fn unpublish(&mut self, id: Val, ref_by: ExprId);
+    fn unpublish(&mut self, id: Id, ref_by: ExprId);
 
     /// This will be called by the compiler whenever a bound variable
     /// is referenced. The ref_by is the toplevel expression that
@@ -551,24 +639,32 @@ pub trait Rt: Debug + Any {
     /// Spawn a task
     ///
     /// When the task completes it's output must be delivered as a
-    /// custom event using the returned `BindId`
+    /// custom event using the returned `BindId`. `desc` describes
+    /// what the task is doing to anyone inspecting the running
+    /// tasks, usually it is the name of the builtin that spawned it.
     ///
     /// Calling `abort` must guarantee that if it is called before the
     /// task completes then no update will be delivered.
     fn spawn<F: Future<Output = (BindId, Box<dyn CustomBuiltinType>)> + Send + 'static>(
         &mut self,
+        desc: ArcStr,
         f: F,
     ) -> Self::AbortHandle;
 
     /// Spawn a task
     ///
     /// When the task completes it's output must be delivered as a
-    /// variable event using the returned `BindId`
+    /// variable event for `id`. `desc` is as for `spawn`. If the
+    /// embedder cancels the task a `Cancelled` error must be
+    /// delivered for `id` instead, so whatever is waiting for the
+    /// result isn't left waiting forever.
     ///
     /// Calling `abort` must guarantee that if it is called before the
     /// task completes then no update will be delivered.
-    fn spawn_var<F: Future<Output = (BindId, Value)> + Send + 'static>(
+    fn spawn_var<F: Future<Output = Value> + Send + 'static>(
         &mut self,
+        id: BindId,
+        desc: ArcStr,
         f: F,
     ) -> Self::AbortHandle;
 
@@ -744,6 +840,17 @@ pub struct ExecCtx<R: Rt, E: UserEvent> {
     builtins_allowed: bool,
     // hash consed variant tags
     tags: FxHashSet<ArcStr>,
+    // the maximum nesting depth of compilation and type expansion
+    max_depth: usize,
+    // the current compilation nesting depth
+    depth: usize,
+    // the maximum length of values produced by casts and size amplifying
+    // built-ins
+    max_len: Option<usize>,
+    // the most verbose level this runtime will log at
+    log_level: LevelFilter,
+    // whether float arithmetic producing NaN or infinity is an error
+    float_errors: bool,
     /// context global library state for built-in functions
     pub libstate: LibState,
     /// the language environment, typdefs, binds, lambdas, etc
@@ -782,6 +889,11 @@ impl<R: Rt, E: UserEvent> ExecCtx<R, E> {
             builtins_allowed: true,
             libstate: LibState::default(),
             tags: FxHashSet::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+            max_len: None,
+            log_level: LevelFilter::Trace,
+            float_errors: false,
             cached: HashMap::default(),
             rt: user,
             lambda_defs: FxHashMap::default(),
@@ -799,6 +911,83 @@ impl<R: Rt, E: UserEvent> ExecCtx<R, E> {
         Ok(())
     }
 
+    /// Set the maximum nesting depth of compilation and type
+    /// expansion. Compiling an expression that nests more deeply than
+    /// this, or typechecking a type whose aliases expand more deeply
+    /// than this, will fail with an error instead of overflowing the
+    /// stack. The default is `DEFAULT_MAX_DEPTH`.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+
+    /// Return the maximum nesting depth of compilation and type expansion
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Set the maximum length of an array, map, string, or bytes
+    /// value that casts and size amplifying built-ins (such as
+    /// `array::init`) will produce. A cast of a value larger than
+    /// this evaluates to a cast error instead of allocating it. The
+    /// default, `None`, is unlimited.
+    pub fn set_max_len(&mut self, len: Option<usize>) {
+        self.max_len = len;
+    }
+
+    /// Return the maximum length of values produced by casts and size
+    /// amplifying built-ins
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    /// Set the most verbose level this runtime will log at, e.g. to see
+    /// the compile and typecheck timings at `LevelFilter::Info`. This
+    /// only filters the messages the runtime itself logs, and only further
+    /// than the log crate's global max level and the installed logger do.
+    /// The default, `LevelFilter::Trace`, filters nothing.
+    pub fn set_log_level(&mut self, level: LevelFilter) {
+        self.log_level = level;
+    }
+
+    /// Return the most verbose level this runtime will log at
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level
+    }
+
+    /// Cast v to typ, returning an error instead of an error value if it
+    /// can't be cast, or if it is longer than the maximum length
+    pub fn try_cast_value(&self, typ: &Type, v: Value) -> Result<Value> {
+        if let Some(max) = self.max_len {
+            typ::check_len(max, &v)?
+        }
+        typ.try_cast_value(&self.env, v)
+    }
+
+    /// Cast v to typ, like `Type::cast_value`, but values longer than the
+    /// maximum length are a cast error. Built-ins casting external input
+    /// should use this.
+    pub fn cast_value(&self, typ: &Type, v: Value) -> Value {
+        match self.try_cast_value(typ, v) {
+            Ok(v) => v,
+            Err(e) => errf!(CAST_ERR_TAG, "{e:?}"),
+        }
+    }
+
+    /// Treat float arithmetic that produces NaN or infinity as an
+    /// arithmetic error, like integer division by zero. Checked
+    /// operators such as `/?` return an `ArithError`, and unchecked
+    /// operators log the error and don't update. This applies to all
+    /// code, see `CFlag::FloatErrors` to enable it only for some. The
+    /// default, false, follows IEEE 754.
+    pub fn set_float_errors(&mut self, enabled: bool) {
+        self.float_errors = enabled;
+    }
+
+    /// Return true if float arithmetic producing NaN or infinity is an error
+    pub fn float_errors(&self) -> bool {
+        self.float_errors
+    }
+
     /// Built in functions should call this when variables are set
     /// unless they are sure the variable does not need to be
     /// cached. This will also call the user ctx set_var.
@@ -873,6 +1062,15 @@ pub fn compile<R: Rt, E: UserEvent>(
     flags: BitFlags<CFlag>,
     scope: &Scope,
     spec: Expr,
+) -> Result<Node<R, E>> {
+    with_max_depth(ctx.max_depth, || compile_int(ctx, flags, scope, spec))
+}
+
+fn compile_int<R: Rt, E: UserEvent>(
+    ctx: &mut ExecCtx<R, E>,
+    flags: BitFlags<CFlag>,
+    scope: &Scope,
+    spec: Expr,
 ) -> Result<Node<R, E>> {
     let top_id = spec.id;
     let env = ctx.env.clone();
@@ -884,7 +1082,7 @@ pub fn compile<R: Rt, E: UserEvent>(
             return Err(e);
         }
     };
-    info!("compile time {:?}", st.elapsed());
+    rtlog!(ctx, Level::Info, "compile time {:?}", st.elapsed());
     let st = Instant::now();
     if let Err(e) = node.typecheck(ctx) {
         ctx.env = env;
@@ -897,6 +1095,39 @@ pub fn compile<R: Rt, E: UserEvent>(
             return Err(e);
         }
     }
-    info!("typecheck time {:?}", st.elapsed());
+    rtlog!(ctx, Level::Info, "typecheck time {:?}", st.elapsed());
     Ok(node)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn init_scope_restores() {
+        let mut event = Event::new(NoUserEvent);
+        {
+            let mut scope = event.init_scope(true);
+            assert!(scope.init);
+            let mut inner = scope.init_scope(false);
+            assert!(!inner.init);
+            inner.variables.insert(BindId::new(), Value::Null);
+        }
+        assert!(!event.init);
+        assert_eq!(event.variables.len(), 1);
+        fn early(event: &mut Event<NoUserEvent>) -> Option<()> {
+            let scope = event.init_scope(true);
+            scope.variables.get(&BindId::new())?;
+            Some(())
+        }
+        assert_eq!(early(&mut event), None);
+        assert!(!event.init);
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _scope = event.init_scope(true);
+            panic!("unwind")
+        }));
+        assert!(res.is_err());
+        assert!(!event.init);
+    }
+}
@@ -1,6 +1,6 @@
 use super::pattern::StructPatternNode;
 use crate::{
-    compiler::compile,
+    compiler::{check_type_refs, compile},
     expr::{self, Expr, ExprId, ExprKind, ModPath},
     format_with_flags,
     typ::Type,
@@ -16,6 +16,7 @@ use triomphe::Arc;
 pub(crate) struct Bind<R: Rt, E: UserEvent> {
     spec: Expr,
     typ: Type,
+    ptyp: Option<Type>,
     pattern: StructPatternNode,
     node: Node<R, E>,
 }
@@ -29,55 +30,106 @@ impl<R: Rt, E: UserEvent> Bind<R, E> {
         top_id: ExprId,
         b: &expr::BindExpr,
     ) -> Result<Node<R, E>> {
-        let expr::BindExpr { rec, pattern, typ, value } = b;
-        let (node, pattern, typ) = if *rec {
-            if !pattern.single_bind().is_some() {
-                bail!("at {} can't use rec on a complex pattern", spec.pos)
-            }
-            match value {
-                Expr { kind: ExprKind::Lambda(_), .. } => (),
-                _ => bail!("let rec may only be used for lambdas"),
-            }
-            let typ = match typ {
-                Some(typ) => typ.scope_refs(&scope.lexical),
-                None => Type::empty_tvar(),
-            };
-            let pattern = StructPatternNode::compile(ctx, &typ, pattern, scope)
-                .with_context(|| format!("at {}", spec.pos))?;
-            let node = compile(ctx, flags, value.clone(), &scope, top_id)?;
-            let ntyp = node.typ();
-            if !typ.contains(&ctx.env, ntyp)? {
-                format_with_flags(PrintFlag::DerefTVars, || {
-                    bail!("at {} error {} can't be matched by {typ}", ntyp, spec.pos)
-                })?
+        let expr::BindExpr { annotations, rec, pattern, typ, value } = b;
+        if *rec {
+            let decl = Self::declare_rec(ctx, &spec, scope, b)?;
+            return Self::compile_rec(ctx, flags, spec, scope, top_id, b, decl);
+        }
+        let node = compile(ctx, flags, value.clone(), &scope, top_id)?;
+        let (typ, ptyp) = match typ {
+            Some(typ) => {
+                let typ = typ.scope_refs(&scope.lexical);
+                check_type_refs(ctx, &spec, &typ)?;
+                (typ, None)
             }
-            (node, pattern, typ)
-        } else {
-            let node = compile(ctx, flags, value.clone(), &scope, top_id)?;
-            let typ = match typ {
-                Some(typ) => typ.scope_refs(&scope.lexical),
-                None => {
-                    let typ = node.typ().clone();
-                    let ptyp = pattern.infer_type_predicate(&ctx.env)?;
-                    if !ptyp.contains(&ctx.env, &typ)? {
-                        format_with_flags(PrintFlag::DerefTVars, || {
-                            bail!(
-                                "at {} match error {typ} can't be matched by {ptyp}",
-                                spec.pos
-                            )
-                        })?
-                    }
-                    typ
+            None => {
+                let typ = node.typ().clone();
+                let ptyp = pattern.infer_type_predicate(&ctx.env)?;
+                if !ptyp.contains(&ctx.env, &typ)? {
+                    format_with_flags(PrintFlag::DerefTVars, || {
+                        bail!(
+                            "at {} match error {typ} can't be matched by {ptyp}",
+                            spec.pos
+                        )
+                    })?
                 }
-            };
-            let pattern = StructPatternNode::compile(ctx, &typ, pattern, scope)
-                .with_context(|| format!("at {}", spec.pos))?;
-            (node, pattern, typ)
+                (typ, Some(ptyp))
+            }
         };
+        let pattern = StructPatternNode::compile(ctx, &typ, pattern, scope)
+            .with_context(|| format!("at {}", spec.pos))?;
         if pattern.is_refutable() {
             bail!("at {} refutable patterns are not allowed in let", spec.pos);
         }
-        Ok(Box::new(Self { spec, typ, pattern, node }))
+        Self::annotate(ctx, &pattern, annotations);
+        Ok(Box::new(Self { spec, typ, ptyp, pattern, node }))
+    }
+
+    /// Bind the name of a `let rec` without compiling its value. Every
+    /// binding in a group of consecutive `let rec`s is declared before
+    /// any of their values are compiled so they may refer to each other.
+    pub(crate) fn declare_rec(
+        ctx: &mut ExecCtx<R, E>,
+        spec: &Expr,
+        scope: &Scope,
+        b: &expr::BindExpr,
+    ) -> Result<(StructPatternNode, Type)> {
+        let expr::BindExpr { annotations, rec: _, pattern, typ, value } = b;
+        if !pattern.single_bind().is_some() {
+            bail!("at {} can't use rec on a complex pattern", spec.pos)
+        }
+        match value {
+            Expr { kind: ExprKind::Lambda(_), .. } => (),
+            _ => bail!("let rec may only be used for lambdas"),
+        }
+        let typ = match typ {
+            Some(typ) => {
+                let typ = typ.scope_refs(&scope.lexical);
+                check_type_refs(ctx, spec, &typ)?;
+                typ
+            }
+            None => Type::empty_tvar(),
+        };
+        let pattern = StructPatternNode::compile(ctx, &typ, pattern, scope)
+            .with_context(|| format!("at {}", spec.pos))?;
+        Self::annotate(ctx, &pattern, annotations);
+        Ok((pattern, typ))
+    }
+
+    /// record the annotations on every variable bound by pattern, for
+    /// tools that introspect the environment
+    fn annotate(
+        ctx: &mut ExecCtx<R, E>,
+        pattern: &StructPatternNode,
+        annotations: &Arc<[expr::Annotation]>,
+    ) {
+        if !annotations.is_empty() {
+            pattern.ids(&mut |id| {
+                if let Some(bind) = ctx.env.by_id.get_mut_cow(&id) {
+                    bind.annotations = annotations.clone();
+                }
+            })
+        }
+    }
+
+    /// Compile the value of a `let rec` previously declared with `declare_rec`
+    pub(crate) fn compile_rec(
+        ctx: &mut ExecCtx<R, E>,
+        flags: BitFlags<CFlag>,
+        spec: Expr,
+        scope: &Scope,
+        top_id: ExprId,
+        b: &expr::BindExpr,
+        (pattern, typ): (StructPatternNode, Type),
+    ) -> Result<Node<R, E>> {
+        let node = compile(ctx, flags, b.value.clone(), &scope, top_id)?;
+        let ntyp = node.typ();
+        if !typ.contains(&ctx.env, ntyp)? {
+            format_with_flags(PrintFlag::DerefTVars, || {
+                bail!("at {} error {} can't be matched by {typ}", ntyp, spec.pos)
+            })?
+        }
+        Ok(Box::new(Self { spec, typ, ptyp: None, pattern, node }))
     }
 
     /// Return the id if this bind has only a single binding, otherwise return None
@@ -137,6 +189,20 @@ impl<R: Rt, E: UserEvent> Update<R, E> for Bind<R, E> {
     fn typecheck(&mut self, ctx: &mut ExecCtx<R, E>) -> Result<()> {
         wrap!(self.node, self.node.typecheck(ctx))?;
         wrap!(self.node, self.typ.check_contains(&ctx.env, self.node.typ()))?;
+        // without an annotation the type of the value may only be known
+        // after typechecking, so check again that the pattern can
+        // destructure it
+        if let Some(ptyp) = &self.ptyp {
+            let typ = self.node.typ();
+            if !ptyp.contains(&ctx.env, typ)? {
+                format_with_flags(PrintFlag::DerefTVars, || {
+                    bail!(
+                        "at {} match error {typ} can't be matched by {ptyp}",
+                        self.spec.pos
+                    )
+                })?
+            }
+        }
         Ok(())
     }
 }
@@ -13,7 +13,7 @@ use enumflags2::BitFlags;
 use fxhash::{FxHashMap, FxHashSet};
 use netidx::subscriber::Value;
 use poolshark::local::LPooled;
-use std::{collections::hash_map::Entry, mem};
+use std::collections::hash_map::Entry;
 use triomphe::Arc as TArc;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -59,6 +59,32 @@ fn compile_apply_args<R: Rt, E: UserEvent>(
     Ok(res)
 }
 
+/// warn (or fail if warnings are errors) about a call to a function that
+/// throws `t` from a context where nothing will catch it
+fn warn_unhandled(
+    flags: BitFlags<CFlag>,
+    spec: &Expr,
+    t: &Type,
+    fspec: &Expr,
+) -> Result<()> {
+    if flags.contains(CFlag::WarnUnhandled | CFlag::WarningsAreErrors) {
+        bail!(
+            "ERROR: {} at {} error {} raised from function call {} will not be caught",
+            spec.ori,
+            spec.pos,
+            t,
+            fspec
+        )
+    }
+    if flags.contains(CFlag::WarnUnhandled) {
+        eprintln!(
+            "WARNING: {} at {} error {} raised from function call {} will not be caught",
+            spec.ori, spec.pos, t, fspec
+        )
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 pub(crate) struct CallSite<R: Rt, E: UserEvent> {
     pub(super) spec: TArc<Expr>,
@@ -238,24 +264,25 @@ impl<R: Rt, E: UserEvent> CallSite<R, E> {
         // since Constant only fires on init); existing args may not have
         // changed this cycle but their cached values must be visible to
         // the newly bound function body.
-        let prev_init = mem::replace(&mut event.init, true);
-        for arg in self.args.values_mut() {
-            if arg.is_default {
-                if let Some(ref mut node) = arg.node {
-                    if let Some(v) = node.update(ctx, event) {
-                        ctx.cached.insert(arg.id, v.clone());
-                        event.variables.insert(arg.id, v);
+        {
+            let mut event = event.init_scope(true);
+            for arg in self.args.values_mut() {
+                if arg.is_default {
+                    if let Some(ref mut node) = arg.node {
+                        if let Some(v) = node.update(ctx, &mut event) {
+                            ctx.cached.insert(arg.id, v.clone());
+                            event.variables.insert(arg.id, v);
+                            set.push(arg.id);
+                        }
+                    }
+                } else if let Entry::Vacant(e) = event.variables.entry(arg.id) {
+                    if let Some(v) = ctx.cached.get(&arg.id) {
+                        e.insert(v.clone());
                         set.push(arg.id);
                     }
                 }
-            } else if let Entry::Vacant(e) = event.variables.entry(arg.id) {
-                if let Some(v) = ctx.cached.get(&arg.id) {
-                    e.insert(v.clone());
-                    set.push(arg.id);
-                }
             }
         }
-        event.init = prev_init;
         let mut rf = (f.init)(
             &scope,
             ctx,
@@ -310,19 +337,20 @@ impl<R: Rt, E: UserEvent> Update<R, E> for CallSite<R, E> {
                 res
             }
             Some((_, f)) => {
-                let init = mem::replace(&mut event.init, true);
-                let mut refs = Refs::default();
-                f.refs(&mut refs);
-                refs.with_external_refs(|id| {
-                    if let Entry::Vacant(e) = event.variables.entry(id) {
-                        if let Some(v) = ctx.cached.get(&id) {
-                            e.insert(v.clone());
-                            set.push(id);
+                let res = {
+                    let mut event = event.init_scope(true);
+                    let mut refs = Refs::default();
+                    f.refs(&mut refs);
+                    refs.with_external_refs(|id| {
+                        if let Entry::Vacant(e) = event.variables.entry(id) {
+                            if let Some(v) = ctx.cached.get(&id) {
+                                e.insert(v.clone());
+                                set.push(id);
+                            }
                         }
-                    }
-                });
-                let res = f.update(ctx, &mut self.arg_refs, event);
-                event.init = init;
+                    });
+                    f.update(ctx, &mut self.arg_refs, &mut event)
+                };
                 for id in set.drain(..) {
                     event.variables.remove(&id);
                 }
@@ -493,8 +521,25 @@ impl<R: Rt, E: UserEvent> Update<R, E> for CallSite<R, E> {
         for (tv, tc) in ftype.constraints.read().iter() {
             wrap!(self, tc.check_contains(&ctx.env, &Type::TVar(tv.clone())))?;
         }
-        if let Some(t) = ftype.throws.with_deref(|t| t.cloned()) {
-            match ctx.env.lookup_catch(&self.scope.dynamic) {
+        match ftype.throws.with_deref(|t| t.cloned()) {
+            // the thrown type isn't known yet (e.g. it depends on a lambda
+            // that hasn't been checked), check again once it is
+            None if self.flags.contains(CFlag::WarnUnhandled)
+                && ctx.env.lookup_catch(&self.scope.dynamic).is_err() =>
+            {
+                let throws = ftype.throws.clone();
+                let flags = self.flags;
+                let spec = self.spec.clone();
+                let fspec = self.fnode.spec().clone();
+                ctx.deferred_checks.push(Box::new(move |_| {
+                    match throws.with_deref(|t| t.cloned()) {
+                        None | Some(Type::Bottom) => Ok(()),
+                        Some(t) => warn_unhandled(flags, &spec, &t, &fspec),
+                    }
+                }));
+            }
+            None => (),
+            Some(t) => match ctx.env.lookup_catch(&self.scope.dynamic) {
                 Ok(id) => {
                     if let Some(bind) = ctx.env.by_id.get(&id)
                         && let Type::TVar(tv) = &bind.typ
@@ -508,24 +553,8 @@ impl<R: Rt, E: UserEvent> Update<R, E> for CallSite<R, E> {
                     }
                 }
                 Err(_) if t == Type::Bottom => (), // it doesn't throw any errors
-                Err(_) => {
-                    if self
-                        .flags
-                        .contains(CFlag::WarnUnhandled | CFlag::WarningsAreErrors)
-                    {
-                        bail!(
-                            "ERROR: {} at {} error {} raised from function call {} will not be caught",
-                            self.spec.ori, self.spec.pos, t, self.fnode.spec()
-                        )
-                    }
-                    if self.flags.contains(CFlag::WarnUnhandled) {
-                        eprintln!(
-                            "WARNING: {} at {} error {} raised from function call {} will not be caught",
-                            self.spec.ori, self.spec.pos, t, self.fnode.spec()
-                        )
-                    }
-                }
-            }
+                Err(_) => warn_unhandled(self.flags, &self.spec, &t, self.fnode.spec())?,
+            },
         }
         wrap!(self.fnode, self.rtype.check_contains(&ctx.env, &ftype.rtype))?;
         if !ftype.lambda_ids.read().is_empty() {
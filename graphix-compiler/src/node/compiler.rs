@@ -18,6 +18,7 @@ use crate::{
     },
     node::{
         error::OrNever,
+        inline,
         map::{Map, MapRef},
         op::{CheckedAdd, CheckedDiv, CheckedMod, CheckedMul, CheckedSub},
         ExplicitParens, Nop,
@@ -35,6 +36,36 @@ pub(crate) fn compile<R: Rt, E: UserEvent>(
     spec: Expr,
     scope: &Scope,
     top_id: ExprId,
+) -> Result<Node<R, E>> {
+    if ctx.depth >= ctx.max_depth {
+        bail!("at {} maximum nesting depth {} exceeded", spec.pos, ctx.max_depth)
+    }
+    ctx.depth += 1;
+    let res = compile_int(ctx, flags, spec, scope, top_id);
+    ctx.depth -= 1;
+    res
+}
+
+/// check the type references in a type annotation written at spec, so that an
+/// undefined type, or a type given the wrong number of parameters, is reported
+/// at the location that references it.
+pub(crate) fn check_type_refs<R: Rt, E: UserEvent>(
+    ctx: &ExecCtx<R, E>,
+    spec: &Expr,
+    typ: &Type,
+) -> Result<()> {
+    match typ.check_refs(&ctx.env) {
+        Ok(()) => Ok(()),
+        Err(e) => bail!("at {} {e}", spec.pos),
+    }
+}
+
+fn compile_int<R: Rt, E: UserEvent>(
+    ctx: &mut ExecCtx<R, E>,
+    flags: BitFlags<CFlag>,
+    spec: Expr,
+    scope: &Scope,
+    top_id: ExprId,
 ) -> Result<Node<R, E>> {
     match &spec.kind {
         ExprKind::NoOp => Ok(Nop::new(Type::Bottom)),
@@ -44,7 +75,21 @@ pub(crate) fn compile<R: Rt, E: UserEvent>(
         ExprKind::Constant(v) => Constant::compile(spec.clone(), v),
         ExprKind::Do { exprs } => {
             let scope = scope.append(&format_compact!("do{}", spec.id.inner()));
-            Block::compile(ctx, flags, spec.clone(), &scope, top_id, false, exprs)
+            let inlined = if flags.contains(CFlag::InlineLambdas) {
+                inline::inline_block(exprs)
+            } else {
+                None
+            };
+            match inlined {
+                None => {
+                    Block::compile(ctx, flags, spec.clone(), &scope, top_id, false, exprs)
+                }
+                Some(exprs) => {
+                    let kind = ExprKind::Do { exprs: exprs.clone() };
+                    let spec = Expr { kind, ..spec.clone() };
+                    Block::compile(ctx, flags, spec, &scope, top_id, false, &exprs)
+                }
+            }
         }
         ExprKind::Array { args } => {
             Array::compile(ctx, flags, spec.clone(), scope, top_id, args)
@@ -10,6 +10,7 @@ use anyhow::{bail, Result};
 use arcstr::ArcStr;
 use enumflags2::BitFlags;
 use netidx_value::{ValArray, Value};
+use poolshark::local::LPooled;
 use smallvec::SmallVec;
 use std::iter;
 use triomphe::Arc;
@@ -496,9 +497,11 @@ impl<R: Rt, E: UserEvent> Update<R, E> for Variant<R, E> {
         } else {
             let (updated, determined) = update_args!(self.n, ctx, event);
             if updated && determined {
-                let a = iter::once(Value::String(self.tag.clone()))
-                    .chain(self.n.iter().map(|n| n.cached.clone().unwrap()));
-                Some(Value::Array(ValArray::from_iter(a)))
+                let mut a: LPooled<Vec<Value>> =
+                    iter::once(Value::String(self.tag.clone()))
+                        .chain(self.n.iter().map(|n| n.cached.clone().unwrap()))
+                        .collect();
+                Some(Value::Array(ValArray::from_iter_exact(a.drain(..))))
             } else {
                 None
             }
@@ -3,7 +3,7 @@ use crate::{
     deref_typ,
     env::Env,
     expr::{self, Expr, ExprId, ModPath},
-    format_with_flags,
+    format_with_flags, rtlog,
     typ::Type,
     wrap, BindId, CFlag, Event, ExecCtx, Node, PrintFlag, Refs, Rt, Scope, Update,
     UserEvent,
@@ -12,6 +12,7 @@ use anyhow::{anyhow, bail, Result};
 use arcstr::{literal, ArcStr};
 use compact_str::format_compact;
 use enumflags2::BitFlags;
+use log::Level;
 use netidx_value::{Typ, Value};
 use poolshark::local::LPooled;
 use std::{collections::hash_map::Entry, sync::LazyLock};
@@ -200,7 +201,9 @@ impl<R: Rt, E: UserEvent> Update<R, E> for Qop<R, E> {
                     None
                 }
                 None => {
-                    log::error!(
+                    rtlog!(
+                        ctx,
+                        Level::Error,
                         "unhandled error in {} at {} {e}",
                         self.spec.ori,
                         self.spec.pos
@@ -334,7 +337,13 @@ impl<R: Rt, E: UserEvent> Update<R, E> for OrNever<R, E> {
         match self.n.update(ctx, event) {
             None => None,
             Some(Value::Error(e)) => {
-                log::warn!("ignored error in {} at {} {e}", self.spec.ori, self.spec.pos);
+                rtlog!(
+                    ctx,
+                    Level::Warn,
+                    "ignored error in {} at {} {e}",
+                    self.spec.ori,
+                    self.spec.pos
+                );
                 None
             }
             Some(v) => Some(v),
@@ -50,7 +50,8 @@ pub fn constant<R: Rt, E: UserEvent>(v: Value) -> Node<R, E> {
     })
 }
 
-/// generate and return an apply node for the given lambda
+/// generate and return an apply node for the given lambda. args are given
+/// in signature order, labeled arguments are passed by label.
 pub fn apply<R: Rt, E: UserEvent>(
     fnode: Node<R, E>,
     scope: Scope,
@@ -60,14 +61,19 @@ pub fn apply<R: Rt, E: UserEvent>(
 ) -> Node<R, E> {
     let ftype = typ.reset_tvars();
     ftype.alias_tvars(&mut LPooled::take());
+    let mut pos = 0;
     let args: FxHashMap<ArgKey, Arg<R, E>> = args
         .into_iter()
         .enumerate()
         .map(|(i, node)| {
-            (
-                ArgKey::Positional(i),
-                Arg { id: BindId::new(), node: Some(node), is_default: false },
-            )
+            let key = match typ.args.get(i).and_then(|a| a.label.as_ref()) {
+                Some((name, _)) => ArgKey::Named(name.clone()),
+                None => {
+                    pos += 1;
+                    ArgKey::Positional(pos - 1)
+                }
+            };
+            (key, Arg { id: BindId::new(), node: Some(node), is_default: false })
         })
         .collect();
     Box::new(CallSite {
@@ -0,0 +1,244 @@
+//! Inline small lambdas at their only call site, see `CFlag::InlineLambdas`
+use crate::expr::{ApplyExpr, BindExpr, Expr, ExprKind, LambdaExpr};
+use arcstr::ArcStr;
+use fxhash::FxHashSet;
+use netidx::{path::Path, utils::Either};
+use triomphe::Arc;
+
+/// lambdas whose bodies contain more than this many expressions are not
+/// inlined
+pub(crate) const INLINE_MAX_SIZE: usize = 16;
+
+fn walk<'a>(e: &'a Expr, f: &mut impl FnMut(&'a Expr)) {
+    f(e);
+    e.with_children(&mut |c| walk(c, f))
+}
+
+fn size(e: &Expr) -> usize {
+    let mut n = 0;
+    walk(e, &mut |_| n += 1);
+    n
+}
+
+/// every name referenced or connected to in e, including each part of module
+/// paths. This is deliberately conservative, names bound inside e are
+/// included as well.
+fn names<'a>(e: &'a Expr, res: &mut FxHashSet<&'a str>) {
+    walk(e, &mut |e| match &e.kind {
+        ExprKind::Ref { name } | ExprKind::Connect { name, .. } => {
+            res.extend(Path::parts(&name.0))
+        }
+        _ => (),
+    })
+}
+
+/// the number of times the variable name is referenced or connected to in e
+fn uses(e: &Expr, name: &str) -> usize {
+    let mut n = 0;
+    walk(e, &mut |e| match &e.kind {
+        ExprKind::Ref { name: p } | ExprKind::Connect { name: p, .. }
+            if Path::parts(&p.0).any(|s| s == name) =>
+        {
+            n += 1
+        }
+        _ => (),
+    });
+    n
+}
+
+/// if e is `let name = |args| body` with a lambda we know how to inline
+/// return it's name and definition
+fn inlinable(e: &Expr) -> Option<(&ArcStr, &LambdaExpr, &Expr)> {
+    let b = match &e.kind {
+        ExprKind::Bind(b) if !b.rec && b.typ.is_none() => b,
+        _ => return None,
+    };
+    let name = b.pattern.single_bind()?;
+    let l = match &b.value.kind {
+        ExprKind::Lambda(l) => l,
+        _ => return None,
+    };
+    let body = match &l.body {
+        Either::Left(body) => body,
+        Either::Right(_) => return None,
+    };
+    let simple = l.vargs.is_none()
+        && l.rtype.is_none()
+        && l.throws.is_none()
+        && l.constraints.is_empty()
+        && l.args.iter().all(|a| a.labeled.is_none())
+        && size(body) <= INLINE_MAX_SIZE;
+    simple.then_some((name, &**l, body))
+}
+
+/// if e calls name, either directly or as the value of a bind or connect,
+/// return the call
+fn call_of<'a>(e: &'a Expr, name: &str) -> Option<&'a ApplyExpr> {
+    let call = match &e.kind {
+        ExprKind::Bind(b) if !b.rec => &b.value,
+        ExprKind::Connect { value, .. } => &**value,
+        _ => e,
+    };
+    match &call.kind {
+        ExprKind::Apply(a) => match &a.function.kind {
+            ExprKind::Ref { name: f } if *f == [name] => Some(a),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// replace the call in e, as found by `call_of`, with block
+fn replace_call(e: &Expr, block: Expr) -> Expr {
+    let kind = match &e.kind {
+        ExprKind::Bind(b) => {
+            ExprKind::Bind(Arc::new(BindExpr { value: block, ..(**b).clone() }))
+        }
+        ExprKind::Connect { name, deref, .. } => ExprKind::Connect {
+            name: name.clone(),
+            value: Arc::new(block),
+            deref: *deref,
+        },
+        _ => return block,
+    };
+    Expr { kind, ..e.clone() }
+}
+
+/// try to inline the lambda bound by exprs[i], return the new block if it was
+/// inlined
+fn inline_at(exprs: &[Expr], i: usize) -> Option<Vec<Expr>> {
+    let (name, l, body) = inlinable(&exprs[i])?;
+    let rest = &exprs[i + 1..];
+    if rest.iter().map(|e| uses(e, name)).sum::<usize>() != 1 {
+        return None;
+    }
+    let j = rest.iter().position(|e| uses(e, name) == 1)?;
+    let call = call_of(&rest[j], name)?;
+    if call.args.len() != l.args.len() || call.args.iter().any(|(n, _)| n.is_some()) {
+        return None;
+    }
+    // nothing between the definition and the call may shadow a variable the
+    // body captures, or change how names and types resolve
+    let mut captured = FxHashSet::default();
+    names(body, &mut captured);
+    for e in &rest[..j] {
+        match &e.kind {
+            ExprKind::Use { .. } | ExprKind::Module { .. } | ExprKind::TypeDef(_) => {
+                return None
+            }
+            ExprKind::Bind(b) => {
+                let mut shadowed = false;
+                b.pattern.with_names(&mut |n| shadowed |= captured.contains(&**n));
+                if shadowed {
+                    return None;
+                }
+            }
+            _ => (),
+        }
+    }
+    // the arguments are bound in order, so they may not reference any of the
+    // parameters
+    let mut params = FxHashSet::default();
+    for a in l.args.iter() {
+        a.pattern.with_names(&mut |n| {
+            params.insert(&**n);
+        });
+    }
+    let mut referenced = FxHashSet::default();
+    for (_, a) in call.args.iter() {
+        names(a, &mut referenced);
+    }
+    if !params.is_disjoint(&referenced) {
+        return None;
+    }
+    let binds = l.args.iter().zip(call.args.iter()).map(|(a, (_, v))| {
+        let bind = BindExpr {
+            annotations: Arc::from_iter([]),
+            rec: false,
+            pattern: a.pattern.clone(),
+            typ: a.constraint.clone(),
+            value: v.clone(),
+        };
+        ExprKind::Bind(Arc::new(bind)).to_expr(v.pos)
+    });
+    let exprs_ = Arc::from_iter(binds.chain([body.clone()]));
+    let block = ExprKind::Do { exprs: exprs_ }.to_expr(body.pos);
+    let mut res = Vec::with_capacity(exprs.len() - 1);
+    res.extend(exprs[..i].iter().cloned());
+    res.extend(rest[..j].iter().cloned());
+    res.push(replace_call(&rest[j], block));
+    res.extend(rest[j + 1..].iter().cloned());
+    Some(res)
+}
+
+/// Inline each lambda in the block that is small, not recursive, and called
+/// from exactly one place that is known at compile time. The call is
+/// replaced by a block binding the arguments followed by the lambda body,
+/// and the binding of the lambda is removed. Returns None if nothing was
+/// inlined.
+pub(crate) fn inline_block(exprs: &[Expr]) -> Option<Arc<[Expr]>> {
+    let mut cur: Option<Vec<Expr>> = None;
+    let mut i = 0;
+    loop {
+        let block = cur.as_deref().unwrap_or(exprs);
+        if i >= block.len() {
+            break;
+        }
+        match inline_at(block, i) {
+            Some(b) => cur = Some(b),
+            None => i += 1,
+        }
+    }
+    cur.map(Arc::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::expr::parser::parse_one;
+
+    fn calls(e: &Expr) -> usize {
+        let mut n = 0;
+        walk(e, &mut |e| {
+            if let ExprKind::Apply(_) = &e.kind {
+                n += 1
+            }
+        });
+        n
+    }
+
+    fn inline(s: &str) -> Option<Arc<[Expr]>> {
+        match &parse_one(s).unwrap().kind {
+            ExprKind::Do { exprs } => inline_block(exprs),
+            _ => panic!("expected a block"),
+        }
+    }
+
+    #[test]
+    fn inline_predicate() {
+        let res = inline("{ let y = 2; let f = |x| x > y; let z = f(3); z }");
+        let res = res.expect("expected f to be inlined");
+        assert_eq!(res.len(), 3);
+        assert!(res.iter().all(|e| calls(e) == 0));
+        assert!(res.iter().all(|e| uses(e, "f") == 0));
+    }
+
+    #[test]
+    fn no_inline() {
+        // recursive
+        let res = inline("{ let rec f = |x| select x { 0 => 0, x => f(x - 1) }; f(3) }");
+        assert!(res.is_none());
+        // called twice
+        let res = inline("{ let f = |x| x + 1; f(1) + f(2) }");
+        assert!(res.is_none());
+        // late bound
+        let res = inline("{ let f = |x| x + 1; f <- |x| x + 2; f(1) }");
+        assert!(res.is_none());
+        // captured variable shadowed before the call
+        let res = inline("{ let y = 1; let f = |x| x + y; let y = 2; f(0) }");
+        assert!(res.is_none());
+        // argument references a parameter
+        let res = inline("{ let x = 1; let f = |y, x| x + y; f(x, 2) }");
+        assert!(res.is_none());
+    }
+}
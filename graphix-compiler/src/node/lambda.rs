@@ -1,11 +1,15 @@
-use super::{compiler::compile, Nop};
+use super::{
+    compiler::{check_type_refs, compile},
+    Nop,
+};
 use crate::{
     env::{Bind, Env},
     expr::{self, Arg, ErrorContext, Expr, ExprId},
+    format_with_flags,
     node::pattern::StructPatternNode,
     typ::{FnArgType, FnType, Type},
-    wrap, Apply, BindId, CFlag, Event, ExecCtx, InitFn, LambdaId, Node, Refs, Rt, Scope,
-    TypecheckPhase, Update, UserEvent,
+    wrap, Apply, BindId, CFlag, Event, ExecCtx, InitFn, LambdaId, Node, PrintFlag, Refs,
+    Rt, Scope, TypecheckPhase, Update, UserEvent,
 };
 use anyhow::{anyhow, bail, Context, Result};
 use arcstr::ArcStr;
@@ -116,7 +120,16 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for GXLambda<R, E> {
             wrap!(arg, typ.check_contains(&ctx.env, &arg.typ()))?;
         }
         wrap!(self.body, self.body.typecheck(ctx))?;
-        wrap!(self.body, self.typ.rtype.check_contains(&ctx.env, &self.body.typ()))?;
+        let rtype = &self.typ.rtype;
+        let btype = self.body.typ();
+        if let Err(e) = rtype.check_contains(&ctx.env, btype) {
+            let e = format_with_flags(PrintFlag::DerefTVars, || {
+                e.context(format_compact!(
+                    "the lambda body returns {btype}, which is not contained by its return type {rtype}"
+                ))
+            });
+            return wrap!(self.body, Err(e));
+        }
         for (tv, tc) in self.typ.constraints.read().iter() {
             tc.check_contains(&ctx.env, &Type::TVar(tv.clone()))?
         }
@@ -310,6 +323,16 @@ impl Lambda {
                 Ok((tv, tc))
             })
             .collect::<Result<LPooled<Vec<_>>>>()?;
+        let annotations = vargs
+            .iter()
+            .flatten()
+            .chain(rtype.iter())
+            .chain(throws.iter())
+            .chain(argspec.iter().filter_map(|a| a.constraint.as_ref()))
+            .chain(constraints.iter().map(|(_, tc)| tc));
+        for typ in annotations {
+            check_type_refs(ctx, &spec, typ)?
+        }
         let constraints = Arc::new(RwLock::new(constraints));
         let original_scope = scope.clone();
         let _original_scope = scope.clone();
@@ -466,6 +489,7 @@ impl<R: Rt, E: UserEvent> Update<R, E> for Lambda {
             faux_id,
             Bind {
                 doc: None,
+                annotations: Arc::from_iter([]),
                 export: false,
                 id: faux_id,
                 name: "faux".into(),
@@ -499,6 +523,14 @@ impl<R: Rt, E: UserEvent> Update<R, E> for Lambda {
                 .normalize();
             ftyp.throws
                 .check_contains(&ctx.env, &inferred_throws)
+                .with_context(|| {
+                    format_with_flags(PrintFlag::DerefTVars, || {
+                        format_compact!(
+                            "the lambda body throws {inferred_throws}, which is not contained by its throws type {}",
+                            ftyp.throws
+                        )
+                    })
+                })
                 .with_context(|| ErrorContext(Update::<R, E>::spec(self).clone()))?;
             ftyp.constrain_known();
             Ok(())
@@ -1,11 +1,12 @@
 use crate::{
     env,
-    expr::{Expr, ExprId, ExprKind, ModPath},
+    expr::{BindExpr, Expr, ExprId, ExprKind, ModPath},
     typ::{TVal, TVar, Type},
     BindId, CFlag, Event, ExecCtx, Node, Refs, Rt, Scope, Update, UserEvent, CAST_ERR,
 };
 use anyhow::{anyhow, bail, Context, Result};
 use arcstr::{literal, ArcStr};
+use bind::Bind;
 use compiler::compile;
 use enumflags2::BitFlags;
 use netidx_value::{Typ, Value};
@@ -19,6 +20,7 @@ pub(crate) mod compiler;
 pub(crate) mod data;
 pub(crate) mod error;
 pub mod genn;
+pub(crate) mod inline;
 pub mod lambda;
 pub(crate) mod map;
 pub(crate) mod module;
@@ -269,7 +271,7 @@ impl TypeDef {
         spec: Expr,
         scope: &Scope,
         name: &ArcStr,
-        params: &Arc<[(TVar, Option<Type>)]>,
+        params: &Arc<[(TVar, Option<Type>, Option<Type>)]>,
         typ: &Type,
     ) -> Result<Node<R, E>> {
         let typ = typ.scope_refs(&scope.lexical);
@@ -380,11 +382,37 @@ impl<R: Rt, E: UserEvent> Block<R, E> {
         module: bool,
         exprs: &Arc<[Expr]>,
     ) -> Result<Node<R, E>> {
-        let children = exprs
-            .iter()
-            .map(|e| compile(ctx, flags, e.clone(), scope, top_id))
-            .collect::<Result<Box<[Node<R, E>]>>>()?;
-        Ok(Box::new(Self { module, spec, children }))
+        fn rec_bind(e: &Expr) -> Option<&BindExpr> {
+            match &e.kind {
+                ExprKind::Bind(b) if b.rec => Some(b),
+                _ => None,
+            }
+        }
+        let mut children: Vec<Node<R, E>> = Vec::with_capacity(exprs.len());
+        let mut i = 0;
+        while i < exprs.len() {
+            // declare every name in a run of let recs before compiling any
+            // of their bodies so that they may be mutually recursive
+            let n = exprs[i..].iter().take_while(|e| rec_bind(e).is_some()).count();
+            if n == 0 {
+                children.push(compile(ctx, flags, exprs[i].clone(), scope, top_id)?);
+                i += 1;
+                continue;
+            }
+            let group = &exprs[i..i + n];
+            let decls = group
+                .iter()
+                .map(|e| Bind::declare_rec(ctx, e, scope, rec_bind(e).unwrap()))
+                .collect::<Result<Vec<_>>>()?;
+            for (e, decl) in group.iter().zip(decls) {
+                let b = rec_bind(e).unwrap();
+                let node =
+                    Bind::compile_rec(ctx, flags, e.clone(), scope, top_id, b, decl)?;
+                children.push(node);
+            }
+            i += n;
+        }
+        Ok(Box::new(Self { module, spec, children: Box::from(children) }))
     }
 }
 
@@ -690,6 +718,7 @@ impl<R: Rt, E: UserEvent> TypeCast<R, E> {
     ) -> Result<Node<R, E>> {
         let n = compile(ctx, flags, expr.clone(), scope, top_id)?;
         let target = typ.scope_refs(&scope.lexical);
+        compiler::check_type_refs(ctx, &spec, &target)?;
         if let Err(e) = target.check_cast(&ctx.env) {
             bail!("in cast at {} {e}", spec.pos);
         }
@@ -700,7 +729,7 @@ impl<R: Rt, E: UserEvent> TypeCast<R, E> {
 
 impl<R: Rt, E: UserEvent> Update<R, E> for TypeCast<R, E> {
     fn update(&mut self, ctx: &mut ExecCtx<R, E>, event: &mut Event<E>) -> Option<Value> {
-        self.n.update(ctx, event).map(|v| self.target.cast_value(&ctx.env, v))
+        self.n.update(ctx, event).map(|v| ctx.cast_value(&self.target, v))
     }
 
     fn spec(&self) -> &Expr {
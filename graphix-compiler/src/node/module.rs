@@ -1,6 +1,6 @@
 use crate::{
     compiler::compile,
-    env::Env,
+    env::{scope_params, Env},
     errf,
     expr::{
         parser, BindSig, Doc, Expr, ExprId, ExprKind, ModPath, Origin, Sandbox, Sig,
@@ -134,9 +134,13 @@ fn check_sig<R: Rt, E: UserEvent>(
             };
             match &sig_td.typ {
                 Type::Abstract { id, params: _ } => {
-                    for (tv0, con0) in td.params.iter() {
-                        match sig_td.params.iter().find(|(tv1, _)| tv0.name == tv1.name) {
-                            Some((_, con1)) if con0 != con1 => {
+                    for (tv0, con0, _) in td.params.iter() {
+                        match sig_td
+                            .params
+                            .iter()
+                            .find(|(tv1, _, _)| tv0.name == tv1.name)
+                        {
+                            Some((_, con1, _)) if con0 != con1 => {
                                 let con0 = match con0 {
                                     None => "missing",
                                     Some(t) => &format_compact!("{t}"),
@@ -159,7 +163,7 @@ fn check_sig<R: Rt, E: UserEvent>(
                 }
                 _ => {
                     if sig_td.name != td.name
-                        || sig_td.params != td.params
+                        || *sig_td.params != *scope_params(&td.params, &scope.lexical)
                         || sig_td.typ != td.typ.scope_refs(&scope.lexical)
                     {
                         bail!(
@@ -357,10 +361,6 @@ impl<R: Rt, E: UserEvent> Update<R, E> for Module<R, E> {
             }
             compiled = true;
         }
-        let init = event.init;
-        if compiled {
-            event.init = true;
-        }
         for (inner_id, proxy_id) in &self.proxy {
             if let Some(v) = event.variables.get(proxy_id) {
                 let v = v.clone();
@@ -368,8 +368,10 @@ impl<R: Rt, E: UserEvent> Update<R, E> for Module<R, E> {
                 ctx.cached.insert(*inner_id, v);
             }
         }
-        self.nodes.iter_mut().fold(None, |_, n| n.update(ctx, event));
-        event.init = init;
+        let init = event.init || compiled;
+        self.nodes
+            .iter_mut()
+            .fold(None, |_, n| n.update(ctx, &mut event.init_scope(init)));
         for (inner_id, proxy_id) in &self.proxy {
             if let Some(v) = event.variables.remove(inner_id) {
                 event.variables.insert(*proxy_id, v.clone());
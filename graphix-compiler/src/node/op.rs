@@ -1,14 +1,20 @@
 use super::{compiler::compile, CFlag, Cached};
 use crate::{
     defetyp,
+    env::Env,
     expr::{Expr, ExprId},
-    typ::Type,
+    rtlog,
+    typ::{
+        units::{self, Unit},
+        Type,
+    },
     wrap, Event, ExecCtx, Node, Refs, Rt, Scope, Update, UserEvent,
 };
 use anyhow::{bail, Result};
 use arcstr::ArcStr;
 use compact_str::format_compact;
 use enumflags2::BitFlags;
+use log::Level;
 use netidx_value::{Typ, ValArray, Value};
 use std::fmt;
 use triomphe::Arc;
@@ -283,6 +289,147 @@ impl fmt::Display for Op {
     }
 }
 
+fn is_finite(v: &Value) -> bool {
+    match v {
+        Value::F32(f) => f.is_finite(),
+        Value::F64(f) => f.is_finite(),
+        _ => true,
+    }
+}
+
+/// Decimal arithmetic is done here rather than by Value so that it is
+/// always exact, never going through a float, and so that overflow
+/// and division by zero are always arithmetic errors.
+fn decimal_arith(op: Op, lhs: &Value, rhs: &Value) -> Option<Value> {
+    let (l, r) = match (lhs, rhs) {
+        (Value::Decimal(l), Value::Decimal(r)) => (**l, **r),
+        _ => return None,
+    };
+    let res = match op.base_op() {
+        Op::Add => l.checked_add(r),
+        Op::Sub => l.checked_sub(r),
+        Op::Mul => l.checked_mul(r),
+        Op::Div | Op::Mod if r.is_zero() => {
+            return Some(Value::error("division by zero"));
+        }
+        Op::Div => l.checked_div(r),
+        Op::Mod => l.checked_rem(r),
+        _ => unreachable!(),
+    };
+    Some(match res {
+        Some(d) => Value::Decimal(Arc::new(d)),
+        None => Value::error("decimal overflow"),
+    })
+}
+
+/// Integer addition, subtraction, and multiplication of two numbers of the
+/// same type are done here rather than by Value so that overflow is always
+/// an arithmetic error, and never wraps around.
+fn int_arith(op: Op, lhs: &Value, rhs: &Value) -> Option<Value> {
+    macro_rules! checked {
+        ($($typ:ident),+) => {
+            match (lhs, rhs) {
+                $((Value::$typ(l), Value::$typ(r)) => {
+                    let (res, name) = match op.base_op() {
+                        Op::Add => (l.checked_add(*r), "add"),
+                        Op::Sub => (l.checked_sub(*r), "subtract"),
+                        Op::Mul => (l.checked_mul(*r), "multiply"),
+                        _ => return None,
+                    };
+                    Some(match res {
+                        Some(v) => Value::$typ(v),
+                        None => {
+                            let e = format_compact!("attempt to {name} with overflow");
+                            Value::error(e.as_str())
+                        }
+                    })
+                })+
+                _ => None,
+            }
+        };
+    }
+    checked!(U8, I8, U16, I16, U32, V32, I32, Z32, U64, V64, I64, Z64)
+}
+
+/// How arithmetic on quantities is done, see typ::units
+#[derive(Debug, Clone)]
+struct Quantities {
+    lhs: bool,
+    rhs: bool,
+    /// the unit of the result, or None if it is a plain number
+    tag: Option<ArcStr>,
+}
+
+impl Quantities {
+    /// If either side is a quantity check that op may be applied to them,
+    /// and return the type of the result
+    fn typecheck(
+        env: &Env,
+        op: Op,
+        lhs: &Type,
+        rhs: &Type,
+    ) -> Result<Option<(Self, Type)>> {
+        let (lq, rq) = (units::quantity(env, lhs), units::quantity(env, rhs));
+        let q = match lq.as_ref().or(rq.as_ref()) {
+            None => return Ok(None),
+            Some(q) => q,
+        };
+        let number = Type::Primitive(Typ::number());
+        let (lunit, lt) = match &lq {
+            Some(q) => (Some(Unit::parse(&q.tag)), q.typ.clone()),
+            None => {
+                number.check_contains(env, lhs)?;
+                (None, lhs.clone())
+            }
+        };
+        let (runit, rt) = match &rq {
+            Some(q) => (Some(Unit::parse(&q.tag)), q.typ.clone()),
+            None => {
+                number.check_contains(env, rhs)?;
+                (None, rhs.clone())
+            }
+        };
+        let unit = match (op.base_op(), &lunit, &runit) {
+            (Op::Add | Op::Sub | Op::Mod, Some(l), Some(r)) if l == r => Some(l.clone()),
+            (Op::Add | Op::Sub | Op::Mod, _, _) => {
+                bail!("can't perform {lhs} {op} {rhs}, the units are different")
+            }
+            (Op::Mul, Some(l), Some(r)) => Some(l.mul(r)),
+            (Op::Div, Some(l), Some(r)) => Some(l.div(r)),
+            (Op::Mul | Op::Div, Some(u), None) | (Op::Mul, None, Some(u)) => {
+                Some(u.clone())
+            }
+            (Op::Div, None, Some(u)) => Some(Unit::parse("").div(u)),
+            (_, _, _) => unreachable!(),
+        };
+        let tag = unit.and_then(|u| u.tag());
+        let t = lt.union(env, &rt)?;
+        let typ = match &tag {
+            Some(tag) => q.typ(tag.clone(), t),
+            None => t,
+        };
+        Ok(Some((Self { lhs: lq.is_some(), rhs: rq.is_some(), tag }, typ)))
+    }
+
+    /// the number carried by v if it is a quantity
+    fn number(quantity: bool, v: &Value) -> Option<Value> {
+        match v {
+            Value::Array(a) if quantity && a.len() == 2 => Some(a[1].clone()),
+            _ if quantity => None,
+            v => Some(v.clone()),
+        }
+    }
+
+    fn wrap(&self, v: Value) -> Value {
+        match &self.tag {
+            None => v,
+            Some(tag) => {
+                Value::Array(ValArray::from_iter([Value::String(tag.clone()), v]))
+            }
+        }
+    }
+}
+
 defetyp!(ARITH_ERR, ARITH_ERR_TAG, "ArithError", "Error<`{}(string)>");
 
 macro_rules! arith_op {
@@ -293,6 +440,8 @@ macro_rules! arith_op {
             typ: Type,
             lhs: Cached<R, E>,
             rhs: Cached<R, E>,
+            float_errors: bool,
+            quantities: Option<Quantities>,
         }
 
         impl<R: Rt, E: UserEvent> $name<R, E> {
@@ -308,7 +457,8 @@ macro_rules! arith_op {
                 let lhs = Cached::new(compile(ctx, flags, lhs.clone(), scope, top_id)?);
                 let rhs = Cached::new(compile(ctx, flags, rhs.clone(), scope, top_id)?);
                 let typ = Type::empty_tvar();
-                Ok(Box::new(Self { spec, typ, lhs, rhs }))
+                let float_errors = flags.contains(CFlag::FloatErrors);
+                Ok(Box::new(Self { spec, typ, lhs, rhs, float_errors, quantities: None }))
             }
         }
 
@@ -319,7 +469,27 @@ macro_rules! arith_op {
                 let lhs = self.lhs.cached.as_ref()?;
                 let rhs = self.rhs.cached.as_ref()?;
                 if lhs_up || rhs_up {
-                    let result = lhs.clone() $op rhs.clone();
+                    let (l, r) = match &self.quantities {
+                        None => (lhs.clone(), rhs.clone()),
+                        Some(q) => (
+                            Quantities::number(q.lhs, lhs)?,
+                            Quantities::number(q.rhs, rhs)?,
+                        ),
+                    };
+                    let v = match decimal_arith($opn, &l, &r) {
+                        Some(v) => v,
+                        None => match int_arith($opn, &l, &r) {
+                            Some(v) => v,
+                            None => l $op r,
+                        },
+                    };
+                    let result = match v {
+                        v if (self.float_errors || ctx.float_errors()) && !is_finite(&v) => {
+                            let e = format_compact!("{lhs} {} {rhs} is {v}", $opn);
+                            Value::error(e.as_str())
+                        }
+                        v => v,
+                    };
                     match result {
                         Value::Error(e) if $checked => {
                             let tag = Value::String(ARITH_ERR_TAG.clone());
@@ -328,11 +498,14 @@ macro_rules! arith_op {
                             Some(Value::Error(Arc::new(var)))
                         }
                         Value::Error(e) => {
-                            log::error!("arith error in {} at {} {e}", self.spec.ori, self.spec.pos);
+                            rtlog!(ctx, Level::Error, "arith error in {} at {} {e}", self.spec.ori, self.spec.pos);
                             eprintln!("arith error in {} at {} {e}", self.spec.ori, self.spec.pos);
                             None
                         }
-                        v => Some(v)
+                        v => match &self.quantities {
+                            Some(q) => Some(q.wrap(v)),
+                            None => Some(v),
+                        }
                     }
                 } else {
                     None
@@ -372,6 +545,17 @@ macro_rules! arith_op {
                     (Some(t), None) => { let _ = rhs.contains(&ctx.env, &t); }
                     (None, Some(t)) => { let _ = lhs.contains(&ctx.env, &t); },
                 }
+                let quantities = wrap!(self, Quantities::typecheck(&ctx.env, $opn, lhs, rhs))?;
+                if let Some((q, ut)) = quantities {
+                    self.quantities = Some(q);
+                    let ut = if $checked {
+                        Type::Set(Arc::from_iter([ut, ARITH_ERR.clone()]))
+                    } else {
+                        ut
+                    };
+                    return wrap!(self, self.typ.check_contains(&ctx.env, &ut));
+                }
+                self.quantities = None;
                 // init types that aren't known by now to Number
                 let typ = Type::Primitive(Typ::number());
                 wrap!(self.lhs.node, typ.contains(&ctx.env, lhs))?;
@@ -10,6 +10,8 @@ use anyhow::{anyhow, bail, Result};
 use arcstr::ArcStr;
 use enumflags2::BitFlags;
 use netidx::{publisher::Typ, subscriber::Value};
+use netidx_value::ValArray;
+use poolshark::local::LPooled;
 use smallvec::SmallVec;
 use std::fmt::Debug;
 use triomphe::Arc;
@@ -37,6 +39,8 @@ pub enum StructPatternNode {
     Struct {
         all: Option<BindId>,
         binds: Box<[(ArcStr, usize, StructPatternNode)]>,
+        /// the binding for the fields not matched by binds and their indexes
+        rest: Option<(BindId, Box<[usize]>)>,
     },
     Variant {
         tag: ArcStr,
@@ -208,7 +212,7 @@ impl StructPatternNode {
                     })?,
                 }
             }
-            StructurePattern::Struct { exhaustive, all, binds } => {
+            StructurePattern::Struct { exhaustive, all, binds, rest } => {
                 struct Ifo {
                     name: ArcStr,
                     index: usize,
@@ -272,7 +276,18 @@ impl StructPatternNode {
                                 ))
                             })
                             .collect::<Result<Box<[(ArcStr, usize, Self)]>>>()?;
-                        Self::Struct { all, binds }
+                        let rest = rest.as_ref().map(|n| {
+                            let (idx, typs): (Vec<_>, Vec<_>) = elts
+                                .iter()
+                                .enumerate()
+                                .filter(|(i, _)| binds.iter().all(|(_, j, _)| i != j))
+                                .map(|(i, t)| (i, t.clone()))
+                                .unzip();
+                            let typ = Type::Struct(Arc::from_iter(typs));
+                            let id = ctx.env.bind_variable(&scope.lexical, n, typ).id;
+                            (id, Box::from(idx))
+                        });
+                        Self::Struct { all, binds, rest }
                     }
                     _ => format_with_flags(PrintFlag::DerefTVars, || {
                         bail!("struct patterns can't match {type_predicate}")
@@ -325,13 +340,16 @@ impl StructPatternNode {
                     n.ids(f)
                 }
             }
-            Self::Struct { all, binds } => {
+            Self::Struct { all, binds, rest } => {
                 if let Some(id) = all {
                     f(*id)
                 }
                 for (_, _, n) in binds.iter() {
                     n.ids(f)
                 }
+                if let Some((id, _)) = rest {
+                    f(*id)
+                }
             }
         }
     }
@@ -395,7 +413,7 @@ impl StructPatternNode {
                 }
                 _ => (),
             },
-            Self::Struct { all, binds } => match v {
+            Self::Struct { all, binds, rest } => match v {
                 Value::Array(a) if a.len() >= binds.len() => {
                     if let Some(id) = all {
                         f(*id, v.clone())
@@ -408,6 +426,11 @@ impl StructPatternNode {
                             }
                         }
                     }
+                    if let Some((id, idx)) = rest {
+                        let mut fields: LPooled<Vec<Value>> =
+                            idx.iter().filter_map(|i| a.get(*i).cloned()).collect();
+                        f(*id, Value::Array(ValArray::from_iter_exact(fields.drain(..))))
+                    }
                 }
                 _ => (),
             },
@@ -449,13 +472,16 @@ impl StructPatternNode {
                     n.unbind(f)
                 }
             }
-            Self::Struct { all, binds } => {
+            Self::Struct { all, binds, rest } => {
                 if let Some(id) = all {
                     f(*id)
                 }
                 for (_, _, n) in binds.iter() {
                     n.unbind(f)
                 }
+                if let Some((id, _)) = rest {
+                    f(*id)
+                }
             }
         }
     }
@@ -503,7 +529,7 @@ impl StructPatternNode {
                 }
                 _ => false,
             },
-            Self::Struct { all: _, binds } => match v {
+            Self::Struct { all: _, binds, rest: _ } => match v {
                 Value::Array(a) => {
                     a.len() >= binds.len()
                         && binds.iter().all(|(_, i, p)| match a.get(*i) {
@@ -523,7 +549,7 @@ impl StructPatternNode {
             Self::Slice { tuple: true, all: _, binds } => {
                 binds.iter().any(|p| p.is_refutable())
             }
-            Self::Struct { all: _, binds } => {
+            Self::Struct { all: _, binds, rest: _ } => {
                 binds.iter().any(|(_, _, p)| p.is_refutable())
             }
             Self::Variant { all: _, tag: _, binds } => {
@@ -542,7 +568,7 @@ impl StructPatternNode {
                 ctx.cached.remove(&id);
                 ctx.env.unbind_variable(*id);
             }
-            Self::Struct { all, binds } => {
+            Self::Struct { all, binds, rest } => {
                 if let Some(id) = all {
                     ctx.cached.remove(id);
                     ctx.env.unbind_variable(*id);
@@ -550,6 +576,10 @@ impl StructPatternNode {
                 for (_, _, n) in binds {
                     n.delete(ctx)
                 }
+                if let Some((id, _)) = rest {
+                    ctx.cached.remove(id);
+                    ctx.env.unbind_variable(*id);
+                }
             }
             Self::Slice { tuple: _, all, binds }
             | Self::Variant { tag: _, all, binds } => {
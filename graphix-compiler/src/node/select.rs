@@ -123,10 +123,7 @@ impl<R: Rt, E: UserEvent> Update<R, E> for Select<R, E> {
                             set.push(id);
                         }
                     });
-                    let init = event.init;
-                    event.init = true;
-                    arms[i].1.update(ctx, event);
-                    event.init = init;
+                    arms[i].1.update(ctx, &mut event.init_scope(true));
                     for id in set.drain(..) {
                         event.variables.remove(&id);
                     }
@@ -23,6 +23,33 @@ pub enum IsAFlags {
     MatchAbstract,
 }
 
+/// check that neither v nor any value nested inside it is longer than max
+pub(crate) fn check_len(max: usize, v: &Value) -> Result<()> {
+    let len = match v {
+        Value::String(s) => s.len(),
+        Value::Bytes(b) => b.len(),
+        Value::Array(a) => {
+            for v in a.iter() {
+                check_len(max, v)?
+            }
+            a.len()
+        }
+        Value::Map(m) => {
+            for (k, v) in m.into_iter() {
+                check_len(max, k)?;
+                check_len(max, v)?
+            }
+            m.len()
+        }
+        Value::Error(e) => return check_len(max, e),
+        _ => 0,
+    };
+    if len > max {
+        bail!("value of length {len} exceeds the maximum length {max}")
+    }
+    Ok(())
+}
+
 impl Type {
     fn check_cast_int(
         &self,
@@ -264,12 +291,18 @@ impl Type {
     }
 
     pub fn cast_value(&self, env: &Env, v: Value) -> Value {
-        match self.cast_value_int(env, &mut LPooled::take(), v) {
+        match self.try_cast_value(env, v) {
             Ok(v) => v,
             Err(e) => errf!(CAST_ERR_TAG, "{e:?}"),
         }
     }
 
+    /// cast v to the type, returning an error instead of an error value if
+    /// it can't be cast
+    pub fn try_cast_value(&self, env: &Env, v: Value) -> Result<Value> {
+        self.cast_value_int(env, &mut LPooled::take(), v)
+    }
+
     fn is_a_int(
         &self,
         env: &Env,
@@ -23,6 +23,8 @@ use triomphe::Arc;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct FnArgType {
+    /// The label of a labeled argument, and true if the argument is
+    /// optional (has a default value)
     pub label: Option<(ArcStr, bool)>,
     pub typ: Type,
 }
@@ -727,6 +729,23 @@ impl FnType {
         tbl
     }
 
+    /// check the type references in the arguments, return type, throws
+    /// type, and constraints, see `Type::check_refs`
+    pub fn check_refs(&self, env: &Env) -> Result<()> {
+        for a in self.args.iter() {
+            a.typ.check_refs(env)?
+        }
+        if let Some(t) = &self.vargs {
+            t.check_refs(env)?
+        }
+        self.rtype.check_refs(env)?;
+        self.throws.check_refs(env)?;
+        for (_, tc) in self.constraints.read().iter() {
+            tc.check_refs(env)?
+        }
+        Ok(())
+    }
+
     pub fn scope_refs(&self, scope: &ModPath) -> Self {
         let vargs = self.vargs.as_ref().map(|t| t.scope_refs(scope));
         let rtype = self.rtype.scope_refs(scope);
@@ -792,8 +811,13 @@ impl fmt::Display for FnType {
             t => write!(f, ") -> {t}")?,
         }
         match &self.throws {
-            Type::Bottom => Ok(()),
-            Type::TVar(tv) if *tv.read().typ.read() == Some(Type::Bottom) => Ok(()),
+            Type::Bottom if !self.explicit_throws => Ok(()),
+            Type::TVar(tv)
+                if *tv.read().typ.read() == Some(Type::Bottom)
+                    && !self.explicit_throws =>
+            {
+                Ok(())
+            }
             t => write!(f, " throws {t}"),
         }
     }
@@ -22,13 +22,12 @@ impl Type {
             (
                 Self::Ref { scope: s0, name: n0, params: p0 },
                 Self::Ref { scope: s1, name: n1, params: p1 },
-            ) if s0 == s1 && n0 == n1 => Ok(p0.len() == p1.len()
-                && p0
-                    .iter()
-                    .zip(p1.iter())
-                    .map(|(t0, t1)| t0.could_match_int(env, hist, t1))
-                    .collect::<Result<AndAc>>()?
-                    .0),
+            ) if s0 == s1 && n0 == n1 && p0.len() == p1.len() => Ok(p0
+                .iter()
+                .zip(p1.iter())
+                .map(|(t0, t1)| t0.could_match_int(env, hist, t1))
+                .collect::<Result<AndAc>>()?
+                .0),
             (t0 @ Self::Ref { .. }, t1) | (t0, t1 @ Self::Ref { .. }) => {
                 let t0_id = hist.ref_id(t0, env);
                 let t1_id = hist.ref_id(t1, env);
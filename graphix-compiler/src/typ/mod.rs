@@ -1,6 +1,6 @@
 use crate::{
     env::{Env, TypeDef},
-    expr::ModPath,
+    expr::{parser::parse_type, ModPath},
     format_with_flags, PrintFlag, PRINT_FLAGS,
 };
 use anyhow::{anyhow, bail, Result};
@@ -9,12 +9,14 @@ use enumflags2::BitFlags;
 use fxhash::{FxHashMap, FxHashSet};
 use netidx::{publisher::Typ, utils::Either};
 use poolshark::{local::LPooled, IsoPoolable};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use smallvec::SmallVec;
 use std::{
     cmp::{Eq, PartialEq},
     fmt::Debug,
     iter,
     ops::{Deref, DerefMut},
+    result,
 };
 use triomphe::Arc;
 
@@ -27,7 +29,9 @@ mod print;
 mod setops;
 mod tval;
 mod tvar;
+pub(crate) mod units;
 
+pub(crate) use cast::check_len;
 pub use fntyp::{FnArgType, FnType};
 pub use tval::TVal;
 pub use tvar::TVar;
@@ -44,6 +48,7 @@ struct RefHist<H: IsoPoolable> {
     inner: LPooled<H>,
     ref_ids: LPooled<FxHashMap<usize, SmallVec<[(Arc<[Type]>, usize); 2]>>>,
     next_id: usize,
+    depth: usize,
 }
 
 impl<H: IsoPoolable> Deref for RefHist<H> {
@@ -61,7 +66,22 @@ impl<H: IsoPoolable> DerefMut for RefHist<H> {
 
 impl<H: IsoPoolable> RefHist<H> {
     fn new(inner: LPooled<H>) -> Self {
-        RefHist { inner, ref_ids: LPooled::take(), next_id: 0 }
+        RefHist { inner, ref_ids: LPooled::take(), next_id: 0, depth: 0 }
+    }
+
+    /// Enter a Ref expansion, failing if the expansion is nested more
+    /// deeply than the maximum depth. Must be paired with `leave`.
+    fn enter(&mut self) -> Result<()> {
+        let max = crate::max_depth();
+        if self.depth >= max {
+            bail!("maximum type expansion depth {max} exceeded")
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
     }
 
     /// Return a stable ID for a Ref type based on (typedef identity, params).
@@ -117,6 +137,22 @@ impl Default for Type {
     }
 }
 
+/// Types serialize as their source form, printed without replacing
+/// primitive sets, and are parsed when deserialized.
+impl Serialize for Type {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer
+            .serialize_str(&format_with_flags(BitFlags::empty(), || self.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Type {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> result::Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        parse_type(&s).map_err(de::Error::custom)
+    }
+}
+
 impl Type {
     pub fn empty_tvar() -> Self {
         Type::TVar(TVar::default())
@@ -157,11 +193,11 @@ impl Type {
                 let def = env
                     .lookup_typedef(scope, name)
                     .ok_or_else(|| anyhow!("undefined type {name} in {scope}"))?;
-                if def.params.len() != params.len() {
-                    bail!("{} expects {} type parameters", name, def.params.len());
-                }
+                def.check_arity(name, params.len())?;
                 let mut known: LPooled<FxHashMap<ArcStr, Type>> = LPooled::take();
-                for ((tv, ct), arg) in def.params.iter().zip(params.iter()) {
+                for (i, (tv, ct, default)) in def.params.iter().enumerate() {
+                    // check_arity ensures omitted params have a default
+                    let Some(arg) = params.get(i).or(default.as_ref()) else { break };
                     if let Some(ct) = ct {
                         ct.check_contains(env, arg)?;
                     }
@@ -173,6 +209,36 @@ impl Type {
         }
     }
 
+    /// check that every type reference names a defined type and supplies the
+    /// number of type parameters it expects
+    pub fn check_refs(&self, env: &Env) -> Result<()> {
+        match self {
+            Self::Bottom | Self::Any | Self::Primitive(_) => Ok(()),
+            Self::Error(t) | Self::Array(t) | Self::ByRef(t) => t.check_refs(env),
+            Self::Map { key, value } => {
+                key.check_refs(env)?;
+                value.check_refs(env)
+            }
+            Self::Abstract { params: ts, .. }
+            | Self::Tuple(ts)
+            | Self::Variant(_, ts)
+            | Self::Set(ts) => ts.iter().try_for_each(|t| t.check_refs(env)),
+            Self::Struct(ts) => ts.iter().try_for_each(|(_, t)| t.check_refs(env)),
+            Self::TVar(tv) => match tv.read().typ.read().as_ref() {
+                Some(t) => t.check_refs(env),
+                None => Ok(()),
+            },
+            Self::Ref { scope, name, params } => {
+                let def = env
+                    .lookup_typedef(scope, name)
+                    .ok_or_else(|| anyhow!("undefined type {name} in {scope}"))?;
+                def.check_arity(name, params.len())?;
+                params.iter().try_for_each(|t| t.check_refs(env))
+            }
+            Self::Fn(f) => f.check_refs(env),
+        }
+    }
+
     pub fn any() -> Self {
         Self::Any
     }
@@ -1,11 +1,15 @@
 use super::{PrintFlag, Type};
 use super::cast::IsAFlags;
-use crate::{env::Env, typ::format_with_flags};
+use crate::{
+    env::Env,
+    expr::print::{PrettyBuf, PrettyDisplay},
+    typ::format_with_flags,
+};
 use fxhash::FxHashSet;
 use netidx::publisher::Value;
 use netidx_value::NakedValue;
 use poolshark::local::LPooled;
-use std::fmt;
+use std::fmt::{self, Write};
 
 /// A value with it's type, used for formatting
 pub struct TVal<'a> {
@@ -134,3 +138,84 @@ impl<'a> fmt::Display for TVal<'a> {
         self.fmt_int(f, &mut LPooled::take())
     }
 }
+
+fn pretty_items<T>(
+    buf: &mut PrettyBuf,
+    open: &str,
+    close: &str,
+    items: &[T],
+    mut f: impl FnMut(&mut PrettyBuf, &T) -> fmt::Result,
+) -> fmt::Result {
+    writeln!(buf, "{open}")?;
+    buf.with_indent(2, |buf| {
+        for (i, item) in items.iter().enumerate() {
+            f(buf, item)?;
+            if i < items.len() - 1 {
+                buf.kill_newline();
+                writeln!(buf, ",")?;
+            }
+        }
+        Ok(())
+    })?;
+    writeln!(buf, "{close}")
+}
+
+impl<'a> PrettyDisplay for TVal<'a> {
+    fn fmt_pretty_inner(&self, buf: &mut PrettyBuf) -> fmt::Result {
+        let env = self.env;
+        if !self.typ.is_a_with(env, IsAFlags::MatchAbstract.into(), self.v) {
+            return writeln!(buf, "{self}");
+        }
+        match (self.typ, self.v) {
+            (Type::Ref { .. }, v) => match self.typ.lookup_ref(env) {
+                Err(_) => writeln!(buf, "{self}"),
+                Ok(typ) => TVal { env, typ: &typ, v }.fmt_pretty_inner(buf),
+            },
+            (Type::TVar(tv), v) => match &*tv.read().typ.read() {
+                None => writeln!(buf, "{self}"),
+                Some(typ) => TVal { env, typ, v }.fmt_pretty_inner(buf),
+            },
+            (Type::Set(ts), v) => match ts.iter().find(|t| t.is_a(env, v)) {
+                None => writeln!(buf, "{self}"),
+                Some(typ) => TVal { env, typ, v }.fmt_pretty_inner(buf),
+            },
+            (Type::Array(typ), Value::Array(a)) => {
+                pretty_items(buf, "[", "]", &a[..], |buf, v| {
+                    TVal { env, typ, v }.fmt_pretty(buf)
+                })
+            }
+            (Type::Map { key, value }, Value::Map(m)) => {
+                let kvs = m.into_iter().collect::<LPooled<Vec<_>>>();
+                pretty_items(buf, "{", "}", &kvs[..], |buf, (k, v)| {
+                    write!(buf, "{} => ", TVal { env, typ: key, v: k })?;
+                    buf.with_indent(2, |buf| TVal { env, typ: value, v }.fmt_pretty(buf))
+                })
+            }
+            (Type::Struct(flds), Value::Array(a)) => {
+                let flds = flds.iter().zip(a.iter()).collect::<LPooled<Vec<_>>>();
+                pretty_items(buf, "{", "}", &flds[..], |buf, ((n, typ), v)| {
+                    write!(buf, "{n}: ")?;
+                    match v {
+                        Value::Array(a) if a.len() == 2 => buf.with_indent(2, |buf| {
+                            TVal { env, typ, v: &a[1] }.fmt_pretty(buf)
+                        }),
+                        _ => writeln!(buf, "err"),
+                    }
+                })
+            }
+            (Type::Tuple(ts), Value::Array(a)) => {
+                let flds = ts.iter().zip(a.iter()).collect::<LPooled<Vec<_>>>();
+                pretty_items(buf, "(", ")", &flds[..], |buf, (typ, v)| {
+                    TVal { env, typ, v }.fmt_pretty(buf)
+                })
+            }
+            (Type::Variant(n, ts), Value::Array(a)) if a.len() >= 2 => {
+                let flds = ts.iter().zip(a[1..].iter()).collect::<LPooled<Vec<_>>>();
+                pretty_items(buf, &format!("`{n}("), ")", &flds[..], |buf, (typ, v)| {
+                    TVal { env, typ, v }.fmt_pretty(buf)
+                })
+            }
+            _ => writeln!(buf, "{self}"),
+        }
+    }
+}
@@ -7,12 +7,14 @@ use arcstr::ArcStr;
 use compact_str::format_compact;
 use fxhash::{FxHashMap, FxHashSet};
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     cmp::{Eq, PartialEq},
     collections::hash_map::Entry,
     fmt::{self, Debug},
     hash::Hash,
     ops::Deref,
+    result,
 };
 use triomphe::Arc;
 
@@ -96,6 +98,19 @@ impl fmt::Display for TVar {
     }
 }
 
+/// Type variables serialize as their name, and deserialize unbound
+impl Serialize for TVar {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        self.name.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TVar {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> result::Result<Self, D::Error> {
+        Ok(Self::empty_named(ArcStr::deserialize(de)?))
+    }
+}
+
 impl Default for TVar {
     fn default() -> Self {
         Self::empty_named(ArcStr::from(format_compact!("_{}", TVarId::new().0).as_str()))
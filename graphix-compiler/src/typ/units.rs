@@ -0,0 +1,153 @@
+//! Units of measure
+//!
+//! A quantity is a variant with a single argument of type `Quantity<'a>`,
+//! such as `` `Meters(Quantity<f64>) ``, and the tag of the variant is its
+//! unit. `Quantity<'a>`, defined in core, is just the number `'a`, it only
+//! marks the variant as a quantity, other variants are never given unit
+//! arithmetic. Arithmetic
+//! on quantities is checked, + - and % require the units to be the same,
+//! while * and / combine them. Combined units are written as the sorted
+//! factors of the numerator joined by `_`, followed by `_per_` and the
+//! sorted factors of the denominator, so `` `Meters(f64) `` divided by
+//! `` `Seconds(f64) `` is `` `Meters_per_Seconds(f64) ``, and a unit with
+//! no numerator is written `Per_`, e.g. `` `Per_Seconds(f64) ``. Units
+//! that cancel out completely leave a plain number.
+use crate::{env::Env, typ::Type, DEFAULT_MAX_DEPTH};
+use arcstr::ArcStr;
+use netidx::{path::Path, publisher::Typ};
+use smallvec::SmallVec;
+use std::fmt::Write;
+use triomphe::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Unit {
+    num: SmallVec<[ArcStr; 4]>,
+    den: SmallVec<[ArcStr; 4]>,
+}
+
+impl Unit {
+    /// parse the unit written as tag
+    pub(crate) fn parse(tag: &str) -> Self {
+        fn factors(s: &str) -> SmallVec<[ArcStr; 4]> {
+            s.split('_').filter(|s| !s.is_empty()).map(ArcStr::from).collect()
+        }
+        let (num, den) = match tag.strip_prefix("Per_") {
+            Some(den) => ("", den),
+            None => match tag.split_once("_per_") {
+                Some((num, den)) => (num, den),
+                None => (tag, ""),
+            },
+        };
+        Self { num: factors(num), den: factors(den) }.normalize()
+    }
+
+    fn normalize(mut self) -> Self {
+        let mut i = 0;
+        while i < self.num.len() {
+            match self.den.iter().position(|d| d == &self.num[i]) {
+                Some(j) => {
+                    self.num.remove(i);
+                    self.den.remove(j);
+                }
+                None => i += 1,
+            }
+        }
+        self.num.sort();
+        self.den.sort();
+        self
+    }
+
+    pub(crate) fn mul(&self, other: &Self) -> Self {
+        let num = self.num.iter().chain(other.num.iter()).cloned().collect();
+        let den = self.den.iter().chain(other.den.iter()).cloned().collect();
+        Self { num, den }.normalize()
+    }
+
+    pub(crate) fn div(&self, other: &Self) -> Self {
+        self.mul(&Self { num: other.den.clone(), den: other.num.clone() })
+    }
+
+    /// the tag of the unit, or None if it is dimensionless
+    pub(crate) fn tag(&self) -> Option<ArcStr> {
+        if self.num.is_empty() && self.den.is_empty() {
+            return None;
+        }
+        let mut s = String::new();
+        if self.num.is_empty() {
+            s.push_str("Per");
+        }
+        for (i, f) in self.num.iter().enumerate() {
+            if i > 0 {
+                s.push('_');
+            }
+            s.push_str(f);
+        }
+        if !self.den.is_empty() {
+            if !self.num.is_empty() {
+                s.push_str("_per");
+            }
+            for f in self.den.iter() {
+                write!(s, "_{f}").unwrap();
+            }
+        }
+        Some(ArcStr::from(s))
+    }
+}
+
+/// the name of the type that marks a variant as a quantity
+const QUANTITY: &str = "Quantity";
+
+/// A quantity, see the module docs
+#[derive(Debug, Clone)]
+pub(crate) struct Quantity {
+    /// the unit of the quantity
+    pub(crate) tag: ArcStr,
+    /// the type of the number
+    pub(crate) typ: Type,
+    /// the `Quantity<'a>` reference the variant carries
+    marker: Type,
+}
+
+impl Quantity {
+    /// the type of a quantity with the unit tag carrying a number of type
+    /// typ, marked the same way as self
+    pub(crate) fn typ(&self, tag: ArcStr, typ: Type) -> Type {
+        let marker = match &self.marker {
+            Type::Ref { scope, name, .. } => Type::Ref {
+                scope: scope.clone(),
+                name: name.clone(),
+                params: Arc::from_iter([typ]),
+            },
+            _ => typ,
+        };
+        Type::Variant(tag, Arc::from_iter([marker]))
+    }
+}
+
+/// If t is a quantity return it
+pub(crate) fn quantity(env: &Env, t: &Type) -> Option<Quantity> {
+    let mut t = t.with_deref(|t| t.cloned())?;
+    for _ in 0..DEFAULT_MAX_DEPTH {
+        t = match t {
+            t @ Type::Ref { .. } => t.lookup_ref(env).ok()?.with_deref(|t| t.cloned())?,
+            Type::Variant(tag, args) if args.len() == 1 => {
+                let marker = args[0].with_deref(|t| t.cloned())?;
+                match &marker {
+                    Type::Ref { name, params, .. }
+                        if params.len() == 1
+                            && Path::basename(&**name) == Some(QUANTITY) => {}
+                    _ => return None,
+                }
+                let typ = marker.lookup_ref(env).ok()?;
+                return match typ.with_deref(|t| t.cloned())? {
+                    Type::Primitive(p) if !p.is_empty() && Typ::number().contains(p) => {
+                        Some(Quantity { tag, typ, marker })
+                    }
+                    _ => None,
+                };
+            }
+            _ => return None,
+        }
+    }
+    None
+}
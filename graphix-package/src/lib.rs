@@ -10,7 +10,13 @@ use compact_str::{format_compact, CompactString};
 use crates_io_api::AsyncClient;
 use flate2::bufread::MultiGzDecoder;
 use fxhash::FxHashMap;
-use graphix_compiler::{env::Env, expr::ExprId, ExecCtx};
+use graphix_compiler::{
+    env::Env,
+    expr::{
+        parser::parse, Expr, ExprId, ExprKind, ModPath, Origin, ScopeVisitor, Source,
+    },
+    ExecCtx,
+};
 use graphix_rt::{CompExp, GXExt, GXHandle, GXRt};
 use handlebars::Handlebars;
 pub use indexmap::IndexSet;
@@ -118,6 +124,129 @@ pub trait Package<X: GXExt> {
     fn main_program() -> Option<&'static str>;
 }
 
+/// the package a module in the module table belongs to, the first part of
+/// its path is either the package directory, or {package}.gx for single
+/// file packages
+fn package_of(path: &str) -> Option<&str> {
+    netidx_core::path::Path::parts(path)
+        .next()
+        .map(|p| p.split_once('.').map_or(p, |(n, _)| n))
+}
+
+/// finds references to a top level module in an expression
+struct Refers<'a> {
+    name: &'a str,
+    found: bool,
+}
+
+impl<'a, 'b> ScopeVisitor<'b> for Refers<'a> {
+    fn enter(&mut self, e: &'b Expr) {
+        if let ExprKind::Use { name } = &e.kind {
+            self.found |=
+                netidx_core::path::Path::parts(&name.0).next() == Some(self.name)
+        }
+    }
+
+    fn reference(&mut self, _e: &'b Expr, name: &'b ModPath, binder: Option<&'b Expr>) {
+        self.found |= binder.is_none()
+            && netidx_core::path::Path::parts(&name.0).next() == Some(self.name)
+    }
+}
+
+/// return the first registered package, other than name itself, whose
+/// graphix code refers to the package name
+fn dependent(
+    modules: &FxHashMap<netidx_core::path::Path, ArcStr>,
+    name: &str,
+) -> Result<Option<ArcStr>> {
+    for (path, src) in modules {
+        let pkg = match package_of(path) {
+            Some(pkg) if pkg != name && path.ends_with(".gx") => pkg,
+            Some(_) | None => continue,
+        };
+        let ori = Origin {
+            parent: None,
+            source: Source::Internal(ArcStr::from(&**path)),
+            text: src.clone(),
+        };
+        let exprs = parse(ori).with_context(|| format!("parsing {path}"))?;
+        let mut refers = Refers { name, found: false };
+        for e in exprs.iter() {
+            e.walk_scoped(&mut refers)
+        }
+        if refers.found {
+            return Ok(Some(ArcStr::from(pkg)));
+        }
+    }
+    Ok(None)
+}
+
+/// Mount registered packages under different names.
+///
+/// `mounts` maps a package name, as registered in `root_mods`, to the path
+/// it should be mounted at, e.g. `str` => `/text` makes the str package
+/// available as `text::len`. Both the module table and `root_mods` are
+/// rewritten, so the root module generated from `root_mods` declares the
+/// new name. Mount paths must be a single top level name, and the core
+/// package can't be moved because the root module `use`s it.
+///
+/// References to a package from the graphix code of other packages are not
+/// rewritten, so mounting a package that another registered package uses
+/// is an error. For example the gui package uses `str`, so `str` can't be
+/// mounted elsewhere if gui is registered.
+pub fn mount(
+    modules: &mut FxHashMap<netidx_core::path::Path, ArcStr>,
+    root_mods: &mut IndexSet<ArcStr>,
+    mounts: &FxHashMap<ArcStr, netidx_core::path::Path>,
+) -> Result<()> {
+    if mounts.is_empty() {
+        return Ok(());
+    }
+    let mut renames: FxHashMap<ArcStr, ArcStr> = FxHashMap::default();
+    for (name, path) in mounts {
+        if !root_mods.contains(name) {
+            bail!("can't mount {name}, it isn't registered")
+        }
+        if name == "core" {
+            bail!("the core package can't be mounted at {path}")
+        }
+        let mut parts = netidx_core::path::Path::parts(path);
+        let new = match (parts.next(), parts.next()) {
+            (Some(new), None) => ArcStr::from(new),
+            _ => bail!("invalid mount path {path}, expected a single top level name"),
+        };
+        if new != *name
+            && (root_mods.contains(&new) || renames.values().any(|n| *n == new))
+        {
+            bail!("can't mount {name} at {path}, {new} already exists")
+        }
+        if new != *name
+            && let Some(dep) = dependent(modules, name)?
+        {
+            bail!("can't mount {name} at {path}, the {dep} package uses it")
+        }
+        renames.insert(name.clone(), new);
+    }
+    let renamed = modules
+        .drain()
+        .map(|(path, src)| {
+            let renamed = package_of(&path)
+                .and_then(|n| Some((n.len(), renames.get(n)?)))
+                .map(|(len, new)| format!("/{new}{}", &path[1 + len..]));
+            match renamed {
+                None => (path, src),
+                Some(p) => (netidx_core::path::Path::from(p), src),
+            }
+        })
+        .collect::<Vec<_>>();
+    modules.extend(renamed);
+    *root_mods = root_mods
+        .drain(..)
+        .map(|name| renames.get(&name).cloned().unwrap_or(name))
+        .collect();
+    Ok(())
+}
+
 // package skeleton, our version, and deps template
 struct Skel {
     version: &'static str,
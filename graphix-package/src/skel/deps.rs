@@ -52,6 +52,16 @@ pub(crate) enum CustomResult<X: GXExt> {
     NotCustom(CompExp<X>),
 }
 
+/// return true if the output of e would be shown by a custom display
+pub(crate) fn is_custom<X: GXExt>(gx: &GXHandle<X>, env: &Env, e: &CompExp<X>) -> bool {
+    {{#each deps}}
+    if {{this.crate_name}}::P::is_custom(gx, env, e) {
+        return true;
+    }
+    {{/each}}
+    false
+}
+
 pub(crate) async fn maybe_init_custom<X: GXExt>(
     gx: &GXHandle<X>,
     env: &Env,
@@ -1,3 +1,6 @@
+use arcstr::{literal, ArcStr};
+use fxhash::FxHashMap;
+use indexmap::IndexSet;
 use std::path::Path;
 use std::sync::{Mutex, Once};
 use std::time::Duration;
@@ -256,3 +259,39 @@ async fn build_standalone_produces_working_binary() {
         captured_stdout, captured_stderr
     );
 }
+
+fn mount_fixture(
+    dep: &str,
+) -> (FxHashMap<netidx_core::path::Path, ArcStr>, IndexSet<ArcStr>) {
+    let modules = FxHashMap::from_iter([
+        (netidx_core::path::Path::from("/str/mod.gx"), literal!("let len = |s| 0")),
+        (netidx_core::path::Path::from("/dep.gx"), ArcStr::from(dep)),
+    ]);
+    let root_mods =
+        IndexSet::from_iter([literal!("core"), literal!("str"), literal!("dep")]);
+    (modules, root_mods)
+}
+
+#[test]
+fn mount_renames() {
+    let mounts =
+        FxHashMap::from_iter([(literal!("str"), netidx_core::path::Path::from("/text"))]);
+    // a shadowing local named str is not a use of the package
+    let (mut modules, mut root_mods) = mount_fixture("let f = |str| str + 1");
+    super::mount(&mut modules, &mut root_mods, &mounts).unwrap();
+    assert!(modules.contains_key(&netidx_core::path::Path::from("/text/mod.gx")));
+    assert!(!modules.contains_key(&netidx_core::path::Path::from("/str/mod.gx")));
+    assert!(root_mods.contains("text") && !root_mods.contains("str"));
+}
+
+#[test]
+fn mount_rejects_dependents() {
+    let mounts =
+        FxHashMap::from_iter([(literal!("str"), netidx_core::path::Path::from("/text"))]);
+    for dep in ["let f = |s| str::len(s)", "{ use str; len(\"x\") }"] {
+        let (mut modules, mut root_mods) = mount_fixture(dep);
+        let e = super::mount(&mut modules, &mut root_mods, &mounts).unwrap_err();
+        assert!(e.to_string().contains("the dep package uses it"), "{e}");
+        assert!(modules.contains_key(&netidx_core::path::Path::from("/str/mod.gx")));
+    }
+}
@@ -0,0 +1,85 @@
+//! Render the dataflow graph of a compiled expression as Graphviz DOT
+use fxhash::FxHashMap;
+use graphix_compiler::expr::{Expr, ExprId, ExprKind, ModPath, ScopeVisitor};
+use std::fmt::Write;
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[derive(Default)]
+struct Dot {
+    buf: String,
+    next: usize,
+    /// the nodes of the expressions being visited, innermost last
+    stack: Vec<String>,
+    /// the node of each expression visited so far
+    nodes: FxHashMap<ExprId, String>,
+    /// variables referenced by the expression but bound outside of it
+    external: FxHashMap<String, String>,
+}
+
+impl Dot {
+    fn external(&mut self, name: &ModPath) -> String {
+        let name = name.to_string();
+        if let Some(n) = self.external.get(&name) {
+            return n.clone();
+        }
+        let n = format!("v{}", self.external.len());
+        writeln!(self.buf, "  {n} [shape=ellipse, label=\"{}\"];", escape(&name))
+            .unwrap();
+        self.external.insert(name, n.clone());
+        n
+    }
+}
+
+impl<'a> ScopeVisitor<'a> for Dot {
+    fn enter(&mut self, e: &'a Expr) {
+        let id = format!("n{}", self.next);
+        self.next += 1;
+        let label = match &e.kind {
+            ExprKind::Ref { name } => format!("Ref {name}"),
+            ExprKind::Constant(v) => format!("Constant {v}"),
+            k => k.name().to_string(),
+        };
+        let label = escape(&format!("{label}\n{}", e.pos));
+        writeln!(self.buf, "  {id} [label=\"{label}\"];").unwrap();
+        self.nodes.insert(e.id, id.clone());
+        self.stack.push(id)
+    }
+
+    fn exit(&mut self, _e: &'a Expr) {
+        let id = self.stack.pop().unwrap();
+        if let Some(parent) = self.stack.last() {
+            writeln!(self.buf, "  {id} -> {parent};").unwrap()
+        }
+    }
+
+    fn reference(&mut self, e: &'a Expr, name: &'a ModPath, binder: Option<&'a Expr>) {
+        // the binder is always visited before the expressions in its scope
+        let src = match binder.and_then(|b| self.nodes.get(&b.id)) {
+            Some(n) => n.clone(),
+            None => self.external(name),
+        };
+        let id = &self.nodes[&e.id];
+        match &e.kind {
+            ExprKind::Connect { .. } => {
+                writeln!(self.buf, "  {id} -> {src} [style=dashed];").unwrap()
+            }
+            _ => writeln!(self.buf, "  {src} -> {id} [style=dashed];").unwrap(),
+        }
+    }
+}
+
+/// Render the expression as a DOT digraph. Each sub expression is a node
+/// labeled with its kind and position, solid edges point from a sub
+/// expression to the expression that consumes it, and dashed edges connect
+/// the expression that binds a variable in scope to the expressions that
+/// reference or connect to it.
+pub(crate) fn to_dot(spec: &Expr) -> String {
+    let mut dot = Dot::default();
+    dot.buf.push_str("digraph {\n  node [shape=box];\n");
+    spec.walk_scoped(&mut dot);
+    dot.buf.push_str("}\n");
+    dot.buf
+}
@@ -1,21 +1,25 @@
 use anyhow::{anyhow, bail, Context, Result};
-use arcstr::ArcStr;
+use arcstr::{literal, ArcStr};
 use enumflags2::BitFlags;
 use futures::{channel::mpsc, future::try_join_all, StreamExt};
-use fxhash::{FxBuildHasher, FxHashMap};
+use fxhash::{FxBuildHasher, FxHashMap, FxHashSet};
 use graphix_compiler::{
     compile,
-    expr::{self, Expr, ExprId, ExprKind, ModuleResolver, Origin, Source},
+    env::Env,
+    errf,
+    expr::{self, Expr, ExprId, ExprKind, ModuleCache, ModuleResolver, Origin, Source},
     node::{genn, lambda::LambdaDef},
+    rtlog,
     typ::Type,
     BindId, CFlag, CustomBuiltinType, Event, ExecCtx, Node, Refs, Scope,
 };
 use indexmap::IndexMap;
-use log::{debug, error, info};
+use log::{error, Level};
 use netidx::{
+    path::Path,
     protocol::valarray::ValArray,
     publisher::Value,
-    subscriber::{self, Dval},
+    subscriber::{self, Dval, SubId},
 };
 use netidx_protocols::rpc::server::RpcCall;
 use poolshark::{
@@ -30,17 +34,32 @@ use std::{
 };
 use tokio::{
     fs, select,
-    sync::mpsc::{self as tmpsc, error::SendTimeoutError, UnboundedReceiver},
+    sync::{
+        mpsc::{self as tmpsc, error::SendTimeoutError, UnboundedReceiver},
+        oneshot,
+    },
     task::{JoinError, JoinSet},
     time::{self, Instant},
 };
 use triomphe::Arc;
 
 use crate::{
-    Callable, CallableId, CompExp, CompRes, GXConfig, GXEvent, GXExt, GXHandle, GXRt,
-    Ref, ToGX, UpdateBatch, WriteBatch,
+    dot, Callable, CallableId, CompExp, CompRes, GXConfig, GXEvent, GXExt, GXHandle,
+    GXRt, Param, Ref, TestCases, TestOutcome, TestResult, ToGX, UpdateBatch, WriteBatch,
 };
 
+static WRITE_ERR_TAG: ArcStr = literal!("WriteError");
+
+fn external_refs<X: GXExt>(n: &Node<GXRt<X>, X::UserEvent>) -> FxHashSet<BindId> {
+    let mut refs = Refs::default();
+    n.refs(&mut refs);
+    let mut res = FxHashSet::default();
+    refs.with_external_refs(|id| {
+        res.insert(id);
+    });
+    res
+}
+
 fn is_output<X: GXExt>(n: &Node<GXRt<X>, X::UserEvent>) -> bool {
     match &n.spec().kind {
         ExprKind::Bind { .. }
@@ -104,11 +123,17 @@ pub(super) struct GX<X: GXExt> {
     callables: FxHashMap<CallableId, CallableInt>,
     sub: tmpsc::Sender<GPooled<Vec<GXEvent>>>,
     resolvers: Arc<[ModuleResolver]>,
+    module_cache: ModuleCache,
     publish_timeout: Option<Duration>,
     last_rpc_gc: Instant,
     batch_pool: Pool<Vec<GXEvent>>,
     flags: BitFlags<CFlag>,
     commit_tasks: JoinSet<()>,
+    profile: Option<FxHashMap<ExprId, u64>>,
+    paused: bool,
+    shutdown: Option<Vec<oneshot::Sender<()>>>,
+    /// waiting for every registered test case to finish, see `run_tests`
+    tests_done: Option<oneshot::Sender<Vec<TestResult>>>,
 }
 
 impl<X: GXExt> GX<X> {
@@ -139,17 +164,22 @@ impl<X: GXExt> GX<X> {
             callables: HashMap::default(),
             sub: cfg.sub,
             resolvers: Arc::from(cfg.resolvers),
+            module_cache: ModuleCache::new(),
             publish_timeout: cfg.publish_timeout,
             last_rpc_gc: Instant::now(),
             batch_pool: Pool::new(10, 1000000),
             flags: cfg.flags,
             commit_tasks: JoinSet::new(),
+            profile: cfg.profile.then(FxHashMap::default),
+            paused: false,
+            shutdown: None,
+            tests_done: None,
         };
         let st = Instant::now();
         if let Some(root) = cfg.root {
             t.compile_root(cfg.flags, root).await?;
         }
-        info!("root init time: {:?}", st.elapsed());
+        rtlog!(t.ctx, Level::Info, "root init time: {:?}", st.elapsed());
         Ok(t)
     }
 
@@ -211,6 +241,11 @@ impl<X: GXExt> GX<X> {
                 push_event!(id, v, netidx, subscribed, net_updates)
             }
         }
+        let nodes = &self.nodes;
+        self.ctx.rt.net_last.retain(|id, _| nodes.contains_key(id));
+        for id in self.ctx.rt.net_last.keys() {
+            self.ctx.rt.updated.entry(*id).or_insert(false);
+        }
         for _ in 0..self.ctx.rt.net_writes.len() {
             let (id, v) = self.ctx.rt.net_writes.pop_front().unwrap();
             push_event!(id, v, writes, published, net_writes)
@@ -218,11 +253,23 @@ impl<X: GXExt> GX<X> {
         if let Some(mut writes) = writes {
             for wr in writes.drain(..) {
                 let id = wr.id;
+                if let Some(typ) = self.ctx.rt.published_types.get(&id)
+                    && !typ.is_a(&self.ctx.env, &wr.value)
+                {
+                    if let Some(reply) = wr.send_result {
+                        reply.send(errf!(
+                            WRITE_ERR_TAG,
+                            "expected a {typ}, got {}",
+                            wr.value
+                        ))
+                    }
+                    continue;
+                }
                 push_event!(id, wr, writes, published, net_writes)
             }
         }
         if let Err(e) = self.ctx.rt.ext.do_cycle(&mut self.event) {
-            error!("could not marshall user events {e:?}")
+            rtlog!(self.ctx, Level::Error, "could not marshall user events {e:?}")
         }
         for (id, n) in self.nodes.iter_mut() {
             if let Some(init) = self.ctx.rt.updated.get(id) {
@@ -240,19 +287,37 @@ impl<X: GXExt> GX<X> {
                         }
                     });
                 }
+                // the last value of a shared subscription is only visible
+                // to the expression that just subscribed. A real update in
+                // this cycle is newer, and goes to every subscriber anyway.
+                let mut clear_net: LPooled<Vec<SubId>> = LPooled::take();
+                if let Some(mut last) = self.ctx.rt.net_last.remove(id) {
+                    for (sid, ev) in last.drain(..) {
+                        if let Entry::Vacant(e) = self.event.netidx.entry(sid) {
+                            e.insert(ev);
+                            clear_net.push(sid);
+                        }
+                    }
+                }
                 if let Some(v) = n.update(&mut self.ctx, &mut self.event) {
                     batch.push(GXEvent::Updated(*id, v))
                 }
+                if let Some(profile) = &mut self.profile {
+                    *profile.entry(*id).or_default() += 1;
+                }
                 for id in clear.drain(..) {
                     self.event.variables.remove(&id);
                 }
+                for sid in clear_net.drain(..) {
+                    self.event.netidx.remove(&sid);
+                }
             }
         }
         loop {
             match self.sub.send_timeout(batch, Duration::from_millis(100)).await {
                 Ok(()) => break,
                 Err(SendTimeoutError::Closed(_)) => {
-                    error!("could not send batch");
+                    rtlog!(self.ctx, Level::Error, "could not send batch");
                     break;
                 }
                 Err(SendTimeoutError::Timeout(b)) => {
@@ -291,16 +356,31 @@ impl<X: GXExt> GX<X> {
                     let _ = res.send(self.check(&path).await);
                 }
                 ToGX::Compile { text, rt, res } => {
-                    let _ = res.send(self.compile(rt, text).await);
+                    let _ = res.send(self.compile(rt, text, None).await);
+                }
+                ToGX::CompileIn { text, env, rt, res } => {
+                    let _ = res.send(self.compile(rt, text, Some(env)).await);
                 }
                 ToGX::Load { path, rt, res } => {
-                    let _ = res.send(self.load(rt, &path).await);
+                    let resolvers = self.resolvers.clone();
+                    let _ = res.send(self.load(rt, &path, &resolvers).await);
+                }
+                ToGX::LoadWithResolvers { path, resolvers, rt, res } => {
+                    let resolvers = resolvers
+                        .into_iter()
+                        .chain(self.resolvers.iter().cloned())
+                        .collect::<Vec<_>>();
+                    let resolvers = Arc::from(resolvers);
+                    let _ = res.send(self.load(rt, &path, &resolvers).await);
                 }
                 ToGX::Delete { id } => {
                     if let Some(mut n) = self.nodes.shift_remove(&id) {
                         n.delete(&mut self.ctx);
                     }
-                    debug!("delete {id:?}");
+                    if let Some(profile) = &mut self.profile {
+                        profile.remove(&id);
+                    }
+                    rtlog!(self.ctx, Level::Debug, "delete {id:?}");
                     batch.push(GXEvent::Env(self.ctx.env.clone()));
                 }
                 ToGX::CompileCallable { id, rt, res } => {
@@ -314,11 +394,76 @@ impl<X: GXExt> GX<X> {
                     tasks.push((id, v))
                 }
                 ToGX::DeleteCallable { id } => self.delete_callable(id),
+                ToGX::Subscriptions { id, res } => {
+                    let _ = res.send(self.subscriptions(id));
+                }
+                ToGX::Profile { res } => {
+                    let _ = res.send(self.profile());
+                }
+                ToGX::GraphDot { id, res } => {
+                    let _ = res.send(self.graph_dot(id));
+                }
                 ToGX::Call { id, args } => {
                     if let Err(e) = self.call_callable(id, args, tasks) {
-                        error!("calling callable {id:?} failed with {e:?}")
+                        rtlog!(
+                            self.ctx,
+                            Level::Error,
+                            "calling callable {id:?} failed with {e:?}"
+                        )
                     }
                 }
+                ToGX::TestResults { clear, res } => {
+                    let cases = self.ctx.libstate.get_or_default::<TestCases>();
+                    let results =
+                        if clear { mem::take(&mut cases.0) } else { cases.0.clone() };
+                    let _ = res.send(results);
+                }
+                ToGX::WaitTests { res } => {
+                    self.tests_done = Some(res);
+                    self.check_tests_done();
+                }
+                ToGX::ListTasks { res } => {
+                    let _ = res.send(self.ctx.rt.list_tasks());
+                }
+                ToGX::CancelTask { id, res } => {
+                    let _ = res.send(self.ctx.rt.cancel_task(id));
+                }
+                ToGX::Shutdown { res } => {
+                    self.shutdown.get_or_insert_default().extend(res)
+                }
+                ToGX::Pause => self.paused = true,
+                ToGX::Resume => self.paused = false,
+                ToGX::SetLogLevel(level) => self.ctx.set_log_level(level),
+            }
+        }
+    }
+
+    /// unpublish everything, and give the resolver a chance to hear about
+    /// it. If the resolver is unreachable don't wait for it.
+    async fn unpublish_all(&mut self) {
+        self.ctx.rt.unpublish_all();
+        let timeout = Duration::from_secs(1);
+        if time::timeout(timeout, self.ctx.rt.publisher.flushed()).await.is_err() {
+            rtlog!(
+                self.ctx,
+                Level::Error,
+                "timed out waiting for the resolver while shutting down"
+            )
+        }
+    }
+
+    /// reply to `WaitTests` once no test case is still running. A file that
+    /// is loaded registers its cases during its init cycle, so wait for any
+    /// pending cycle to run before deciding there are none.
+    fn check_tests_done(&mut self) {
+        if self.tests_done.is_none() || self.cycle_ready() {
+            return;
+        }
+        let cases = self.ctx.libstate.get_or_default::<TestCases>();
+        if cases.0.iter().all(|r| r.outcome != TestOutcome::Running) {
+            let results = cases.0.clone();
+            if let Some(res) = self.tests_done.take() {
+                let _ = res.send(results);
             }
         }
     }
@@ -328,6 +473,7 @@ impl<X: GXExt> GX<X> {
             || self.ctx.rt.var_updates.len() > 0
             || self.ctx.rt.custom_updates.len() > 0
             || self.ctx.rt.net_updates.len() > 0
+            || !self.ctx.rt.net_last.is_empty()
             || self.ctx.rt.net_writes.len() > 0
             || self.ctx.rt.rpc_overflow.len() > 0
             || self.ctx.rt.ext.is_ready()
@@ -338,9 +484,10 @@ impl<X: GXExt> GX<X> {
         let ori = Origin { parent: None, source: Source::Unspecified, text };
         let exprs = expr::parser::parse(ori.clone())
             .with_context(|| format!("parsing the root module {ori}"))?;
-        let exprs =
-            try_join_all(exprs.iter().map(|e| e.resolve_modules(&self.resolvers)))
-                .await?;
+        let exprs = try_join_all(
+            exprs.iter().map(|e| e.resolve_modules(&self.resolvers, &self.module_cache)),
+        )
+        .await?;
         let mut nodes = exprs
             .iter()
             .map(|e| {
@@ -356,30 +503,48 @@ impl<X: GXExt> GX<X> {
         Ok(())
     }
 
-    async fn compile(&mut self, rt: GXHandle<X>, text: ArcStr) -> Result<CompRes<X>> {
+    async fn compile(
+        &mut self,
+        rt: GXHandle<X>,
+        text: ArcStr,
+        env: Option<Env>,
+    ) -> Result<CompRes<X>> {
         let scope = Scope::root();
         let ori = Origin { parent: None, source: Source::Unspecified, text };
         let exprs = expr::parser::parse(ori.clone())?;
-        let exprs =
-            try_join_all(exprs.iter().map(|e| e.resolve_modules(&self.resolvers)))
-                .await?;
-        let mut nodes = exprs
-            .iter()
-            .map(|e| compile(&mut self.ctx, self.flags, &scope, e.clone()))
-            .collect::<Result<LPooled<Vec<_>>>>()
-            .with_context(|| ori.clone())?;
-        let exprs = exprs
+        let exprs = try_join_all(
+            exprs.iter().map(|e| e.resolve_modules(&self.resolvers, &self.module_cache)),
+        )
+        .await?;
+        let flags = self.flags;
+        let compile_all = |ctx: &mut ExecCtx<GXRt<X>, X::UserEvent>| {
+            exprs
+                .iter()
+                .map(|e| compile(ctx, flags, &scope, e.clone()))
+                .collect::<Result<LPooled<Vec<_>>>>()
+        };
+        let (nodes, env) = match env {
+            None => (compile_all(&mut self.ctx), None),
+            Some(mut env) => {
+                let nodes = self.ctx.with_restored_mut(&mut env, compile_all);
+                (nodes, Some(env))
+            }
+        };
+        let mut nodes = nodes.with_context(|| ori.clone())?;
+        let comp = exprs
             .iter()
             .zip(nodes.drain(..))
             .map(|(e, n)| {
                 let output = is_output(&n);
                 let typ = n.typ().clone();
+                let refs = external_refs(&n);
                 self.ctx.rt.updated.insert(e.id, true);
                 self.nodes.insert(e.id, n);
-                CompExp { id: e.id, output, typ, rt: rt.clone() }
+                CompExp { id: e.id, output, typ, refs, rt: rt.clone() }
             })
             .collect::<SmallVec<[_; 1]>>();
-        Ok(CompRes { exprs, env: self.ctx.env.clone() })
+        let env = env.unwrap_or_else(|| self.ctx.env.clone());
+        Ok(CompRes { exprs: comp, env, srcs: exprs })
     }
 
     async fn load_exprs(&self, source: &Source) -> Result<(Origin, Arc<[Expr]>)> {
@@ -437,13 +602,16 @@ impl<X: GXExt> GX<X> {
         let env = self.ctx.env.clone();
         let go = async {
             let st = Instant::now();
-            info!("parse time: {:?}", st.elapsed());
+            rtlog!(self.ctx, Level::Info, "parse time: {:?}", st.elapsed());
             let scope = Scope::root();
             let (ori, exprs) = self.load_exprs(source).await?;
-            let exprs =
-                try_join_all(exprs.iter().map(|e| e.resolve_modules(&self.resolvers)))
-                    .await?;
-            info!("resolve time: {:?}", st.elapsed());
+            let exprs = try_join_all(
+                exprs
+                    .iter()
+                    .map(|e| e.resolve_modules(&self.resolvers, &self.module_cache)),
+            )
+            .await?;
+            rtlog!(self.ctx, Level::Info, "resolve time: {:?}", st.elapsed());
             let mut nodes: LPooled<Vec<_>> = LPooled::take();
             for e in exprs.iter() {
                 let res = compile(&mut self.ctx, self.flags, &scope, e.clone())
@@ -468,16 +636,22 @@ impl<X: GXExt> GX<X> {
         res
     }
 
-    async fn load(&mut self, rt: GXHandle<X>, source: &Source) -> Result<CompRes<X>> {
+    async fn load(
+        &mut self,
+        rt: GXHandle<X>,
+        source: &Source,
+        resolvers: &Arc<[ModuleResolver]>,
+    ) -> Result<CompRes<X>> {
         let scope = Scope::root();
         let st = Instant::now();
         let (ori, exprs) = self.load_exprs(source).await?;
-        info!("parse time: {:?}", st.elapsed());
+        rtlog!(self.ctx, Level::Info, "parse time: {:?}", st.elapsed());
         let st = Instant::now();
-        let exprs =
-            try_join_all(exprs.iter().map(|e| e.resolve_modules(&self.resolvers)))
-                .await?;
-        info!("resolve time: {:?}", st.elapsed());
+        let exprs = try_join_all(
+            exprs.iter().map(|e| e.resolve_modules(resolvers, &self.module_cache)),
+        )
+        .await?;
+        rtlog!(self.ctx, Level::Info, "resolve time: {:?}", st.elapsed());
         let mut res = smallvec![];
         for e in exprs.iter() {
             let top_id = e.id;
@@ -485,27 +659,37 @@ impl<X: GXExt> GX<X> {
                 .with_context(|| ori.clone())?;
             let has_out = is_output(&n);
             let typ = n.typ().clone();
+            let refs = external_refs(&n);
             self.nodes.insert(top_id, n);
             self.ctx.rt.updated.insert(top_id, true);
-            res.push(CompExp { id: top_id, output: has_out, typ, rt: rt.clone() })
+            res.push(CompExp { id: top_id, output: has_out, typ, refs, rt: rt.clone() })
         }
-        Ok(CompRes { exprs: res, env: self.ctx.env.clone() })
+        Ok(CompRes { exprs: res, env: self.ctx.env.clone(), srcs: exprs })
     }
 
     fn compile_callable(&mut self, v: Value, rt: GXHandle<X>) -> Result<Callable<X>> {
         let lb = v
             .downcast_ref::<LambdaDef<GXRt<X>, X::UserEvent>>()
             .ok_or_else(|| anyhow!("invalid lambda {v}"))?;
-        let args = lb.typ.args.iter();
-        let args = args
-            .map(|a| {
-                if a.label.as_ref().map(|(_, opt)| *opt).unwrap_or(false) {
-                    bail!("can't call lambda with an optional argument from rust")
-                } else {
-                    Ok(BindId::new())
+        let args = lb.typ.args.iter().map(|_| BindId::new()).collect::<Box<[_]>>();
+        let params = lb
+            .typ
+            .args
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                let default = lb.argspec.get(i).and_then(|a| a.labeled.clone().flatten());
+                let default_value =
+                    default.as_ref().and_then(|e| self.eval_default(lb, e.clone()));
+                Param {
+                    label: a.label.as_ref().map(|(l, _)| l.clone()),
+                    typ: a.typ.clone(),
+                    optional: a.label.as_ref().map(|(_, opt)| *opt).unwrap_or(false),
+                    default,
+                    default_value,
                 }
             })
-            .collect::<Result<Box<[_]>>>()?;
+            .collect::<Box<[_]>>();
         let eid = ExprId::new();
         let argn = lb.typ.args.iter().zip(args.iter());
         let argn = argn
@@ -520,7 +704,33 @@ impl<X: GXExt> GX<X> {
         self.callables.insert(cid, CallableInt { expr: eid, args });
         self.nodes.insert(eid, n);
         let env = self.ctx.env.clone();
-        Ok(Callable { expr: eid, rt, env, id: cid, typ: (*lb.typ).clone() })
+        Ok(Callable { expr: eid, rt, env, id: cid, params, typ: (*lb.typ).clone() })
+    }
+
+    /// evaluate the default value expression of a lambda argument, return
+    /// None if it can't be compiled or doesn't produce a value immediately
+    fn eval_default(
+        &mut self,
+        lb: &LambdaDef<GXRt<X>, X::UserEvent>,
+        e: Expr,
+    ) -> Option<Value> {
+        let scope =
+            Scope { lexical: lb.scope.lexical.clone(), dynamic: expr::ModPath::root() };
+        let res = self.ctx.with_restored(lb.env.clone(), |ctx| {
+            compile(ctx, BitFlags::empty(), &scope, e)
+        });
+        let mut n = match res {
+            Ok(n) => n,
+            Err(e) => {
+                rtlog!(self.ctx, Level::Debug, "failed to compile default value {e:?}");
+                return None;
+            }
+        };
+        self.event.init = true;
+        let v = n.update(&mut self.ctx, &mut self.event);
+        self.event.clear();
+        n.delete(&mut self.ctx);
+        v
     }
 
     fn compile_ref(&mut self, rt: GXHandle<X>, id: BindId) -> Result<Ref<X>> {
@@ -561,11 +771,42 @@ impl<X: GXExt> GX<X> {
         Ok(())
     }
 
+    fn subscriptions(&self, id: ExprId) -> FxHashSet<Path> {
+        self.ctx
+            .rt
+            .subscribed
+            .iter()
+            .filter(|(_, exprs)| exprs.contains_key(&id))
+            .filter_map(|(sid, _)| self.ctx.rt.subscribed_paths.get(sid).cloned())
+            .collect()
+    }
+
+    fn profile(&self) -> Result<Vec<(ExprId, u64)>> {
+        match &self.profile {
+            None => bail!("profiling is not enabled"),
+            Some(profile) => {
+                let mut res = profile.iter().map(|(id, n)| (*id, *n)).collect::<Vec<_>>();
+                res.sort_by(|(_, n0), (_, n1)| n1.cmp(n0));
+                Ok(res)
+            }
+        }
+    }
+
+    fn graph_dot(&self, id: ExprId) -> Result<String> {
+        match self.nodes.get(&id) {
+            None => bail!("no compiled expression with id {id:?}"),
+            Some(n) => Ok(dot::to_dot(n.spec())),
+        }
+    }
+
     fn delete_callable(&mut self, id: CallableId) {
         if let Some(c) = self.callables.remove(&id) {
             if let Some(mut n) = self.nodes.shift_remove(&c.expr) {
                 n.delete(&mut self.ctx)
             }
+            if let Some(profile) = &mut self.profile {
+                profile.remove(&c.expr);
+            }
         }
     }
 
@@ -579,13 +820,24 @@ impl<X: GXExt> GX<X> {
         let mut rpcs = vec![];
         let onemin = Duration::from_secs(60);
         'main: loop {
+            if let Some(mut waiting) = self.shutdown.take() {
+                self.unpublish_all().await;
+                // drop the program and library state before replying, so
+                // resources it holds (files, databases) are released by
+                // the time shutdown returns
+                drop(self);
+                for res in waiting.drain(..) {
+                    let _ = res.send(());
+                }
+                break 'main Ok(());
+            }
             let now = Instant::now();
-            let ready = self.cycle_ready();
+            let ready = !self.paused && self.cycle_ready();
             let mut updates = None;
             let mut writes = None;
             macro_rules! peek {
                 (updates) => {
-                    if self.ctx.rt.net_updates.is_empty() {
+                    if !self.paused && self.ctx.rt.net_updates.is_empty() {
                         while let Ok(mut up) = self.ctx.rt.updates.try_recv() {
                             match &mut updates {
                                 None => updates = Some(up),
@@ -595,7 +847,7 @@ impl<X: GXExt> GX<X> {
                     }
                 };
                 (writes) => {
-                    if self.ctx.rt.net_writes.is_empty() {
+                    if !self.paused && self.ctx.rt.net_writes.is_empty() {
                         if let Ok(wr) = self.ctx.rt.writes.try_recv() {
                             writes = Some(wr);
                         }
@@ -626,7 +878,7 @@ impl<X: GXExt> GX<X> {
                     }
                 };
                 (rpcs) => {
-                    if self.ctx.rt.rpc_overflow.is_empty() {
+                    if !self.paused && self.ctx.rt.rpc_overflow.is_empty() {
                         while let Ok(up) = self.ctx.rt.rpcs.try_recv() {
                             rpcs.push(up);
                         }
@@ -643,21 +895,21 @@ impl<X: GXExt> GX<X> {
             }
             select! {
                 rp = maybe_next(
-                    self.ctx.rt.rpc_overflow.is_empty(),
+                    !self.paused && self.ctx.rt.rpc_overflow.is_empty(),
                     &mut self.ctx.rt.rpcs
                 ) => {
                     rpcs.push(rp);
                     peek!(updates, tasks, custom_tasks, watches, var_watches, writes, rpcs, input)
                 }
                 wr = maybe_next(
-                    self.ctx.rt.net_writes.is_empty(),
+                    !self.paused && self.ctx.rt.net_writes.is_empty(),
                     &mut self.ctx.rt.writes
                 ) => {
                     writes = Some(wr);
                     peek!(updates, tasks, custom_tasks, watches, var_watches, rpcs, input);
                 },
                 up = maybe_next(
-                    self.ctx.rt.net_updates.is_empty(),
+                    !self.paused && self.ctx.rt.net_updates.is_empty(),
                     &mut self.ctx.rt.updates
                 ) => {
                     updates = Some(up);
@@ -696,13 +948,18 @@ impl<X: GXExt> GX<X> {
                 },
                 n = to_rt.recv_many(&mut input, 100000) => {
                     if n == 0 {
+                        self.unpublish_all().await;
                         break 'main Ok(())
                     }
                     peek!(updates, writes, watches, tasks, var_watches, custom_tasks, rpcs);
                 },
                 r = self.ctx.rt.ext.update_sources() => {
                     if let Err(e) = r {
-                        error!("failed to update custom event sources {e:?}")
+                        rtlog!(
+                            self.ctx,
+                            Level::Error,
+                            "failed to update custom event sources {e:?}"
+                        )
                     }
                     peek!(updates, writes, watches, tasks, var_watches, custom_tasks, rpcs, input);
                 },
@@ -719,6 +976,14 @@ impl<X: GXExt> GX<X> {
             }
             let mut batch = self.batch_pool.take();
             self.process_input_batch(&mut tasks, &mut input, &mut batch).await;
+            // updates and writes are not read while paused, so if we have
+            // some here the pause arrived with them, finish that cycle first
+            if self.paused && updates.is_none() && writes.is_none() {
+                if !batch.is_empty() && self.sub.send(batch).await.is_err() {
+                    rtlog!(self.ctx, Level::Error, "could not send batch")
+                }
+                continue 'main;
+            }
             self.do_cycle(
                 updates,
                 writes,
@@ -730,6 +995,7 @@ impl<X: GXExt> GX<X> {
                 batch,
             )
             .await;
+            self.check_tests_done();
             if !self.ctx.rt.rpc_clients.is_empty() {
                 if now - self.last_rpc_gc >= onemin {
                     self.last_rpc_gc = now;
@@ -10,18 +10,19 @@
 //! can be interacted with via a handle. All features of the standard
 //! library are supported by this runtime.
 use anyhow::{anyhow, bail, Result};
-use arcstr::ArcStr;
+use arcstr::{literal, ArcStr};
 use derive_builder::Builder;
 use enumflags2::BitFlags;
-use fxhash::FxHashSet;
+use fxhash::{FxHashMap, FxHashSet};
 use graphix_compiler::{
     env::Env,
-    expr::{ExprId, ModPath, ModuleResolver, Source},
+    expr::{Expr, ExprId, ModPath, ModuleResolver, Source, Span},
     typ::{FnType, Type},
     BindId, CFlag, Event, ExecCtx, NoUserEvent, Scope, UserEvent,
 };
-use log::error;
+use log::{error, LevelFilter};
 use netidx::{
+    path::Path,
     protocol::valarray::ValArray,
     publisher::{Value, WriteRequest},
     subscriber::{self, SubId},
@@ -37,13 +38,16 @@ use tokio::{
         mpsc::{self as tmpsc},
         oneshot,
     },
-    task::{self, JoinHandle},
+    task, time,
 };
 
+mod dot;
 mod gx;
 mod rt;
+mod stubs;
 use gx::GX;
 pub use rt::GXRt;
+pub use stubs::{builtin_module, builtin_module_source};
 
 /// Trait to extend the event loop
 ///
@@ -125,9 +129,21 @@ pub struct CompExp<X: GXExt> {
     pub id: ExprId,
     pub typ: Type,
     pub output: bool,
+    /// The variables defined outside the expression that it refers to
+    pub refs: FxHashSet<BindId>,
     rt: GXHandle<X>,
 }
 
+impl<X: GXExt> CompExp<X> {
+    /// Get the netidx paths the expression is currently subscribed to
+    ///
+    /// Subscriptions are made as the expression runs, so this reflects the
+    /// state of the expression when the runtime processes the request.
+    pub async fn subscriptions(&self) -> Result<FxHashSet<Path>> {
+        self.rt.exec(|res| ToGX::Subscriptions { id: self.id, res }).await
+    }
+}
+
 impl<X: GXExt> Drop for CompExp<X> {
     fn drop(&mut self) {
         let _ = self.rt.0.tx.send(ToGX::Delete { id: self.id });
@@ -138,6 +154,17 @@ impl<X: GXExt> Drop for CompExp<X> {
 pub struct CompRes<X: GXExt> {
     pub exprs: SmallVec<[CompExp<X>; 1]>,
     pub env: Env,
+    // the resolved source expressions, for spans
+    srcs: Vec<Expr>,
+}
+
+impl<X: GXExt> CompRes<X> {
+    /// The source span of every expression in the compiled program,
+    /// including sub expressions, by id. See `Expr::spans`. The spans
+    /// are computed by each call, so only ask for them when they are needed.
+    pub fn spans(&self) -> FxHashMap<ExprId, Span> {
+        self.srcs.iter().flat_map(|e| e.spans()).collect()
+    }
 }
 
 pub struct Ref<X: GXExt> {
@@ -249,10 +276,27 @@ impl<X: GXExt, T: Into<Value> + FromValue + Clone> TRef<X, T> {
 
 atomic_id!(CallableId);
 
+/// A parameter of a callable function
+#[derive(Debug, Clone)]
+pub struct Param {
+    /// The label of the parameter, None if it is positional
+    pub label: Option<ArcStr>,
+    /// The type of the parameter
+    pub typ: Type,
+    /// True if the parameter is labeled and may be omitted by the caller
+    pub optional: bool,
+    /// The default value expression of an optional parameter
+    pub default: Option<Expr>,
+    /// The value of the default expression when the callable was compiled,
+    /// if it produced one
+    pub default_value: Option<Value>,
+}
+
 pub struct Callable<X: GXExt> {
     rt: GXHandle<X>,
     id: CallableId,
     env: Env,
+    params: Box<[Param]>,
     pub typ: FnType,
     pub expr: ExprId,
 }
@@ -269,6 +313,11 @@ impl<X: GXExt> Callable<X> {
         self.id
     }
 
+    /// Get the parameters of the function in signature order
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
     /// Call the lambda with args
     ///
     /// Argument types and arity will be checked and an error will be returned
@@ -287,6 +336,40 @@ impl<X: GXExt> Callable<X> {
         self.call_unchecked(args).await
     }
 
+    /// Call the lambda with labeled args
+    ///
+    /// Args may be given in any order. Omitted optional args take the value
+    /// of their default at the time the callable was compiled. It is an error
+    /// to pass an unknown label, to omit a required argument, or to call a
+    /// function that has unlabeled arguments. Types are checked as in `call`.
+    pub async fn call_named(&self, args: Vec<(ArcStr, Value)>) -> Result<()> {
+        let mut named: FxHashMap<ArcStr, Value> = FxHashMap::default();
+        for (l, v) in args {
+            if !self.params.iter().any(|p| p.label.as_ref() == Some(&l)) {
+                bail!("unknown argument {l}")
+            }
+            if named.insert(l.clone(), v).is_some() {
+                bail!("argument {l} passed more than once")
+            }
+        }
+        let mut res = Vec::with_capacity(self.params.len());
+        for (i, p) in self.params.iter().enumerate() {
+            let l = match &p.label {
+                Some(l) => l,
+                None => bail!("argument {i} is not labeled and can't be passed by name"),
+            };
+            match named.remove(l) {
+                Some(v) => res.push(v),
+                None if p.optional => match &p.default_value {
+                    Some(v) => res.push(v.clone()),
+                    None => bail!("the default value of argument {l} is not available"),
+                },
+                None => bail!("missing required argument {l}"),
+            }
+        }
+        self.call(ValArray::from_iter_exact(res.into_iter())).await
+    }
+
     /// Call the lambda with args. Argument types and arity will NOT
     /// be checked. This can result in a runtime panic, invalid
     /// results, and probably other bad things.
@@ -308,6 +391,41 @@ impl<X: GXExt> Callable<X> {
     }
 }
 
+/// The outcome of a graphix test case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestOutcome {
+    /// The case has not produced a result yet
+    Running,
+    Passed,
+    /// The case failed, with the error it returned or raised
+    Failed(ArcStr),
+}
+
+/// The result of a test case registered with `test::case`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub name: ArcStr,
+    pub outcome: TestOutcome,
+}
+
+/// The test cases registered by `test::case`, in registration order. This is
+/// kept in the `LibState` of the execution context, see `GXHandle::run_tests`.
+#[derive(Debug, Default)]
+pub struct TestCases(pub Vec<TestResult>);
+
+atomic_id!(TaskId);
+
+/// An async operation spawned by a builtin that hasn't finished yet, such as
+/// an http request or a file read, see `GXHandle::list_tasks`
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    pub id: TaskId,
+    /// What the task is doing, usually the name of the builtin that spawned it
+    pub desc: ArcStr,
+    /// How long the task has been running
+    pub elapsed: Duration,
+}
+
 enum DeferredCall {
     Call(ValArray, oneshot::Sender<Result<()>>),
     CallUnchecked(ValArray, oneshot::Sender<Result<()>>),
@@ -411,6 +529,12 @@ enum ToGX<X: GXExt> {
         rt: GXHandle<X>,
         res: oneshot::Sender<Result<CompRes<X>>>,
     },
+    LoadWithResolvers {
+        path: Source,
+        resolvers: Vec<ModuleResolver>,
+        rt: GXHandle<X>,
+        res: oneshot::Sender<Result<CompRes<X>>>,
+    },
     Check {
         path: Source,
         res: oneshot::Sender<Result<()>>,
@@ -420,6 +544,12 @@ enum ToGX<X: GXExt> {
         rt: GXHandle<X>,
         res: oneshot::Sender<Result<CompRes<X>>>,
     },
+    CompileIn {
+        text: ArcStr,
+        env: Env,
+        rt: GXHandle<X>,
+        res: oneshot::Sender<Result<CompRes<X>>>,
+    },
     CompileCallable {
         id: Value,
         rt: GXHandle<X>,
@@ -441,6 +571,37 @@ enum ToGX<X: GXExt> {
     DeleteCallable {
         id: CallableId,
     },
+    Subscriptions {
+        id: ExprId,
+        res: oneshot::Sender<FxHashSet<Path>>,
+    },
+    Profile {
+        res: oneshot::Sender<Result<Vec<(ExprId, u64)>>>,
+    },
+    GraphDot {
+        id: ExprId,
+        res: oneshot::Sender<Result<String>>,
+    },
+    TestResults {
+        clear: bool,
+        res: oneshot::Sender<Vec<TestResult>>,
+    },
+    WaitTests {
+        res: oneshot::Sender<Vec<TestResult>>,
+    },
+    ListTasks {
+        res: oneshot::Sender<Vec<TaskInfo>>,
+    },
+    CancelTask {
+        id: TaskId,
+        res: oneshot::Sender<bool>,
+    },
+    Shutdown {
+        res: Option<oneshot::Sender<()>>,
+    },
+    Pause,
+    Resume,
+    SetLogLevel(LevelFilter),
 }
 
 #[derive(Debug, Clone)]
@@ -451,18 +612,21 @@ pub enum GXEvent {
 
 struct GXHandleInner<X: GXExt> {
     tx: tmpsc::UnboundedSender<ToGX<X>>,
-    task: JoinHandle<()>,
 }
 
 impl<X: GXExt> Drop for GXHandleInner<X> {
     fn drop(&mut self) {
-        self.task.abort()
+        let _ = self.tx.send(ToGX::Shutdown { res: None });
     }
 }
 
 /// A handle to a running GX instance.
 ///
-/// Drop the handle to shutdown the associated background tasks.
+/// Drop the last handle to shutdown the associated background tasks, see
+/// `GXHandle::shutdown`. Dropping the handle only asks the runtime to shut
+/// down, the task isn't aborted, so that it can unpublish cleanly. A
+/// runtime that is wedged, e.g. in a builtin that never returns, will
+/// never see the request, and will outlive its last handle.
 pub struct GXHandle<X: GXExt>(Arc<GXHandleInner<X>>);
 
 impl<X: GXExt> fmt::Debug for GXHandle<X> {
@@ -513,6 +677,21 @@ impl<X: GXExt> GXHandle<X> {
         Ok(self.exec(|tx| ToGX::Compile { text, res: tx, rt: self.clone() }).await??)
     }
 
+    /// Compile and execute a graphix expression in the lexical environment
+    /// `env` instead of the current one
+    ///
+    /// `env` is usually a snapshot captured earlier, e.g. from `get_env` or
+    /// the `env` of a `CompRes`. Names are resolved in `env` for this
+    /// compilation only, the runtime's environment is restored afterwards.
+    /// The `env` of the returned `CompRes` is `env` with anything the
+    /// expression defined added, so it can be passed to a later `compile_in`.
+    /// Otherwise this behaves like `compile`.
+    pub async fn compile_in(&self, text: ArcStr, env: Env) -> Result<CompRes<X>> {
+        Ok(self
+            .exec(|tx| ToGX::CompileIn { text, env, res: tx, rt: self.clone() })
+            .await??)
+    }
+
     /// Load and execute a file or netidx value
     ///
     /// When the `CompExp` objects contained in the `CompRes` are
@@ -523,6 +702,25 @@ impl<X: GXExt> GXHandle<X> {
         Ok(self.exec(|tx| ToGX::Load { path, res: tx, rt: self.clone() }).await??)
     }
 
+    /// Load and execute a file or netidx value, searching the
+    /// specified resolvers for modules before the resolvers the
+    /// runtime was configured with. The extra resolvers are only used
+    /// by this load.
+    pub async fn load_with_resolvers(
+        &self,
+        path: Source,
+        resolvers: Vec<ModuleResolver>,
+    ) -> Result<CompRes<X>> {
+        Ok(self
+            .exec(|tx| ToGX::LoadWithResolvers {
+                path,
+                resolvers,
+                res: tx,
+                rt: self.clone(),
+            })
+            .await??)
+    }
+
     /// Compile a callable interface to a lambda id
     ///
     /// This is how you call a lambda directly from rust. When the returned
@@ -598,6 +796,51 @@ impl<X: GXExt> GXHandle<X> {
         self.0.tx.send(ToGX::Set { id, v }).map_err(|_| anyhow!("runtime is dead"))
     }
 
+    /// Delete the top level expression identified by `id`
+    ///
+    /// This is what happens when the `CompExp` for the expression is
+    /// dropped. Deleting an expression that doesn't exist, or was
+    /// already deleted, does nothing.
+    pub fn delete(&self, id: ExprId) -> Result<()> {
+        self.0.tx.send(ToGX::Delete { id }).map_err(|_| anyhow!("runtime is dead"))
+    }
+
+    /// Get the number of times each top level expression has updated
+    ///
+    /// The result is sorted by count, most frequently updated first. Profiling
+    /// must be enabled in the `GXConfig`, otherwise an error is returned.
+    pub async fn profile(&self) -> Result<Vec<(ExprId, u64)>> {
+        self.exec(|res| ToGX::Profile { res }).await?
+    }
+
+    /// Render the dataflow graph of a compiled top level expression as
+    /// Graphviz DOT
+    ///
+    /// Nodes are the sub expressions of the compiled expression, labeled with
+    /// their kind and source position. Solid edges run from each sub
+    /// expression to the expression that consumes it, dashed edges connect
+    /// variables to the expressions that reference them.
+    pub async fn graph_dot(&self, id: ExprId) -> Result<String> {
+        self.exec(|res| ToGX::GraphDot { id, res }).await?
+    }
+
+    /// List the async operations spawned by builtins that are still running,
+    /// longest running first
+    pub async fn list_tasks(&self) -> Result<Vec<TaskInfo>> {
+        self.exec(|res| ToGX::ListTasks { res }).await
+    }
+
+    /// Cancel a running async operation
+    ///
+    /// The builtin that spawned the task will never receive its result,
+    /// if the result would have been a value it receives a `Cancelled`
+    /// error instead, and will start a new task the next time its
+    /// arguments change. Return false if there is no such task, or it already finished, in
+    /// which case its result may still be delivered.
+    pub async fn cancel_task(&self, id: TaskId) -> Result<bool> {
+        self.exec(|res| ToGX::CancelTask { id, res }).await
+    }
+
     /// Call a callable by id with the given arguments
     ///
     /// This is a fire-and-forget call that does not wait for the result.
@@ -605,6 +848,75 @@ impl<X: GXExt> GXHandle<X> {
     pub fn call(&self, id: CallableId, args: ValArray) -> Result<()> {
         self.0.tx.send(ToGX::Call { id, args }).map_err(|_| anyhow!("runtime is dead"))
     }
+
+    /// Load a file of graphix tests, run them, and return their results
+    ///
+    /// Every `test::case` in the file runs as soon as it is loaded. This
+    /// waits until every registered case has passed or failed, or until
+    /// `timeout` elapses, in which case the cases that are still running are
+    /// reported as failed. A file with no cases returns an empty result
+    /// as soon as it has been initialized. The file is deleted before
+    /// returning. Don't run more than one set of tests at a time on the same
+    /// runtime, they share the registry of cases.
+    pub async fn run_tests(
+        &self,
+        path: Source,
+        timeout: Duration,
+    ) -> Result<Vec<TestResult>> {
+        self.exec(|res| ToGX::TestResults { clear: true, res }).await?;
+        let res = self.load(path).await?;
+        let wait = self.exec(|res| ToGX::WaitTests { res });
+        let mut results = match time::timeout(timeout, wait).await {
+            Ok(results) => results?,
+            Err(_) => self.exec(|res| ToGX::TestResults { clear: false, res }).await?,
+        };
+        drop(res);
+        for r in results.iter_mut() {
+            if r.outcome == TestOutcome::Running {
+                r.outcome = TestOutcome::Failed(literal!("timed out"))
+            }
+        }
+        Ok(results)
+    }
+
+    /// Shutdown the runtime
+    ///
+    /// Everything published by the runtime, values and rpcs, is unpublished,
+    /// and the runtime waits briefly for the resolver to acknowledge it
+    /// before exiting, so no stale entries are left behind. If the resolver
+    /// can't be reached the runtime exits anyway. When this returns the
+    /// program and all library state have been dropped. Dropping the last
+    /// handle does the same thing without waiting for it to finish. Once
+    /// the runtime has shut down every other call on this handle will fail.
+    pub async fn shutdown(&self) -> Result<()> {
+        self.exec(|res| ToGX::Shutdown { res: Some(res) }).await
+    }
+
+    /// Stop running cycles until `resume` is called
+    ///
+    /// While paused no updates are delivered. Variables that are set,
+    /// subscription updates, writes, and task results are queued, and will be
+    /// delivered in order once the runtime is resumed. Compiling and deleting
+    /// expressions still works while paused. A cycle that is already in
+    /// progress when the pause is received will complete.
+    pub fn pause(&self) -> Result<()> {
+        self.0.tx.send(ToGX::Pause).map_err(|_| anyhow!("runtime is dead"))
+    }
+
+    /// Resume running cycles after a `pause`, delivering everything that was
+    /// queued while the runtime was paused
+    pub fn resume(&self) -> Result<()> {
+        self.0.tx.send(ToGX::Resume).map_err(|_| anyhow!("runtime is dead"))
+    }
+
+    /// Set the most verbose level this runtime will log at, e.g. to see
+    /// the compile and typecheck timings at `LevelFilter::Info`. This only
+    /// affects the messages logged by this runtime, other runtimes in the
+    /// process keep their own level. The log crate's global max level and
+    /// the installed logger still apply, so this can only filter more.
+    pub fn set_log_level(&self, level: LevelFilter) -> Result<()> {
+        self.0.tx.send(ToGX::SetLogLevel(level)).map_err(|_| anyhow!("runtime is dead"))
+    }
 }
 
 #[derive(Builder)]
@@ -631,6 +943,10 @@ pub struct GXConfig<X: GXExt> {
     /// The set of compiler flags. Default empty.
     #[builder(default)]
     flags: BitFlags<CFlag>,
+    /// Count how many times each top level expression updates, see
+    /// `GXHandle::profile`. Default false.
+    #[builder(default)]
+    profile: bool,
 }
 
 impl<X: GXExt> GXConfig<X> {
@@ -653,7 +969,7 @@ impl<X: GXExt> GXConfig<X> {
     pub async fn start(self) -> Result<GXHandle<X>> {
         let (init_tx, init_rx) = oneshot::channel();
         let (tx, rx) = tmpsc::unbounded_channel();
-        let task = task::spawn(async move {
+        task::spawn(async move {
             match GX::new(self).await {
                 Ok(bs) => {
                     let _ = init_tx.send(Ok(()));
@@ -667,6 +983,6 @@ impl<X: GXExt> GXConfig<X> {
             };
         });
         init_rx.await??;
-        Ok(GXHandle(Arc::new(GXHandleInner { tx, task })))
+        Ok(GXHandle(Arc::new(GXHandleInner { tx })))
     }
 }
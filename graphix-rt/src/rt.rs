@@ -1,16 +1,16 @@
-use crate::{GXExt, UpdateBatch, WriteBatch};
+use crate::{GXExt, TaskId, TaskInfo, UpdateBatch, WriteBatch};
 use anyhow::{bail, Result};
 use arcstr::{literal, ArcStr};
 use chrono::prelude::*;
 use compact_str::format_compact;
 use futures::{channel::mpsc, stream::SelectAll, FutureExt};
 use fxhash::FxHashMap;
-use graphix_compiler::{expr::ExprId, BindId, CustomBuiltinType, Rt};
+use graphix_compiler::{errf, expr::ExprId, typ::Type, BindId, CustomBuiltinType, Rt};
 use netidx::{
     path::Path,
     protocol::valarray::ValArray,
     publisher::{self, Id, PublishFlags, Publisher, Val, Value, WriteRequest},
-    resolver_client::ChangeTracker,
+    resolver_client::{ChangeTracker, Glob, GlobSet},
     subscriber::{self, Dval, SubId, Subscriber, UpdatesFlags},
 };
 use netidx_protocols::rpc::{
@@ -37,22 +37,43 @@ pub(super) struct RpcClient {
     pub(super) last_used: Instant,
 }
 
+/// a task spawned on behalf of a builtin, kept so it can be listed
+/// and cancelled from the handle
+#[derive(Debug)]
+pub(super) struct RunningTask {
+    desc: ArcStr,
+    // the variable the result is delivered to, if any
+    id: Option<BindId>,
+    started: Instant,
+    abort: task::AbortHandle,
+}
+
 #[derive(Debug)]
 pub struct GXRt<X: GXExt> {
     pub(super) by_ref: FxHashMap<BindId, FxHashMap<ExprId, usize>>,
     pub(super) subscribed: FxHashMap<SubId, FxHashMap<ExprId, usize>>,
+    pub(super) subscribed_paths: FxHashMap<SubId, Path>,
+    pub(super) subscriptions: FxHashMap<Path, Dval>,
     pub(super) published: FxHashMap<Id, FxHashMap<ExprId, usize>>,
+    pub(super) published_types: FxHashMap<Id, Type>,
+    pub(super) published_vals: FxHashMap<Id, (Path, Val)>,
+    pub(super) published_paths: FxHashMap<Path, Id>,
     pub(super) var_updates: VecDeque<(BindId, Value)>,
     pub(super) custom_updates: VecDeque<(BindId, Box<dyn CustomBuiltinType>)>,
     pub(super) net_updates: VecDeque<(SubId, subscriber::Event)>,
+    // the last value of a shared subscription, to be delivered only to the
+    // expression that just subscribed
+    pub(super) net_last: FxHashMap<ExprId, Vec<(SubId, subscriber::Event)>>,
     pub(super) net_writes: VecDeque<(Id, WriteRequest)>,
     pub(super) rpc_overflow: VecDeque<(BindId, RpcCall)>,
     pub(super) rpc_clients: FxHashMap<Path, RpcClient>,
     pub(super) published_rpcs: FxHashMap<Path, rpc::server::Proc>,
     pub(super) pending_unsubscribe: VecDeque<(Instant, Dval)>,
     pub(super) change_trackers: FxHashMap<BindId, Arc<Mutex<ChangeTracker>>>,
+    pub(super) last_globs: FxHashMap<BindId, Arc<Mutex<Value>>>,
     pub(super) tasks: JoinSet<(BindId, Value)>,
     pub(super) custom_tasks: JoinSet<(BindId, Box<dyn CustomBuiltinType>)>,
+    pub(super) running: FxHashMap<TaskId, RunningTask>,
     pub(super) watches:
         SelectAll<mpsc::Receiver<GPooled<Vec<(BindId, Box<dyn CustomBuiltinType>)>>>>,
     pub(super) var_watches: SelectAll<mpsc::Receiver<GPooled<Vec<(BindId, Value)>>>>,
@@ -94,18 +115,26 @@ impl<X: GXExt> GXRt<X> {
             var_updates: VecDeque::new(),
             custom_updates: VecDeque::new(),
             net_updates: VecDeque::new(),
+            net_last: HashMap::default(),
             net_writes: VecDeque::new(),
             rpc_overflow: VecDeque::new(),
             rpc_clients: HashMap::default(),
             subscribed: HashMap::default(),
+            subscribed_paths: HashMap::default(),
+            subscriptions: HashMap::default(),
             pending_unsubscribe: VecDeque::new(),
             published: HashMap::default(),
+            published_types: HashMap::default(),
+            published_vals: HashMap::default(),
+            published_paths: HashMap::default(),
             change_trackers: HashMap::default(),
+            last_globs: HashMap::default(),
             published_rpcs: HashMap::default(),
             updated: HashMap::default(),
             ext: X::default(),
             tasks,
             custom_tasks,
+            running: HashMap::default(),
             watches,
             var_watches,
             dummy_watch_tx,
@@ -121,6 +150,71 @@ impl<X: GXExt> GXRt<X> {
             rpcs,
         }
     }
+
+    /// stop publishing every value and rpc
+    pub(super) fn unpublish_all(&mut self) {
+        self.published.clear();
+        self.published_types.clear();
+        self.published_vals.clear();
+        self.published_paths.clear();
+        self.published_rpcs.clear();
+    }
+
+    /// stop publishing id, dropping the val unpublishes it
+    fn remove_published(&mut self, id: Id) {
+        self.published.remove(&id);
+        self.published_types.remove(&id);
+        if let Some((path, _)) = self.published_vals.remove(&id) {
+            self.published_paths.remove(&path);
+        }
+    }
+
+    fn register_task(
+        &mut self,
+        desc: ArcStr,
+        id: Option<BindId>,
+        abort: &task::AbortHandle,
+    ) {
+        self.running.retain(|_, t| !t.abort.is_finished());
+        let t =
+            RunningTask { desc, id, started: Instant::now(), abort: abort.clone() };
+        self.running.insert(TaskId::new(), t);
+    }
+
+    /// the tasks spawned by builtins that haven't finished, longest
+    /// running first
+    pub(super) fn list_tasks(&mut self) -> Vec<TaskInfo> {
+        self.running.retain(|_, t| !t.abort.is_finished());
+        let now = Instant::now();
+        let mut res = self
+            .running
+            .iter()
+            .map(|(id, t)| TaskInfo {
+                id: *id,
+                desc: t.desc.clone(),
+                elapsed: now - t.started,
+            })
+            .collect::<Vec<_>>();
+        res.sort_by(|t0, t1| t1.elapsed.cmp(&t0.elapsed));
+        res
+    }
+
+    /// abort the task, return false if it already finished. If the task
+    /// delivers its result to a variable then a `Cancelled` error is
+    /// delivered in its place.
+    pub(super) fn cancel_task(&mut self, id: TaskId) -> bool {
+        match self.running.remove(&id) {
+            Some(t) if !t.abort.is_finished() => {
+                t.abort.abort();
+                if let Some(id) = t.id {
+                    let e = errf!("Cancelled", "{} was cancelled", t.desc);
+                    self.set_var(id, e)
+                }
+                true
+            }
+            Some(_) | None => false,
+        }
+    }
 }
 
 macro_rules! or_err {
@@ -157,16 +251,24 @@ impl<X: GXExt> Rt for GXRt<X> {
             var_updates,
             custom_updates,
             net_updates,
+            net_last,
             net_writes,
             rpc_clients,
             rpc_overflow,
             subscribed,
+            subscribed_paths,
+            subscriptions,
             published,
+            published_types,
+            published_vals,
+            published_paths,
             published_rpcs,
             pending_unsubscribe,
             change_trackers,
+            last_globs,
             tasks,
             custom_tasks,
+            running,
             watches,
             var_watches,
             dummy_watch_tx,
@@ -189,18 +291,26 @@ impl<X: GXExt> Rt for GXRt<X> {
         var_updates.clear();
         custom_updates.clear();
         net_updates.clear();
+        net_last.clear();
         net_writes.clear();
         rpc_overflow.clear();
         rpc_clients.clear();
         subscribed.clear();
+        subscribed_paths.clear();
+        subscriptions.clear();
         published.clear();
+        published_types.clear();
+        published_vals.clear();
+        published_paths.clear();
         published_rpcs.clear();
         pending_unsubscribe.clear();
         change_trackers.clear();
+        last_globs.clear();
         *tasks = JoinSet::new();
         tasks.spawn(async { future::pending().await });
         *custom_tasks = JoinSet::new();
         custom_tasks.spawn(async { future::pending().await });
+        running.clear();
         *watches = SelectAll::new();
         let (tx, rx) = mpsc::channel(1);
         *dummy_watch_tx = tx;
@@ -286,9 +396,29 @@ impl<X: GXExt> Rt for GXRt<X> {
     }
 
     fn subscribe(&mut self, flags: UpdatesFlags, path: Path, ref_by: ExprId) -> Dval {
-        let dval =
-            self.subscriber.subscribe_updates(path, [(flags, self.updates_tx.clone())]);
+        // every expression subscribed to the same path shares one dval
+        // and one registration of updates_tx. If flags ask for the last
+        // value it is delivered to ref_by alone, the other subscribers
+        // already have it.
+        let dval = match self.subscriptions.get(&path) {
+            Some(dval) => {
+                if flags.contains(UpdatesFlags::BEGIN_WITH_LAST)
+                    && let ev @ subscriber::Event::Update(_) = dval.last()
+                {
+                    self.net_last.entry(ref_by).or_default().push((dval.id(), ev));
+                }
+                dval.clone()
+            }
+            None => {
+                let dval = self
+                    .subscriber
+                    .subscribe_updates(path.clone(), [(flags, self.updates_tx.clone())]);
+                self.subscriptions.insert(path.clone(), dval.clone());
+                dval
+            }
+        };
         *self.subscribed.entry(dval.id()).or_default().entry(ref_by).or_default() += 1;
+        self.subscribed_paths.insert(dval.id(), path);
         dval
     }
 
@@ -302,6 +432,9 @@ impl<X: GXExt> Rt for GXRt<X> {
             }
             if exprs.is_empty() {
                 self.subscribed.remove(&dv.id());
+                if let Some(path) = self.subscribed_paths.remove(&dv.id()) {
+                    self.subscriptions.remove(&path);
+                }
             }
         }
         self.pending_unsubscribe.push_back((Instant::now(), dv));
@@ -349,28 +482,74 @@ impl<X: GXExt> Rt for GXRt<X> {
         });
     }
 
+    fn glob(&mut self, id: BindId, pattern: Path) {
+        let last = self
+            .last_globs
+            .entry(id)
+            .or_insert_with(|| Arc::new(Mutex::new(Value::Null)));
+        let last = Arc::clone(last);
+        let resolver = self.subscriber.resolver();
+        self.tasks.spawn(async move {
+            let glob = or_err!(id, Glob::new(ArcStr::from(&*pattern)));
+            let globs = or_err!(id, GlobSet::new(false, [glob]));
+            let mut batches = or_err!(id, resolver.list_matching(&globs).await);
+            let mut paths = vec![];
+            for mut batch in batches.drain(..) {
+                paths.extend(batch.drain(..));
+            }
+            paths.sort();
+            paths.dedup();
+            let paths = paths.into_iter().map(|p| Value::String(p.into()));
+            let paths = Value::Array(ValArray::from_iter_exact(paths));
+            let mut last = last.lock().await;
+            if *last == paths {
+                return (id, Value::Null);
+            }
+            *last = paths.clone();
+            (id, paths)
+        });
+    }
+
     fn stop_list(&mut self, id: BindId) {
         self.change_trackers.remove(&id);
+        self.last_globs.remove(&id);
     }
 
-    fn publish(&mut self, path: Path, value: Value, ref_by: ExprId) -> Result<Val> {
+    fn publish(
+        &mut self,
+        path: Path,
+        value: Value,
+        typ: Option<Type>,
+        replace: bool,
+        ref_by: ExprId,
+    ) -> Result<Id> {
+        if replace && let Some(id) = self.published_paths.get(&path).copied() {
+            self.remove_published(id)
+        }
         let val = self.publisher.publish_with_flags_and_writes(
             PublishFlags::empty(),
-            path,
+            path.clone(),
             value,
             Some(self.writes_tx.clone()),
         )?;
         let id = val.id();
         *self.published.entry(id).or_default().entry(ref_by).or_default() += 1;
-        Ok(val)
+        if let Some(typ) = typ {
+            self.published_types.insert(id, typ);
+        }
+        self.published_paths.insert(path.clone(), id);
+        self.published_vals.insert(id, (path, val));
+        Ok(id)
     }
 
-    fn update(&mut self, val: &Val, value: Value) {
-        val.update(&mut self.batch, value);
+    fn update(&mut self, id: Id, value: Value) {
+        if let Some((_, val)) = self.published_vals.get(&id) {
+            val.update(&mut self.batch, value);
+        }
     }
 
-    fn unpublish(&mut self, val: Val, ref_by: ExprId) {
-        if let Some(refs) = self.published.get_mut(&val.id()) {
+    fn unpublish(&mut self, id: Id, ref_by: ExprId) {
+        if let Some(refs) = self.published.get_mut(&id) {
             if let Some(cn) = refs.get_mut(&ref_by) {
                 *cn -= 1;
                 if *cn == 0 {
@@ -378,7 +557,7 @@ impl<X: GXExt> Rt for GXRt<X> {
                 }
             }
             if refs.is_empty() {
-                self.published.remove(&val.id());
+                self.remove_published(id)
             }
         }
     }
@@ -424,16 +603,23 @@ impl<X: GXExt> Rt for GXRt<X> {
         F: Future<Output = (BindId, Box<dyn CustomBuiltinType>)> + Send + 'static,
     >(
         &mut self,
+        desc: ArcStr,
         f: F,
     ) -> Self::AbortHandle {
-        self.custom_tasks.spawn(f)
+        let abort = self.custom_tasks.spawn(f);
+        self.register_task(desc, None, &abort);
+        abort
     }
 
-    fn spawn_var<F: Future<Output = (BindId, Value)> + Send + 'static>(
+    fn spawn_var<F: Future<Output = Value> + Send + 'static>(
         &mut self,
+        id: BindId,
+        desc: ArcStr,
         f: F,
     ) -> Self::AbortHandle {
-        self.tasks.spawn(f)
+        let abort = self.tasks.spawn(async move { (id, f.await) });
+        self.register_task(desc, Some(id), &abort);
+        abort
     }
 
     fn watch(
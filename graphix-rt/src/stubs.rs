@@ -0,0 +1,84 @@
+//! Generate graphix modules exposing builtins registered from rust
+use anyhow::{bail, Result};
+use arcstr::ArcStr;
+use fxhash::FxHashMap;
+use graphix_compiler::{
+    expr::ModuleResolver,
+    typ::{FnType, Type},
+};
+use netidx::path::Path;
+use std::fmt::Write;
+
+fn write_stub(buf: &mut String, module: &str, builtin: &str, typ: &FnType) -> Result<()> {
+    let name = builtin.strip_prefix(module).and_then(|s| s.strip_prefix('_'));
+    let name = name.unwrap_or(builtin);
+    write!(buf, "let {name} = ")?;
+    for (i, (tv, t)) in typ.constraints.read().iter().enumerate() {
+        if i > 0 {
+            buf.push_str(", ");
+        }
+        write!(buf, "{tv}: {t}")?;
+    }
+    buf.push('|');
+    for (i, a) in typ.args.iter().enumerate() {
+        if i > 0 {
+            buf.push_str(", ");
+        }
+        match &a.label {
+            None => write!(buf, "a{i}: {}", a.typ)?,
+            Some((l, false)) => write!(buf, "#{l}: {}", a.typ)?,
+            Some((l, true)) => {
+                bail!("{builtin}: can't generate a default for optional argument #{l}")
+            }
+        }
+    }
+    if let Some(t) = &typ.vargs {
+        if !typ.args.is_empty() {
+            buf.push_str(", ");
+        }
+        write!(buf, "@args: {t}")?;
+    }
+    match &typ.rtype {
+        Type::Fn(ft) => write!(buf, "| -> ({ft})")?,
+        t => write!(buf, "| -> {t}")?,
+    }
+    match &typ.throws {
+        Type::Bottom if !typ.explicit_throws => (),
+        t => write!(buf, " throws {t}")?,
+    }
+    writeln!(buf, " '{builtin};")?;
+    Ok(())
+}
+
+/// Generate the source of a module binding each of the specified builtins.
+///
+/// `builtins` is a list of builtin names, as registered with
+/// `ExecCtx::register_builtin`, and their types. Each builtin is bound to its
+/// name with the `{module}_` prefix removed, so `mymod_add` is bound as
+/// `add`. Positional arguments are named `a0`, `a1`, etc. Optional labeled
+/// arguments need a default value, which can't be generated, so they are an
+/// error.
+pub fn builtin_module_source(
+    module: &str,
+    builtins: &[(&str, FnType)],
+) -> Result<ArcStr> {
+    let mut buf = String::new();
+    for (builtin, typ) in builtins {
+        write_stub(&mut buf, module, builtin, typ)?;
+    }
+    Ok(ArcStr::from(buf))
+}
+
+/// Generate a VFS module resolver containing a module named `module` that
+/// binds each of the specified builtins, see `builtin_module_source`.
+///
+/// Add the resolver to the `GXConfig` and declare the module with
+/// `mod {module}` to use it.
+pub fn builtin_module(
+    module: &str,
+    builtins: &[(&str, FnType)],
+) -> Result<ModuleResolver> {
+    let source = builtin_module_source(module, builtins)?;
+    let path = Path::from(format!("/{module}.gx"));
+    Ok(ModuleResolver::VFS(FxHashMap::from_iter([(path, source)])))
+}
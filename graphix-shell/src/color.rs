@@ -0,0 +1,153 @@
+use std::{
+    fmt::Write,
+    io::{self, IsTerminal},
+    str::FromStr,
+};
+
+const RESET: &str = "\x1b[0m";
+const KEYWORD: &str = "\x1b[1;34m";
+const PRIMITIVE: &str = "\x1b[36m";
+const TVAR: &str = "\x1b[35m";
+const TAG: &str = "\x1b[33m";
+
+const KEYWORDS: &[&str] = &["fn", "throws", "Array", "Map", "Error", "Any"];
+
+const PRIMITIVES: &[&str] = &[
+    "u8",
+    "i8",
+    "u16",
+    "i16",
+    "u32",
+    "v32",
+    "i32",
+    "z32",
+    "u64",
+    "v64",
+    "i64",
+    "z64",
+    "f32",
+    "f64",
+    "decimal",
+    "datetime",
+    "duration",
+    "bool",
+    "string",
+    "bytes",
+    "error",
+    "array",
+    "map",
+    "null",
+    "Number",
+    "Int",
+    "SignedInt",
+    "UnsignedInt",
+    "Float",
+    "Real",
+];
+
+/// When to color output printed by the shell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Color {
+    /// color only if stdout is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            s => anyhow::bail!("invalid color {s}, expected auto, always, or never"),
+        }
+    }
+}
+
+impl Color {
+    pub fn enabled(&self) -> bool {
+        match self {
+            Self::Auto => io::stdout().is_terminal(),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Color an already formatted type. Only escape sequences are added, the
+/// formatted text itself is left exactly as it was.
+pub(crate) fn colorize_type(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() * 2);
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        let (color, len) = if c == '\'' || c == '`' {
+            let len =
+                rest[1..].find(|c| !is_ident(c)).map(|i| i + 1).unwrap_or(rest.len());
+            (if c == '\'' { Some(TVAR) } else { Some(TAG) }, len)
+        } else if is_ident(c) {
+            let len = rest.find(|c| !is_ident(c)).unwrap_or(rest.len());
+            let word = &rest[..len];
+            if KEYWORDS.contains(&word) {
+                (Some(KEYWORD), len)
+            } else if PRIMITIVES.contains(&word) {
+                (Some(PRIMITIVE), len)
+            } else {
+                (None, len)
+            }
+        } else {
+            (None, c.len_utf8())
+        };
+        match color {
+            Some(color) if len > 1 || c.is_alphanumeric() => {
+                write!(res, "{color}{}{RESET}", &rest[..len]).unwrap()
+            }
+            _ => res.push_str(&rest[..len]),
+        }
+        rest = &rest[len..];
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphix_compiler::{expr::parser::parse_type, format_with_flags, PrintFlag};
+
+    fn print(color: Color, typ: &str) -> String {
+        let typ = parse_type(typ).unwrap();
+        let s =
+            format_with_flags(PrintFlag::DerefTVars | PrintFlag::ReplacePrims, || {
+                typ.to_string()
+            });
+        if color.enabled() {
+            colorize_type(&s)
+        } else {
+            s
+        }
+    }
+
+    #[test]
+    fn color_never() {
+        let s = print(Color::Never, "fn(Array<'a>, string) -> [`Foo(i64), null]");
+        assert!(!s.contains('\x1b'));
+    }
+
+    #[test]
+    fn color_always() {
+        let t = "fn(Array<'a>, string) -> [`Foo(i64), null]";
+        let s = print(Color::Always, t);
+        assert!(s.contains('\x1b'));
+        let plain = print(Color::Never, t);
+        let stripped = s.replace(RESET, "").replace(KEYWORD, "").replace(PRIMITIVE, "");
+        let stripped = stripped.replace(TVAR, "").replace(TAG, "");
+        assert_eq!(stripped, plain);
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Result};
 use arcstr::ArcStr;
-use graphix_compiler::{env::Env, expr::ModPath, typ::Type};
+use graphix_compiler::{env::Env, expr::ModPath, typ::Type, BindId};
 use log::debug;
 use netidx::path::Path;
 use reedline::{Completer, Span, Suggestion};
@@ -8,10 +8,19 @@ use reedline::{Completer, Span, Suggestion};
 #[derive(Debug)]
 enum CompletionContext<'a> {
     Bind(Span, &'a str),
+    Members(Span, &'a str),
     ArgLbl { span: Span, function: &'a str, arg: &'a str },
 }
 
 impl<'a> CompletionContext<'a> {
+    fn bind(span: Span, s: &'a str) -> Self {
+        if s.ends_with("::") {
+            Self::Members(span, s)
+        } else {
+            Self::Bind(span, s)
+        }
+    }
+
     fn from_str(s: &'a str) -> Result<Self> {
         let mut arg_lbl = 0;
         let mut fend = 0;
@@ -34,7 +43,7 @@ impl<'a> CompletionContext<'a> {
                         arg,
                     });
                 } else {
-                    return Ok(Self::Bind(
+                    return Ok(Self::bind(
                         Span { start: prev, end: s.len() },
                         s.get(prev..).ok_or_else(|| anyhow!("invalid bind"))?,
                     ));
@@ -42,12 +51,77 @@ impl<'a> CompletionContext<'a> {
             }
             prev = i;
         }
-        Ok(Self::Bind(Span { start: 0, end: s.len() }, s))
+        Ok(Self::bind(Span { start: 0, end: s.len() }, s))
     }
 }
 
 pub(super) struct BComplete(pub Env);
 
+impl BComplete {
+    fn describe_bind(&self, id: &BindId) -> String {
+        match self.0.by_id.get(id) {
+            None => format!("_"),
+            Some(b) => {
+                use std::fmt::Write;
+                let mut res = String::new();
+                match &b.typ {
+                    Type::Fn(ft) => {
+                        let ft = ft.replace_auto_constrained();
+                        write!(res, "{} ", ft).unwrap()
+                    }
+                    t => write!(res, "{} ", t).unwrap(),
+                }
+                if let Some(doc) = &b.doc {
+                    write!(res, "{doc}").unwrap();
+                };
+                res
+            }
+        }
+    }
+
+    /// suggest the binds, types, and submodules of the module named by
+    /// prefix, which ends in ::
+    fn members(&self, span: Span, prefix: &str, res: &mut Vec<Suggestion>) {
+        let name = ModPath::from_iter(prefix.trim_end_matches("::").split("::"));
+        let module = match self.0.canonical_modpath(&ModPath::root(), &name) {
+            Some(m) => m,
+            None => return,
+        };
+        let mut push = |name: &str, description: String| {
+            res.push(Suggestion {
+                span,
+                value: format!("{prefix}{name}"),
+                description: Some(description),
+                style: None,
+                extra: None,
+                append_whitespace: false,
+                match_indices: None,
+            })
+        };
+        if let Some(binds) = self.0.binds.get(&module) {
+            for (name, id) in binds {
+                push(name, self.describe_bind(id))
+            }
+        }
+        if let Some(typedefs) = self.0.typedefs.get(&module) {
+            for (name, td) in typedefs {
+                let description = match &td.doc {
+                    None => format!("type {}", td.typ),
+                    Some(doc) => format!("type {} {doc}", td.typ),
+                };
+                push(name, description)
+            }
+        }
+        for m in &self.0.modules {
+            if Path::dirname(&*m.0) == Some(&*module.0)
+                && let Some(name) = Path::basename(&*m.0)
+            {
+                push(name, "module".into())
+            }
+        }
+    }
+}
+
 impl Completer for BComplete {
     fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
         debug!("{line}: {pos}");
@@ -75,24 +149,7 @@ impl Completer for BComplete {
                         }
                         for (value, id) in self.0.lookup_matching(&ModPath::root(), &part)
                         {
-                            let description = match self.0.by_id.get(&id) {
-                                None => format!("_"),
-                                Some(b) => {
-                                    use std::fmt::Write;
-                                    let mut res = String::new();
-                                    match &b.typ {
-                                        Type::Fn(ft) => {
-                                            let ft = ft.replace_auto_constrained();
-                                            write!(res, "{} ", ft).unwrap()
-                                        }
-                                        t => write!(res, "{} ", t).unwrap(),
-                                    }
-                                    if let Some(doc) = &b.doc {
-                                        write!(res, "{doc}").unwrap();
-                                    };
-                                    res
-                                }
-                            };
+                            let description = self.describe_bind(&id);
                             let value = match Path::dirname(&part.0) {
                                 None => String::from(value.as_str()),
                                 Some(dir) => {
@@ -112,6 +169,9 @@ impl Completer for BComplete {
                             })
                         }
                     }
+                    CompletionContext::Members(span, prefix) => {
+                        self.members(span, prefix, &mut res)
+                    }
                     CompletionContext::ArgLbl { span, function, arg: part } => {
                         let function = ModPath::from_iter(function.split("::"));
                         if let Some((_, b)) =
@@ -144,3 +204,39 @@ impl Completer for BComplete {
         res
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphix_package::Package;
+    use graphix_package_core::testing::{self, RegisterFn};
+    use graphix_rt::NoExt;
+    use tokio::sync::mpsc;
+
+    const REGISTER: &[RegisterFn] = &[
+        <graphix_package_core::P as Package<NoExt>>::register,
+        <graphix_package_str::P as Package<NoExt>>::register,
+    ];
+
+    fn complete(env: &Env, line: &str) -> Vec<String> {
+        let mut c = BComplete(env.clone());
+        c.complete(line, line.len()).into_iter().map(|s| s.value).collect()
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn complete_members() -> Result<()> {
+        let (tx, _rx) = mpsc::channel(10);
+        let ctx = testing::init(tx, REGISTER).await?;
+        let env = ctx.rt.get_env().await?;
+        let res = complete(&env, "str::");
+        for f in ["str::len", "str::split", "str::starts_with"] {
+            assert!(res.iter().any(|s| s == f), "missing {f} in {res:?}")
+        }
+        assert!(res.iter().all(|s| s.starts_with("str::")));
+        let res = complete(&env, "let x = str::len(str::");
+        assert!(res.iter().any(|s| s == "str::len"));
+        assert!(complete(&env, "nosuchmodule::").is_empty());
+        ctx.shutdown().await;
+        Ok(())
+    }
+}
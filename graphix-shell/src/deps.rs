@@ -58,6 +58,38 @@ pub(crate) enum CustomResult<X: GXExt> {
     NotCustom(CompExp<X>),
 }
 
+/// return true if the output of e would be shown by a custom display
+pub(crate) fn is_custom<X: GXExt>(gx: &GXHandle<X>, env: &Env, e: &CompExp<X>) -> bool {
+    macro_rules! try_pkg {
+        ($pkg:path) => {
+            if <$pkg>::is_custom(gx, env, e) {
+                return true;
+            }
+        };
+    }
+    try_pkg!(graphix_package_core::P);
+    try_pkg!(graphix_package_array::P);
+    try_pkg!(graphix_package_list::P);
+    try_pkg!(graphix_package_str::P);
+    try_pkg!(graphix_package_map::P);
+    try_pkg!(graphix_package_sys::P);
+    try_pkg!(graphix_package_args::P);
+    try_pkg!(graphix_package_http::P);
+    try_pkg!(graphix_package_json::P);
+    try_pkg!(graphix_package_toml::P);
+    try_pkg!(graphix_package_pack::P);
+    try_pkg!(graphix_package_xls::P);
+    try_pkg!(graphix_package_sqlite::P);
+    try_pkg!(graphix_package_db::P);
+    try_pkg!(graphix_package_hbs::P);
+    try_pkg!(graphix_package_re::P);
+    try_pkg!(graphix_package_rand::P);
+    try_pkg!(graphix_package_tui::P);
+    #[cfg(feature = "gui")]
+    try_pkg!(graphix_package_gui::P);
+    false
+}
+
 pub(crate) async fn maybe_init_custom<X: GXExt>(
     gx: &GXHandle<X>,
     env: &Env,
@@ -3,11 +3,33 @@ use anyhow::{bail, Error, Result};
 use futures::{channel::mpsc, StreamExt};
 use graphix_rt::GXExt;
 use reedline::{
-    default_emacs_keybindings, DefaultPrompt, DefaultPromptSegment, Emacs, IdeMenu,
-    KeyCode, KeyModifiers, MenuBuilder, Reedline, ReedlineEvent, ReedlineMenu, Signal,
+    default_emacs_keybindings, DefaultPrompt, DefaultPromptSegment, Emacs,
+    FileBackedHistory, IdeMenu, KeyCode, KeyModifiers, MenuBuilder, Reedline,
+    ReedlineEvent, ReedlineMenu, Signal,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
 };
 use tokio::{sync::oneshot, task};
 
+/// the maximum number of lines kept in the history file
+const HISTORY_SIZE: usize = 10_000;
+
+/// the default location of the history file
+fn history_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("graphix").join("history.txt"))
+}
+
+/// open (or create) the history file at path, creating any missing parent
+/// directories.
+fn open_history(path: &Path) -> Result<FileBackedHistory> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(FileBackedHistory::with_file(HISTORY_SIZE, path.to_path_buf())?)
+}
+
 pub(super) struct InputReader {
     go: Option<oneshot::Sender<Option<Env>>>,
     recv: mpsc::UnboundedReceiver<(oneshot::Sender<Option<Env>>, Result<Signal>)>,
@@ -16,6 +38,7 @@ pub(super) struct InputReader {
 impl InputReader {
     pub(super) fn run(
         mut c_rx: oneshot::Receiver<Option<Env>>,
+        history: Option<PathBuf>,
     ) -> mpsc::UnboundedReceiver<(oneshot::Sender<Option<Env>>, Result<Signal>)> {
         let (tx, rx) = mpsc::unbounded();
         task::spawn(async move {
@@ -28,10 +51,23 @@ impl InputReader {
                     ReedlineEvent::MenuNext,
                 ]),
             );
+            keybinds.add_binding(
+                KeyModifiers::CONTROL,
+                KeyCode::Char('r'),
+                ReedlineEvent::SearchHistory,
+            );
             let menu = IdeMenu::default().with_name("completion");
             let mut line_editor = Reedline::create()
                 .with_menu(ReedlineMenu::EngineCompleter(Box::new(menu)))
                 .with_edit_mode(Box::new(Emacs::new(keybinds)));
+            if let Some(path) = history {
+                match open_history(&path) {
+                    Ok(h) => line_editor = line_editor.with_history(Box::new(h)),
+                    Err(e) => {
+                        eprintln!("could not open history {}: {e:?}", path.display())
+                    }
+                }
+            }
             let prompt = DefaultPrompt {
                 left_prompt: DefaultPromptSegment::Basic("".into()),
                 right_prompt: DefaultPromptSegment::Empty,
@@ -59,8 +95,14 @@ impl InputReader {
     }
 
     pub(super) fn new() -> Self {
+        Self::with_history(history_path())
+    }
+
+    /// create an input reader that persists its history to the specified
+    /// file, or keeps history only in memory if it is None.
+    pub(super) fn with_history(history: Option<PathBuf>) -> Self {
         let (tx_go, rx_go) = oneshot::channel();
-        let recv = Self::run(rx_go);
+        let recv = Self::run(rx_go, history);
         Self { go: Some(tx_go), recv }
     }
 
@@ -74,7 +116,7 @@ impl InputReader {
                 let _ = (&mut cdc.stop).await;
                 Ok(Signal::CtrlC)
             }
-            Output::EmptyScript | Output::Text(_) => {
+            Output::EmptyScript | Output::Text(_) | Output::Json(_) => {
                 tokio::signal::ctrl_c().await?;
                 Ok(Signal::CtrlC)
             }
@@ -93,3 +135,36 @@ impl InputReader {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reedline::{History, HistoryItem, SearchDirection, SearchQuery};
+
+    fn read_history(path: &Path) -> Result<Vec<String>> {
+        let h = open_history(path)?;
+        let q = SearchQuery::everything(SearchDirection::Forward, None);
+        Ok(h.search(q)?.into_iter().map(|i| i.command_line).collect())
+    }
+
+    #[test]
+    fn history_persists() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("graphix").join("history.txt");
+        {
+            let mut h = open_history(&path)?;
+            for line in ["let x = 42", "x + 1"] {
+                h.save(HistoryItem::from_command_line(line))?;
+            }
+            h.sync()?;
+        }
+        assert_eq!(read_history(&path)?, ["let x = 42", "x + 1"]);
+        {
+            let mut h = open_history(&path)?;
+            h.save(HistoryItem::from_command_line("x * 2"))?;
+            h.sync()?;
+        }
+        assert_eq!(read_history(&path)?, ["let x = 42", "x + 1", "x * 2"]);
+        Ok(())
+    }
+}
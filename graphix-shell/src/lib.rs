@@ -2,40 +2,50 @@
     html_logo_url = "https://graphix-lang.github.io/graphix/graphix-icon.svg",
     html_favicon_url = "https://graphix-lang.github.io/graphix/graphix-icon.svg"
 )]
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use arcstr::ArcStr;
 use derive_builder::Builder;
 use enumflags2::BitFlags;
 use fxhash::FxHashMap;
 use graphix_compiler::{
     env::Env,
-    expr::{CouldNotResolve, ExprId, ModuleResolver, Source},
+    expr::{
+        print::PrettyDisplay, CouldNotResolve, ExprId, ModPath, ModuleResolver, Source,
+    },
     format_with_flags,
-    typ::TVal,
+    typ::{TVal, Type},
     CFlag, ExecCtx, PrintFlag,
 };
 use graphix_package::MainThreadHandle;
 use graphix_package_core::ProgramArgs;
+use graphix_package_json::value_to_json;
 use graphix_rt::{CompExp, GXConfig, GXEvent, GXExt, GXHandle, GXRt};
 use input::InputReader;
 use netidx::{
     publisher::{Publisher, Value},
     subscriber::Subscriber,
 };
-use poolshark::global::GPooled;
+use poolshark::{global::GPooled, local::LPooled};
 use reedline::Signal;
 use std::{marker::PhantomData, process::exit, time::Duration};
 use tokio::{select, sync::mpsc};
 
+mod color;
 mod completion;
 mod deps;
 mod input;
 
+pub use color::Color;
+
+/// the line width beyond which pretty printed values are broken up
+const PRETTY_WIDTH: usize = 80;
+
 enum Output<X: GXExt> {
     None,
     EmptyScript,
     Custom(deps::Cdc<X>),
     Text(CompExp<X>),
+    Json(CompExp<X>),
 }
 
 impl<X: GXExt> Output<X> {
@@ -62,19 +72,79 @@ impl<X: GXExt> Output<X> {
         *self = Self::None;
     }
 
-    async fn process_update(&mut self, env: &Env, id: ExprId, v: Value) {
+    async fn process_update(&mut self, env: &Env, id: ExprId, v: Value, pretty: bool) {
         match self {
             Self::None | Output::EmptyScript => (),
             Self::Custom(cdc) => cdc.custom.process_update(env, id, v).await,
-            Self::Text(e) => {
-                if e.id == id {
-                    println!("{}", TVal { env: &env, typ: &e.typ, v: &v })
+            Self::Text(_) => {
+                if let Some(s) = self.text_update(env, id, &v, pretty) {
+                    print!("{s}")
                 }
             }
+            Self::Json(_) => match self.json_update(id, &v) {
+                None => (),
+                Some(Ok(s)) => println!("{s}"),
+                Some(Err(e)) => eprintln!("error: {e}"),
+            },
+        }
+    }
+
+    /// the text to print for an update of id, if this is text output
+    /// and id is the expression being output
+    fn text_update(
+        &self,
+        env: &Env,
+        id: ExprId,
+        v: &Value,
+        pretty: bool,
+    ) -> Option<LPooled<String>> {
+        use std::fmt::Write;
+        match self {
+            Self::Text(e) if e.id == id => {
+                let v = TVal { env, typ: &e.typ, v };
+                if pretty {
+                    Some(v.to_string_pretty(PRETTY_WIDTH))
+                } else {
+                    let mut s: LPooled<String> = LPooled::take();
+                    writeln!(s, "{v}").ok()?;
+                    Some(s)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// the line of JSON to print for an update of id, if this is JSON
+    /// output and id is the expression being output
+    fn json_update(
+        &self,
+        id: ExprId,
+        v: &Value,
+    ) -> Option<std::result::Result<String, String>> {
+        match self {
+            Self::Json(e) if e.id == id => Some(json_line(v)),
+            _ => None,
         }
     }
 }
 
+/// render v as a single line of JSON
+fn json_line(v: &Value) -> std::result::Result<String, String> {
+    value_to_json(v).map(|j| j.to_string())
+}
+
+/// return the type and the documentation, if any, of the variable
+/// name, as it would be resolved at the top level
+fn doc(env: &Env, name: &str) -> Option<(String, Option<ArcStr>)> {
+    let name = ModPath::from_iter(name.split("::"));
+    let (_, b) = env.lookup_bind(&ModPath::root(), &name)?;
+    let typ = format_with_flags(PrintFlag::ReplacePrims, || match &b.typ {
+        Type::Fn(ft) => ft.replace_auto_constrained().to_string(),
+        t => t.to_string(),
+    });
+    Some((typ, b.doc.clone()))
+}
+
 #[derive(Debug, Clone)]
 pub enum Mode {
     /// Read input line by line from the user and compile/execute it.
@@ -137,6 +207,17 @@ pub struct Shell<X: GXExt> {
     /// program arguments to pass to the graphix script
     #[builder(default)]
     program_args: Vec<ArcStr>,
+    /// print values that don't fit on one line indented over multiple
+    /// lines. In the REPL this can be changed with `:pretty on|off`
+    #[builder(default)]
+    pretty: bool,
+    /// when to color the types of printed expressions
+    #[builder(default)]
+    color: Color,
+    /// in script mode print each output value as a line of JSON instead of
+    /// text. Outputs with a custom display, e.g. a tui, are rejected.
+    #[builder(default)]
+    json: bool,
     #[builder(setter(skip), default)]
     _phantom: PhantomData<X>,
 }
@@ -216,7 +297,14 @@ impl<X: GXExt> Shell<X> {
                 exprs.extend(r.exprs);
                 env = gx.get_env().await?;
                 if let Some(e) = exprs.pop() {
-                    *output = Output::from_expr(&gx, &env, e, run_on_main).await;
+                    *output = if self.json {
+                        if deps::is_custom(gx, &env, &e) {
+                            bail!("values of type {} can't be output as json", e.typ)
+                        }
+                        Output::Json(e)
+                    } else {
+                        Output::from_expr(&gx, &env, e, run_on_main).await
+                    };
                 }
                 *newenv = None
             }
@@ -244,6 +332,33 @@ impl<X: GXExt> Shell<X> {
         Ok(env)
     }
 
+    fn command(&mut self, env: &Env, line: &str) {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(":pretty"), Some("on"), None) => self.pretty = true,
+            (Some(":pretty"), Some("off"), None) => self.pretty = false,
+            (Some(":pretty"), None, None) => {
+                println!("pretty is {}", if self.pretty { "on" } else { "off" })
+            }
+            (Some(":pretty"), _, _) => eprintln!("usage: :pretty on|off"),
+            (Some(":doc"), Some(name), None) => match doc(env, name) {
+                None => eprintln!("{name} is not bound"),
+                Some((typ, doc)) => {
+                    if self.color.enabled() {
+                        println!("{name}: {}", color::colorize_type(&typ))
+                    } else {
+                        println!("{name}: {typ}")
+                    }
+                    if let Some(doc) = doc {
+                        println!("{doc}")
+                    }
+                }
+            },
+            (Some(":doc"), _, _) => eprintln!("usage: :doc name"),
+            _ => eprintln!("unknown command {line}"),
+        }
+    }
+
     pub async fn run(mut self, run_on_main: MainThreadHandle) -> Result<()> {
         let (tx, mut from_gx) = mpsc::channel(100);
         let gx = self.init(tx).await?;
@@ -259,15 +374,15 @@ impl<X: GXExt> Shell<X> {
             println!("Welcome to the graphix shell");
             println!("Press ctrl-c to cancel, ctrl-d to exit, and tab for help")
         }
-        loop {
+        let res = loop {
             select! {
                 batch = from_gx.recv() => match batch {
-                    None => bail!("graphix runtime is dead"),
+                    None => break Err(anyhow!("graphix runtime is dead")),
                     Some(mut batch) => {
                         for e in batch.drain(..) {
                             match e {
                                 GXEvent::Updated(id, v) => {
-                                    output.process_update(&env, id, v).await
+                                    output.process_update(&env, id, v, self.pretty).await
                                 },
                                 GXEvent::Env(e) => {
                                     env = e;
@@ -285,6 +400,9 @@ impl<X: GXExt> Shell<X> {
                             output.clear().await;
                         }
                         Ok(Signal::CtrlD) => break Ok(()),
+                        Ok(Signal::Success(line)) if line.trim_start().starts_with(':') => {
+                            self.command(&env, line.trim())
+                        }
                         Ok(Signal::Success(line)) => {
                             match gx.compile(ArcStr::from(line)).await {
                                 Err(e) => eprintln!("error: {e:?}"),
@@ -297,10 +415,15 @@ impl<X: GXExt> Shell<X> {
                                         let typ = e.typ
                                             .with_deref(|t| t.cloned())
                                             .unwrap_or_else(|| e.typ.clone());
-                                        format_with_flags(
+                                        let typ = format_with_flags(
                                             PrintFlag::DerefTVars | PrintFlag::ReplacePrims,
-                                            || println!("-: {}", typ)
+                                            || typ.to_string()
                                         );
+                                        if self.color.enabled() {
+                                            println!("-: {}", color::colorize_type(&typ))
+                                        } else {
+                                            println!("-: {typ}")
+                                        }
                                         output.clear().await;
                                         output = Output::from_expr(
                                             &gx, &env, e, &run_on_main,
@@ -314,6 +437,169 @@ impl<X: GXExt> Shell<X> {
                     }
                 },
             }
+        };
+        // wait for the runtime to unpublish everything before the process
+        // exits, dropping the handle alone doesn't wait
+        output.clear().await;
+        if let Err(e) = gx.shutdown().await
+            && res.is_ok()
+        {
+            eprintln!("error shutting down {e:?}")
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphix_package::Package;
+    use graphix_package_core::testing::{self, RegisterFn};
+    use graphix_rt::NoExt;
+    use std::path::PathBuf;
+
+    const REGISTER: &[RegisterFn] = &[
+        <graphix_package_core::P as Package<NoExt>>::register,
+        <graphix_package_str::P as Package<NoExt>>::register,
+    ];
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn doc_command() -> Result<()> {
+        let (tx, _rx) = mpsc::channel(10);
+        let ctx = testing::init(tx, REGISTER).await?;
+        let res = ctx
+            .rt
+            .compile(ArcStr::from(
+                "/// add one to x\nlet add_one = |x: i64| -> i64 x + 1",
+            ))
+            .await?;
+        let (typ, d) = doc(&res.env, "add_one").unwrap();
+        assert_eq!(typ, "fn(i64) -> i64");
+        assert!(d.unwrap().contains("add one to x"));
+        let (typ, d) = doc(&res.env, "str::len").unwrap();
+        assert_eq!(typ, "fn(string) -> i64");
+        assert!(d.unwrap().contains("length of the string"));
+        assert!(doc(&res.env, "no_such_bind").is_none());
+        drop(res);
+        ctx.shutdown().await;
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn pretty_command() -> Result<()> {
+        let env = netidx::InternalOnly::new().await?;
+        let (tx, mut rx) = mpsc::channel(10);
+        let mut shell = ShellBuilder::<NoExt>::default()
+            .publisher(env.publisher().clone())
+            .subscriber(env.subscriber().clone())
+            .no_init(true)
+            .build()?;
+        let gx = shell.init(tx).await?;
+        let mut res =
+            gx.compile(ArcStr::from("array::init(20, |i| \"item[i]\")")).await?;
+        let e = res.exprs.pop().unwrap();
+        let (id, genv) = (e.id, res.env.clone());
+        let output = Output::Text(e);
+        let v = loop {
+            let mut batch = rx.recv().await.unwrap();
+            if let Some(v) = batch.drain(..).find_map(|ev| match ev {
+                GXEvent::Updated(eid, v) if eid == id => Some(v),
+                _ => None,
+            }) {
+                break v;
+            }
+        };
+        let text = |shell: &Shell<NoExt>| {
+            output.text_update(&genv, id, &v, shell.pretty).unwrap().lines().count()
+        };
+        assert_eq!(text(&shell), 1);
+        shell.command(&genv, ":pretty on");
+        assert!(shell.pretty);
+        assert_eq!(text(&shell), 22);
+        shell.command(&genv, ":pretty sideways");
+        assert!(shell.pretty);
+        shell.command(&genv, ":pretty off");
+        assert!(!shell.pretty);
+        assert_eq!(text(&shell), 1);
+        drop(output);
+        drop(res);
+        gx.shutdown().await?;
+        env.shutdown().await;
+        Ok(())
+    }
+
+    /// load path as a script with json output, returning the output the
+    /// shell would use
+    async fn json_script(
+        env: &netidx::InternalOnly,
+        path: PathBuf,
+        tx: mpsc::Sender<GPooled<Vec<GXEvent>>>,
+    ) -> Result<(GXHandle<NoExt>, Result<Output<NoExt>>)> {
+        let mut shell = ShellBuilder::<NoExt>::default()
+            .publisher(env.publisher().clone())
+            .subscriber(env.subscriber().clone())
+            .mode(Mode::Script(Source::File(path)))
+            .json(true)
+            .no_init(true)
+            .build()?;
+        let gx = shell.init(tx).await?;
+        let (run_on_main, _main_rx) = MainThreadHandle::new();
+        let mut output = Output::EmptyScript;
+        let mut newenv = None;
+        let mut exprs = vec![];
+        let res = shell
+            .load_env(&gx, &mut newenv, &mut output, &mut exprs, &run_on_main)
+            .await
+            .map(|_| output);
+        Ok((gx, res))
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn json_output() -> Result<()> {
+        let env = netidx::InternalOnly::new().await?;
+        let (tx, mut rx) = mpsc::channel(10);
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("main.gx");
+        std::fs::write(&path, "let n = 1;\nn <- once(n) + 1;\n{n: n, s: \"foo\"}")?;
+        let (gx, output) = json_script(&env, path, tx).await?;
+        let output = output?;
+        assert!(matches!(output, Output::Json(_)));
+        let mut lines = vec![];
+        while lines.len() < 2 {
+            let mut batch = rx.recv().await.unwrap();
+            for ev in batch.drain(..) {
+                if let GXEvent::Updated(id, v) = ev
+                    && let Some(line) = output.json_update(id, &v)
+                {
+                    lines.push(line.unwrap());
+                }
+            }
+        }
+        for (i, line) in lines.iter().enumerate() {
+            assert!(!line.contains('\n'));
+            let v: serde_json::Value = serde_json::from_str(line)?;
+            assert_eq!(v, serde_json::json!({"n": i + 1, "s": "foo"}));
+        }
+        drop(output);
+        gx.shutdown().await?;
+        env.shutdown().await;
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn json_output_rejects_tui() -> Result<()> {
+        let env = netidx::InternalOnly::new().await?;
+        let (tx, _rx) = mpsc::channel(10);
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("main.gx");
+        std::fs::write(&path, "use tui;\nuse tui::text;\ntext(&\"hello\")")?;
+        let (gx, output) = json_script(&env, path, tx).await?;
+        match output {
+            Ok(_) => panic!("a tui output was accepted as json"),
+            Err(e) => assert!(e.to_string().contains("can't be output as json"), "{e}"),
         }
+        gx.shutdown().await?;
+        env.shutdown().await;
+        Ok(())
     }
 }
@@ -9,7 +9,7 @@ use graphix_compiler::{
 };
 use graphix_package::{GraphixPM, MainThreadHandle, PackageId};
 use graphix_rt::NoExt;
-use graphix_shell::{Mode, ShellBuilder};
+use graphix_shell::{Color, Mode, ShellBuilder};
 use log::info;
 use netidx::{
     config::Config,
@@ -28,6 +28,10 @@ enum RawFlag {
     NoUnused,
     Error,
     NoError,
+    Inline,
+    NoInline,
+    FloatErrors,
+    NoFloatErrors,
 }
 
 impl FromStr for RawFlag {
@@ -41,6 +45,10 @@ impl FromStr for RawFlag {
             "no-unused" => Ok(Self::NoUnused),
             "error" => Ok(Self::Error),
             "no-error" => Ok(Self::NoError),
+            "inline" => Ok(Self::Inline),
+            "no-inline" => Ok(Self::NoInline),
+            "float-errors" => Ok(Self::FloatErrors),
+            "no-float-errors" => Ok(Self::NoFloatErrors),
             s => bail!("invalid flag {s}"),
         }
     }
@@ -58,6 +66,10 @@ impl RawFlag {
                 Self::NoUnused => disable.insert(CFlag::WarnUnused),
                 Self::Error => enable.insert(CFlag::WarningsAreErrors),
                 Self::NoError => disable.insert(CFlag::WarningsAreErrors),
+                Self::Inline => enable.insert(CFlag::InlineLambdas),
+                Self::NoInline => disable.insert(CFlag::InlineLambdas),
+                Self::FloatErrors => enable.insert(CFlag::FloatErrors),
+                Self::NoFloatErrors => disable.insert(CFlag::FloatErrors),
             }
         }
         (enable, disable)
@@ -162,16 +174,29 @@ struct Params {
     /// do not attempt to run the init module
     #[arg(short = 'i', long)]
     no_init: bool,
+    /// print values that don't fit on one line indented over multiple lines
+    #[arg(long)]
+    pretty: bool,
+    /// color output, auto colors only when stdout is a terminal
+    #[arg(long, default_value = "auto")]
+    color: Color,
     /// do not execute the program, just veryify that it compiles and
     /// type checks.
     #[arg(long = "check")]
     check: bool,
+    /// print each value of the program as a line of JSON instead of text.
+    /// Requires a file to run.
+    #[arg(long)]
+    json: bool,
     /// run the program in the specified file instead of starting the REPL
     file: Option<ArcStr>,
     /// enable or disable compiler flags. Currently supported flags are,
     /// - unhandled, no-unhandled: warn about unhandled ? operators (default)
     /// - unused, no-unused: warn about unused variables (default)
     /// - error, no-error makes warnings errors
+    /// - inline, no-inline: inline small lambdas called from only one place
+    /// - float-errors, no-float-errors: float arithmetic producing NaN or
+    ///   infinity is an arithmetic error
     ///
     /// the no- variant turns the flag off. If both are specifed the no- variant
     /// always wins
@@ -317,6 +342,9 @@ fn tokio_main(
             p.program_args.iter().map(|s| ArcStr::from(s.as_str())).collect();
         shell = shell.program_args(program_args);
         shell = shell.no_init(p.no_init);
+        shell = shell.pretty(p.pretty);
+        shell = shell.color(p.color);
+        shell = shell.json(p.json);
         if let Some(t) = p.publish_timeout {
             shell = shell.publish_timeout(Duration::from_secs(t));
         }
@@ -326,6 +354,9 @@ fn tokio_main(
         if p.file.is_none() && p.check {
             bail!("check mode requires a file to check")
         }
+        if p.file.is_none() && p.json {
+            bail!("json output requires a file to run")
+        }
         if let Some(f) = &p.file {
             let source = match f.strip_prefix("netidx:") {
                 Some(path) => {
@@ -12,9 +12,11 @@ use graphix_compiler::{
     TypecheckPhase, UserEvent,
 };
 use graphix_package_core::{
-    CachedArgs, CachedVals, EvalCached, FoldFn, FoldQ, MapFn, MapQ, Slot,
+    pooled_array, CachedArgs, CachedVals, EvalCached, FoldFn, FoldQ, MapCollection,
+    MapFn, MapQ, Slot,
 };
 use graphix_rt::GXRt;
+use log::error;
 use netidx::{publisher::Typ, subscriber::Value, utils::Either};
 use netidx_value::ValArray;
 use smallvec::{smallvec, SmallVec};
@@ -38,6 +40,69 @@ impl<R: Rt, E: UserEvent> MapFn<R, E> for MapImpl {
 
 type Map<R, E> = MapQ<R, E, MapImpl>;
 
+/// The two arrays zip_with maps in lock step, the shorter one
+/// decides the length.
+#[derive(Debug, Clone, Default)]
+struct ZipColl(ValArray, ValArray);
+
+impl MapCollection for ZipColl {
+    const INPUTS: usize = 2;
+
+    fn len(&self) -> usize {
+        self.0.len().min(self.1.len())
+    }
+
+    fn iter_values(&self) -> impl Iterator<Item = Value> {
+        self.0.iter().zip(self.1.iter()).map(|(x, y)| {
+            Value::Array(ValArray::from_iter_exact([x.clone(), y.clone()].into_iter()))
+        })
+    }
+
+    fn iter_args(&self) -> impl Iterator<Item = SmallVec<[Value; 2]>> {
+        self.0.iter().zip(self.1.iter()).map(|(x, y)| smallvec![x.clone(), y.clone()])
+    }
+
+    fn select(v: Value) -> Option<Self> {
+        match v {
+            Value::Array(a) => match &a[..] {
+                [Value::Array(x), Value::Array(y)] => Some(Self(x.clone(), y.clone())),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn project(self) -> Value {
+        Value::Array(ValArray::from_iter_exact(self.iter_values()))
+    }
+
+    fn etyp(ft: &FnType) -> Result<Type> {
+        match (&ft.args[0].typ, &ft.args[1].typ) {
+            (Type::Array(x), Type::Array(y)) => {
+                Ok(Type::Tuple(TArc::from_iter([(**x).clone(), (**y).clone()])))
+            }
+            (x, y) => bail!("expected two arrays not {x} and {y}"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ZipWithImpl;
+
+impl<R: Rt, E: UserEvent> MapFn<R, E> for ZipWithImpl {
+    type Collection = ZipColl;
+
+    const NAME: &str = "array_zip_with";
+
+    fn finish(&mut self, slots: &[Slot<R, E>], _: &ZipColl) -> Option<Value> {
+        Some(Value::Array(ValArray::from_iter_exact(
+            slots.iter().map(|s| s.cur.clone().unwrap()),
+        )))
+    }
+}
+
+type ZipWith<R, E> = MapQ<R, E, ZipWithImpl>;
+
 #[derive(Debug, Default)]
 struct FilterImpl;
 
@@ -47,7 +112,7 @@ impl<R: Rt, E: UserEvent> MapFn<R, E> for FilterImpl {
     const NAME: &str = "array_filter";
 
     fn finish(&mut self, slots: &[Slot<R, E>], a: &ValArray) -> Option<Value> {
-        Some(Value::Array(ValArray::from_iter(slots.iter().zip(a.iter()).filter_map(
+        Some(Value::Array(pooled_array(slots.iter().zip(a.iter()).filter_map(
             |(p, v)| match p.cur {
                 Some(Value::Bool(true)) => Some(v.clone()),
                 _ => None,
@@ -67,7 +132,7 @@ impl<R: Rt, E: UserEvent> MapFn<R, E> for FlatMapImpl {
     const NAME: &str = "array_flat_map";
 
     fn finish(&mut self, slots: &[Slot<R, E>], _: &ValArray) -> Option<Value> {
-        Some(Value::Array(ValArray::from_iter(slots.iter().flat_map(|s| {
+        Some(Value::Array(pooled_array(slots.iter().flat_map(|s| {
             match s.cur.as_ref().unwrap() {
                 Value::Array(a) => Either::Left(a.clone().into_iter()),
                 v => Either::Right(iter::once(v.clone())),
@@ -87,7 +152,7 @@ impl<R: Rt, E: UserEvent> MapFn<R, E> for FilterMapImpl {
     const NAME: &str = "array_filter_map";
 
     fn finish(&mut self, slots: &[Slot<R, E>], _: &ValArray) -> Option<Value> {
-        Some(Value::Array(ValArray::from_iter(slots.iter().filter_map(|s| {
+        Some(Value::Array(pooled_array(slots.iter().filter_map(|s| {
             match s.cur.as_ref().unwrap() {
                 Value::Null => None,
                 v => Some(v.clone()),
@@ -144,7 +209,7 @@ impl<R: Rt, E: UserEvent> MapFn<R, E> for FindMapImpl {
 
 type FindMap<R, E> = MapQ<R, E, FindMapImpl>;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct FoldImpl;
 
 impl<R: Rt, E: UserEvent> FoldFn<R, E> for FoldImpl {
@@ -155,6 +220,44 @@ impl<R: Rt, E: UserEvent> FoldFn<R, E> for FoldImpl {
 
 type Fold<R, E> = FoldQ<R, E, FoldImpl>;
 
+/// the last value of each accumulator. When an upstream change only
+/// recomputes the tail of the chain the rest of it is still current.
+#[derive(Debug, Default)]
+struct ScanImpl(Vec<Option<Value>>);
+
+impl<R: Rt, E: UserEvent> FoldFn<R, E> for ScanImpl {
+    type Collection = ValArray;
+
+    const NAME: &str = "array_scan";
+
+    fn finish(&mut self, up: bool, accs: &[Option<Value>]) -> Option<Value> {
+        if accs.is_empty() {
+            self.0.clear();
+            return up.then(|| Value::Array(ValArray::default()));
+        }
+        self.0.resize(accs.len(), None);
+        let mut updated = false;
+        for (last, acc) in self.0.iter_mut().zip(accs.iter()) {
+            if let Some(v) = acc {
+                updated = true;
+                *last = Some(v.clone());
+            }
+        }
+        if !updated || self.0.iter().any(|v| v.is_none()) {
+            return None;
+        }
+        Some(Value::Array(ValArray::from_iter_exact(
+            self.0.iter().map(|v| v.clone().unwrap()),
+        )))
+    }
+
+    fn sleep(&mut self) {
+        self.0.clear()
+    }
+}
+
+type Scan<R, E> = FoldQ<R, E, ScanImpl>;
+
 #[derive(Debug, Default)]
 struct ConcatEv(SmallVec<[Value; 32]>);
 
@@ -392,6 +495,55 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for SortEv {
 
 type Sort = CachedArgs<SortEv>;
 
+#[derive(Debug, Default)]
+struct ReverseEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for ReverseEv {
+    const NAME: &str = "array_reverse";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match &from.0[0] {
+            Some(Value::Array(a)) if a.len() <= 1 => Some(Value::Array(a.clone())),
+            Some(Value::Array(a)) => {
+                Some(Value::Array(ValArray::from_iter_exact(a.iter().rev().cloned())))
+            }
+            Some(_) | None => None,
+        }
+    }
+}
+
+type Reverse = CachedArgs<ReverseEv>;
+
+#[derive(Debug, Default)]
+struct RotateEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for RotateEv {
+    const NAME: &str = "array_rotate";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match &from.0[..] {
+            [Some(Value::Array(a)), Some(Value::I64(n))] => {
+                if a.is_empty() {
+                    return Some(Value::Array(a.clone()));
+                }
+                // positive n rotates left, negative n rotates right
+                let n = n.rem_euclid(a.len() as i64) as usize;
+                if n == 0 {
+                    return Some(Value::Array(a.clone()));
+                }
+                Some(Value::Array(ValArray::from_iter_exact(
+                    a[n..].iter().chain(a[..n].iter()).cloned(),
+                )))
+            }
+            _ => None,
+        }
+    }
+}
+
+type Rotate = CachedArgs<RotateEv>;
+
 #[derive(Debug, Default)]
 struct EnumerateEv;
 
@@ -412,6 +564,28 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for EnumerateEv {
 
 type Enumerate = CachedArgs<EnumerateEv>;
 
+#[derive(Debug, Default)]
+struct IndexOfEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for IndexOfEv {
+    const NAME: &str = "array_index_of";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match &from.0[..] {
+            [Some(Value::Array(a)), Some(v)] => {
+                Some(match a.iter().position(|e| e == v) {
+                    Some(i) => Value::I64(i as i64),
+                    None => Value::Null,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+type IndexOf = CachedArgs<IndexOfEv>;
+
 #[derive(Debug, Default)]
 struct ZipEv;
 
@@ -767,6 +941,14 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Init<R, E> {
             event.variables.insert(self.fid, v);
         }
         let (size_fired, resized) = match from[0].update(ctx, event) {
+            Some(Value::I64(n))
+                if let Some(max) = ctx.max_len()
+                    && n > 0
+                    && n as usize > max =>
+            {
+                error!("array::init: {n} exceeds the maximum array length {max}");
+                (false, false)
+            }
             Some(Value::I64(n)) => {
                 let n = n.max(0) as usize;
                 if n == slen {
@@ -803,7 +985,7 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Init<R, E> {
                             &self.mftyp,
                             self.top_id,
                         );
-                        self.slots.push(Slot { id, pred, cur: None });
+                        self.slots.push(Slot { ids: smallvec![id], pred, cur: None });
                     }
                     (true, true)
                 }
@@ -813,30 +995,28 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Init<R, E> {
         // set index bindings for new slots
         if resized && self.slots.len() > slen {
             for i in slen..self.slots.len() {
-                let id = self.slots[i].id;
+                let id = self.slots[i].ids[0];
                 event.variables.insert(id, Value::I64(i as i64));
             }
         }
         if size_fired && self.slots.is_empty() {
             return Some(Value::Array(ValArray::default()));
         }
-        let init = event.init;
         let mut up = resized;
         for (i, s) in self.slots.iter_mut().enumerate() {
             if i == slen {
-                event.init = true;
                 if let Entry::Vacant(e) = event.variables.entry(self.fid)
                     && let Some(v) = ctx.cached.get(&self.fid)
                 {
                     e.insert(v.clone());
                 }
             }
-            if let Some(v) = s.pred.update(ctx, event) {
+            let init = event.init || i >= slen;
+            if let Some(v) = s.pred.update(ctx, &mut event.init_scope(init)) {
                 s.cur = Some(v);
                 up = true;
             }
         }
-        event.init = init;
         if up && self.slots.iter().all(|s| s.cur.is_some()) {
             Some(Value::Array(ValArray::from_iter_exact(
                 self.slots.iter().map(|s| s.cur.clone().unwrap()),
@@ -895,10 +1075,13 @@ graphix_derive::defpackage! {
         FlatMap as FlatMap<GXRt<X>, X::UserEvent>,
         Enumerate,
         Zip,
+        ZipWith as ZipWith<GXRt<X>, X::UserEvent>,
         Unzip,
         Flatten,
         Fold as Fold<GXRt<X>, X::UserEvent>,
+        Scan as Scan<GXRt<X>, X::UserEvent>,
         Group as Group<GXRt<X>, X::UserEvent>,
+        IndexOf,
         Init as Init<GXRt<X>, X::UserEvent>,
         Iter,
         IterQ,
@@ -906,6 +1089,8 @@ graphix_derive::defpackage! {
         Map as Map<GXRt<X>, X::UserEvent>,
         PushBack,
         PushFront,
+        Reverse,
+        Rotate,
         Sort,
         Window,
     ],
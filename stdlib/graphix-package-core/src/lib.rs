@@ -5,33 +5,41 @@
 use anyhow::{bail, Result};
 use arcstr::{literal, ArcStr};
 use compact_str::format_compact;
+use fxhash::{FxHashMap, FxHasher64};
 use graphix_compiler::{
+    env::Env,
     err, errf,
     expr::{Expr, ExprId},
     node::genn,
     typ::{FnType, TVal, Type},
     Apply, BindId, BuiltIn, Event, ExecCtx, LambdaId, Node, Refs, Rt, Scope,
-    TypecheckPhase, UserEvent,
+    TypecheckPhase, UserEvent, DEFAULT_MAX_DEPTH,
 };
 use graphix_rt::GXRt;
 use immutable_chunkmap::map::Map as CMap;
 use netidx::path::Path;
+use netidx::publisher::Typ;
 use netidx::subscriber::Value;
 use netidx_core::utils::Either;
-use netidx_value::{FromValue, ValArray};
+use netidx_value::{FromValue, PBytes, ValArray};
 use poolshark::local::LPooled;
+use rust_decimal::{Decimal, RoundingStrategy};
+use smallvec::{smallvec, SmallVec};
 use std::{
     any::Any,
+    cmp,
     collections::{hash_map::Entry, VecDeque},
-    fmt::Debug,
-    iter,
-    marker::PhantomData,
+    fmt::{Debug, Display},
+    hash::{Hash as _, Hasher},
+    iter, mem,
     time::Duration,
 };
 use tokio::time::Instant;
 use triomphe::Arc as TArc;
+use unicode_segmentation::UnicodeSegmentation;
 
 pub(crate) mod buffer;
+pub(crate) mod test;
 
 // ── Cast context for typed deserialization ────────────────────────
 
@@ -162,6 +170,14 @@ pub mod testing;
 
 // ── Shared helpers ────────────────────────────────────────────────
 
+/// Build a ValArray from an iterator of unknown length. The elements are
+/// collected in a pooled buffer first, so building an array every cycle
+/// doesn't allocate and grow a new Vec each time.
+pub fn pooled_array(iter: impl IntoIterator<Item = Value>) -> ValArray {
+    let mut buf: LPooled<Vec<Value>> = iter.into_iter().collect();
+    ValArray::from_iter_exact(buf.drain(..))
+}
+
 /// Check if a Value is a struct-shaped array: non-empty, every element is
 /// a 2-element array with a string first element, keys sorted ascending.
 pub fn is_struct(arr: &ValArray) -> bool {
@@ -241,15 +257,23 @@ impl CachedVals {
         }
     }
 
+    /// iterate over the cached values, flattening arrays. Only arrays
+    /// go through `Value::flatten`, everything else is yielded as is.
     pub fn flat_iter<'a>(&'a self) -> impl Iterator<Item = Option<Value>> + 'a {
         self.0.iter().flat_map(|v| match v {
             None => Either::Left(iter::once(None)),
-            Some(v) => Either::Right(v.clone().flatten().map(Some)),
+            Some(v @ Value::Array(_)) => Either::Right(v.clone().flatten().map(Some)),
+            Some(v) => Either::Left(iter::once(Some(v.clone()))),
         })
     }
 
+    /// cast the cached value of argument i to T, None if the argument
+    /// hasn't updated yet, or can't be cast
     pub fn get<T: FromValue>(&self, i: usize) -> Option<T> {
-        self.0.get(i).and_then(|v| v.as_ref()).and_then(|v| v.clone().cast_to::<T>().ok())
+        match self.0.get(i)? {
+            None => None,
+            Some(v) => v.clone().cast_to::<T>().ok(),
+        }
     }
 }
 
@@ -338,6 +362,82 @@ impl<R: Rt, E: UserEvent, T: EvalCached<R, E>> Apply<R, E> for CachedArgs<T> {
     }
 }
 
+/// `EvalCached` for builtins that take exactly one argument. There is
+/// nothing to cache, so eval is passed the argument each time it updates.
+pub trait EvalCached1<R: Rt, E: UserEvent>:
+    Debug + Default + Send + Sync + 'static
+{
+    const NAME: &str;
+    const NEEDS_CALLSITE: bool;
+
+    fn init(
+        _ctx: &mut ExecCtx<R, E>,
+        _typ: &FnType,
+        _resolved: Option<&FnType>,
+        _scope: &Scope,
+        _from: &[Node<R, E>],
+        _top_id: ExprId,
+    ) -> Self {
+        Self::default()
+    }
+
+    fn eval(&mut self, ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value>;
+
+    fn typecheck(
+        &mut self,
+        _ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        _phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct CachedArgs1<T>(T);
+
+impl<R: Rt, E: UserEvent, T: EvalCached1<R, E>> BuiltIn<R, E> for CachedArgs1<T> {
+    const NAME: &str = T::NAME;
+    const NEEDS_CALLSITE: bool = T::NEEDS_CALLSITE;
+
+    fn init<'a, 'b, 'c, 'd>(
+        ctx: &'a mut ExecCtx<R, E>,
+        typ: &'a graphix_compiler::typ::FnType,
+        resolved: Option<&'d FnType>,
+        scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        if from.len() != 1 {
+            bail!("{} expected 1 argument", T::NAME)
+        }
+        Ok(Box::new(CachedArgs1(T::init(ctx, typ, resolved, scope, from, top_id))))
+    }
+}
+
+impl<R: Rt, E: UserEvent, T: EvalCached1<R, E>> Apply<R, E> for CachedArgs1<T> {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let v = from[0].update(ctx, event)?;
+        self.0.eval(ctx, v)
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        self.0.typecheck(ctx, from, phase)
+    }
+
+    fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {}
+}
+
 pub trait EvalCachedAsync: Debug + Default + Send + Sync + 'static {
     const NAME: &str;
     const NEEDS_CALLSITE: bool;
@@ -377,11 +477,26 @@ pub trait EvalCachedAsync: Debug + Default + Send + Sync + 'static {
     fn eval(args: Self::Args) -> impl Future<Output = Value> + Send;
 }
 
+/// true if v is the `Cancelled` error the runtime delivers in place of the
+/// result of a task that was cancelled from the handle
+pub fn is_cancelled(v: &Value) -> bool {
+    match v {
+        Value::Error(e) => match &**e {
+            Value::Array(a) => {
+                matches!(a.first(), Some(Value::String(s)) if &**s == "Cancelled")
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
 #[derive(Debug)]
 pub struct CachedArgsAsync<T: EvalCachedAsync> {
     cached: CachedVals,
     id: BindId,
     top_id: ExprId,
+    desc: ArcStr,
     queued: VecDeque<T::Args>,
     running: bool,
     t: T,
@@ -404,6 +519,7 @@ impl<R: Rt, E: UserEvent, T: EvalCachedAsync> BuiltIn<R, E> for CachedArgsAsync<
         let t = CachedArgsAsync::<T> {
             id,
             top_id,
+            desc: ArcStr::from(T::NAME),
             cached: CachedVals::new(from),
             queued: VecDeque::new(),
             running: false,
@@ -433,8 +549,7 @@ impl<R: Rt, E: UserEvent, T: EvalCachedAsync> Apply<R, E> for CachedArgsAsync<T>
             && let Some(args) = self.queued.pop_front()
         {
             self.running = true;
-            let id = self.id;
-            ctx.rt.spawn_var(async move { (id, T::eval(args).await) });
+            ctx.rt.spawn_var(self.id, self.desc.clone(), T::eval(args));
         }
         res
     }
@@ -464,14 +579,29 @@ impl<R: Rt, E: UserEvent, T: EvalCachedAsync> Apply<R, E> for CachedArgsAsync<T>
 }
 
 pub trait MapCollection: Debug + Clone + Default + Send + Sync + 'static {
+    /// The number of collection arguments that are mapped in lock
+    /// step. When it is more than one each element is a tuple holding
+    /// one value from each input, and the function is called with
+    /// the tuple fields as separate arguments.
+    const INPUTS: usize = 1;
+
     /// return the length of the collection
     fn len(&self) -> usize;
 
     /// iterate the collection elements as values
     fn iter_values(&self) -> impl Iterator<Item = Value>;
 
+    /// iterate the arguments the function is called with for each
+    /// element, one value for each input. Collections with more than
+    /// one input should override this to avoid building a tuple for
+    /// every element.
+    fn iter_args(&self) -> impl Iterator<Item = SmallVec<[Value; 2]>> {
+        self.iter_values().map(|v| smallvec![v])
+    }
+
     /// given a value, return Some if the value is the collection type
-    /// we are mapping.
+    /// we are mapping. When there is more than one input the value is
+    /// a tuple of the latest value of each input.
     fn select(v: Value) -> Option<Self>;
 
     /// given a collection wrap it in a value
@@ -556,16 +686,54 @@ pub trait MapFn<R: Rt, E: UserEvent>: Debug + Default + Send + Sync + 'static {
 
 #[derive(Debug)]
 pub struct Slot<R: Rt, E: UserEvent> {
-    pub id: BindId,
+    /// the bind id of each argument of pred
+    pub ids: SmallVec<[BindId; 2]>,
     pub pred: Node<R, E>,
     pub cur: Option<Value>,
 }
 
 impl<R: Rt, E: UserEvent> Slot<R, E> {
+    /// bind an argument of type `typ` for each of `args` in `scope`
+    /// and apply the function `fid` to them
+    pub fn new(
+        ctx: &mut ExecCtx<R, E>,
+        scope: &Scope,
+        fid: BindId,
+        ftyp: &TArc<FnType>,
+        args: &[Type],
+        top_id: ExprId,
+    ) -> Self {
+        let mut ids = smallvec![];
+        let mut fargs = vec![];
+        for typ in args {
+            let (id, node) = genn::bind(ctx, &scope.lexical, "x", typ.clone(), top_id);
+            ids.push(id);
+            fargs.push(node);
+        }
+        let fnode = genn::reference(ctx, fid, Type::Fn(ftyp.clone()), top_id);
+        let pred = genn::apply(fnode, scope.clone(), fargs, ftyp, top_id);
+        Self { ids, pred, cur: None }
+    }
+
+    /// set the arguments of pred
+    fn set(
+        &self,
+        ctx: &mut ExecCtx<R, E>,
+        event: &mut Event<E>,
+        args: SmallVec<[Value; 2]>,
+    ) {
+        for (id, v) in self.ids.iter().zip(args) {
+            ctx.cached.insert(*id, v.clone());
+            event.variables.insert(*id, v);
+        }
+    }
+
     pub fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
         self.pred.delete(ctx);
-        ctx.cached.remove(&self.id);
-        ctx.env.unbind_variable(self.id);
+        for id in &self.ids {
+            ctx.cached.remove(id);
+            ctx.env.unbind_variable(*id);
+        }
     }
 }
 
@@ -575,12 +743,47 @@ pub struct MapQ<R: Rt, E: UserEvent, T: MapFn<R, E>> {
     predid: BindId,
     top_id: ExprId,
     mftyp: TArc<FnType>,
-    etyp: Type,
+    /// the type of each argument of the mapped function
+    atyps: SmallVec<[Type; 2]>,
+    /// the latest value of each input
+    inputs: SmallVec<[Option<Value>; 2]>,
     slots: Vec<Slot<R, E>>,
     cur: T::Collection,
     t: T,
 }
 
+impl<R: Rt, E: UserEvent, T: MapFn<R, E>> MapQ<R, E, T> {
+    /// update the inputs, returning the collection if any of them
+    /// updated and together they are the collection we are mapping
+    fn select(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<T::Collection> {
+        let mut up = false;
+        for (n, cur) in from.iter_mut().zip(self.inputs.iter_mut()) {
+            if let Some(v) = n.update(ctx, event) {
+                *cur = Some(v);
+                up = true;
+            }
+        }
+        if !up {
+            return None;
+        }
+        match &mut self.inputs[..] {
+            [v] => v.take().and_then(T::Collection::select),
+            vs => {
+                let mut vs =
+                    vs.iter().cloned().collect::<Option<LPooled<Vec<Value>>>>()?;
+                T::Collection::select(Value::Array(ValArray::from_iter_exact(
+                    vs.drain(..),
+                )))
+            }
+        }
+    }
+}
+
 impl<R: Rt, E: UserEvent, T: MapFn<R, E>> BuiltIn<R, E> for MapQ<R, E, T> {
     const NAME: &str = T::NAME;
     const NEEDS_CALLSITE: bool = false;
@@ -593,26 +796,34 @@ impl<R: Rt, E: UserEvent, T: MapFn<R, E>> BuiltIn<R, E> for MapQ<R, E, T> {
         from: &'c [Node<R, E>],
         top_id: ExprId,
     ) -> Result<Box<dyn Apply<R, E>>> {
-        match from {
-            [_, _] => {
-                let typ = resolved.unwrap_or(typ);
-                Ok(Box::new(Self {
-                    scope: scope
-                        .append(&format_compact!("fn{}", LambdaId::new().inner())),
-                    predid: BindId::new(),
-                    top_id,
-                    etyp: T::Collection::etyp(typ)?,
-                    mftyp: match &typ.args[1].typ {
-                        Type::Fn(ft) => ft.clone(),
-                        t => bail!("expected a function not {t}"),
-                    },
-                    slots: vec![],
-                    cur: Default::default(),
-                    t: T::default(),
-                }))
-            }
-            _ => bail!("expected two arguments"),
+        let n = T::Collection::INPUTS;
+        if from.len() != n + 1 {
+            bail!("expected {} arguments", n + 1)
         }
+        let typ = resolved.unwrap_or(typ);
+        let etyp = T::Collection::etyp(typ)?;
+        let atyps = if n == 1 {
+            smallvec![etyp]
+        } else {
+            match etyp {
+                Type::Tuple(ts) if ts.len() == n => ts.iter().cloned().collect(),
+                t => bail!("expected a tuple of {n} elements not {t}"),
+            }
+        };
+        Ok(Box::new(Self {
+            scope: scope.append(&format_compact!("fn{}", LambdaId::new().inner())),
+            predid: BindId::new(),
+            top_id,
+            atyps,
+            mftyp: match &typ.args[n].typ {
+                Type::Fn(ft) => ft.clone(),
+                t => bail!("expected a function not {t}"),
+            },
+            inputs: smallvec![None; n],
+            slots: vec![],
+            cur: Default::default(),
+            t: T::default(),
+        }))
     }
 }
 
@@ -624,78 +835,61 @@ impl<R: Rt, E: UserEvent, T: MapFn<R, E>> Apply<R, E> for MapQ<R, E, T> {
         event: &mut Event<E>,
     ) -> Option<Value> {
         let slen = self.slots.len();
-        if let Some(v) = from[1].update(ctx, event) {
+        let (from, f) = from.split_at_mut(T::Collection::INPUTS);
+        if let Some(v) = f[0].update(ctx, event) {
             ctx.cached.insert(self.predid, v.clone());
             event.variables.insert(self.predid, v);
         }
-        let (up, resized) =
-            match from[0].update(ctx, event).and_then(|v| T::Collection::select(v)) {
-                Some(a) if a.len() == slen => (Some(a), false),
-                Some(a) if a.len() < slen => {
-                    while self.slots.len() > a.len() {
-                        if let Some(mut s) = self.slots.pop() {
-                            s.delete(ctx)
-                        }
+        let (up, resized) = match self.select(ctx, from, event) {
+            Some(a) if a.len() == slen => (Some(a), false),
+            Some(a) if a.len() < slen => {
+                while self.slots.len() > a.len() {
+                    if let Some(mut s) = self.slots.pop() {
+                        s.delete(ctx)
                     }
-                    (Some(a), true)
                 }
-                Some(a) => {
-                    while self.slots.len() < a.len() {
-                        let (id, node) = genn::bind(
-                            ctx,
-                            &self.scope.lexical,
-                            "x",
-                            self.etyp.clone(),
-                            self.top_id,
-                        );
-                        let fargs = vec![node];
-                        let fnode = genn::reference(
-                            ctx,
-                            self.predid,
-                            Type::Fn(self.mftyp.clone()),
-                            self.top_id,
-                        );
-                        let pred = genn::apply(
-                            fnode,
-                            self.scope.clone(),
-                            fargs,
-                            &self.mftyp,
-                            self.top_id,
-                        );
-                        self.slots.push(Slot { id, pred, cur: None });
-                    }
-                    (Some(a), true)
+                (Some(a), true)
+            }
+            Some(a) => {
+                while self.slots.len() < a.len() {
+                    self.slots.push(Slot::new(
+                        ctx,
+                        &self.scope,
+                        self.predid,
+                        &self.mftyp,
+                        &self.atyps,
+                        self.top_id,
+                    ));
                 }
-                None => (None, false),
-            };
+                (Some(a), true)
+            }
+            None => (None, false),
+        };
         if let Some(a) = up {
-            for (s, v) in self.slots.iter().zip(a.iter_values()) {
-                ctx.cached.insert(s.id, v.clone());
-                event.variables.insert(s.id, v);
+            for (s, args) in self.slots.iter().zip(a.iter_args()) {
+                s.set(ctx, event, args)
             }
             self.cur = a.clone();
             if a.len() == 0 {
                 return Some(T::Collection::project(a));
             }
         }
-        let init = event.init;
         let mut up = resized;
         for (i, s) in self.slots.iter_mut().enumerate() {
             if i == slen {
                 // new nodes were added starting here
-                event.init = true;
                 if let Entry::Vacant(e) = event.variables.entry(self.predid)
                     && let Some(v) = ctx.cached.get(&self.predid)
                 {
                     e.insert(v.clone());
                 }
             }
-            if let Some(v) = s.pred.update(ctx, event) {
+            let init = event.init || i >= slen;
+            if let Some(v) = s.pred.update(ctx, &mut event.init_scope(init)) {
                 s.cur = Some(v);
                 up = true;
             }
         }
-        event.init = init;
         if up && self.slots.iter().all(|s| s.cur.is_some()) {
             self.t.finish(&mut &self.slots, &self.cur)
         } else {
@@ -709,19 +903,15 @@ impl<R: Rt, E: UserEvent, T: MapFn<R, E>> Apply<R, E> for MapQ<R, E, T> {
         from: &mut [Node<R, E>],
         _phase: TypecheckPhase<'_>,
     ) -> anyhow::Result<()> {
-        let mftyp = match &from[1].typ() {
+        let mftyp = match &from[T::Collection::INPUTS].typ() {
             Type::Fn(ft) => ft.clone(),
             t => bail!("expected a function not {t}"),
         };
-        let (_, node) =
-            genn::bind(ctx, &self.scope.lexical, "x", self.etyp.clone(), self.top_id);
-        let fargs = vec![node];
-        let ft = mftyp.clone();
-        let fnode = genn::reference(ctx, self.predid, Type::Fn(ft.clone()), self.top_id);
-        let mut node = genn::apply(fnode, self.scope.clone(), fargs, &ft, self.top_id);
-        node.typecheck(ctx)?;
-        node.delete(ctx);
-        Ok(())
+        let mut slot =
+            Slot::new(ctx, &self.scope, self.predid, &mftyp, &self.atyps, self.top_id);
+        let r = slot.pred.typecheck(ctx);
+        slot.delete(ctx);
+        r
     }
 
     fn refs(&self, refs: &mut Refs) {
@@ -739,6 +929,9 @@ impl<R: Rt, E: UserEvent, T: MapFn<R, E>> Apply<R, E> for MapQ<R, E, T> {
 
     fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
         self.cur = Default::default();
+        for v in &mut self.inputs {
+            *v = None;
+        }
         for sl in &mut self.slots {
             sl.cur = None;
             sl.pred.sleep(ctx);
@@ -746,10 +939,21 @@ impl<R: Rt, E: UserEvent, T: MapFn<R, E>> Apply<R, E> for MapQ<R, E, T> {
     }
 }
 
-pub trait FoldFn<R: Rt, E: UserEvent>: Debug + Send + Sync + 'static {
+pub trait FoldFn<R: Rt, E: UserEvent>: Debug + Default + Send + Sync + 'static {
     type Collection: MapCollection;
 
     const NAME: &str;
+
+    /// produce the result from the accumulator after each element,
+    /// `None` where it did not update this cycle. `up` is true if any
+    /// argument updated this cycle. The default returns the final
+    /// accumulator.
+    fn finish(&mut self, _up: bool, accs: &[Option<Value>]) -> Option<Value> {
+        accs.last().and_then(|v| v.clone())
+    }
+
+    /// forget any state kept between cycles
+    fn sleep(&mut self) {}
 }
 
 #[derive(Debug)]
@@ -766,7 +970,7 @@ pub struct FoldQ<R: Rt, E: UserEvent, T: FoldFn<R, E>> {
     etyp: Type,
     ityp: Type,
     init: Option<Value>,
-    t: PhantomData<T>,
+    t: T,
 }
 
 impl<R: Rt, E: UserEvent, T: FoldFn<R, E>> BuiltIn<R, E> for FoldQ<R, E, T> {
@@ -800,7 +1004,7 @@ impl<R: Rt, E: UserEvent, T: FoldFn<R, E>> BuiltIn<R, E> for FoldQ<R, E, T> {
                         t => bail!("expected a function not {t}"),
                     },
                     init: None,
-                    t: PhantomData,
+                    t: T::default(),
                 }))
             }
             _ => bail!("expected three arguments"),
@@ -815,10 +1019,12 @@ impl<R: Rt, E: UserEvent, T: FoldFn<R, E>> Apply<R, E> for FoldQ<R, E, T> {
         from: &mut [Node<R, E>],
         event: &mut Event<E>,
     ) -> Option<Value> {
+        let mut up = false;
         let init = match from[0].update(ctx, event).and_then(|v| T::Collection::select(v))
         {
             None => self.nodes.len(),
             Some(a) if a.len() == self.binds.len() => {
+                up = true;
                 for (id, v) in self.binds.iter().zip(a.iter_values()) {
                     ctx.cached.insert(*id, v.clone());
                     event.variables.insert(*id, v.clone());
@@ -826,6 +1032,7 @@ impl<R: Rt, E: UserEvent, T: FoldFn<R, E>> Apply<R, E> for FoldQ<R, E, T> {
                 self.nodes.len()
             }
             Some(a) => {
+                up = true;
                 let vals = a.iter_values().collect::<LPooled<Vec<Value>>>();
                 while self.binds.len() < a.len() {
                     self.binds.push(BindId::new());
@@ -881,18 +1088,18 @@ impl<R: Rt, E: UserEvent, T: FoldFn<R, E>> Apply<R, E> for FoldQ<R, E, T> {
             }
         };
         if let Some(v) = from[1].update(ctx, event) {
+            up = true;
             ctx.cached.insert(self.initid, v.clone());
             event.variables.insert(self.initid, v.clone());
             self.init = Some(v);
         }
         if let Some(v) = from[2].update(ctx, event) {
+            up = true;
             ctx.cached.insert(self.fid, v.clone());
             event.variables.insert(self.fid, v);
         }
-        let old_init = event.init;
         for i in 0..self.nodes.len() {
             if i == init {
-                event.init = true;
                 if let Some(v) = ctx.cached.get(&self.fid)
                     && let Entry::Vacant(e) = event.variables.entry(self.fid)
                 {
@@ -910,7 +1117,9 @@ impl<R: Rt, E: UserEvent, T: FoldFn<R, E>> Apply<R, E> for FoldQ<R, E, T> {
                     }
                 }
             }
-            match self.nodes[i].update(ctx, event) {
+            // new nodes were added starting at init
+            let ninit = event.init || i >= init;
+            match self.nodes[i].update(ctx, &mut event.init_scope(ninit)) {
                 Some(v) => {
                     ctx.cached.insert(self.initids[i], v.clone());
                     event.variables.insert(self.initids[i], v.clone());
@@ -923,8 +1132,7 @@ impl<R: Rt, E: UserEvent, T: FoldFn<R, E>> Apply<R, E> for FoldQ<R, E, T> {
                 }
             }
         }
-        event.init = old_init;
-        self.inits.last().and_then(|v| v.clone())
+        self.t.finish(up, &self.inits)
     }
 
     fn typecheck(
@@ -965,6 +1173,7 @@ impl<R: Rt, E: UserEvent, T: FoldFn<R, E>> Apply<R, E> for FoldQ<R, E, T> {
         for v in &mut self.inits {
             *v = None
         }
+        self.t.sleep();
         for n in &mut self.nodes {
             n.sleep(ctx)
         }
@@ -1075,6 +1284,59 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for ToError {
     fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {}
 }
 
+#[derive(Debug, Default)]
+struct OrElseEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for OrElseEv {
+    const NAME: &str = "core_or_else";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        let mut last_err = None;
+        for v in from.0.iter().flatten() {
+            match v {
+                Value::Error(_) => last_err = Some(v),
+                v => return Some(v.clone()),
+            }
+        }
+        last_err.cloned()
+    }
+}
+
+type OrElse = CachedArgs<OrElseEv>;
+
+#[derive(Debug, Default)]
+struct SequenceEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for SequenceEv {
+    const NAME: &str = "core_sequence";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        let a = match &from.0[0] {
+            Some(Value::Array(a)) => a,
+            _ => return None,
+        };
+        let mut res: LPooled<Vec<Value>> = LPooled::take();
+        for v in a.iter() {
+            match v {
+                Value::Error(_) => return Some(v.clone()),
+                Value::Array(elts) => match &elts[..] {
+                    [Value::String(tag), v] if tag == "Ok" => res.push(v.clone()),
+                    [Value::String(tag), e] if tag == "Err" => {
+                        return Some(Value::Error(triomphe::Arc::new(e.clone())));
+                    }
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        Some(Value::Array(ValArray::from_iter_exact(res.drain(..))))
+    }
+}
+
+type Sequence = CachedArgs<SequenceEv>;
+
 #[derive(Debug)]
 struct Once {
     val: bool,
@@ -1306,6 +1568,9 @@ fn div_vals(lhs: Option<Value>, rhs: Option<Value>) -> Option<Value> {
     match (lhs, rhs) {
         (None, None) | (Some(_), None) => None,
         (None, r @ Some(_)) => r,
+        (Some(Value::Decimal(_)), Some(Value::Decimal(r))) if r.is_zero() => {
+            Some(errf!("ArithError", "division by zero"))
+        }
         (Some(l), Some(r)) => Some(l / r),
     }
 }
@@ -1376,6 +1641,69 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for MaxEv {
 
 type Max = CachedArgs<MaxEv>;
 
+/// compare two numbers, numbers of different types are compared by their
+/// value as f64
+fn num_cmp(v0: &Value, v1: &Value) -> Option<cmp::Ordering> {
+    if mem::discriminant(v0) == mem::discriminant(v1) {
+        v0.partial_cmp(v1)
+    } else {
+        let v0 = v0.clone().cast_to::<f64>().ok()?;
+        let v1 = v1.clone().cast_to::<f64>().ok()?;
+        v0.partial_cmp(&v1)
+    }
+}
+
+macro_rules! running_extremum {
+    ($name:ident, $builtin:literal, $ord:expr) => {
+        #[derive(Debug)]
+        struct $name(Option<Value>);
+
+        impl<R: Rt, E: UserEvent> BuiltIn<R, E> for $name {
+            const NAME: &str = $builtin;
+            const NEEDS_CALLSITE: bool = false;
+
+            fn init<'a, 'b, 'c, 'd>(
+                _ctx: &'a mut ExecCtx<R, E>,
+                _typ: &'a FnType,
+                _resolved: Option<&'d FnType>,
+                _scope: &'b Scope,
+                _from: &'c [Node<R, E>],
+                _top_id: ExprId,
+            ) -> Result<Box<dyn Apply<R, E>>> {
+                Ok(Box::new($name(None)))
+            }
+        }
+
+        impl<R: Rt, E: UserEvent> Apply<R, E> for $name {
+            fn update(
+                &mut self,
+                ctx: &mut ExecCtx<R, E>,
+                from: &mut [Node<R, E>],
+                event: &mut Event<E>,
+            ) -> Option<Value> {
+                let v = from[0].update(ctx, event)?;
+                let new = match &self.0 {
+                    None => num_cmp(&v, &v).is_some(),
+                    Some(cur) => num_cmp(&v, cur) == Some($ord),
+                };
+                if new {
+                    self.0 = Some(v.clone());
+                    Some(v)
+                } else {
+                    None
+                }
+            }
+
+            fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {
+                self.0 = None
+            }
+        }
+    };
+}
+
+running_extremum!(RunningMin, "core_running_min", cmp::Ordering::Less);
+running_extremum!(RunningMax, "core_running_max", cmp::Ordering::Greater);
+
 #[derive(Debug, Default)]
 struct AndEv;
 
@@ -1446,50 +1774,111 @@ macro_rules! int_binop {
     };
 }
 
+/// shift `l` by `by` bits using `f`, or return an `ArithError` if `by` is
+/// negative or not less than the width of the type
+fn shift<T, S>(l: T, by: S, f: fn(T, u32) -> Option<T>, mk: fn(T) -> Value) -> Value
+where
+    S: TryInto<u32> + Display + Copy,
+{
+    static TAG: ArcStr = literal!("ArithError");
+    match by.try_into().ok().and_then(|by| f(l, by)) {
+        Some(v) => mk(v),
+        None => errf!(TAG, "shift by {by} is out of range"),
+    }
+}
+
 macro_rules! int_shift {
     ($from:expr, $method:ident) => {
         match (&$from.0[0], &$from.0[1]) {
             (Some(Value::U8(l)), Some(Value::U8(r))) => {
-                Some(Value::U8(l.$method(*r as u32)))
+                Some(shift(*l, *r, u8::$method, Value::U8))
             }
             (Some(Value::I8(l)), Some(Value::I8(r))) => {
-                Some(Value::I8(l.$method(*r as u32)))
+                Some(shift(*l, *r, i8::$method, Value::I8))
             }
             (Some(Value::U16(l)), Some(Value::U16(r))) => {
-                Some(Value::U16(l.$method(*r as u32)))
+                Some(shift(*l, *r, u16::$method, Value::U16))
             }
             (Some(Value::I16(l)), Some(Value::I16(r))) => {
-                Some(Value::I16(l.$method(*r as u32)))
+                Some(shift(*l, *r, i16::$method, Value::I16))
             }
             (Some(Value::U32(l)), Some(Value::U32(r))) => {
-                Some(Value::U32(l.$method(*r as u32)))
+                Some(shift(*l, *r, u32::$method, Value::U32))
             }
             (Some(Value::V32(l)), Some(Value::V32(r))) => {
-                Some(Value::V32(l.$method(*r as u32)))
+                Some(shift(*l, *r, u32::$method, Value::V32))
             }
             (Some(Value::I32(l)), Some(Value::I32(r))) => {
-                Some(Value::I32(l.$method(*r as u32)))
+                Some(shift(*l, *r, i32::$method, Value::I32))
             }
             (Some(Value::Z32(l)), Some(Value::Z32(r))) => {
-                Some(Value::Z32(l.$method(*r as u32)))
+                Some(shift(*l, *r, i32::$method, Value::Z32))
             }
             (Some(Value::U64(l)), Some(Value::U64(r))) => {
-                Some(Value::U64(l.$method(*r as u32)))
+                Some(shift(*l, *r, u64::$method, Value::U64))
             }
             (Some(Value::V64(l)), Some(Value::V64(r))) => {
-                Some(Value::V64(l.$method(*r as u32)))
+                Some(shift(*l, *r, u64::$method, Value::V64))
             }
             (Some(Value::I64(l)), Some(Value::I64(r))) => {
-                Some(Value::I64(l.$method(*r as u32)))
+                Some(shift(*l, *r, i64::$method, Value::I64))
             }
             (Some(Value::Z64(l)), Some(Value::Z64(r))) => {
-                Some(Value::Z64(l.$method(*r as u32)))
+                Some(shift(*l, *r, i64::$method, Value::Z64))
             }
             _ => None,
         }
     };
 }
 
+macro_rules! int_method {
+    ($from:expr, $method:ident) => {
+        match (&$from.0[0], &$from.0[1]) {
+            (Some(Value::U8(l)), Some(Value::U8(r))) => Some(Value::U8(l.$method(*r))),
+            (Some(Value::I8(l)), Some(Value::I8(r))) => Some(Value::I8(l.$method(*r))),
+            (Some(Value::U16(l)), Some(Value::U16(r))) => Some(Value::U16(l.$method(*r))),
+            (Some(Value::I16(l)), Some(Value::I16(r))) => Some(Value::I16(l.$method(*r))),
+            (Some(Value::U32(l)), Some(Value::U32(r))) => Some(Value::U32(l.$method(*r))),
+            (Some(Value::V32(l)), Some(Value::V32(r))) => Some(Value::V32(l.$method(*r))),
+            (Some(Value::I32(l)), Some(Value::I32(r))) => Some(Value::I32(l.$method(*r))),
+            (Some(Value::Z32(l)), Some(Value::Z32(r))) => Some(Value::Z32(l.$method(*r))),
+            (Some(Value::U64(l)), Some(Value::U64(r))) => Some(Value::U64(l.$method(*r))),
+            (Some(Value::V64(l)), Some(Value::V64(r))) => Some(Value::V64(l.$method(*r))),
+            (Some(Value::I64(l)), Some(Value::I64(r))) => Some(Value::I64(l.$method(*r))),
+            (Some(Value::Z64(l)), Some(Value::Z64(r))) => Some(Value::Z64(l.$method(*r))),
+            _ => None,
+        }
+    };
+}
+
+#[derive(Debug, Default)]
+struct WrappingAddEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for WrappingAddEv {
+    const NAME: &str = "core_wrapping_add";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        int_method!(from, wrapping_add)
+    }
+}
+
+type WrappingAdd = CachedArgs<WrappingAddEv>;
+
+#[derive(Debug, Default)]
+struct SaturatingAddEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for SaturatingAddEv {
+    const NAME: &str = "core_saturating_add";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        int_method!(from, saturating_add)
+    }
+}
+
+type SaturatingAdd = CachedArgs<SaturatingAddEv>;
+
 #[derive(Debug, Default)]
 struct BitAndEv;
 
@@ -1568,7 +1957,7 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for ShlEv {
     const NEEDS_CALLSITE: bool = false;
 
     fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        int_shift!(from, wrapping_shl)
+        int_shift!(from, checked_shl)
     }
 }
 
@@ -1582,7 +1971,7 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for ShrEv {
     const NEEDS_CALLSITE: bool = false;
 
     fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        int_shift!(from, wrapping_shr)
+        int_shift!(from, checked_shr)
     }
 }
 
@@ -1721,196 +2110,2386 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Filter<R, E> {
 }
 
 #[derive(Debug)]
-struct Queue {
-    triggered: usize,
-    queue: VecDeque<Value>,
-    id: BindId,
-    top_id: ExprId,
+struct Memoize<R: Rt, E: UserEvent> {
+    capacity: usize,
+    stamp: u64,
+    cache: FxHashMap<Value, (u64, Value)>,
+    current: Option<Value>,
+    last: Option<Value>,
+    f: Node<R, E>,
+    fid: BindId,
+    x: BindId,
 }
 
-impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Queue {
-    const NAME: &str = "core_queue";
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Memoize<R, E> {
+    const NAME: &str = "core_memoize";
     const NEEDS_CALLSITE: bool = false;
 
     fn init<'a, 'b, 'c, 'd>(
         ctx: &'a mut ExecCtx<R, E>,
-        _typ: &'a FnType,
-        _resolved: Option<&'d FnType>,
-        _scope: &'b Scope,
+        typ: &'a graphix_compiler::typ::FnType,
+        resolved: Option<&'d FnType>,
+        scope: &'b Scope,
         from: &'c [Node<R, E>],
         top_id: ExprId,
     ) -> Result<Box<dyn Apply<R, E>>> {
         match from {
-            [_, _] => {
-                let id = BindId::new();
-                ctx.rt.ref_var(id, top_id);
-                Ok(Box::new(Self { triggered: 0, queue: VecDeque::new(), id, top_id }))
+            [_, _, _] => {
+                let typ = resolved.unwrap_or(typ);
+                let (x, xn) =
+                    genn::bind(ctx, &scope.lexical, "x", typ.args[1].typ.clone(), top_id);
+                let fid = BindId::new();
+                let ftyp = match &typ.args[2].typ {
+                    Type::Fn(ft) => ft.clone(),
+                    t => bail!("expected a function not {t}"),
+                };
+                let fnode = genn::reference(ctx, fid, Type::Fn(ftyp.clone()), top_id);
+                let f = genn::apply(fnode, scope.clone(), vec![xn], &ftyp, top_id);
+                Ok(Box::new(Self {
+                    capacity: usize::MAX,
+                    stamp: 0,
+                    cache: FxHashMap::default(),
+                    current: None,
+                    last: None,
+                    f,
+                    fid,
+                    x,
+                }))
             }
-            _ => bail!("expected two arguments"),
+            _ => bail!("expected three arguments"),
         }
     }
 }
 
-impl<R: Rt, E: UserEvent> Apply<R, E> for Queue {
+impl<R: Rt, E: UserEvent> Memoize<R, E> {
+    fn evict(&mut self) {
+        while self.cache.len() > self.capacity {
+            let lru = self.cache.iter().min_by_key(|(_, (stamp, _))| *stamp);
+            match lru.map(|(k, _)| k.clone()) {
+                Some(k) => self.cache.remove(&k),
+                None => break,
+            };
+        }
+    }
+
+    fn call(&mut self, ctx: &mut ExecCtx<R, E>, event: &mut Event<E>, k: Value) {
+        self.last = Some(k.clone());
+        ctx.cached.insert(self.x, k.clone());
+        event.variables.insert(self.x, k);
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Memoize<R, E> {
     fn update(
         &mut self,
         ctx: &mut ExecCtx<R, E>,
         from: &mut [Node<R, E>],
         event: &mut Event<E>,
     ) -> Option<Value> {
-        if from[0].update(ctx, event).is_some() {
-            self.triggered += 1;
+        if let Some(Value::I64(n)) = from[0].update(ctx, event) {
+            self.capacity = n.max(1) as usize;
+            self.evict();
         }
-        if let Some(v) = from[1].update(ctx, event) {
-            self.queue.push_back(v);
+        if let Some(v) = from[2].update(ctx, event) {
+            // results computed by a different function are no longer valid
+            self.cache.clear();
+            ctx.cached.insert(self.fid, v.clone());
+            event.variables.insert(self.fid, v);
+            if let Some(k) = self.current.clone()
+                && self.last.as_ref() != Some(&k)
+            {
+                self.call(ctx, event, k)
+            }
         }
-        while self.triggered > 0 && self.queue.len() > 0 {
-            self.triggered -= 1;
-            ctx.rt.set_var(self.id, self.queue.pop_front().unwrap());
+        let mut res = None;
+        if let Some(k) = from[1].update(ctx, event) {
+            self.current = Some(k.clone());
+            self.stamp += 1;
+            match self.cache.get_mut(&k) {
+                Some((stamp, v)) => {
+                    *stamp = self.stamp;
+                    res = Some(v.clone());
+                }
+                None => self.call(ctx, event, k),
+            }
         }
-        event.variables.get(&self.id).cloned()
+        if let Some(v) = self.f.update(ctx, event)
+            && let Some(k) = self.last.clone()
+        {
+            self.stamp += 1;
+            self.cache.insert(k.clone(), (self.stamp, v.clone()));
+            self.evict();
+            if self.current.as_ref() == Some(&k) {
+                res = Some(v);
+            }
+        }
+        res
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        _phase: TypecheckPhase<'_>,
+    ) -> anyhow::Result<()> {
+        self.f.typecheck(ctx)?;
+        Ok(())
+    }
+
+    fn refs(&self, refs: &mut Refs) {
+        self.f.refs(refs)
     }
 
     fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
-        ctx.rt.unref_var(self.id, self.top_id);
+        ctx.cached.remove(&self.fid);
+        ctx.cached.remove(&self.x);
+        ctx.env.unbind_variable(self.x);
+        self.f.delete(ctx);
     }
 
     fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
-        ctx.rt.unref_var(self.id, self.top_id);
-        self.id = BindId::new();
-        ctx.rt.ref_var(self.id, self.top_id);
-        self.triggered = 0;
-        self.queue.clear();
+        self.cache.clear();
+        self.current = None;
+        self.last = None;
+        self.f.sleep(ctx);
+    }
+}
+
+/// Build a node that calls the zero argument function bound to a new id,
+/// for built-ins that call a thunk argument on demand. Run it with
+/// `invoke_thunk`.
+fn thunk_node<R: Rt, E: UserEvent>(
+    ctx: &mut ExecCtx<R, E>,
+    ftyp: &TArc<FnType>,
+    scope: &Scope,
+    top_id: ExprId,
+) -> (BindId, Node<R, E>) {
+    let fid = BindId::new();
+    let fnode = genn::reference(ctx, fid, Type::Fn(ftyp.clone()), top_id);
+    let node = genn::apply(fnode, scope.clone(), vec![], ftyp, top_id);
+    (fid, node)
+}
+
+/// Call the thunk f through a node built by `thunk_node` from scratch. The
+/// variables it refers to are delivered as if they had all just updated,
+/// so it runs as if it had just been compiled, and its output is returned.
+fn invoke_thunk<R: Rt, E: UserEvent>(
+    ctx: &mut ExecCtx<R, E>,
+    event: &mut Event<E>,
+    fid: BindId,
+    node: &mut Node<R, E>,
+    f: Value,
+) -> Option<Value> {
+    ctx.cached.insert(fid, f.clone());
+    event.variables.insert(fid, f);
+    let mut set: LPooled<Vec<BindId>> = LPooled::take();
+    let mut refs = Refs::default();
+    node.refs(&mut refs);
+    refs.with_external_refs(|id| {
+        if let Entry::Vacant(e) = event.variables.entry(id)
+            && let Some(v) = ctx.cached.get(&id)
+        {
+            e.insert(v.clone());
+            set.push(id);
+        }
+    });
+    let res = node.update(ctx, &mut event.init_scope(true));
+    for id in set.drain(..) {
+        event.variables.remove(&id);
     }
+    res
 }
 
 #[derive(Debug)]
-struct Hold {
-    triggered: usize,
-    current: Option<Value>,
+struct Switch<R: Rt, E: UserEvent> {
+    scope: Scope,
+    top_id: ExprId,
+    ftyp: TArc<FnType>,
+    key: Option<Value>,
+    lambdas: CMap<Value, Value, 32>,
+    selected: Option<Value>,
+    arms: FxHashMap<Value, (BindId, Node<R, E>)>,
 }
 
-impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Hold {
-    const NAME: &str = "core_hold";
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Switch<R, E> {
+    const NAME: &str = "core_switch";
     const NEEDS_CALLSITE: bool = false;
 
     fn init<'a, 'b, 'c, 'd>(
         _ctx: &'a mut ExecCtx<R, E>,
-        _typ: &'a FnType,
-        _resolved: Option<&'d FnType>,
-        _scope: &'b Scope,
+        typ: &'a graphix_compiler::typ::FnType,
+        resolved: Option<&'d FnType>,
+        scope: &'b Scope,
         from: &'c [Node<R, E>],
-        _top_id: ExprId,
+        top_id: ExprId,
     ) -> Result<Box<dyn Apply<R, E>>> {
         match from {
-            [_, _] => Ok(Box::new(Self { triggered: 0, current: None })),
+            [_, _] => {
+                let typ = resolved.unwrap_or(typ);
+                let ftyp = match &typ.args[1].typ {
+                    Type::Map { value, .. } => match &**value {
+                        Type::Fn(ft) => ft.clone(),
+                        t => bail!("expected a function not {t}"),
+                    },
+                    t => bail!("expected a map not {t}"),
+                };
+                Ok(Box::new(Self {
+                    scope: scope
+                        .append(&format_compact!("fn{}", LambdaId::new().inner())),
+                    top_id,
+                    ftyp,
+                    key: None,
+                    lambdas: CMap::default(),
+                    selected: None,
+                    arms: FxHashMap::default(),
+                }))
+            }
             _ => bail!("expected two arguments"),
         }
     }
 }
 
-impl<R: Rt, E: UserEvent> Apply<R, E> for Hold {
+impl<R: Rt, E: UserEvent> Switch<R, E> {
+    /// wake the arm for k, building it if this is the first time k has been
+    /// selected, and return it's output
+    fn wake(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        event: &mut Event<E>,
+        k: Value,
+    ) -> Option<Value> {
+        let f = self.lambdas.get(&k)?.clone();
+        let (fid, node) = self
+            .arms
+            .entry(k.clone())
+            .or_insert_with(|| thunk_node(ctx, &self.ftyp, &self.scope, self.top_id));
+        self.selected = Some(k);
+        invoke_thunk(ctx, event, *fid, node, f)
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Switch<R, E> {
     fn update(
         &mut self,
         ctx: &mut ExecCtx<R, E>,
         from: &mut [Node<R, E>],
         event: &mut Event<E>,
     ) -> Option<Value> {
-        if from[0].update(ctx, event).is_some() {
-            self.triggered += 1;
+        if let Some(k) = from[0].update(ctx, event) {
+            self.key = Some(k);
         }
-        if let Some(v) = from[1].update(ctx, event) {
-            self.current = Some(v);
+        if let Some(Value::Map(m)) = from[1].update(ctx, event) {
+            // arms that are asleep will pick up their new lambda when they
+            // are woken
+            for (k, (fid, _)) in self.arms.iter() {
+                if let Some(f) = m.get(k)
+                    && self.lambdas.get(k) != Some(f)
+                {
+                    ctx.cached.insert(*fid, f.clone());
+                    if self.selected.as_ref() == Some(k) {
+                        event.variables.insert(*fid, f.clone());
+                    }
+                }
+            }
+            self.lambdas = m;
         }
-        if self.triggered > 0
-            && let Some(v) = self.current.take()
-        {
-            self.triggered -= 1;
-            Some(v)
-        } else {
-            None
+        let want = self.key.clone().filter(|k| self.lambdas.get(k).is_some());
+        if want != self.selected {
+            if let Some(k) = self.selected.take()
+                && let Some((_, node)) = self.arms.get_mut(&k)
+            {
+                node.sleep(ctx);
+            }
+            return want.and_then(|k| self.wake(ctx, event, k));
         }
+        let (_, node) = self.arms.get_mut(self.selected.as_ref()?)?;
+        node.update(ctx, event)
     }
 
-    fn delete(&mut self, _: &mut ExecCtx<R, E>) {}
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        _phase: TypecheckPhase<'_>,
+    ) -> anyhow::Result<()> {
+        let ft = self.ftyp.clone();
+        let fnode =
+            genn::reference(ctx, BindId::new(), Type::Fn(ft.clone()), self.top_id);
+        let mut node = genn::apply(fnode, self.scope.clone(), vec![], &ft, self.top_id);
+        node.typecheck(ctx)?;
+        node.delete(ctx);
+        Ok(())
+    }
 
-    fn sleep(&mut self, _: &mut ExecCtx<R, E>) {
-        self.triggered = 0;
-        self.current = None;
+    fn refs(&self, refs: &mut Refs) {
+        for (_, node) in self.arms.values() {
+            node.refs(refs)
+        }
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        for (_, (fid, mut node)) in self.arms.drain() {
+            ctx.cached.remove(&fid);
+            node.delete(ctx);
+        }
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.key = None;
+        self.lambdas = CMap::default();
+        self.selected = None;
+        for (_, node) in self.arms.values_mut() {
+            node.sleep(ctx);
+        }
     }
 }
 
 #[derive(Debug)]
-struct Seq {
-    id: BindId,
+struct Retry<R: Rt, E: UserEvent> {
+    scope: Scope,
     top_id: ExprId,
-    args: CachedVals,
+    ftyp: TArc<FnType>,
+    attempts: u64,
+    backoff: Duration,
+    f: Option<Value>,
+    op: Option<(BindId, Node<R, E>)>,
+    attempt: u64,
+    running: bool,
+    succeeded: bool,
+    tid: Option<BindId>,
 }
 
-impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Seq {
-    const NAME: &str = "core_seq";
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Retry<R, E> {
+    const NAME: &str = "core_retry";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        typ: &'a graphix_compiler::typ::FnType,
+        resolved: Option<&'d FnType>,
+        scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _, _] => {
+                let typ = resolved.unwrap_or(typ);
+                let ftyp = match &typ.args[2].typ {
+                    Type::Fn(ft) => ft.clone(),
+                    t => bail!("expected a function not {t}"),
+                };
+                Ok(Box::new(Self {
+                    scope: scope
+                        .append(&format_compact!("fn{}", LambdaId::new().inner())),
+                    top_id,
+                    ftyp,
+                    attempts: 1,
+                    backoff: Duration::ZERO,
+                    f: None,
+                    op: None,
+                    attempt: 0,
+                    running: false,
+                    succeeded: false,
+                    tid: None,
+                }))
+            }
+            _ => bail!("expected three arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Retry<R, E> {
+    fn cancel(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(id) = self.tid.take() {
+            ctx.rt.unref_var(id, self.top_id);
+        }
+    }
+
+    fn stop(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx);
+        if self.running
+            && let Some((_, node)) = &mut self.op
+        {
+            node.sleep(ctx);
+        }
+        self.running = false;
+    }
+
+    /// invoke op from scratch and handle it's output
+    fn invoke(&mut self, ctx: &mut ExecCtx<R, E>, event: &mut Event<E>) -> Option<Value> {
+        let f = self.f.clone()?;
+        let (fid, node) = self
+            .op
+            .get_or_insert_with(|| thunk_node(ctx, &self.ftyp, &self.scope, self.top_id));
+        self.running = true;
+        let res = invoke_thunk(ctx, event, *fid, node, f);
+        self.handle(ctx, res)
+    }
+
+    /// put op to sleep on an error and schedule the next attempt, or
+    /// return the final error once attempts are exhausted
+    fn handle(&mut self, ctx: &mut ExecCtx<R, E>, v: Option<Value>) -> Option<Value> {
+        match v? {
+            Value::Error(e) if !self.succeeded => {
+                self.stop(ctx);
+                self.attempt += 1;
+                if self.attempt < self.attempts {
+                    let exp = u32::try_from(self.attempt - 1).unwrap_or(u32::MAX);
+                    let wait = self
+                        .backoff
+                        .checked_mul(2u32.saturating_pow(exp))
+                        .unwrap_or(Duration::MAX);
+                    let id = BindId::new();
+                    self.tid = Some(id);
+                    ctx.rt.ref_var(id, self.top_id);
+                    ctx.rt.set_timer(id, wait);
+                    None
+                } else {
+                    Some(Value::Error(e))
+                }
+            }
+            v => {
+                self.succeeded = true;
+                Some(v)
+            }
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Retry<R, E> {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if let Some(n) = from[0].update(ctx, event) {
+            self.attempts = n.cast_to::<u64>().unwrap_or(1).max(1);
+        }
+        if let Some(Value::Duration(d)) = from[1].update(ctx, event) {
+            self.backoff = *d;
+        }
+        if let Some(f) = from[2].update(ctx, event) {
+            self.stop(ctx);
+            self.f = Some(f);
+            self.attempt = 0;
+            self.succeeded = false;
+            return self.invoke(ctx, event);
+        }
+        if let Some(id) = self.tid
+            && event.variables.contains_key(&id)
+        {
+            self.cancel(ctx);
+            return self.invoke(ctx, event);
+        }
+        if !self.running {
+            return None;
+        }
+        let (_, node) = self.op.as_mut()?;
+        let v = node.update(ctx, event);
+        self.handle(ctx, v)
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        _phase: TypecheckPhase<'_>,
+    ) -> anyhow::Result<()> {
+        let ft = self.ftyp.clone();
+        let fnode =
+            genn::reference(ctx, BindId::new(), Type::Fn(ft.clone()), self.top_id);
+        let mut node = genn::apply(fnode, self.scope.clone(), vec![], &ft, self.top_id);
+        node.typecheck(ctx)?;
+        node.delete(ctx);
+        Ok(())
+    }
+
+    fn refs(&self, refs: &mut Refs) {
+        if let Some((_, node)) = &self.op {
+            node.refs(refs)
+        }
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx);
+        if let Some((fid, mut node)) = self.op.take() {
+            ctx.cached.remove(&fid);
+            node.delete(ctx);
+        }
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.stop(ctx);
+        self.f = None;
+        self.attempt = 0;
+        self.succeeded = false;
+    }
+}
+
+#[derive(Debug)]
+struct WithTimeout<R: Rt, E: UserEvent> {
+    scope: Scope,
+    top_id: ExprId,
+    ftyp: TArc<FnType>,
+    timeout: Option<Duration>,
+    f: Option<Value>,
+    op: Option<(BindId, Node<R, E>)>,
+    running: bool,
+    waiting: bool,
+    tid: Option<BindId>,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for WithTimeout<R, E> {
+    const NAME: &str = "core_with_timeout";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        typ: &'a FnType,
+        resolved: Option<&'d FnType>,
+        scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _] => {
+                let typ = resolved.unwrap_or(typ);
+                let ftyp = match &typ.args[0].typ {
+                    Type::Fn(ft) => ft.clone(),
+                    t => bail!("expected a function not {t}"),
+                };
+                Ok(Box::new(Self {
+                    scope: scope
+                        .append(&format_compact!("fn{}", LambdaId::new().inner())),
+                    top_id,
+                    ftyp,
+                    timeout: None,
+                    f: None,
+                    op: None,
+                    running: false,
+                    waiting: false,
+                    tid: None,
+                }))
+            }
+            _ => bail!("expected two arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> WithTimeout<R, E> {
+    fn cancel(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(id) = self.tid.take() {
+            ctx.rt.unref_var(id, self.top_id);
+        }
+    }
+
+    /// start the timer if op hasn't produced a value yet
+    fn arm(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let (true, None, Some(timeout)) = (self.waiting, self.tid, self.timeout) {
+            let id = BindId::new();
+            self.tid = Some(id);
+            ctx.rt.ref_var(id, self.top_id);
+            ctx.rt.set_timer(id, timeout);
+        }
+    }
+
+    fn stop(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx);
+        if self.running
+            && let Some((_, node)) = &mut self.op
+        {
+            node.sleep(ctx);
+        }
+        self.running = false;
+        self.waiting = false;
+    }
+
+    /// invoke op from scratch
+    fn invoke(&mut self, ctx: &mut ExecCtx<R, E>, event: &mut Event<E>) -> Option<Value> {
+        let f = self.f.clone()?;
+        let (fid, node) = self
+            .op
+            .get_or_insert_with(|| thunk_node(ctx, &self.ftyp, &self.scope, self.top_id));
+        self.running = true;
+        self.waiting = true;
+        invoke_thunk(ctx, event, *fid, node, f)
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for WithTimeout<R, E> {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if let Some(Value::Duration(d)) = from[1].update(ctx, event) {
+            // a new timeout restarts the wait
+            self.timeout = Some(*d);
+            self.cancel(ctx);
+        }
+        let v = match from[0].update(ctx, event) {
+            Some(f) => {
+                self.stop(ctx);
+                self.f = Some(f);
+                self.invoke(ctx, event)
+            }
+            None if self.running => match &mut self.op {
+                Some((_, node)) => node.update(ctx, event),
+                None => None,
+            },
+            None => None,
+        };
+        if v.is_some() {
+            // values produced after the timeout are still passed through
+            self.cancel(ctx);
+            self.waiting = false;
+            return v;
+        }
+        if let Some(id) = self.tid
+            && event.variables.contains_key(&id)
+        {
+            self.cancel(ctx);
+            self.waiting = false;
+            let d = self.timeout.unwrap_or_default();
+            return Some(errf!("Timeout", "no value within {d:?}"));
+        }
+        self.arm(ctx);
+        None
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        _phase: TypecheckPhase<'_>,
+    ) -> anyhow::Result<()> {
+        let ft = self.ftyp.clone();
+        let fnode =
+            genn::reference(ctx, BindId::new(), Type::Fn(ft.clone()), self.top_id);
+        let mut node = genn::apply(fnode, self.scope.clone(), vec![], &ft, self.top_id);
+        node.typecheck(ctx)?;
+        node.delete(ctx);
+        Ok(())
+    }
+
+    fn refs(&self, refs: &mut Refs) {
+        if let Some((_, node)) = &self.op {
+            node.refs(refs)
+        }
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx);
+        if let Some((fid, mut node)) = self.op.take() {
+            ctx.cached.remove(&fid);
+            node.delete(ctx);
+        }
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.stop(ctx);
+        self.f = None;
+    }
+}
+
+#[derive(Debug)]
+struct DistinctBy<R: Rt, E: UserEvent> {
+    cur: Option<Value>,
+    key: Option<Value>,
+    f: Node<R, E>,
+    fid: BindId,
+    x: BindId,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for DistinctBy<R, E> {
+    const NAME: &str = "core_distinct_by";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        ctx: &'a mut ExecCtx<R, E>,
+        typ: &'a graphix_compiler::typ::FnType,
+        resolved: Option<&'d FnType>,
+        scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _] => {
+                let typ = resolved.unwrap_or(typ);
+                let (x, xn) =
+                    genn::bind(ctx, &scope.lexical, "x", typ.args[0].typ.clone(), top_id);
+                let fid = BindId::new();
+                let ftyp = match &typ.args[1].typ {
+                    Type::Fn(ft) => ft.clone(),
+                    t => bail!("expected a function not {t}"),
+                };
+                let fnode = genn::reference(ctx, fid, Type::Fn(ftyp.clone()), top_id);
+                let f = genn::apply(fnode, scope.clone(), vec![xn], &ftyp, top_id);
+                Ok(Box::new(Self { cur: None, key: None, f, fid, x }))
+            }
+            _ => bail!("expected two arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for DistinctBy<R, E> {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if let Some(v) = from[1].update(ctx, event) {
+            ctx.cached.insert(self.fid, v.clone());
+            event.variables.insert(self.fid, v);
+        }
+        if let Some(v) = from[0].update(ctx, event) {
+            self.cur = Some(v.clone());
+            ctx.cached.insert(self.x, v.clone());
+            event.variables.insert(self.x, v);
+        }
+        let key = self.f.update(ctx, event)?;
+        if self.key.as_ref() == Some(&key) {
+            None
+        } else {
+            self.key = Some(key);
+            self.cur.clone()
+        }
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        _phase: TypecheckPhase<'_>,
+    ) -> anyhow::Result<()> {
+        self.f.typecheck(ctx)?;
+        Ok(())
+    }
+
+    fn refs(&self, refs: &mut Refs) {
+        self.f.refs(refs)
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        ctx.cached.remove(&self.fid);
+        ctx.cached.remove(&self.x);
+        ctx.env.unbind_variable(self.x);
+        self.f.delete(ctx);
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cur = None;
+        self.key = None;
+        self.f.sleep(ctx);
+    }
+}
+
+#[derive(Debug)]
+struct StatefulFold<R: Rt, E: UserEvent> {
+    acc: Option<Value>,
+    f: Node<R, E>,
+    fid: BindId,
+    accid: BindId,
+    x: BindId,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for StatefulFold<R, E> {
+    const NAME: &str = "core_stateful_fold";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        ctx: &'a mut ExecCtx<R, E>,
+        typ: &'a graphix_compiler::typ::FnType,
+        resolved: Option<&'d FnType>,
+        scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _, _] => {
+                let typ = resolved.unwrap_or(typ);
+                let accid = BindId::new();
+                let x = BindId::new();
+                let ftyp = match &typ.args[2].typ {
+                    Type::Fn(ft) => ft.clone(),
+                    t => bail!("expected a function not {t}"),
+                };
+                let accn = genn::reference(ctx, accid, typ.args[0].typ.clone(), top_id);
+                let xn = genn::reference(ctx, x, typ.args[1].typ.clone(), top_id);
+                let fid = BindId::new();
+                let fnode = genn::reference(ctx, fid, Type::Fn(ftyp.clone()), top_id);
+                let f = genn::apply(fnode, scope.clone(), vec![accn, xn], &ftyp, top_id);
+                Ok(Box::new(Self { acc: None, f, fid, accid, x }))
+            }
+            _ => bail!("expected three arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for StatefulFold<R, E> {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if let Some(v) = from[0].update(ctx, event) {
+            // a new initial value replaces whatever has been accumulated
+            ctx.cached.insert(self.accid, v.clone());
+            self.acc = Some(v);
+        }
+        if let Some(v) = from[2].update(ctx, event) {
+            ctx.cached.insert(self.fid, v.clone());
+            event.variables.insert(self.fid, v);
+        }
+        if let Some(v) = from[1].update(ctx, event)
+            && let Some(acc) = self.acc.clone()
+        {
+            event.variables.insert(self.accid, acc);
+            ctx.cached.insert(self.x, v.clone());
+            event.variables.insert(self.x, v);
+        }
+        let acc = self.f.update(ctx, event)?;
+        ctx.cached.insert(self.accid, acc.clone());
+        self.acc = Some(acc.clone());
+        Some(acc)
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        _phase: TypecheckPhase<'_>,
+    ) -> anyhow::Result<()> {
+        self.f.typecheck(ctx)?;
+        Ok(())
+    }
+
+    fn refs(&self, refs: &mut Refs) {
+        self.f.refs(refs)
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        ctx.cached.remove(&self.fid);
+        ctx.cached.remove(&self.accid);
+        ctx.cached.remove(&self.x);
+        self.f.delete(ctx);
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.acc = None;
+        self.f.sleep(ctx);
+    }
+}
+
+#[derive(Debug)]
+struct Queue {
+    triggered: usize,
+    queue: VecDeque<Value>,
+    id: BindId,
+    top_id: ExprId,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Queue {
+    const NAME: &str = "core_queue";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _] => {
+                let id = BindId::new();
+                ctx.rt.ref_var(id, top_id);
+                Ok(Box::new(Self { triggered: 0, queue: VecDeque::new(), id, top_id }))
+            }
+            _ => bail!("expected two arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Queue {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if from[0].update(ctx, event).is_some() {
+            self.triggered += 1;
+        }
+        if let Some(v) = from[1].update(ctx, event) {
+            self.queue.push_back(v);
+        }
+        while self.triggered > 0 && self.queue.len() > 0 {
+            self.triggered -= 1;
+            ctx.rt.set_var(self.id, self.queue.pop_front().unwrap());
+        }
+        event.variables.get(&self.id).cloned()
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        ctx.rt.unref_var(self.id, self.top_id);
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        ctx.rt.unref_var(self.id, self.top_id);
+        self.id = BindId::new();
+        ctx.rt.ref_var(self.id, self.top_id);
+        self.triggered = 0;
+        self.queue.clear();
+    }
+}
+
+#[derive(Debug)]
+struct Hold {
+    triggered: usize,
+    current: Option<Value>,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Hold {
+    const NAME: &str = "core_hold";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        _top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _] => Ok(Box::new(Self { triggered: 0, current: None })),
+            _ => bail!("expected two arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Hold {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if from[0].update(ctx, event).is_some() {
+            self.triggered += 1;
+        }
+        if let Some(v) = from[1].update(ctx, event) {
+            self.current = Some(v);
+        }
+        if self.triggered > 0
+            && let Some(v) = self.current.take()
+        {
+            self.triggered -= 1;
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    fn delete(&mut self, _: &mut ExecCtx<R, E>) {}
+
+    fn sleep(&mut self, _: &mut ExecCtx<R, E>) {
+        self.triggered = 0;
+        self.current = None;
+    }
+}
+
+#[derive(Debug)]
+struct Batch {
+    n: usize,
+    batch: LPooled<Vec<Value>>,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Batch {
+    const NAME: &str = "core_batch";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        _top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _, _] => Ok(Box::new(Self { n: 0, batch: LPooled::take() })),
+            _ => bail!("expected three arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Batch {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let flush = from[0].update(ctx, event).is_some();
+        if let Some(Value::I64(n)) = from[2].update(ctx, event) {
+            self.n = n.max(1) as usize;
+        }
+        if let Some(v) = from[1].update(ctx, event) {
+            self.batch.push(v);
+        }
+        let full = self.n > 0 && self.batch.len() >= self.n;
+        if (full || flush) && !self.batch.is_empty() {
+            Some(Value::Array(ValArray::from_iter_exact(self.batch.drain(..))))
+        } else {
+            None
+        }
+    }
+
+    fn delete(&mut self, _: &mut ExecCtx<R, E>) {}
+
+    fn sleep(&mut self, _: &mut ExecCtx<R, E>) {
+        self.batch.clear();
+    }
+}
+
+#[derive(Debug)]
+struct GroupAdjacent<R: Rt, E: UserEvent> {
+    pending: Option<Value>,
+    key: Option<Value>,
+    group: Vec<Value>,
+    f: Node<R, E>,
+    fid: BindId,
+    x: BindId,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for GroupAdjacent<R, E> {
+    const NAME: &str = "core_group_adjacent";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        ctx: &'a mut ExecCtx<R, E>,
+        typ: &'a FnType,
+        resolved: Option<&'d FnType>,
+        scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _, _] => {
+                let typ = resolved.unwrap_or(typ);
+                let (x, xn) =
+                    genn::bind(ctx, &scope.lexical, "x", typ.args[1].typ.clone(), top_id);
+                let fid = BindId::new();
+                let ftyp = match &typ.args[2].typ {
+                    Type::Fn(ft) => ft.clone(),
+                    t => bail!("expected a function not {t}"),
+                };
+                let fnode = genn::reference(ctx, fid, Type::Fn(ftyp.clone()), top_id);
+                let f = genn::apply(fnode, scope.clone(), vec![xn], &ftyp, top_id);
+                Ok(Box::new(Self { pending: None, key: None, group: vec![], f, fid, x }))
+            }
+            _ => bail!("expected three arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> GroupAdjacent<R, E> {
+    fn take_group(&mut self) -> Option<Value> {
+        if self.group.is_empty() {
+            None
+        } else {
+            Some(Value::Array(ValArray::from_iter_exact(self.group.drain(..))))
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for GroupAdjacent<R, E> {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let flush = from[0].update(ctx, event).is_some();
+        if let Some(v) = from[2].update(ctx, event) {
+            ctx.cached.insert(self.fid, v.clone());
+            event.variables.insert(self.fid, v);
+        }
+        if let Some(v) = from[1].update(ctx, event) {
+            self.pending = Some(v.clone());
+            ctx.cached.insert(self.x, v.clone());
+            event.variables.insert(self.x, v);
+        }
+        let mut res = None;
+        if let Some(key) = self.f.update(ctx, event)
+            && let Some(v) = self.pending.take()
+        {
+            // the key changed, so the current group is complete
+            if self.key.as_ref().is_some_and(|k| k != &key) {
+                res = self.take_group();
+            }
+            self.key = Some(key);
+            self.group.push(v);
+        }
+        if flush && res.is_none() {
+            self.key = None;
+            res = self.take_group();
+        }
+        res
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        _phase: TypecheckPhase<'_>,
+    ) -> anyhow::Result<()> {
+        self.f.typecheck(ctx)?;
+        Ok(())
+    }
+
+    fn refs(&self, refs: &mut Refs) {
+        self.f.refs(refs)
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        ctx.cached.remove(&self.fid);
+        ctx.cached.remove(&self.x);
+        ctx.env.unbind_variable(self.x);
+        self.f.delete(ctx);
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.pending = None;
+        self.key = None;
+        self.group.clear();
+        self.f.sleep(ctx);
+    }
+}
+
+#[derive(Debug)]
+struct When {
+    open: bool,
+    current: Option<Value>,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for When {
+    const NAME: &str = "core_when";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        _top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _] => Ok(Box::new(Self { open: false, current: None })),
+            _ => bail!("expected two arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for When {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let was_open = self.open;
+        let cond = from[0].update(ctx, event);
+        let v = from[1].update(ctx, event);
+        if let Some(v) = &v {
+            self.current = Some(v.clone());
+        }
+        match cond {
+            None => (),
+            Some(Value::Bool(b)) => self.open = b,
+            Some(_) => self.open = false,
+        }
+        if !self.open {
+            None
+        } else if !was_open {
+            self.current.clone()
+        } else {
+            v
+        }
+    }
+
+    fn delete(&mut self, _: &mut ExecCtx<R, E>) {}
+
+    fn sleep(&mut self, _: &mut ExecCtx<R, E>) {
+        self.open = false;
+        self.current = None;
+    }
+}
+
+#[derive(Debug)]
+struct Seq {
+    id: BindId,
+    top_id: ExprId,
+    args: CachedVals,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Seq {
+    const NAME: &str = "core_seq";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        let id = BindId::new();
+        ctx.rt.ref_var(id, top_id);
+        let args = CachedVals::new(from);
+        Ok(Box::new(Self { id, top_id, args }))
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Seq {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if self.args.update(ctx, from, event) {
+            match &self.args.0[..] {
+                [Some(Value::I64(i)), Some(Value::I64(j))] if i <= j => {
+                    for v in *i..*j {
+                        ctx.rt.set_var(self.id, Value::I64(v));
+                    }
+                }
+                _ => {
+                    let e = literal!("SeqError");
+                    return Some(err!(e, "invalid args i must be <= j"));
+                }
+            }
+        }
+        event.variables.get(&self.id).cloned()
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        ctx.rt.unref_var(self.id, self.top_id);
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        ctx.rt.unref_var(self.id, self.top_id);
+        self.id = BindId::new();
+        ctx.rt.ref_var(self.id, self.top_id);
+    }
+}
+
+#[derive(Debug)]
+struct CompareAndSet(CachedVals);
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for CompareAndSet {
+    const NAME: &str = "core_compare_and_set";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        _top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        Ok(Box::new(Self(CachedVals::new(from))))
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for CompareAndSet {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let mut up = [false; 3];
+        self.0.update_diff(&mut up, ctx, from, event);
+        // only an update of new attempts the swap
+        if !up[2] {
+            return None;
+        }
+        let (r, expected, new) = match &self.0 .0[..] {
+            [Some(Value::U64(r)), Some(expected), Some(new)] => (*r, expected, new),
+            _ => return None,
+        };
+        // set_var updates the cache immediately, so a later swap of the same
+        // variable in this cycle sees the value we set
+        let target = ctx.env.byref_chain.get(&BindId::from(r)).copied();
+        match target {
+            Some(id) if ctx.cached.get(&id) == Some(expected) => {
+                ctx.set_var(id, new.clone());
+                Some(Value::Bool(true))
+            }
+            Some(_) | None => Some(Value::Bool(false)),
+        }
+    }
+
+    fn delete(&mut self, _ctx: &mut ExecCtx<R, E>) {}
+
+    fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {
+        self.0.clear()
+    }
+}
+
+/// return the fields of the struct type t, following aliases. If by_ref
+/// is true t must be a reference to a struct.
+fn struct_fields(env: &Env, t: &Type, by_ref: bool) -> Result<TArc<[(ArcStr, Type)]>> {
+    let expected = if by_ref { "a reference to a struct" } else { "a struct" };
+    let mut t = t.clone();
+    for _ in 0..DEFAULT_MAX_DEPTH {
+        t = match t.with_deref(|t| t.cloned()) {
+            Some(Type::ByRef(t)) if by_ref => (*t).clone(),
+            Some(Type::Struct(flds)) => return Ok(flds),
+            Some(t @ Type::Ref { .. }) => t.lookup_ref(env)?,
+            Some(t) => bail!("expected {expected} not {t}"),
+            None => bail!("type must be known, annotations needed"),
+        }
+    }
+    bail!("the type of {expected} is too deeply nested")
+}
+
+#[derive(Debug)]
+struct SetField {
+    args: CachedVals,
+    fields: Option<TArc<[(ArcStr, Type)]>>,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for SetField {
+    const NAME: &str = "core_set_field";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        _top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        Ok(Box::new(Self { args: CachedVals::new(from), fields: None }))
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for SetField {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let mut up = [false; 3];
+        self.args.update_diff(&mut up, ctx, from, event);
+        // only an update of value writes the field
+        if !up[2] {
+            return None;
+        }
+        let (r, field, value) = match &self.args.0[..] {
+            [Some(Value::U64(r)), Some(Value::String(f)), Some(v)] => (*r, f, v),
+            _ => return None,
+        };
+        let flds = match &self.fields {
+            Some(flds) => flds,
+            None => {
+                return Some(err!(
+                    literal!("SetFieldError"),
+                    "the struct type is unknown"
+                ))
+            }
+        };
+        match flds.iter().find(|(n, _)| n == field) {
+            None => return Some(errf!(literal!("SetFieldError"), "no field {field}")),
+            Some((_, t)) if !t.is_a(&ctx.env, value) => {
+                return Some(errf!(
+                    literal!("SetFieldError"),
+                    "field {field} has type {t}, {value} doesn't match"
+                ));
+            }
+            Some(_) => (),
+        }
+        let id = match ctx.env.byref_chain.get(&BindId::from(r)) {
+            Some(id) => *id,
+            None => return Some(err!(literal!("SetFieldError"), "invalid reference")),
+        };
+        let cur = match ctx.cached.get(&id) {
+            Some(Value::Array(a)) => a.clone(),
+            Some(_) | None => {
+                return Some(err!(literal!("SetFieldError"), "the struct has no value"))
+            }
+        };
+        let mut found = false;
+        let mut res: LPooled<Vec<Value>> = LPooled::take();
+        for fv in cur.iter() {
+            match fv {
+                Value::Array(kv)
+                    if kv.len() == 2 && kv[0] == Value::String(field.clone()) =>
+                {
+                    found = true;
+                    res.push(Value::Array(ValArray::from([kv[0].clone(), value.clone()])))
+                }
+                fv => res.push(fv.clone()),
+            }
+        }
+        if !found {
+            return Some(errf!(literal!("SetFieldError"), "no field {field}"));
+        }
+        ctx.set_var(id, Value::Array(ValArray::from_iter_exact(res.drain(..))));
+        Some(Value::Null)
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        // the struct type isn't known yet when the lambda itself is
+        // checked, it must be at the call site
+        match phase {
+            TypecheckPhase::Lambda => Ok(()),
+            TypecheckPhase::CallSite(resolved) => {
+                match struct_fields(&ctx.env, &resolved.args[0].typ, true) {
+                    Ok(flds) => self.fields = Some(flds),
+                    Err(e) => bail!("core::set_field: {e}"),
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn delete(&mut self, _ctx: &mut ExecCtx<R, E>) {}
+
+    fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {
+        self.args.clear()
+    }
+}
+
+#[derive(Debug)]
+struct Throttle {
+    wait: Duration,
+    last: Option<Instant>,
+    tid: Option<BindId>,
+    top_id: ExprId,
+    args: CachedVals,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Throttle {
+    const NAME: &str = "core_throttle";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        let args = CachedVals::new(from);
+        Ok(Box::new(Self { wait: Duration::ZERO, last: None, tid: None, top_id, args }))
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Throttle {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        macro_rules! maybe_schedule {
+            ($last:expr) => {{
+                let now = Instant::now();
+                if now - *$last >= self.wait {
+                    *$last = now;
+                    return self.args.0[1].clone();
+                } else {
+                    let id = BindId::new();
+                    ctx.rt.ref_var(id, self.top_id);
+                    ctx.rt.set_timer(id, self.wait - (now - *$last));
+                    self.tid = Some(id);
+                    return None;
+                }
+            }};
+        }
+        let mut up = [false; 2];
+        self.args.update_diff(&mut up, ctx, from, event);
+        if up[0]
+            && let Some(Value::Duration(d)) = &self.args.0[0]
+        {
+            self.wait = **d;
+            if let Some(id) = self.tid.take()
+                && let Some(last) = &mut self.last
+            {
+                ctx.rt.unref_var(id, self.top_id);
+                maybe_schedule!(last)
+            }
+        }
+        if up[1] && self.tid.is_none() {
+            match &mut self.last {
+                Some(last) => maybe_schedule!(last),
+                None => {
+                    self.last = Some(Instant::now());
+                    return self.args.0[1].clone();
+                }
+            }
+        }
+        if let Some(id) = self.tid
+            && let Some(_) = event.variables.get(&id)
+        {
+            ctx.rt.unref_var(id, self.top_id);
+            self.tid = None;
+            self.last = Some(Instant::now());
+            return self.args.0[1].clone();
+        }
+        None
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(id) = self.tid.take() {
+            ctx.rt.unref_var(id, self.top_id);
+        }
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.delete(ctx);
+        self.last = None;
+        self.wait = Duration::ZERO;
+        self.args.clear();
+    }
+}
+
+#[derive(Debug)]
+struct DebounceDistinct {
+    last: Option<Value>,
+    tid: Option<BindId>,
+    top_id: ExprId,
+    args: CachedVals,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for DebounceDistinct {
+    const NAME: &str = "core_debounce_distinct";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        let args = CachedVals::new(from);
+        Ok(Box::new(Self { last: None, tid: None, top_id, args }))
+    }
+}
+
+impl DebounceDistinct {
+    fn cancel<R: Rt, E: UserEvent>(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(id) = self.tid.take() {
+            ctx.rt.unref_var(id, self.top_id);
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for DebounceDistinct {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let fired = self.tid.is_some_and(|id| event.variables.contains_key(&id));
+        let mut up = [false; 2];
+        self.args.update_diff(&mut up, ctx, from, event);
+        // any update restarts the wait for x to settle
+        if up[0] || up[1] {
+            self.cancel(ctx);
+            if let [Some(_), Some(Value::Duration(d))] = &self.args.0[..] {
+                let id = BindId::new();
+                ctx.rt.ref_var(id, self.top_id);
+                ctx.rt.set_timer(id, **d);
+                self.tid = Some(id);
+            }
+            return None;
+        }
+        if !fired {
+            return None;
+        }
+        self.cancel(ctx);
+        let v = self.args.0[0].clone()?;
+        if self.last.as_ref() == Some(&v) {
+            return None;
+        }
+        self.last = Some(v.clone());
+        Some(v)
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx)
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx);
+        self.last = None;
+        self.args.clear();
+    }
+}
+
+/// the number of ticks of the timer in each rate window
+const RATE_TICKS: usize = 10;
+
+#[derive(Debug)]
+struct Rate {
+    window: Duration,
+    /// the updates of x in each tick of the window, the current tick last
+    counts: VecDeque<usize>,
+    total: usize,
+    tid: Option<BindId>,
+    top_id: ExprId,
+    args: CachedVals,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Rate {
+    const NAME: &str = "core_rate";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        let args = CachedVals::new(from);
+        Ok(Box::new(Self {
+            window: Duration::ZERO,
+            counts: VecDeque::new(),
+            total: 0,
+            tid: None,
+            top_id,
+            args,
+        }))
+    }
+}
+
+impl Rate {
+    fn reset<R: Rt, E: UserEvent>(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(id) = self.tid.take() {
+            ctx.rt.unref_var(id, self.top_id);
+        }
+        self.counts.clear();
+        self.total = 0;
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Rate {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let mut up = [false; 2];
+        self.args.update_diff(&mut up, ctx, from, event);
+        let mut fired = false;
+        if let Some(id) = self.tid
+            && event.variables.contains_key(&id)
+        {
+            // time only moves forward on our own timer's ticks
+            ctx.rt.unref_var(id, self.top_id);
+            self.tid = None;
+            fired = true;
+            self.counts.push_back(0);
+            if self.counts.len() > RATE_TICKS
+                && let Some(n) = self.counts.pop_front()
+            {
+                self.total -= n;
+            }
+        }
+        if up[0]
+            && let Some(Value::Duration(d)) = &self.args.0[0]
+        {
+            // the counts were taken over the old window
+            self.window = **d;
+            self.reset(ctx);
+        }
+        if self.window.is_zero() {
+            self.reset(ctx);
+            return None;
+        }
+        if up[1] {
+            match self.counts.back_mut() {
+                Some(n) => *n += 1,
+                None => self.counts.push_back(1),
+            }
+            self.total += 1;
+        }
+        if !(up[0] || up[1] || fired) {
+            return None;
+        }
+        // keep ticking until every update has left the window, so the
+        // rate decays even if x stops updating
+        if self.total == 0 {
+            self.reset(ctx)
+        } else if self.tid.is_none() {
+            let id = BindId::new();
+            ctx.rt.ref_var(id, self.top_id);
+            ctx.rt.set_timer(id, self.window / RATE_TICKS as u32);
+            self.tid = Some(id);
+        }
+        Some(Value::F64(self.total as f64 / self.window.as_secs_f64()))
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(id) = self.tid.take() {
+            ctx.rt.unref_var(id, self.top_id);
+        }
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.reset(ctx);
+        self.window = Duration::ZERO;
+        self.args.clear();
+    }
+}
+
+#[derive(Debug)]
+struct SampleRate {
+    period: Option<Duration>,
+    pending: bool,
+    tid: Option<BindId>,
+    top_id: ExprId,
+    args: CachedVals,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for SampleRate {
+    const NAME: &str = "core_sample_rate";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        let args = CachedVals::new(from);
+        Ok(Box::new(Self { period: None, pending: false, tid: None, top_id, args }))
+    }
+}
+
+impl SampleRate {
+    fn cancel<R: Rt, E: UserEvent>(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(id) = self.tid.take() {
+            ctx.rt.unref_var(id, self.top_id);
+        }
+    }
+
+    fn schedule<R: Rt, E: UserEvent>(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(period) = self.period
+            && self.tid.is_none()
+        {
+            let id = BindId::new();
+            ctx.rt.ref_var(id, self.top_id);
+            ctx.rt.set_timer(id, period);
+            self.tid = Some(id);
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for SampleRate {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let fired = self.tid.is_some_and(|id| event.variables.contains_key(&id));
+        let mut up = [false; 2];
+        self.args.update_diff(&mut up, ctx, from, event);
+        if up[1] {
+            self.period = match &self.args.0[1] {
+                Some(Value::F64(hz)) if hz.is_finite() && *hz > 0. => {
+                    Some(Duration::from_secs_f64(1. / hz))
+                }
+                _ => None,
+            };
+            // the pending tick was set for the old frequency
+            self.cancel(ctx);
+        }
+        if up[0] {
+            self.pending = true;
+        }
+        if fired {
+            self.cancel(ctx);
+            if self.pending {
+                self.pending = false;
+                // keep ticking while x is live, go idle once it stops
+                self.schedule(ctx);
+                return self.args.0[0].clone();
+            }
+            return None;
+        }
+        if self.pending {
+            self.schedule(ctx);
+        }
+        None
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx)
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx);
+        self.period = None;
+        self.pending = false;
+        self.args.clear();
+    }
+}
+
+#[derive(Debug)]
+struct Count {
+    count: i64,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Count {
+    const NAME: &str = "core_count";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        _from: &'c [Node<R, E>],
+        _top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        Ok(Box::new(Count { count: 0 }))
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Count {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if from.into_iter().fold(false, |u, n| u || n.update(ctx, event).is_some()) {
+            self.count += 1;
+            Some(Value::I64(self.count))
+        } else {
+            None
+        }
+    }
+
+    fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {
+        self.count = 0
+    }
+}
+
+#[derive(Debug, Default)]
+struct MeanEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for MeanEv {
+    const NAME: &str = "core_mean";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        static TAG: ArcStr = literal!("MeanError");
+        let mut total = 0.;
+        let mut samples = 0;
+        let mut error = None;
+        for v in from.flat_iter() {
+            if let Some(v) = v {
+                match v.cast_to::<f64>() {
+                    Err(e) => error = Some(errf!(TAG, "{e:?}")),
+                    Ok(v) => {
+                        total += v;
+                        samples += 1;
+                    }
+                }
+            }
+        }
+        if let Some(e) = error {
+            Some(e)
+        } else if samples == 0 {
+            Some(err!(TAG, "mean requires at least one argument"))
+        } else {
+            Some(Value::F64(total / samples as f64))
+        }
+    }
+}
+
+type Mean = CachedArgs<MeanEv>;
+
+#[derive(Debug, Default)]
+struct ClampEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for ClampEv {
+    const NAME: &str = "core_clamp";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        static TAG: ArcStr = literal!("ClampError");
+        let x = from.get::<f64>(0)?;
+        let lo = from.get::<f64>(1)?;
+        let hi = from.get::<f64>(2)?;
+        if lo.is_nan() || hi.is_nan() {
+            Some(errf!(TAG, "invalid range, lo {lo} and hi {hi} must be numbers"))
+        } else if lo > hi {
+            Some(errf!(TAG, "invalid range, lo {lo} is greater than hi {hi}"))
+        } else {
+            Some(Value::F64(x.clamp(lo, hi)))
+        }
+    }
+}
+
+type Clamp = CachedArgs<ClampEv>;
+
+#[derive(Debug, Default)]
+struct LerpEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for LerpEv {
+    const NAME: &str = "core_lerp";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        let a = from.get::<f64>(0)?;
+        let b = from.get::<f64>(1)?;
+        let t = from.get::<f64>(2)?;
+        Some(Value::F64(a + (b - a) * t))
+    }
+}
+
+type Lerp = CachedArgs<LerpEv>;
+
+#[derive(Debug, Default)]
+struct RoundToEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for RoundToEv {
+    const NAME: &str = "core_round_to";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        static TAG: ArcStr = literal!("RoundError");
+        let strategy = match from.0[0].as_ref()? {
+            Value::String(s) if s == "HalfUp" => RoundingStrategy::MidpointAwayFromZero,
+            Value::String(s) if s == "HalfEven" => RoundingStrategy::MidpointNearestEven,
+            Value::String(s) if s == "Down" => RoundingStrategy::ToZero,
+            v => return Some(errf!(TAG, "invalid rounding mode {v}")),
+        };
+        let x = match from.0[1].as_ref()? {
+            Value::Decimal(d) => **d,
+            v => return Some(errf!(TAG, "{v} is not a decimal")),
+        };
+        let places = from.get::<i64>(2)?;
+        if places < 0 || places > Decimal::MAX_PRECISION as i64 {
+            return Some(errf!(
+                TAG,
+                "places must be between 0 and {}, not {places}",
+                Decimal::MAX_PRECISION
+            ));
+        }
+        let d = x.round_dp_with_strategy(places as u32, strategy);
+        Some(Value::Decimal(TArc::new(d)))
+    }
+}
+
+type RoundTo = CachedArgs<RoundToEv>;
+
+/// the first concrete type f selects from the elements of the resolved
+/// set return type
+fn extract_rtype(
+    resolved: Option<&FnType>,
+    f: impl Fn(&Type) -> Option<&Type>,
+) -> Option<Type> {
+    let elts = match &resolved?.rtype {
+        Type::Set(elts) => elts,
+        _ => return None,
+    };
+    let typ = elts.iter().find_map(f)?;
+    if typ.has_unbound() {
+        return None;
+    }
+    Some(typ.clone())
+}
+
+/// the type 'a in the resolved return type [`Ok('a), `Err] of try_cast
+fn extract_ok_type(resolved: Option<&FnType>) -> Option<Type> {
+    extract_rtype(resolved, |t| match t {
+        Type::Variant(tag, args) if tag == "Ok" && args.len() == 1 => Some(&args[0]),
+        _ => None,
+    })
+}
+
+#[derive(Debug, Default)]
+struct TryCastEv {
+    cast_typ: Option<Type>,
+}
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for TryCastEv {
+    const NAME: &str = "core_try_cast";
+    const NEEDS_CALLSITE: bool = true;
+
+    fn init(
+        _ctx: &mut ExecCtx<R, E>,
+        _typ: &FnType,
+        resolved: Option<&FnType>,
+        _scope: &Scope,
+        _from: &[Node<R, E>],
+        _top_id: ExprId,
+    ) -> Self {
+        Self { cast_typ: extract_ok_type(resolved) }
+    }
+
+    fn typecheck(
+        &mut self,
+        _ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        match phase {
+            TypecheckPhase::Lambda => Ok(()),
+            TypecheckPhase::CallSite(resolved) => {
+                self.cast_typ = extract_ok_type(Some(resolved));
+                if self.cast_typ.is_none() {
+                    bail!("core::try_cast requires a concrete return type")
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn eval(&mut self, ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        let v = from.0[0].clone()?;
+        let typ = self.cast_typ.as_ref()?;
+        Some(match ctx.try_cast_value(typ, v) {
+            Ok(v) => Value::Array(ValArray::from([Value::String(literal!("Ok")), v])),
+            Err(_) => Value::String(literal!("Err")),
+        })
+    }
+}
+
+type TryCast = CachedArgs<TryCastEv>;
+
+#[derive(Debug, Default)]
+struct StructToMapEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for StructToMapEv {
+    const NAME: &str = "core_struct_to_map";
+    const NEEDS_CALLSITE: bool = true;
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        match phase {
+            TypecheckPhase::Lambda => Ok(()),
+            TypecheckPhase::CallSite(resolved) => {
+                if let Err(e) = struct_fields(&ctx.env, &resolved.args[0].typ, false) {
+                    bail!("core::struct_to_map: {e}")
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match from.0[0].as_ref()? {
+            Value::Array(flds) => {
+                Some(Value::Map(CMap::from_iter(flds.iter().filter_map(|fv| match fv {
+                    Value::Array(kv) if kv.len() == 2 => {
+                        Some((kv[0].clone(), kv[1].clone()))
+                    }
+                    _ => None,
+                }))))
+            }
+            _ => None,
+        }
+    }
+}
+
+type StructToMap = CachedArgs<StructToMapEv>;
+
+/// the struct type 'a in the resolved return type Result<'a, 'e> of
+/// map_to_struct, and its fields
+fn extract_struct_type(
+    env: &Env,
+    resolved: Option<&FnType>,
+) -> Option<(Type, TArc<[(ArcStr, Type)]>)> {
+    let typ = extract_rtype(resolved, |t| match t {
+        Type::Error(_) => None,
+        t => Some(t),
+    })?;
+    let flds = struct_fields(env, &typ, false).ok()?;
+    Some((typ, flds))
+}
+
+#[derive(Debug, Default)]
+struct MapToStructEv {
+    target: Option<(Type, TArc<[(ArcStr, Type)]>)>,
+}
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for MapToStructEv {
+    const NAME: &str = "core_map_to_struct";
+    const NEEDS_CALLSITE: bool = true;
+
+    fn init(
+        ctx: &mut ExecCtx<R, E>,
+        _typ: &FnType,
+        resolved: Option<&FnType>,
+        _scope: &Scope,
+        _from: &[Node<R, E>],
+        _top_id: ExprId,
+    ) -> Self {
+        Self { target: extract_struct_type(&ctx.env, resolved) }
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        match phase {
+            TypecheckPhase::Lambda => Ok(()),
+            TypecheckPhase::CallSite(resolved) => {
+                self.target = extract_struct_type(&ctx.env, Some(resolved));
+                if self.target.is_none() {
+                    bail!("core::map_to_struct requires a concrete struct return type")
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn eval(&mut self, ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        static TAG: ArcStr = literal!("StructError");
+        let m = match from.0[0].as_ref()? {
+            Value::Map(m) => m,
+            _ => return None,
+        };
+        let (typ, flds) = self.target.as_ref()?;
+        for (k, _) in m.into_iter() {
+            match k {
+                Value::String(k) if flds.iter().any(|(n, _)| n == k) => (),
+                Value::String(k) => return Some(errf!(TAG, "unexpected field {k}")),
+                k => return Some(errf!(TAG, "unexpected key {k}")),
+            }
+        }
+        let mut res: LPooled<Vec<Value>> = LPooled::take();
+        for (n, _) in flds.iter() {
+            match m.get(&Value::String(n.clone())) {
+                Some(v) => res.push(Value::Array(ValArray::from([
+                    Value::String(n.clone()),
+                    v.clone(),
+                ]))),
+                None => return Some(errf!(TAG, "missing field {n}")),
+            }
+        }
+        let v = Value::Array(ValArray::from_iter_exact(res.drain(..)));
+        Some(match ctx.try_cast_value(typ, v) {
+            Ok(v) => v,
+            Err(e) => errf!(TAG, "{e}"),
+        })
+    }
+}
+
+type MapToStruct = CachedArgs<MapToStructEv>;
+
+#[derive(Debug)]
+struct MovingAverage {
+    n: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for MovingAverage {
+    const NAME: &str = "core_moving_average";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        _top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _] => Ok(Box::new(Self { n: 0, window: VecDeque::new(), sum: 0. })),
+            _ => bail!("expected two arguments"),
+        }
+    }
+}
+
+impl MovingAverage {
+    fn evict(&mut self) {
+        while self.window.len() > self.n {
+            if let Some(v) = self.window.pop_front() {
+                self.sum -= v
+            }
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for MovingAverage {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if let Some(n) = from[0].update(ctx, event).and_then(|v| v.cast_to::<i64>().ok())
+        {
+            self.n = n.max(1) as usize;
+            self.evict();
+        }
+        let v = from[1].update(ctx, event)?.cast_to::<f64>().ok()?;
+        if self.n == 0 {
+            return None;
+        }
+        self.window.push_back(v);
+        self.sum += v;
+        self.evict();
+        Some(Value::F64(self.sum / self.window.len() as f64))
+    }
+
+    fn delete(&mut self, _: &mut ExecCtx<R, E>) {}
+
+    fn sleep(&mut self, _: &mut ExecCtx<R, E>) {
+        self.n = 0;
+        self.window.clear();
+        self.sum = 0.;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    bounds: Option<Vec<f64>>,
+    counts: Vec<u64>,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Histogram {
+    const NAME: &str = "core_histogram";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        _top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _] => Ok(Box::new(Self::default())),
+            _ => bail!("expected two arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Histogram {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if let Some(mut b) =
+            from[1].update(ctx, event).and_then(|v| v.cast_to::<Vec<f64>>().ok())
+        {
+            // the counts are meaningless once the buckets change
+            b.sort_by(|b0, b1| b0.total_cmp(b1));
+            self.counts = vec![0; b.len() + 1];
+            self.bounds = Some(b);
+        }
+        let v = from[0].update(ctx, event)?.cast_to::<f64>().ok()?;
+        let bounds = self.bounds.as_ref()?;
+        self.counts[bounds.partition_point(|b| *b <= v)] += 1;
+        Some(Value::Array(ValArray::from_iter_exact(
+            self.counts.iter().map(|n| Value::U64(*n)),
+        )))
+    }
+
+    fn delete(&mut self, _: &mut ExecCtx<R, E>) {}
+
+    fn sleep(&mut self, _: &mut ExecCtx<R, E>) {
+        self.bounds = None;
+        self.counts.clear();
+    }
+}
+
+#[derive(Debug)]
+struct Uniq(Option<Value>);
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Uniq {
+    const NAME: &str = "core_uniq";
     const NEEDS_CALLSITE: bool = false;
 
     fn init<'a, 'b, 'c, 'd>(
-        ctx: &'a mut ExecCtx<R, E>,
+        _ctx: &'a mut ExecCtx<R, E>,
         _typ: &'a FnType,
         _resolved: Option<&'d FnType>,
         _scope: &'b Scope,
-        from: &'c [Node<R, E>],
-        top_id: ExprId,
+        _from: &'c [Node<R, E>],
+        _top_id: ExprId,
     ) -> Result<Box<dyn Apply<R, E>>> {
-        let id = BindId::new();
-        ctx.rt.ref_var(id, top_id);
-        let args = CachedVals::new(from);
-        Ok(Box::new(Self { id, top_id, args }))
+        Ok(Box::new(Uniq(None)))
     }
 }
 
-impl<R: Rt, E: UserEvent> Apply<R, E> for Seq {
+impl<R: Rt, E: UserEvent> Apply<R, E> for Uniq {
     fn update(
         &mut self,
         ctx: &mut ExecCtx<R, E>,
         from: &mut [Node<R, E>],
         event: &mut Event<E>,
     ) -> Option<Value> {
-        if self.args.update(ctx, from, event) {
-            match &self.args.0[..] {
-                [Some(Value::I64(i)), Some(Value::I64(j))] if i <= j => {
-                    for v in *i..*j {
-                        ctx.rt.set_var(self.id, Value::I64(v));
-                    }
-                }
-                _ => {
-                    let e = literal!("SeqError");
-                    return Some(err!(e, "invalid args i must be <= j"));
-                }
+        from[0].update(ctx, event).and_then(|v| {
+            if Some(&v) != self.0.as_ref() {
+                self.0 = Some(v.clone());
+                Some(v)
+            } else {
+                None
             }
-        }
-        event.variables.get(&self.id).cloned()
-    }
-
-    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
-        ctx.rt.unref_var(self.id, self.top_id);
+        })
     }
 
-    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
-        ctx.rt.unref_var(self.id, self.top_id);
-        self.id = BindId::new();
-        ctx.rt.ref_var(self.id, self.top_id);
+    fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {
+        self.0 = None
     }
 }
 
 #[derive(Debug)]
-struct Throttle {
-    wait: Duration,
-    last: Option<Instant>,
-    tid: Option<BindId>,
-    top_id: ExprId,
-    args: CachedVals,
-}
+struct Pairwise(Option<Value>);
 
-impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Throttle {
-    const NAME: &str = "core_throttle";
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Pairwise {
+    const NAME: &str = "core_pairwise";
     const NEEDS_CALLSITE: bool = false;
 
     fn init<'a, 'b, 'c, 'd>(
@@ -1918,90 +4497,35 @@ impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Throttle {
         _typ: &'a FnType,
         _resolved: Option<&'d FnType>,
         _scope: &'b Scope,
-        from: &'c [Node<R, E>],
-        top_id: ExprId,
+        _from: &'c [Node<R, E>],
+        _top_id: ExprId,
     ) -> Result<Box<dyn Apply<R, E>>> {
-        let args = CachedVals::new(from);
-        Ok(Box::new(Self { wait: Duration::ZERO, last: None, tid: None, top_id, args }))
+        Ok(Box::new(Pairwise(None)))
     }
 }
 
-impl<R: Rt, E: UserEvent> Apply<R, E> for Throttle {
+impl<R: Rt, E: UserEvent> Apply<R, E> for Pairwise {
     fn update(
         &mut self,
         ctx: &mut ExecCtx<R, E>,
         from: &mut [Node<R, E>],
         event: &mut Event<E>,
     ) -> Option<Value> {
-        macro_rules! maybe_schedule {
-            ($last:expr) => {{
-                let now = Instant::now();
-                if now - *$last >= self.wait {
-                    *$last = now;
-                    return self.args.0[1].clone();
-                } else {
-                    let id = BindId::new();
-                    ctx.rt.ref_var(id, self.top_id);
-                    ctx.rt.set_timer(id, self.wait - (now - *$last));
-                    self.tid = Some(id);
-                    return None;
-                }
-            }};
-        }
-        let mut up = [false; 2];
-        self.args.update_diff(&mut up, ctx, from, event);
-        if up[0]
-            && let Some(Value::Duration(d)) = &self.args.0[0]
-        {
-            self.wait = **d;
-            if let Some(id) = self.tid.take()
-                && let Some(last) = &mut self.last
-            {
-                ctx.rt.unref_var(id, self.top_id);
-                maybe_schedule!(last)
-            }
-        }
-        if up[1] && self.tid.is_none() {
-            match &mut self.last {
-                Some(last) => maybe_schedule!(last),
-                None => {
-                    self.last = Some(Instant::now());
-                    return self.args.0[1].clone();
-                }
-            }
-        }
-        if let Some(id) = self.tid
-            && let Some(_) = event.variables.get(&id)
-        {
-            ctx.rt.unref_var(id, self.top_id);
-            self.tid = None;
-            self.last = Some(Instant::now());
-            return self.args.0[1].clone();
-        }
-        None
-    }
-
-    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
-        if let Some(id) = self.tid.take() {
-            ctx.rt.unref_var(id, self.top_id);
-        }
+        let cur = from[0].update(ctx, event)?;
+        let prev = self.0.replace(cur.clone())?;
+        Some(Value::Array(ValArray::from([prev, cur])))
     }
 
-    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
-        self.delete(ctx);
-        self.last = None;
-        self.wait = Duration::ZERO;
-        self.args.clear();
+    fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {
+        self.0 = None
     }
 }
 
 #[derive(Debug)]
-struct Count {
-    count: i64,
-}
+struct CollectMap(CMap<Value, Value, 32>);
 
-impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Count {
-    const NAME: &str = "core_count";
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for CollectMap {
+    const NAME: &str = "core_collect_map";
     const NEEDS_CALLSITE: bool = false;
 
     fn init<'a, 'b, 'c, 'd>(
@@ -2012,106 +4536,300 @@ impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Count {
         _from: &'c [Node<R, E>],
         _top_id: ExprId,
     ) -> Result<Box<dyn Apply<R, E>>> {
-        Ok(Box::new(Count { count: 0 }))
+        Ok(Box::new(CollectMap(CMap::new())))
     }
 }
 
-impl<R: Rt, E: UserEvent> Apply<R, E> for Count {
+impl<R: Rt, E: UserEvent> Apply<R, E> for CollectMap {
     fn update(
         &mut self,
         ctx: &mut ExecCtx<R, E>,
         from: &mut [Node<R, E>],
         event: &mut Event<E>,
     ) -> Option<Value> {
-        if from.into_iter().fold(false, |u, n| u || n.update(ctx, event).is_some()) {
-            self.count += 1;
-            Some(Value::I64(self.count))
-        } else {
-            None
+        match from[0].update(ctx, event)? {
+            Value::Array(a) if a.len() == 2 => {
+                self.0 = match &a[1] {
+                    Value::Null => self.0.remove(&a[0]).0,
+                    v => self.0.insert(a[0].clone(), v.clone()).0,
+                };
+                Some(Value::Map(self.0.clone()))
+            }
+            _ => None,
         }
     }
 
     fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {
-        self.count = 0
+        self.0 = CMap::new()
     }
 }
 
 #[derive(Debug, Default)]
-struct MeanEv;
+struct SameEv;
 
-impl<R: Rt, E: UserEvent> EvalCached<R, E> for MeanEv {
-    const NAME: &str = "core_mean";
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for SameEv {
+    const NAME: &str = "core_same";
     const NEEDS_CALLSITE: bool = false;
 
     fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        static TAG: ArcStr = literal!("MeanError");
-        let mut total = 0.;
-        let mut samples = 0;
-        let mut error = None;
-        for v in from.flat_iter() {
-            if let Some(v) = v {
-                match v.cast_to::<f64>() {
-                    Err(e) => error = Some(errf!(TAG, "{e:?}")),
-                    Ok(v) => {
-                        total += v;
-                        samples += 1;
-                    }
-                }
+        let same = match (from.0[0].as_ref()?, from.0[1].as_ref()?) {
+            (Value::Array(a0), Value::Array(a1)) => {
+                a0.len() == a1.len() && a0.as_ptr() == a1.as_ptr()
             }
+            (Value::String(s0), Value::String(s1)) => ArcStr::ptr_eq(s0, s1),
+            (Value::Bytes(b0), Value::Bytes(b1)) => {
+                b0.len() == b1.len() && b0.as_ptr() == b1.as_ptr()
+            }
+            (Value::Error(e0), Value::Error(e1)) => TArc::ptr_eq(e0, e1),
+            (v0, v1) => v0 == v1,
+        };
+        Some(Value::Bool(same))
+    }
+}
+
+type Same = CachedArgs<SameEv>;
+
+#[derive(Debug, Default)]
+struct HashEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for HashEv {
+    const NAME: &str = "core_hash";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        // FxHasher64 is unseeded, so the hash doesn't change between runs
+        let mut h = FxHasher64::default();
+        from.0[0].as_ref()?.hash(&mut h);
+        Some(Value::from(format_compact!("{:016x}", h.finish())))
+    }
+}
+
+type Hash = CachedArgs<HashEv>;
+
+/// Infer the structural type of a value by inspecting it. Struct-shaped
+/// arrays become structs, and the element types of arrays and maps are
+/// the union of the types of their members.
+fn value_type<R: Rt, E: UserEvent>(ctx: &ExecCtx<R, E>, v: &Value) -> Type {
+    let union = |i: &mut dyn Iterator<Item = Type>| {
+        i.fold(Type::Bottom, |acc, t| acc.union(&ctx.env, &t).unwrap_or(Type::Any))
+    };
+    match v {
+        Value::Error(e) => Type::Error(TArc::new(value_type(ctx, e))),
+        Value::Array(a) if is_struct(a) => {
+            Type::Struct(TArc::from_iter(a.iter().map(|v| match v {
+                Value::Array(pair) => match (&pair[0], &pair[1]) {
+                    (Value::String(n), v) => (n.clone(), value_type(ctx, v)),
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            })))
         }
-        if let Some(e) = error {
-            Some(e)
-        } else if samples == 0 {
-            Some(err!(TAG, "mean requires at least one argument"))
-        } else {
-            Some(Value::F64(total / samples as f64))
+        Value::Array(a) => {
+            Type::Array(TArc::new(union(&mut a.iter().map(|v| value_type(ctx, v)))))
+        }
+        Value::Map(m) => {
+            let key = union(&mut m.into_iter().map(|(k, _)| value_type(ctx, k)));
+            let value = union(&mut m.into_iter().map(|(_, v)| value_type(ctx, v)));
+            Type::Map { key: TArc::new(key), value: TArc::new(value) }
         }
+        v => Type::Primitive(Typ::get(v).into()),
     }
 }
 
-type Mean = CachedArgs<MeanEv>;
-
-#[derive(Debug)]
-struct Uniq(Option<Value>);
+#[derive(Debug, Default)]
+struct TypeOfEv;
 
-impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Uniq {
-    const NAME: &str = "core_uniq";
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for TypeOfEv {
+    const NAME: &str = "core_typeof";
     const NEEDS_CALLSITE: bool = false;
 
-    fn init<'a, 'b, 'c, 'd>(
-        _ctx: &'a mut ExecCtx<R, E>,
-        _typ: &'a FnType,
-        _resolved: Option<&'d FnType>,
-        _scope: &'b Scope,
-        _from: &'c [Node<R, E>],
-        _top_id: ExprId,
-    ) -> Result<Box<dyn Apply<R, E>>> {
-        Ok(Box::new(Uniq(None)))
+    fn eval(&mut self, ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        let v = from.0[0].as_ref()?;
+        Some(Value::String(value_type(ctx, v).to_string().into()))
     }
 }
 
-impl<R: Rt, E: UserEvent> Apply<R, E> for Uniq {
-    fn update(
+type TypeOf = CachedArgs<TypeOfEv>;
+
+/// whether every value of type t is a variant. Variants are arrays or
+/// strings at runtime, so a type that mixes them with other types is an
+/// error, their length would be ambiguous.
+fn is_variant(env: &Env, t: &Type, depth: usize) -> Result<bool> {
+    if depth >= DEFAULT_MAX_DEPTH {
+        bail!("the type {t} is too deeply nested")
+    }
+    match t.with_deref(|t| t.cloned()) {
+        Some(Type::Variant(_, _)) => Ok(true),
+        Some(t @ Type::Ref { .. }) => is_variant(env, &t.lookup_ref(env)?, depth + 1),
+        Some(Type::Set(elts)) => {
+            let mut variant = None;
+            for e in elts.iter() {
+                let v = is_variant(env, e, depth + 1)?;
+                if *variant.get_or_insert(v) != v {
+                    bail!("{t} mixes variants with other types")
+                }
+            }
+            Ok(variant.unwrap_or(false))
+        }
+        Some(_) | None => Ok(false),
+    }
+}
+
+#[derive(Debug, Default)]
+struct LenEv {
+    variant: bool,
+}
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for LenEv {
+    const NAME: &str = "core_len";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn typecheck(
         &mut self,
         ctx: &mut ExecCtx<R, E>,
-        from: &mut [Node<R, E>],
-        event: &mut Event<E>,
-    ) -> Option<Value> {
-        from[0].update(ctx, event).and_then(|v| {
-            if Some(&v) != self.0.as_ref() {
-                self.0 = Some(v.clone());
-                Some(v)
-            } else {
-                None
+        _from: &mut [Node<R, E>],
+        phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        match phase {
+            TypecheckPhase::Lambda => Ok(()),
+            TypecheckPhase::CallSite(resolved) => {
+                match is_variant(&ctx.env, &resolved.args[0].typ, 0) {
+                    Ok(variant) => self.variant = variant,
+                    Err(e) => bail!("core::len: {e}"),
+                }
+                Ok(())
             }
-        })
+        }
     }
 
-    fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {
-        self.0 = None
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        // a variant is its tag followed by its arguments, or just the tag
+        // if it has none, count the arguments
+        if self.variant {
+            return match from.0[0].as_ref()? {
+                Value::Array(a) => Some(Value::I64(a.len().saturating_sub(1) as i64)),
+                _ => Some(Value::I64(0)),
+            };
+        }
+        // tuples and structs are arrays, so their arity is the array length
+        let n = match from.0[0].as_ref()? {
+            Value::String(s) => s.graphemes(true).count(),
+            Value::Bytes(b) => b.len(),
+            Value::Array(a) => a.len(),
+            Value::Map(m) => m.len(),
+            v => return Some(errf!("LenError", "{v} is not a collection")),
+        };
+        Some(Value::I64(n as i64))
+    }
+}
+
+type Len = CachedArgs<LenEv>;
+
+/// Render v like Display, but replace arrays and maps nested deeper than
+/// max_depth with an ellipsis, and render at most max_len elements of each
+/// array or map, followed by the number of elements omitted. Rendering
+/// stops once buf is longer than max_total, and strings and bytes are cut
+/// short so that rendering them doesn't go much past it.
+fn fmt_limited(
+    buf: &mut String,
+    v: &Value,
+    depth: u64,
+    max_depth: u64,
+    max_len: u64,
+    max_total: usize,
+) -> std::fmt::Result {
+    use std::fmt::Write;
+    fn elide(buf: &mut String, shown: usize, len: usize) -> std::fmt::Result {
+        if shown < len {
+            if shown > 0 {
+                buf.push_str(", ");
+            }
+            write!(buf, "... ({} more)", len - shown)?;
+        }
+        Ok(())
+    }
+    // enough to go past max_total, so the caller knows it was cut
+    let rest = (max_total + 1).saturating_sub(buf.len());
+    match v {
+        _ if rest == 0 => (),
+        Value::Array(_) if depth >= max_depth => buf.push_str("[...]"),
+        Value::Map(_) if depth >= max_depth => buf.push_str("{...}"),
+        Value::Array(a) => {
+            buf.push('[');
+            let shown = cmp::min(a.len(), max_len as usize);
+            for (i, v) in a[..shown].iter().enumerate() {
+                if buf.len() > max_total {
+                    return Ok(());
+                }
+                if i > 0 {
+                    buf.push_str(", ");
+                }
+                fmt_limited(buf, v, depth + 1, max_depth, max_len, max_total)?;
+            }
+            elide(buf, shown, a.len())?;
+            buf.push(']');
+        }
+        Value::Map(m) => {
+            buf.push('{');
+            let shown = cmp::min(m.len(), max_len as usize);
+            for (i, (k, v)) in m.into_iter().take(shown).enumerate() {
+                if buf.len() > max_total {
+                    return Ok(());
+                }
+                if i > 0 {
+                    buf.push_str(", ");
+                }
+                fmt_limited(buf, k, depth + 1, max_depth, max_len, max_total)?;
+                buf.push_str(" => ");
+                fmt_limited(buf, v, depth + 1, max_depth, max_len, max_total)?;
+            }
+            elide(buf, shown, m.len())?;
+            buf.push('}');
+        }
+        Value::Error(e) => {
+            buf.push_str("error:");
+            fmt_limited(buf, e, depth, max_depth, max_len, max_total)?;
+        }
+        Value::String(s) if s.len() > rest => {
+            let end = s.char_indices().nth(rest).map(|(i, _)| i).unwrap_or(s.len());
+            write!(buf, "{}", Value::String(ArcStr::from(&s[..end])))?
+        }
+        Value::Bytes(b) if b.len() > rest => {
+            write!(buf, "{}", Value::Bytes(PBytes::new(b.slice(..rest))))?
+        }
+        v => write!(buf, "{v}")?,
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct ToStringLimitedEv(String);
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for ToStringLimitedEv {
+    const NAME: &str = "core_to_string_limited";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        let max_depth = from.0[0].clone()?.cast_to::<u64>().ok()?;
+        let max_len = from.0[1].clone()?.cast_to::<u64>().ok()?;
+        let max_total = from.0[2].clone()?.cast_to::<u64>().ok()? as usize;
+        let v = from.0[3].as_ref()?;
+        self.0.clear();
+        fmt_limited(&mut self.0, v, 0, max_depth, max_len, max_total).ok()?;
+        if self.0.len() > max_total {
+            let mut end = max_total;
+            while !self.0.is_char_boundary(end) {
+                end -= 1
+            }
+            self.0.truncate(end);
+            self.0.push_str("...")
+        }
+        Some(Value::String(self.0.as_str().into()))
     }
 }
 
+type ToStringLimited = CachedArgs<ToStringLimitedEv>;
+
 #[derive(Debug)]
 struct Never;
 
@@ -2401,6 +5119,8 @@ graphix_derive::defpackage! {
         IsErr,
         FilterErr,
         ToError,
+        OrElse,
+        Sequence,
         Once,
         Take,
         Skip,
@@ -2410,6 +5130,8 @@ graphix_derive::defpackage! {
         Divide,
         Min,
         Max,
+        RunningMin,
+        RunningMax,
         And,
         Or,
         BitAnd,
@@ -2418,14 +5140,45 @@ graphix_derive::defpackage! {
         BitNot,
         Shl,
         Shr,
+        WrappingAdd,
+        SaturatingAdd,
         Filter as Filter<GXRt<X>, X::UserEvent>,
+        Memoize as Memoize<GXRt<X>, X::UserEvent>,
+        Switch as Switch<GXRt<X>, X::UserEvent>,
+        Retry as Retry<GXRt<X>, X::UserEvent>,
+        WithTimeout as WithTimeout<GXRt<X>, X::UserEvent>,
+        StatefulFold as StatefulFold<GXRt<X>, X::UserEvent>,
+        DistinctBy as DistinctBy<GXRt<X>, X::UserEvent>,
         Queue,
         Hold,
+        Batch,
+        GroupAdjacent as GroupAdjacent<GXRt<X>, X::UserEvent>,
+        When,
         Seq,
+        CompareAndSet,
+        SetField,
         Throttle,
+        DebounceDistinct,
+        Rate,
+        SampleRate,
         Count,
         Mean,
+        Clamp,
+        Lerp,
+        RoundTo,
+        MovingAverage,
+        Histogram,
+        TryCast,
+        StructToMap,
+        MapToStruct,
         Uniq,
+        Pairwise,
+        CollectMap,
+        TypeOf,
+        Len,
+        ToStringLimited,
+        Same,
+        Hash,
         Never,
         Dbg,
         Log,
@@ -2440,5 +5193,8 @@ graphix_derive::defpackage! {
         buffer::BytesLen,
         buffer::BufferEncode,
         buffer::BufferDecode,
+        test::Assert,
+        test::AssertEq,
+        test::Case as test::Case<GXRt<X>, X::UserEvent>,
     ],
 }
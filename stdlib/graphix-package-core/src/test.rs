@@ -0,0 +1,251 @@
+use anyhow::{bail, Result};
+use arcstr::{literal, ArcStr};
+use compact_str::format_compact;
+use graphix_compiler::{
+    errf,
+    expr::ExprId,
+    node::genn,
+    typ::{FnType, Type},
+    Apply, BindId, BuiltIn, Event, ExecCtx, LambdaId, Node, Refs, Rt, Scope,
+    TypecheckPhase, UserEvent,
+};
+use graphix_rt::{TestCases, TestOutcome, TestResult};
+use netidx::subscriber::Value;
+use poolshark::local::LPooled;
+use std::collections::hash_map::Entry;
+use triomphe::Arc as TArc;
+
+use crate::{CachedArgs, CachedVals, EvalCached};
+
+#[derive(Debug, Default)]
+pub(crate) struct AssertEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for AssertEv {
+    const NAME: &str = "core_test_assert";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match from.0[0].as_ref()? {
+            Value::Bool(true) => Some(Value::Null),
+            _ => Some(errf!("AssertionFailed", "assertion failed")),
+        }
+    }
+}
+
+pub(crate) type Assert = CachedArgs<AssertEv>;
+
+#[derive(Debug, Default)]
+pub(crate) struct AssertEqEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for AssertEqEv {
+    const NAME: &str = "core_test_assert_eq";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match (from.0[0].as_ref()?, from.0[1].as_ref()?) {
+            (a, b) if a == b => Some(Value::Null),
+            (a, b) => Some(errf!("AssertionFailed", "{a} != {b}")),
+        }
+    }
+}
+
+pub(crate) type AssertEq = CachedArgs<AssertEqEv>;
+
+/// A test case. The case runs f once it knows it, and records the first
+/// thing f returns, or the first error raised by ? inside f, in the
+/// `TestCases` of the execution context.
+#[derive(Debug)]
+pub(crate) struct Case<R: Rt, E: UserEvent> {
+    scope: Scope,
+    top_id: ExprId,
+    ftyp: TArc<FnType>,
+    catch: BindId,
+    name: ArcStr,
+    case: Option<usize>,
+    f: Option<(BindId, Node<R, E>)>,
+    done: bool,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Case<R, E> {
+    const NAME: &str = "core_test_case";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        ctx: &'a mut ExecCtx<R, E>,
+        typ: &'a FnType,
+        resolved: Option<&'d FnType>,
+        scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        match from {
+            [_, _] => {
+                let typ = resolved.unwrap_or(typ);
+                let ftyp = match &typ.args[1].typ {
+                    Type::Fn(ft) => ft.clone(),
+                    t => bail!("expected a function not {t}"),
+                };
+                // errors raised in f are caught by the case
+                let scope =
+                    scope.append(&format_compact!("tc{}", LambdaId::new().inner()));
+                let catch =
+                    ctx.env.bind_variable(&scope.lexical, "e", Type::empty_tvar()).id;
+                ctx.env.catch.insert_cow(scope.dynamic.clone(), catch);
+                ctx.rt.ref_var(catch, top_id);
+                Ok(Box::new(Self {
+                    scope,
+                    top_id,
+                    ftyp,
+                    catch,
+                    name: literal!(""),
+                    case: None,
+                    f: None,
+                    done: false,
+                }))
+            }
+            _ => bail!("expected two arguments"),
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Case<R, E> {
+    fn result<'a>(&mut self, ctx: &'a mut ExecCtx<R, E>) -> &'a mut TestResult {
+        let cases = ctx.libstate.get_or_default::<TestCases>();
+        let i = match self.case {
+            Some(i) if i < cases.0.len() => i,
+            Some(_) | None => {
+                cases.0.push(TestResult {
+                    name: self.name.clone(),
+                    outcome: TestOutcome::Running,
+                });
+                cases.0.len() - 1
+            }
+        };
+        self.case = Some(i);
+        &mut cases.0[i]
+    }
+
+    /// record the outcome of the case, and return whether it passed
+    fn finish(&mut self, ctx: &mut ExecCtx<R, E>, v: Option<Value>) -> Option<Value> {
+        if self.done {
+            return None;
+        }
+        let outcome = match v? {
+            Value::Error(e) => {
+                TestOutcome::Failed(format_compact!("{e}").as_str().into())
+            }
+            _ => TestOutcome::Passed,
+        };
+        self.done = true;
+        let passed = outcome == TestOutcome::Passed;
+        self.result(ctx).outcome = outcome;
+        Some(Value::Bool(passed))
+    }
+
+    /// run f from scratch
+    fn invoke(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        event: &mut Event<E>,
+        f: Value,
+    ) -> Option<Value> {
+        let (fid, node) = self.f.get_or_insert_with(|| {
+            let fid = BindId::new();
+            let fnode =
+                genn::reference(ctx, fid, Type::Fn(self.ftyp.clone()), self.top_id);
+            let node =
+                genn::apply(fnode, self.scope.clone(), vec![], &self.ftyp, self.top_id);
+            (fid, node)
+        });
+        ctx.cached.insert(*fid, f.clone());
+        event.variables.insert(*fid, f);
+        let mut set: LPooled<Vec<BindId>> = LPooled::take();
+        let mut refs = Refs::default();
+        node.refs(&mut refs);
+        refs.with_external_refs(|id| {
+            if let Entry::Vacant(e) = event.variables.entry(id)
+                && let Some(v) = ctx.cached.get(&id)
+            {
+                e.insert(v.clone());
+                set.push(id);
+            }
+        });
+        let res = node.update(ctx, &mut event.init_scope(true));
+        for id in set.drain(..) {
+            event.variables.remove(&id);
+        }
+        res
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Case<R, E> {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if let Some(Value::String(name)) = from[0].update(ctx, event) {
+            self.name = name.clone();
+            self.result(ctx).name = name;
+        }
+        let v = match from[1].update(ctx, event) {
+            Some(f) => {
+                if let Some((_, node)) = &mut self.f {
+                    node.sleep(ctx)
+                }
+                self.done = false;
+                self.result(ctx).outcome = TestOutcome::Running;
+                self.invoke(ctx, event, f)
+            }
+            None => match &mut self.f {
+                Some((_, node)) => node.update(ctx, event),
+                None => None,
+            },
+        };
+        match event.variables.get(&self.catch) {
+            Some(e) => {
+                let e = e.clone();
+                self.finish(ctx, Some(e))
+            }
+            None => self.finish(ctx, v),
+        }
+    }
+
+    fn typecheck(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        _phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        let ft = self.ftyp.clone();
+        let fnode =
+            genn::reference(ctx, BindId::new(), Type::Fn(ft.clone()), self.top_id);
+        let mut node = genn::apply(fnode, self.scope.clone(), vec![], &ft, self.top_id);
+        node.typecheck(ctx)?;
+        node.delete(ctx);
+        Ok(())
+    }
+
+    fn refs(&self, refs: &mut Refs) {
+        if let Some((_, node)) = &self.f {
+            node.refs(refs)
+        }
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        ctx.rt.unref_var(self.catch, self.top_id);
+        ctx.env.catch.remove_cow(&self.scope.dynamic);
+        if let Some((fid, mut node)) = self.f.take() {
+            ctx.cached.remove(&fid);
+            node.delete(ctx);
+        }
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some((_, node)) = &mut self.f {
+            node.sleep(ctx)
+        }
+        self.done = false;
+    }
+}
@@ -1,6 +1,8 @@
 use anyhow::{bail, Result};
+use enumflags2::BitFlags;
 use graphix_compiler::expr::ModuleResolver;
-use graphix_rt::{GXConfig, GXEvent, GXHandle, GXRt, NoExt};
+use graphix_compiler::CFlag;
+use graphix_rt::{GXConfig, GXConfigBuilder, GXEvent, GXHandle, GXRt, NoExt};
 use netidx::publisher::Value;
 use poolshark::global::GPooled;
 use tokio::sync::mpsc;
@@ -51,6 +53,75 @@ where
             <NoExt as graphix_rt::GXExt>::UserEvent,
         >,
     ),
+{
+    init_with_flags(sub, register, resolvers, BitFlags::empty(), setup).await
+}
+
+/// Like `init_with_setup`, but compile everything with the given compiler flags
+pub async fn init_with_flags<F>(
+    sub: mpsc::Sender<GPooled<Vec<GXEvent>>>,
+    register: &[RegisterFn],
+    resolvers: Vec<ModuleResolver>,
+    flags: BitFlags<CFlag>,
+    setup: F,
+) -> Result<TestCtx>
+where
+    F: FnOnce(
+        &mut graphix_compiler::ExecCtx<
+            GXRt<NoExt>,
+            <NoExt as graphix_rt::GXExt>::UserEvent,
+        >,
+    ),
+{
+    init_with_config(sub, register, resolvers, setup, |b| b.flags(flags)).await
+}
+
+/// Like `init_with_setup`, but allow `config` to adjust the runtime config
+pub async fn init_with_config<F, C>(
+    sub: mpsc::Sender<GPooled<Vec<GXEvent>>>,
+    register: &[RegisterFn],
+    resolvers: Vec<ModuleResolver>,
+    setup: F,
+    config: C,
+) -> Result<TestCtx>
+where
+    F: FnOnce(
+        &mut graphix_compiler::ExecCtx<
+            GXRt<NoExt>,
+            <NoExt as graphix_rt::GXExt>::UserEvent,
+        >,
+    ),
+    C: FnOnce(GXConfigBuilder<NoExt>) -> GXConfigBuilder<NoExt>,
+{
+    init_int(sub, register, &fxhash::FxHashMap::default(), resolvers, setup, config).await
+}
+
+/// Like `init`, but mount packages under different names, see
+/// `graphix_package::mount`
+pub async fn init_with_mounts(
+    sub: mpsc::Sender<GPooled<Vec<GXEvent>>>,
+    register: &[RegisterFn],
+    mounts: &fxhash::FxHashMap<arcstr::ArcStr, netidx_core::path::Path>,
+) -> Result<TestCtx> {
+    init_int(sub, register, mounts, vec![], |_| {}, |b| b).await
+}
+
+async fn init_int<F, C>(
+    sub: mpsc::Sender<GPooled<Vec<GXEvent>>>,
+    register: &[RegisterFn],
+    mounts: &fxhash::FxHashMap<arcstr::ArcStr, netidx_core::path::Path>,
+    resolvers: Vec<ModuleResolver>,
+    setup: F,
+    config: C,
+) -> Result<TestCtx>
+where
+    F: FnOnce(
+        &mut graphix_compiler::ExecCtx<
+            GXRt<NoExt>,
+            <NoExt as graphix_rt::GXExt>::UserEvent,
+        >,
+    ),
+    C: FnOnce(GXConfigBuilder<NoExt>) -> GXConfigBuilder<NoExt>,
 {
     let _ = env_logger::try_init();
     let env = netidx::InternalOnly::new().await?;
@@ -63,6 +134,7 @@ where
     for f in register {
         f(&mut ctx, &mut modules, &mut root_mods)?;
     }
+    graphix_package::mount(&mut modules, &mut root_mods, mounts)?;
     setup(&mut ctx);
     let mut parts = Vec::new();
     for name in &root_mods {
@@ -77,9 +149,7 @@ where
     all_resolvers.extend(resolvers);
     Ok(TestCtx {
         internal_only: env,
-        rt: GXConfig::builder(ctx, sub)
-            .root(root)
-            .resolvers(all_resolvers)
+        rt: config(GXConfig::builder(ctx, sub).root(root).resolvers(all_resolvers))
             .build()?
             .start()
             .await?,
@@ -100,6 +170,32 @@ pub async fn eval_with_setup<F>(
     register: &[RegisterFn],
     setup: F,
 ) -> Result<(Value, TestCtx)>
+where
+    F: FnOnce(
+        &mut graphix_compiler::ExecCtx<
+            GXRt<NoExt>,
+            <NoExt as graphix_rt::GXExt>::UserEvent,
+        >,
+    ),
+{
+    eval_int(code, register, BitFlags::empty(), setup).await
+}
+
+/// Evaluate a graphix expression compiled with the given compiler flags
+pub async fn eval_with_flags(
+    code: &str,
+    register: &[RegisterFn],
+    flags: BitFlags<CFlag>,
+) -> Result<(Value, TestCtx)> {
+    eval_int(code, register, flags, |_| {}).await
+}
+
+async fn eval_int<F>(
+    code: &str,
+    register: &[RegisterFn],
+    flags: BitFlags<CFlag>,
+    setup: F,
+) -> Result<(Value, TestCtx)>
 where
     F: FnOnce(
         &mut graphix_compiler::ExecCtx<
@@ -115,7 +211,7 @@ where
         arcstr::ArcStr::from(gx_code),
     )]);
     let resolver = ModuleResolver::VFS(tbl);
-    let ctx = init_with_setup(tx, register, vec![resolver], setup).await?;
+    let ctx = init_with_flags(tx, register, vec![resolver], flags, setup).await?;
     let compiled = ctx.rt.compile(arcstr::literal!("{ mod test; test::result }")).await?;
     let eid = compiled.exprs[0].id;
     let timeout = tokio::time::sleep(std::time::Duration::from_secs(5));
@@ -1,8 +1,8 @@
 use crate::encoding::{decode_key, decode_value, encode_key};
 use crate::tree::{get_tree_inner, TreeInner};
 use arcstr::ArcStr;
-use graphix_compiler::errf;
-use graphix_package_core::{CachedArgsAsync, CachedVals, EvalCachedAsync};
+use graphix_compiler::{errf, ExecCtx, Rt, UserEvent};
+use graphix_package_core::{is_cancelled, CachedArgsAsync, CachedVals, EvalCachedAsync};
 use netidx::publisher::Typ;
 use netidx_value::{ValArray, Value};
 use poolshark::local::LPooled;
@@ -43,6 +43,15 @@ impl EvalCachedAsync for DbCursorNewEv {
     const NEEDS_CALLSITE: bool = false;
     type Args = (Option<Value>, Arc<TreeInner>);
 
+    fn map_value<R: Rt, E: UserEvent>(
+        &mut self,
+        _ctx: &mut ExecCtx<R, E>,
+        v: Value,
+    ) -> Option<Value> {
+        // there is no error in the type to report the cancellation with
+        (!is_cancelled(&v)).then_some(v)
+    }
+
     fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
         let prefix_val = match cached.0.get(0)?.as_ref()? {
             Value::Null => None,
@@ -220,6 +229,15 @@ impl EvalCachedAsync for DbCursorRangeEv {
     const NEEDS_CALLSITE: bool = false;
     type Args = RangeArgs;
 
+    fn map_value<R: Rt, E: UserEvent>(
+        &mut self,
+        _ctx: &mut ExecCtx<R, E>,
+        v: Value,
+    ) -> Option<Value> {
+        // there is no error in the type to report the cancellation with
+        (!is_cancelled(&v)).then_some(v)
+    }
+
     fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
         let tree = get_tree_inner(cached, 2)?;
         let lo = parse_bound(tree.key_typ, cached.0.get(0)?.as_ref()?)?;
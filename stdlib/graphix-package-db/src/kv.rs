@@ -0,0 +1,183 @@
+use arcstr::ArcStr;
+use fxhash::FxHashMap;
+use graphix_compiler::{
+    errf, expr::ExprId, typ::FnType, ExecCtx, Node, Rt, Scope, UserEvent,
+};
+use graphix_package_core::{CachedArgsAsync, CachedVals, EvalCachedAsync};
+use netidx_value::Value;
+use parking_lot::Mutex;
+use poolshark::global::GPooled;
+use std::sync::Arc;
+
+use crate::encoding::{decode_value, encode_value};
+
+// ── Store registry ────────────────────────────────────────────────
+//
+// Every kv builtin in a runtime shares the stores opened by that runtime,
+// keyed by path. The registry lives in LibState, so the stores are closed
+// when the runtime shuts down.
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct KvStores(Arc<Mutex<FxHashMap<ArcStr, sled::Db>>>);
+
+impl KvStores {
+    fn new<R: Rt, E: UserEvent>(ctx: &mut ExecCtx<R, E>) -> Self {
+        ctx.libstate.get_or_default::<KvStores>().clone()
+    }
+
+    /// open the store at path, or return the already open store. This
+    /// blocks, so it must be called from spawn_blocking.
+    fn open(&self, path: &ArcStr) -> sled::Result<sled::Db> {
+        let mut stores = self.0.lock();
+        match stores.get(path) {
+            Some(db) => Ok(db.clone()),
+            None => {
+                let db = sled::open(&**path)?;
+                stores.insert(path.clone(), db.clone());
+                Ok(db)
+            }
+        }
+    }
+}
+
+// -- KvGet --
+
+#[derive(Debug, Default)]
+pub(crate) struct KvGetEv(KvStores);
+
+impl EvalCachedAsync for KvGetEv {
+    const NAME: &str = "db_kv_get";
+    const NEEDS_CALLSITE: bool = false;
+    type Args = (KvStores, ArcStr, ArcStr);
+
+    fn init<R: Rt, E: UserEvent>(
+        ctx: &mut ExecCtx<R, E>,
+        _typ: &FnType,
+        _resolved: Option<&FnType>,
+        _scope: &Scope,
+        _from: &[Node<R, E>],
+        _top_id: ExprId,
+    ) -> Self {
+        Self(KvStores::new(ctx))
+    }
+
+    fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
+        let path = cached.get::<ArcStr>(0)?;
+        let key = cached.get::<ArcStr>(1)?;
+        Some((self.0.clone(), path, key))
+    }
+
+    fn eval((stores, path, key): Self::Args) -> impl Future<Output = Value> + Send {
+        async move {
+            match tokio::task::spawn_blocking(move || {
+                stores.open(&path)?.get(key.as_bytes())
+            })
+            .await
+            {
+                Err(e) => errf!("DbErr", "task panicked: {e}"),
+                Ok(Err(e)) => errf!("DbErr", "{e}"),
+                Ok(Ok(None)) => Value::Null,
+                Ok(Ok(Some(ivec))) => match decode_value(&ivec) {
+                    Some(v) => v,
+                    None => errf!("DbErr", "failed to decode value"),
+                },
+            }
+        }
+    }
+}
+
+pub(crate) type KvGet = CachedArgsAsync<KvGetEv>;
+
+// -- KvSet --
+
+#[derive(Debug, Default)]
+pub(crate) struct KvSetEv(KvStores);
+
+impl EvalCachedAsync for KvSetEv {
+    const NAME: &str = "db_kv_set";
+    const NEEDS_CALLSITE: bool = false;
+    type Args = (KvStores, ArcStr, ArcStr, GPooled<Vec<u8>>);
+
+    fn init<R: Rt, E: UserEvent>(
+        ctx: &mut ExecCtx<R, E>,
+        _typ: &FnType,
+        _resolved: Option<&FnType>,
+        _scope: &Scope,
+        _from: &[Node<R, E>],
+        _top_id: ExprId,
+    ) -> Self {
+        Self(KvStores::new(ctx))
+    }
+
+    fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
+        let path = cached.get::<ArcStr>(0)?;
+        let key = cached.get::<ArcStr>(1)?;
+        let val = encode_value(cached.0.get(2)?.as_ref()?)?;
+        Some((self.0.clone(), path, key, val))
+    }
+
+    fn eval((stores, path, key, val): Self::Args) -> impl Future<Output = Value> + Send {
+        async move {
+            match tokio::task::spawn_blocking(move || {
+                let db = stores.open(&path)?;
+                db.insert(key.as_bytes(), val.as_slice())?;
+                db.flush()
+            })
+            .await
+            {
+                Err(e) => errf!("DbErr", "task panicked: {e}"),
+                Ok(Err(e)) => errf!("DbErr", "{e}"),
+                Ok(Ok(_)) => Value::Null,
+            }
+        }
+    }
+}
+
+pub(crate) type KvSet = CachedArgsAsync<KvSetEv>;
+
+// -- KvDelete --
+
+#[derive(Debug, Default)]
+pub(crate) struct KvDeleteEv(KvStores);
+
+impl EvalCachedAsync for KvDeleteEv {
+    const NAME: &str = "db_kv_delete";
+    const NEEDS_CALLSITE: bool = false;
+    type Args = (KvStores, ArcStr, ArcStr);
+
+    fn init<R: Rt, E: UserEvent>(
+        ctx: &mut ExecCtx<R, E>,
+        _typ: &FnType,
+        _resolved: Option<&FnType>,
+        _scope: &Scope,
+        _from: &[Node<R, E>],
+        _top_id: ExprId,
+    ) -> Self {
+        Self(KvStores::new(ctx))
+    }
+
+    fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
+        let path = cached.get::<ArcStr>(0)?;
+        let key = cached.get::<ArcStr>(1)?;
+        Some((self.0.clone(), path, key))
+    }
+
+    fn eval((stores, path, key): Self::Args) -> impl Future<Output = Value> + Send {
+        async move {
+            match tokio::task::spawn_blocking(move || {
+                let db = stores.open(&path)?;
+                let old = db.remove(key.as_bytes())?;
+                db.flush()?;
+                Ok::<_, sled::Error>(old.is_some())
+            })
+            .await
+            {
+                Err(e) => errf!("DbErr", "task panicked: {e}"),
+                Ok(Err(e)) => errf!("DbErr", "{e}"),
+                Ok(Ok(existed)) => Value::Bool(existed),
+            }
+        }
+    }
+}
+
+pub(crate) type KvDelete = CachedArgsAsync<KvDeleteEv>;
@@ -8,6 +8,7 @@ mod tree;
 mod cursor;
 mod txn;
 mod subscribe;
+mod kv;
 
 use tree::{
     DbGetType, DbOpen, DbFlush, DbGenerateId, DbTreeNames, DbDropTree, DbTree,
@@ -22,6 +23,7 @@ use txn::{
     DbTxnBatch, DbTxnCommit, DbTxnRollback,
 };
 use subscribe::{DbSubscribe, DbOnInsert, DbOnRemove};
+use kv::{KvGet, KvSet, KvDelete};
 
 pub use tree::{DbValue, TreeValue};
 
@@ -71,5 +73,8 @@ graphix_derive::defpackage! {
         DbTxnBatch,
         DbTxnCommit,
         DbTxnRollback,
+        KvGet,
+        KvSet,
+        KvDelete,
     ],
 }
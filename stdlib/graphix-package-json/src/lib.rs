@@ -180,7 +180,7 @@ impl EvalCachedAsync for JsonReadEv {
         v: Value,
     ) -> Option<Value> {
         match self.cast_typ.as_ref() {
-            Some(typ) => Some(typ.cast_value(&ctx.env, v)),
+            Some(typ) => Some(ctx.cast_value(typ, v)),
             None => Some(errf!("JsonErr", "no concrete return type found")),
         }
     }
@@ -16,10 +16,11 @@ use graphix_package_core::{
     CachedArgs, CachedVals, EvalCached, FoldFn, FoldQ, MapCollection, MapFn, MapQ, Slot,
 };
 use graphix_rt::GXRt;
-use netidx::{publisher::Typ, subscriber::Value};
+use netidx::{publisher::Typ, subscriber::Value, utils::Either};
 use netidx_value::ValArray;
-use smallvec::SmallVec;
-use std::{collections::hash_map::Entry, collections::VecDeque, fmt::Debug};
+use poolshark::local::LPooled;
+use smallvec::{smallvec, SmallVec};
+use std::{collections::hash_map::Entry, collections::VecDeque, fmt::Debug, iter};
 use triomphe::Arc as TArc;
 
 // ── Value-level list helpers ─────────────────────────────────────
@@ -72,9 +73,9 @@ fn count_list(v: &Value) -> Option<usize> {
 }
 
 /// Build a list from an iterator by collecting to a buffer and folding
-/// right with cons. O(n) time, O(n) temporary space via SmallVec.
+/// right with cons. O(n) time, O(n) temporary space via a pooled Vec.
 fn from_iter_back(iter: impl Iterator<Item = Value>) -> Value {
-    let mut buf: SmallVec<[Value; 32]> = iter.collect();
+    let mut buf: LPooled<Vec<Value>> = iter.collect();
     let mut result = make_nil();
     while let Some(v) = buf.pop() {
         result = make_cons(v, result);
@@ -227,12 +228,9 @@ impl<R: Rt, E: UserEvent> MapFn<R, E> for ListFlatMapImpl {
         Some(from_iter_back(slots.iter().flat_map(|s| {
             let v = s.cur.as_ref().unwrap();
             if is_list(v) {
-                let items: SmallVec<[Value; 32]> = ListIter { cur: v.clone() }.collect();
-                items.into_iter()
+                Either::Left(ListIter { cur: v.clone() })
             } else {
-                let mut one: SmallVec<[Value; 32]> = SmallVec::new();
-                one.push(v.clone());
-                one.into_iter()
+                Either::Right(iter::once(v.clone()))
             }
         })))
     }
@@ -283,7 +281,7 @@ type ListFindMap<R, E> = MapQ<R, E, ListFindMapImpl>;
 
 // ── FoldFn implementation ────────────────────────────────────────
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct ListFoldImpl;
 
 impl<R: Rt, E: UserEvent> FoldFn<R, E> for ListFoldImpl {
@@ -947,7 +945,7 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for ListInit<R, E> {
                             &self.mftyp,
                             self.top_id,
                         );
-                        self.slots.push(Slot { id, pred, cur: None });
+                        self.slots.push(Slot { ids: smallvec![id], pred, cur: None });
                     }
                     (true, true)
                 }
@@ -956,30 +954,28 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for ListInit<R, E> {
         };
         if resized && self.slots.len() > slen {
             for i in slen..self.slots.len() {
-                let id = self.slots[i].id;
+                let id = self.slots[i].ids[0];
                 event.variables.insert(id, Value::I64(i as i64));
             }
         }
         if size_fired && self.slots.is_empty() {
             return Some(make_nil());
         }
-        let init = event.init;
         let mut up = resized;
         for (i, s) in self.slots.iter_mut().enumerate() {
             if i == slen {
-                event.init = true;
                 if let Entry::Vacant(e) = event.variables.entry(self.fid)
                     && let Some(v) = ctx.cached.get(&self.fid)
                 {
                     e.insert(v.clone());
                 }
             }
-            if let Some(v) = s.pred.update(ctx, event) {
+            let init = event.init || i >= slen;
+            if let Some(v) = s.pred.update(ctx, &mut event.init_scope(init)) {
                 s.cur = Some(v);
                 up = true;
             }
         }
-        event.init = init;
         if up && self.slots.iter().all(|s| s.cur.is_some()) {
             Some(from_iter_back(self.slots.iter().map(|s| s.cur.clone().unwrap())))
         } else {
@@ -85,7 +85,7 @@ impl<R: Rt, E: UserEvent> MapFn<R, E> for FilterMapImpl {
 
 type FilterMap<R, E> = MapQ<R, E, FilterMapImpl>;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct FoldImpl;
 
 impl<R: Rt, E: UserEvent> FoldFn<R, E> for FoldImpl {
@@ -132,6 +132,25 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for GetEv {
 
 type Get = CachedArgs<GetEv>;
 
+#[derive(Debug, Default)]
+struct GetOrEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for GetOrEv {
+    const NAME: &str = "map_get_or";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match (&from.0[0], &from.0[1], &from.0[2]) {
+            (Some(Value::Map(m)), Some(key), Some(default)) => {
+                Some(m.get(key).unwrap_or(default).clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+type GetOr = CachedArgs<GetOrEv>;
+
 #[derive(Debug, Default)]
 struct InsertEv;
 
@@ -301,6 +320,7 @@ graphix_derive::defpackage! {
         Fold as Fold<GXRt<X>, X::UserEvent>,
         Len,
         Get,
+        GetOr,
         Insert,
         Remove,
         Iter,
@@ -74,7 +74,7 @@ impl EvalCachedAsync for PackReadEv {
         v: Value,
     ) -> Option<Value> {
         match &self.cast_typ {
-            Some(typ) => Some(typ.cast_value(&ctx.env, v)),
+            Some(typ) => Some(ctx.cast_value(typ, v)),
             None => Some(errf!("PackErr", "no concrete return type found")),
         }
     }
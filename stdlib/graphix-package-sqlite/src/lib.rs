@@ -236,7 +236,7 @@ impl EvalCachedAsync for SqliteQueryEv {
         v: Value,
     ) -> Option<Value> {
         match self.cast_typ.as_ref() {
-            Some(typ) => Some(typ.cast_value(&ctx.env, v)),
+            Some(typ) => Some(ctx.cast_value(typ, v)),
             None => Some(errf!(
                 "SqliteError",
                 "sqlite::query requires a concrete return type"
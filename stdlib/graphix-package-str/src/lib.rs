@@ -11,11 +11,14 @@ use graphix_compiler::{
     typ::{FnType, Type},
     Apply, BuiltIn, Event, ExecCtx, Node, Rt, Scope, TypecheckPhase, UserEvent,
 };
-use graphix_package_core::{extract_cast_type, CachedArgs, CachedVals, EvalCached};
+use graphix_package_core::{
+    extract_cast_type, CachedArgs, CachedArgs1, CachedVals, EvalCached, EvalCached1,
+};
 use netidx::{path::Path, subscriber::Value};
 use netidx_value::ValArray;
 use smallvec::SmallVec;
 use std::cell::RefCell;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Debug, Default)]
 struct StartsWithEv;
@@ -129,53 +132,53 @@ type StripSuffix = CachedArgs<StripSuffixEv>;
 #[derive(Debug, Default)]
 struct TrimEv;
 
-impl<R: Rt, E: UserEvent> EvalCached<R, E> for TrimEv {
+impl<R: Rt, E: UserEvent> EvalCached1<R, E> for TrimEv {
     const NAME: &str = "str_trim";
     const NEEDS_CALLSITE: bool = false;
 
-    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        match &from.0[0] {
-            Some(Value::String(val)) => Some(Value::String(val.trim().into())),
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value> {
+        match v {
+            Value::String(val) => Some(Value::String(val.trim().into())),
             _ => None,
         }
     }
 }
 
-type Trim = CachedArgs<TrimEv>;
+type Trim = CachedArgs1<TrimEv>;
 
 #[derive(Debug, Default)]
 struct TrimStartEv;
 
-impl<R: Rt, E: UserEvent> EvalCached<R, E> for TrimStartEv {
+impl<R: Rt, E: UserEvent> EvalCached1<R, E> for TrimStartEv {
     const NAME: &str = "str_trim_start";
     const NEEDS_CALLSITE: bool = false;
 
-    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        match &from.0[0] {
-            Some(Value::String(val)) => Some(Value::String(val.trim_start().into())),
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value> {
+        match v {
+            Value::String(val) => Some(Value::String(val.trim_start().into())),
             _ => None,
         }
     }
 }
 
-type TrimStart = CachedArgs<TrimStartEv>;
+type TrimStart = CachedArgs1<TrimStartEv>;
 
 #[derive(Debug, Default)]
 struct TrimEndEv;
 
-impl<R: Rt, E: UserEvent> EvalCached<R, E> for TrimEndEv {
+impl<R: Rt, E: UserEvent> EvalCached1<R, E> for TrimEndEv {
     const NAME: &str = "str_trim_end";
     const NEEDS_CALLSITE: bool = false;
 
-    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        match &from.0[0] {
-            Some(Value::String(val)) => Some(Value::String(val.trim_end().into())),
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value> {
+        match v {
+            Value::String(val) => Some(Value::String(val.trim_end().into())),
             _ => None,
         }
     }
 }
 
-type TrimEnd = CachedArgs<TrimEndEv>;
+type TrimEnd = CachedArgs1<TrimEndEv>;
 
 #[derive(Debug, Default)]
 struct ReplaceEv;
@@ -201,14 +204,14 @@ type Replace = CachedArgs<ReplaceEv>;
 #[derive(Debug, Default)]
 struct DirnameEv;
 
-impl<R: Rt, E: UserEvent> EvalCached<R, E> for DirnameEv {
+impl<R: Rt, E: UserEvent> EvalCached1<R, E> for DirnameEv {
     const NAME: &str = "str_dirname";
     const NEEDS_CALLSITE: bool = false;
 
-    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        match &from.0[0] {
-            Some(Value::String(path)) => match Path::dirname(path) {
-                None if path != "/" => Some(Value::String(literal!("/"))),
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value> {
+        match v {
+            Value::String(path) => match Path::dirname(&path) {
+                None if &*path != "/" => Some(Value::String(literal!("/"))),
                 None => Some(Value::Null),
                 Some(dn) => Some(Value::String(dn.into())),
             },
@@ -217,18 +220,18 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for DirnameEv {
     }
 }
 
-type Dirname = CachedArgs<DirnameEv>;
+type Dirname = CachedArgs1<DirnameEv>;
 
 #[derive(Debug, Default)]
 struct BasenameEv;
 
-impl<R: Rt, E: UserEvent> EvalCached<R, E> for BasenameEv {
+impl<R: Rt, E: UserEvent> EvalCached1<R, E> for BasenameEv {
     const NAME: &str = "str_basename";
     const NEEDS_CALLSITE: bool = false;
 
-    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        match &from.0[0] {
-            Some(Value::String(path)) => match Path::basename(path) {
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value> {
+        match v {
+            Value::String(path) => match Path::basename(&path) {
                 None => Some(Value::Null),
                 Some(dn) => Some(Value::String(dn.into())),
             },
@@ -237,7 +240,7 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for BasenameEv {
     }
 }
 
-type Basename = CachedArgs<BasenameEv>;
+type Basename = CachedArgs1<BasenameEv>;
 
 #[derive(Debug, Default)]
 struct StringJoinEv;
@@ -474,6 +477,25 @@ macro_rules! string_split {
 string_split!(StringSplitEv, StringSplit, "str_split", split);
 string_split!(StringRSplitEv, StringRSplit, "str_rsplit", rsplit);
 
+#[derive(Debug, Default)]
+struct StringLinesEv;
+
+impl<R: Rt, E: UserEvent> EvalCached1<R, E> for StringLinesEv {
+    const NAME: &str = "str_lines";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value> {
+        match v {
+            Value::String(s) => Some(Value::Array(ValArray::from_iter(
+                s.lines().map(|s| Value::String(ArcStr::from(s))),
+            ))),
+            _ => None,
+        }
+    }
+}
+
+type StringLines = CachedArgs1<StringLinesEv>;
+
 macro_rules! string_splitn {
     ($name:ident, $final_name:ident, $builtin:literal, $fn:ident) => {
         #[derive(Debug, Default)]
@@ -659,36 +681,36 @@ type StringRSplitOnce = CachedArgs<StringRSplitOnceEv>;
 #[derive(Debug, Default)]
 struct StringToLowerEv;
 
-impl<R: Rt, E: UserEvent> EvalCached<R, E> for StringToLowerEv {
+impl<R: Rt, E: UserEvent> EvalCached1<R, E> for StringToLowerEv {
     const NAME: &str = "str_to_lower";
     const NEEDS_CALLSITE: bool = false;
 
-    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        match &from.0[0] {
-            Some(Value::String(s)) => Some(Value::String(s.to_lowercase().into())),
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value> {
+        match v {
+            Value::String(s) => Some(Value::String(s.to_lowercase().into())),
             _ => None,
         }
     }
 }
 
-type StringToLower = CachedArgs<StringToLowerEv>;
+type StringToLower = CachedArgs1<StringToLowerEv>;
 
 #[derive(Debug, Default)]
 struct StringToUpperEv;
 
-impl<R: Rt, E: UserEvent> EvalCached<R, E> for StringToUpperEv {
+impl<R: Rt, E: UserEvent> EvalCached1<R, E> for StringToUpperEv {
     const NAME: &str = "str_to_upper";
     const NEEDS_CALLSITE: bool = false;
 
-    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        match &from.0[0] {
-            Some(Value::String(s)) => Some(Value::String(s.to_uppercase().into())),
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value> {
+        match v {
+            Value::String(s) => Some(Value::String(s.to_uppercase().into())),
             _ => None,
         }
     }
 }
 
-type StringToUpper = CachedArgs<StringToUpperEv>;
+type StringToUpper = CachedArgs1<StringToUpperEv>;
 
 #[derive(Debug, Default)]
 struct SprintfEv {
@@ -726,19 +748,19 @@ type Sprintf = CachedArgs<SprintfEv>;
 #[derive(Debug, Default)]
 struct LenEv;
 
-impl<R: Rt, E: UserEvent> EvalCached<R, E> for LenEv {
+impl<R: Rt, E: UserEvent> EvalCached1<R, E> for LenEv {
     const NAME: &str = "str_len";
     const NEEDS_CALLSITE: bool = false;
 
-    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
-        match &from.0[0] {
-            Some(Value::String(s)) => Some(Value::I64(s.len() as i64)),
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, v: Value) -> Option<Value> {
+        match v {
+            Value::String(s) => Some(Value::I64(s.len() as i64)),
             _ => None,
         }
     }
 }
 
-type Len = CachedArgs<LenEv>;
+type Len = CachedArgs1<LenEv>;
 
 #[derive(Debug, Default)]
 struct SubEv(String);
@@ -769,6 +791,115 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for SubEv {
 
 type Sub = CachedArgs<SubEv>;
 
+#[derive(Debug, Default)]
+struct CharAtEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for CharAtEv {
+    const NAME: &str = "str_char_at";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match &from.0[..] {
+            [Some(Value::String(s)), Some(Value::I64(i))] => {
+                let g = usize::try_from(*i).ok().and_then(|i| s.graphemes(true).nth(i));
+                Some(match g {
+                    Some(g) => Value::String(ArcStr::from(g)),
+                    None => errf!(literal!("CharAtError"), "index {i} out of range"),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+type CharAt = CachedArgs<CharAtEv>;
+
+#[derive(Debug, Default)]
+struct SubstringEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for SubstringEv {
+    const NAME: &str = "str_substring";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        static TAG: ArcStr = literal!("SubstringError");
+        match &from.0[..] {
+            [Some(Value::String(s)), Some(Value::I64(start)), Some(Value::I64(end))] => {
+                if *start < 0 || *end < *start {
+                    return Some(errf!(TAG, "invalid range {start}..{end}"));
+                }
+                let (start, end) = (*start as usize, *end as usize);
+                // byte offsets of each grapheme boundary, including the end
+                let mut bounds = s
+                    .grapheme_indices(true)
+                    .map(|(i, _)| i)
+                    .chain(std::iter::once(s.len()));
+                let i = bounds.nth(start);
+                let j = if end == start { i } else { bounds.nth(end - start - 1) };
+                Some(match (i, j) {
+                    (Some(i), Some(j)) => Value::String(ArcStr::from(&s[i..j])),
+                    _ => errf!(TAG, "range {start}..{end} out of range"),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+type Substring = CachedArgs<SubstringEv>;
+
+#[derive(Debug, Default)]
+struct IndexOfEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for IndexOfEv {
+    const NAME: &str = "str_index_of";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match &from.0[..] {
+            [Some(Value::String(s)), Some(Value::String(needle))] => {
+                // only matches that start on a grapheme boundary count
+                let i = s
+                    .grapheme_indices(true)
+                    .map(|(i, _)| i)
+                    .chain(std::iter::once(s.len()))
+                    .position(|i| s[i..].starts_with(&**needle));
+                Some(match i {
+                    Some(i) => Value::I64(i as i64),
+                    None => Value::Null,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+type IndexOf = CachedArgs<IndexOfEv>;
+
+#[derive(Debug, Default)]
+struct CountEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for CountEv {
+    const NAME: &str = "str_count";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match &from.0[..] {
+            [Some(Value::String(_)), Some(Value::String(needle))]
+                if needle.is_empty() =>
+            {
+                Some(Value::I64(0))
+            }
+            [Some(Value::String(s)), Some(Value::String(needle))] => {
+                Some(Value::I64(s.matches(&**needle).count() as i64))
+            }
+            _ => None,
+        }
+    }
+}
+
+type Count = CachedArgs<CountEv>;
+
 #[derive(Debug, Default)]
 struct ParseEv {
     cast_typ: Option<Type>,
@@ -819,7 +950,7 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for ParseEv {
             _ => return None,
         };
         Some(match &self.cast_typ {
-            Some(typ) => typ.cast_value(&ctx.env, raw),
+            Some(typ) => ctx.cast_value(typ, raw),
             None => errf!("TypeError", "parse requires a concrete type annotation"),
         })
     }
@@ -846,6 +977,7 @@ graphix_derive::defpackage! {
         StringUnescape,
         StringSplit,
         StringRSplit,
+        StringLines,
         StringSplitN,
         StringRSplitN,
         StringSplitOnce,
@@ -857,6 +989,10 @@ graphix_derive::defpackage! {
         Sprintf,
         Len,
         Sub,
+        CharAt,
+        Substring,
+        IndexOf,
+        Count,
         Parse,
     ],
 }
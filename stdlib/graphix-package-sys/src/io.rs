@@ -1,7 +1,7 @@
 use arcstr::ArcStr;
 use bytes::Bytes;
-use graphix_compiler::errf;
-use graphix_package_core::{CachedArgsAsync, CachedVals, EvalCachedAsync};
+use graphix_compiler::{errf, ExecCtx, Rt, UserEvent};
+use graphix_package_core::{is_cancelled, CachedArgsAsync, CachedVals, EvalCachedAsync};
 use netidx_value::{PBytes, Value};
 use std::sync::Arc;
 use tokio::{
@@ -191,6 +191,15 @@ impl EvalCachedAsync for IoStdinEv {
     const NEEDS_CALLSITE: bool = false;
     type Args = ();
 
+    fn map_value<R: Rt, E: UserEvent>(
+        &mut self,
+        _ctx: &mut ExecCtx<R, E>,
+        v: Value,
+    ) -> Option<Value> {
+        // there is no error in the type to report the cancellation with
+        (!is_cancelled(&v)).then_some(v)
+    }
+
     fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
         cached.0.get(0)?.as_ref()?;
         Some(())
@@ -211,6 +220,15 @@ impl EvalCachedAsync for IoStdoutEv {
     const NEEDS_CALLSITE: bool = false;
     type Args = ();
 
+    fn map_value<R: Rt, E: UserEvent>(
+        &mut self,
+        _ctx: &mut ExecCtx<R, E>,
+        v: Value,
+    ) -> Option<Value> {
+        // there is no error in the type to report the cancellation with
+        (!is_cancelled(&v)).then_some(v)
+    }
+
     fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
         cached.0.get(0)?.as_ref()?;
         Some(())
@@ -231,6 +249,15 @@ impl EvalCachedAsync for IoStderrEv {
     const NEEDS_CALLSITE: bool = false;
     type Args = ();
 
+    fn map_value<R: Rt, E: UserEvent>(
+        &mut self,
+        _ctx: &mut ExecCtx<R, E>,
+        v: Value,
+    ) -> Option<Value> {
+        // there is no error in the type to report the cancellation with
+        (!is_cancelled(&v)).then_some(v)
+    }
+
     fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
         cached.0.get(0)?.as_ref()?;
         Some(())
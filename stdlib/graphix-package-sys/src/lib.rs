@@ -401,6 +401,28 @@ impl<R: Rt, E: UserEvent> EvalCached<R, E> for JoinPathEv {
 
 pub(crate) type JoinPath = CachedArgs<JoinPathEv>;
 
+// ── Env ───────────────────────────────────────────────────────
+
+#[derive(Debug, Default)]
+pub(crate) struct GetEnvEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for GetEnvEv {
+    const NAME: &str = "sys_env";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match from.0.first()? {
+            Some(Value::String(name)) => match std::env::var(&**name) {
+                Ok(v) => Some(Value::String(ArcStr::from(v))),
+                Err(_) => Some(Value::Null),
+            },
+            _ => None,
+        }
+    }
+}
+
+pub(crate) type GetEnv = CachedArgs<GetEnvEv>;
+
 // ── Args ──────────────────────────────────────────────────────
 
 #[derive(Debug)]
@@ -455,6 +477,7 @@ graphix_derive::defpackage! {
         GxTempDir,
         TempDirPath,
         JoinPath,
+        GetEnv,
         metadata::IsFile,
         metadata::IsDir,
         metadata::Metadata,
@@ -493,14 +516,20 @@ graphix_derive::defpackage! {
         tls::TlsAccept,
         net::Write,
         net::Subscribe,
+        net::Status,
         net::RpcCall,
         net::List,
         net::ListTable,
+        net::Glob,
         net::Publish as net::Publish<GXRt<X>, X::UserEvent>,
+        net::OnWrite,
         net::PublishRpc as net::PublishRpc<GXRt<X>, X::UserEvent>,
         time::AfterIdle,
         time::Timer,
         time::Now,
+        time::ParseDuration,
+        time::FormatDuration,
+        time::Between,
         dirs_mod::HomeDir,
         dirs_mod::CacheDir,
         dirs_mod::ConfigDir,
@@ -1,6 +1,7 @@
 use anyhow::{anyhow, bail, Result};
 use arcstr::{literal, ArcStr};
 use compact_str::format_compact;
+use fxhash::{FxHashMap, FxHashSet};
 use graphix_compiler::{
     deref_typ, err, errf,
     expr::ExprId,
@@ -12,7 +13,7 @@ use graphix_compiler::{
 use graphix_package_core::{arity1, arity2, extract_cast_type, CachedVals};
 use netidx::{
     path::Path,
-    publisher::{Typ, Val},
+    publisher::{Id, Typ},
     subscriber::{self, Dval, UpdatesFlags, Value},
 };
 use netidx_core::utils::Either;
@@ -229,7 +230,7 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Subscribe {
             event.netidx.get(&dv.id()).map(|e| match e {
                 subscriber::Event::Unsubscribed => Value::error(literal!("unsubscribed")),
                 subscriber::Event::Update(v) => match &self.cast_typ {
-                    Some(typ) => typ.cast_value(&ctx.env, v.clone()),
+                    Some(typ) => ctx.cast_value(typ, v.clone()),
                     None => v.clone(),
                 },
             })
@@ -268,6 +269,118 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Subscribe {
     }
 }
 
+fn status_value(e: &subscriber::Event) -> (bool, Value) {
+    match e {
+        subscriber::Event::Update(_) => (true, Value::String(literal!("Connected"))),
+        subscriber::Event::Unsubscribed => {
+            let e = (literal!("Disconnected"), literal!("unsubscribed"));
+            (false, e.into())
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Status {
+    args: CachedVals,
+    cur: Option<(Path, Dval)>,
+    connected: Option<bool>,
+    top_id: ExprId,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Status {
+    const NAME: &str = "sys_net_status";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        Ok(Box::new(Status {
+            args: CachedVals::new(from),
+            cur: None,
+            connected: None,
+            top_id,
+        }))
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Status {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        static ERR_TAG: ArcStr = literal!("StatusError");
+        let mut up = [false; 1];
+        self.args.update_diff(&mut up, ctx, from, event);
+        let (path, path_up) = arity1!(self.args.0, &up);
+        let mut last = None;
+        match (path, path_up) {
+            (Some(_), false) | (None, false) => (),
+            (None, true) => {
+                if let Some((path, dv)) = self.cur.take() {
+                    ctx.rt.unsubscribe(path, dv, self.top_id)
+                }
+                self.connected = None;
+                return None;
+            }
+            (Some(Value::String(path)), true)
+                if self.cur.as_ref().map(|(p, _)| &**p) != Some(&*path) =>
+            {
+                if let Some((path, dv)) = self.cur.take() {
+                    ctx.rt.unsubscribe(path, dv, self.top_id)
+                }
+                self.connected = None;
+                let path = Path::from(path);
+                if !Path::is_absolute(&path) {
+                    return Some(err!(ERR_TAG, "expected absolute path"));
+                }
+                let dval = ctx.rt.subscribe(
+                    UpdatesFlags::BEGIN_WITH_LAST,
+                    path.clone(),
+                    self.top_id,
+                );
+                // report the current state right away
+                last = Some(dval.last());
+                self.cur = Some((path, dval));
+            }
+            (Some(Value::String(_)), true) => (),
+            (Some(v), true) => {
+                return Some(errf!(ERR_TAG, "invalid path {v}, expected string"))
+            }
+        }
+        let (_, dv) = self.cur.as_ref()?;
+        let (connected, v) = match event.netidx.get(&dv.id()) {
+            Some(e) => status_value(e),
+            None => status_value(last.as_ref()?),
+        };
+        if self.connected == Some(connected) {
+            return None;
+        }
+        self.connected = Some(connected);
+        Some(v)
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some((path, dv)) = self.cur.take() {
+            ctx.rt.unsubscribe(path, dv, self.top_id)
+        }
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.args.clear();
+        self.connected = None;
+        if let Some((path, dv)) = self.cur.take() {
+            ctx.rt.unsubscribe(path, dv, self.top_id);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct RpcCall {
     args: CachedVals,
@@ -341,7 +454,7 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for RpcCall {
             ((None, _), (_, _)) | ((_, None), (_, _)) | ((_, _), (false, false)) => (),
         }
         event.variables.get(&self.id).map(|v| match &self.cast_typ {
-            Some(typ) => typ.cast_value(&ctx.env, v.clone()),
+            Some(typ) => ctx.cast_value(typ, v.clone()),
             None => v.clone(),
         })
     }
@@ -485,6 +598,13 @@ list!(
     "fn(?#update:Any, string) -> Result<Table, `ListError(string)>"
 );
 
+list!(
+    Glob,
+    "sys_net_glob",
+    glob,
+    "fn(?#update:Any, string) -> Result<Array<string>, `ListError(string)>"
+);
+
 fn extract_publish_cast_type(resolved: Option<&FnType>) -> Option<Type> {
     let resolved = resolved?;
     resolved.args.first().and_then(|a| match &a.typ {
@@ -503,7 +623,7 @@ fn extract_publish_cast_type(resolved: Option<&FnType>) -> Option<Type> {
 #[derive(Debug)]
 pub(crate) struct Publish<R: Rt, E: UserEvent> {
     args: CachedVals,
-    current: Option<(Path, Val)>,
+    current: Option<(Path, Id)>,
     top_id: ExprId,
     x: BindId,
     pid: BindId,
@@ -524,7 +644,7 @@ impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Publish<R, E> {
         top_id: ExprId,
     ) -> Result<Box<dyn Apply<R, E>>> {
         match from {
-            [_, _, _] => {
+            [_, _, _, _] => {
                 let typ = resolved.unwrap_or(typ);
                 let scope =
                     scope.append(&format_compact!("fn{}", LambdaId::new().inner()));
@@ -552,7 +672,7 @@ impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Publish<R, E> {
                     cast_typ: extract_publish_cast_type(resolved),
                 }))
             }
-            _ => bail!("expected three arguments"),
+            _ => bail!("expected four arguments"),
         }
     }
 }
@@ -567,7 +687,10 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Publish<R, E> {
         macro_rules! publish {
             ($path:expr, $v:expr) => {{
                 let path = Path::from($path.clone());
-                match ctx.rt.publish(path.clone(), $v.clone(), self.top_id) {
+                let typ = self.cast_typ.clone();
+                let replace = matches!(self.args.0[1], Some(Value::Bool(true)));
+                match ctx.rt.publish(path.clone(), $v.clone(), typ, replace, self.top_id)
+                {
                     Err(e) => {
                         let msg: ArcStr = format_compact!("{e:?}").as_str().into();
                         let e: Value = (literal!("PublishError"), msg).into();
@@ -579,7 +702,7 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Publish<R, E> {
                 }
             }};
         }
-        let mut up = [false; 3];
+        let mut up = [false; 4];
         self.args.update_diff(&mut up, ctx, from, event);
         if up[0] {
             if let Some(v) = self.args.0[0].clone() {
@@ -587,7 +710,7 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Publish<R, E> {
                 event.variables.insert(self.pid, v);
             }
         }
-        match (&up[1..], &self.args.0[1..]) {
+        match (&up[2..], &self.args.0[2..]) {
             ([true, _], [Some(Value::String(path)), Some(v)])
                 if self.current.as_ref().map(|(p, _)| &**p != path).unwrap_or(true) =>
             {
@@ -597,18 +720,23 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Publish<R, E> {
                 publish!(path, v)
             }
             ([_, true], [Some(Value::String(path)), Some(v)]) => match &self.current {
-                Some((_, val)) => ctx.rt.update(val, v.clone()),
+                Some((_, id)) => ctx.rt.update(*id, v.clone()),
                 None => publish!(path, v),
             },
             _ => (),
         }
         let mut reply = None;
-        if let Some((_, val)) = &self.current {
-            if let Some(req) = event.writes.remove(&val.id()) {
+        if let Some((path, id)) = &self.current {
+            if let Some(req) = event.writes.remove(id) {
                 let v = match &self.cast_typ {
-                    Some(typ) => typ.cast_value(&ctx.env, req.value.clone()),
+                    Some(typ) => ctx.cast_value(typ, req.value.clone()),
                     None => req.value.clone(),
                 };
+                if let Some(ids) = ctx.libstate.get_mut::<WriteListeners>() {
+                    for id in ids.0.get(path).into_iter().flatten() {
+                        ctx.rt.set_var(*id, req.value.clone())
+                    }
+                }
                 ctx.cached.insert(self.x, v.clone());
                 event.variables.insert(self.x, v);
                 reply = req.send_result;
@@ -645,8 +773,8 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Publish<R, E> {
     }
 
     fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
-        if let Some((_, val)) = self.current.take() {
-            ctx.rt.unpublish(val, self.top_id);
+        if let Some((_, id)) = self.current.take() {
+            ctx.rt.unpublish(id, self.top_id);
         }
         ctx.cached.remove(&self.pid);
         ctx.cached.remove(&self.x);
@@ -655,14 +783,136 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Publish<R, E> {
     }
 
     fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
-        if let Some((_, val)) = self.current.take() {
-            ctx.rt.unpublish(val, self.top_id);
+        if let Some((_, id)) = self.current.take() {
+            ctx.rt.unpublish(id, self.top_id);
         }
         self.args.clear();
         self.on_write.sleep(ctx);
     }
 }
 
+/// The variables of every `on_write` listening to each published path
+#[derive(Debug, Default)]
+struct WriteListeners(FxHashMap<Path, FxHashSet<BindId>>);
+
+impl WriteListeners {
+    fn remove(&mut self, path: &Path, id: BindId) {
+        if let Some(ids) = self.0.get_mut(path) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.0.remove(path);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct OnWrite {
+    args: CachedVals,
+    current: Option<Path>,
+    top_id: ExprId,
+    id: BindId,
+    cast_typ: Option<Type>,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for OnWrite {
+    const NAME: &str = "sys_net_on_write";
+    const NEEDS_CALLSITE: bool = true;
+
+    fn init<'a, 'b, 'c, 'd>(
+        ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        let id = BindId::new();
+        ctx.rt.ref_var(id, top_id);
+        Ok(Box::new(OnWrite {
+            args: CachedVals::new(from),
+            current: None,
+            top_id,
+            id,
+            cast_typ: extract_cast_type(resolved),
+        }))
+    }
+}
+
+impl OnWrite {
+    fn stop<R: Rt, E: UserEvent>(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(path) = self.current.take() {
+            ctx.libstate.get_or_default::<WriteListeners>().remove(&path, self.id)
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for OnWrite {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        let mut up = [false; 1];
+        self.args.update_diff(&mut up, ctx, from, event);
+        let (path, path_up) = arity1!(self.args.0, &up);
+        match (path, path_up) {
+            (_, false) => (),
+            (None, true) => self.stop(ctx),
+            (Some(path), true) => match as_path(path.clone()) {
+                None => {
+                    self.stop(ctx);
+                    let e = errf!(literal!("OnWriteError"), "invalid path {path:?}");
+                    return Some(Value::Error(TArc::new(e)));
+                }
+                Some(path) if self.current.as_ref() == Some(&path) => (),
+                Some(path) => {
+                    self.stop(ctx);
+                    let listeners = ctx.libstate.get_or_default::<WriteListeners>();
+                    listeners.0.entry(path.clone()).or_default().insert(self.id);
+                    self.current = Some(path);
+                }
+            },
+        }
+        event.variables.get(&self.id).map(|v| match &self.cast_typ {
+            Some(typ) => ctx.cast_value(typ, v.clone()),
+            None => v.clone(),
+        })
+    }
+
+    fn typecheck(
+        &mut self,
+        _ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        match phase {
+            TypecheckPhase::Lambda => Ok(()),
+            TypecheckPhase::CallSite(resolved) => {
+                self.cast_typ = extract_cast_type(Some(resolved));
+                if self.cast_typ.is_none() {
+                    bail!("sys::net::on_write requires a concrete return type")
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.stop(ctx);
+        ctx.rt.unref_var(self.id, self.top_id)
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.stop(ctx);
+        ctx.rt.unref_var(self.id, self.top_id);
+        self.id = BindId::new();
+        ctx.rt.ref_var(self.id, self.top_id);
+        self.args.clear()
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct PublishRpc<R: Rt, E: UserEvent> {
     args: CachedVals,
@@ -920,7 +1170,7 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for PublishRpc<R, E> {
                         Value::Array(ValArray::from([Value::String(n), v]))
                     }));
                 let args = match &self.cast_typ {
-                    Some(typ) => typ.cast_value(&ctx.env, Value::Array(args)),
+                    Some(typ) => ctx.cast_value(typ, Value::Array(args)),
                     None => Value::Array(args),
                 };
                 ctx.cached.insert(self.x, args.clone());
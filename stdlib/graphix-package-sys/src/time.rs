@@ -1,11 +1,11 @@
 use anyhow::{bail, Result};
 use arcstr::literal;
-use chrono::Utc;
+use chrono::{DateTime, Timelike, Utc};
 use graphix_compiler::{
-    err, expr::ExprId, typ::FnType, Apply, BindId, BuiltIn, Event, ExecCtx, Node, Rt,
-    Scope, UserEvent,
+    err, errf, expr::ExprId, typ::FnType, Apply, BindId, BuiltIn, Event, ExecCtx, Node,
+    Rt, Scope, UserEvent,
 };
-use graphix_package_core::{arity2, CachedVals};
+use graphix_package_core::{arity2, CachedArgs, CachedVals, EvalCached};
 use netidx::{publisher::FromValue, subscriber::Value};
 use std::{ops::SubAssign, time::Duration};
 
@@ -286,3 +286,290 @@ impl<R: Rt, E: UserEvent> Apply<R, E> for Now {
     fn delete(&mut self, _ctx: &mut ExecCtx<R, E>) {}
     fn sleep(&mut self, _ctx: &mut ExecCtx<R, E>) {}
 }
+
+const DAY: Duration = Duration::from_secs(86_400);
+
+/// Given the current time and a daily window [start, end) expressed as
+/// offsets from midnight UTC, return whether now is inside the window
+/// and how long it is until the next transition. If end is before start
+/// the window wraps past midnight. An empty window never transitions.
+fn window_state(
+    now: DateTime<Utc>,
+    start: Duration,
+    end: Duration,
+) -> (bool, Option<Duration>) {
+    if start == end {
+        return (false, None);
+    }
+    let tod = Duration::new(
+        now.num_seconds_from_midnight() as u64,
+        now.nanosecond() % 1_000_000_000,
+    );
+    let inside =
+        if start < end { start <= tod && tod < end } else { tod >= start || tod < end };
+    let next = if inside { end } else { start };
+    let wait = if next > tod { next - tod } else { DAY - tod + next };
+    (inside, Some(wait))
+}
+
+#[derive(Debug)]
+pub(crate) struct Between {
+    args: CachedVals,
+    window: Option<(Duration, Duration)>,
+    inside: Option<bool>,
+    id: Option<BindId>,
+    eid: ExprId,
+}
+
+impl<R: Rt, E: UserEvent> BuiltIn<R, E> for Between {
+    const NAME: &str = "sys_time_between";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn init<'a, 'b, 'c, 'd>(
+        _ctx: &'a mut ExecCtx<R, E>,
+        _typ: &'a FnType,
+        _resolved: Option<&'d FnType>,
+        _scope: &'b Scope,
+        from: &'c [Node<R, E>],
+        top_id: ExprId,
+    ) -> Result<Box<dyn Apply<R, E>>> {
+        Ok(Box::new(Self {
+            args: CachedVals::new(from),
+            window: None,
+            inside: None,
+            id: None,
+            eid: top_id,
+        }))
+    }
+}
+
+impl Between {
+    fn cancel<R: Rt, E: UserEvent>(&mut self, ctx: &mut ExecCtx<R, E>) {
+        if let Some(id) = self.id.take() {
+            ctx.rt.unref_var(id, self.eid);
+        }
+    }
+
+    /// compute the current state, arm a timer for the next transition,
+    /// and return the state if it changed
+    fn step<R: Rt, E: UserEvent>(&mut self, ctx: &mut ExecCtx<R, E>) -> Option<Value> {
+        let (start, end) = self.window?;
+        let (inside, wait) = window_state(Utc::now(), start, end);
+        if let Some(wait) = wait {
+            let id = BindId::new();
+            self.id = Some(id);
+            ctx.rt.ref_var(id, self.eid);
+            ctx.rt.set_timer(id, wait);
+        }
+        if self.inside == Some(inside) {
+            None
+        } else {
+            self.inside = Some(inside);
+            Some(Value::Bool(inside))
+        }
+    }
+}
+
+impl<R: Rt, E: UserEvent> Apply<R, E> for Between {
+    fn update(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        from: &mut [Node<R, E>],
+        event: &mut Event<E>,
+    ) -> Option<Value> {
+        if self.args.update(ctx, from, event) {
+            self.cancel(ctx);
+            self.window = None;
+            self.inside = None;
+            let (start, end) = match (&self.args.0[0], &self.args.0[1]) {
+                (Some(start), Some(end)) => (start.clone(), end.clone()),
+                _ => return None,
+            };
+            match (start.cast_to::<Duration>(), end.cast_to::<Duration>()) {
+                (Ok(start), Ok(end)) if start < DAY && end < DAY => {
+                    self.window = Some((start, end))
+                }
+                _ => {
+                    return Some(err!(
+                        literal!("BetweenError"),
+                        "between(start, end): expected durations less than 1 day"
+                    ))
+                }
+            }
+            return self.step(ctx);
+        }
+        match self.id {
+            Some(id) if event.variables.contains_key(&id) => {
+                self.cancel(ctx);
+                self.step(ctx)
+            }
+            Some(_) | None => None,
+        }
+    }
+
+    fn delete(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx)
+    }
+
+    fn sleep(&mut self, ctx: &mut ExecCtx<R, E>) {
+        self.cancel(ctx);
+        self.args.clear();
+        self.window = None;
+        self.inside = None;
+    }
+}
+
+const UNITS: &[(&str, u128)] = &[
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+/// parse a compound duration such as "1h30m15s" or "500ms"
+fn parse_duration(s: &str) -> Result<Duration> {
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        bail!("empty duration")
+    }
+    let mut total: u128 = 0;
+    while !rest.is_empty() {
+        let i = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if i == 0 {
+            bail!("expected a number at {rest:?}")
+        }
+        let n: u128 = rest[..i].parse()?;
+        rest = &rest[i..];
+        let j = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        let unit = &rest[..j];
+        rest = &rest[j..];
+        let scale = match UNITS.iter().find(|(u, _)| *u == unit) {
+            Some((_, scale)) => *scale,
+            None if unit.is_empty() => bail!("missing unit after {n}"),
+            None => {
+                bail!("unknown unit {unit:?}, expected one of d, h, m, s, ms, us, ns")
+            }
+        };
+        total = n
+            .checked_mul(scale)
+            .and_then(|n| total.checked_add(n))
+            .ok_or_else(|| anyhow::anyhow!("duration {s:?} is too large"))?;
+    }
+    let secs = u64::try_from(total / 1_000_000_000)?;
+    Ok(Duration::new(secs, (total % 1_000_000_000) as u32))
+}
+
+/// format a duration in the compact form accepted by parse_duration
+fn format_duration(d: Duration) -> String {
+    let mut total = d.as_nanos();
+    if total == 0 {
+        return "0s".into();
+    }
+    let mut res = String::new();
+    for (unit, scale) in UNITS {
+        let n = total / scale;
+        if n > 0 {
+            res.push_str(&format!("{n}{unit}"));
+            total %= scale;
+        }
+    }
+    res
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ParseDurationEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for ParseDurationEv {
+    const NAME: &str = "sys_time_parse_duration";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match from.0.first()? {
+            Some(Value::String(s)) => match parse_duration(s) {
+                Ok(d) => Some(Value::from(d)),
+                Err(e) => Some(errf!(literal!("DurationError"), "{s:?}: {e}")),
+            },
+            _ => None,
+        }
+    }
+}
+
+pub(crate) type ParseDuration = CachedArgs<ParseDurationEv>;
+
+#[derive(Debug, Default)]
+pub(crate) struct FormatDurationEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for FormatDurationEv {
+    const NAME: &str = "sys_time_format_duration";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        match from.0.first()? {
+            Some(Value::Duration(d)) => Some(Value::String(format_duration(**d).into())),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) type FormatDuration = CachedArgs<FormatDurationEv>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(d: u32, h: u32, m: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, d, h, m, 0).unwrap()
+    }
+
+    fn hm(h: u64, m: u64) -> Duration {
+        Duration::from_secs(h * 3600 + m * 60)
+    }
+
+    #[test]
+    fn within_day() {
+        let (start, end) = (hm(9, 0), hm(17, 0));
+        assert_eq!(window_state(at(1, 8, 0), start, end), (false, Some(hm(1, 0))));
+        assert_eq!(window_state(at(1, 9, 0), start, end), (true, Some(hm(8, 0))));
+        assert_eq!(window_state(at(1, 16, 30), start, end), (true, Some(hm(0, 30))));
+        assert_eq!(window_state(at(1, 17, 0), start, end), (false, Some(hm(16, 0))));
+    }
+
+    #[test]
+    fn wraps_midnight() {
+        let (start, end) = (hm(22, 0), hm(6, 0));
+        assert_eq!(window_state(at(1, 21, 0), start, end), (false, Some(hm(1, 0))));
+        assert_eq!(window_state(at(1, 23, 0), start, end), (true, Some(hm(7, 0))));
+        assert_eq!(window_state(at(2, 0, 0), start, end), (true, Some(hm(6, 0))));
+        assert_eq!(window_state(at(2, 6, 0), start, end), (false, Some(hm(16, 0))));
+    }
+
+    #[test]
+    fn rearms_across_days() {
+        // follow the transitions for a few days and check that each
+        // one lands exactly on a boundary of the next day's window
+        let (start, end) = (hm(9, 0), hm(17, 0));
+        let mut now = at(1, 12, 0);
+        let mut states = vec![];
+        for _ in 0..6 {
+            let (inside, wait) = window_state(now, start, end);
+            states.push((inside, now));
+            now = now + chrono::Duration::from_std(wait.unwrap()).unwrap();
+        }
+        assert_eq!(
+            states,
+            vec![
+                (true, at(1, 12, 0)),
+                (false, at(1, 17, 0)),
+                (true, at(2, 9, 0)),
+                (false, at(2, 17, 0)),
+                (true, at(3, 9, 0)),
+                (false, at(3, 17, 0)),
+            ]
+        );
+        assert_eq!(window_state(at(1, 12, 0), start, start), (false, None));
+    }
+}
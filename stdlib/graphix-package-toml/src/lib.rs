@@ -113,6 +113,41 @@ fn value_to_toml(value: &Value) -> Result<toml::Value, String> {
     }
 }
 
+fn parse(s: &str) -> Value {
+    match toml::from_str::<toml::Value>(s) {
+        Ok(t) => toml_to_value(t),
+        Err(e) => errf!("TomlErr", "{e}"),
+    }
+}
+
+fn typecheck_cast(
+    cast_typ: &mut Option<Type>,
+    phase: TypecheckPhase<'_>,
+    name: &str,
+) -> Result<()> {
+    match phase {
+        TypecheckPhase::Lambda => Ok(()),
+        TypecheckPhase::CallSite(resolved) => {
+            *cast_typ = extract_cast_type(Some(resolved));
+            if cast_typ.is_none() {
+                bail!("{name} requires a concrete return type")
+            }
+            Ok(())
+        }
+    }
+}
+
+fn cast<R: Rt, E: UserEvent>(
+    cast_typ: &Option<Type>,
+    ctx: &mut ExecCtx<R, E>,
+    v: Value,
+) -> Value {
+    match cast_typ {
+        Some(typ) => ctx.cast_value(typ, v),
+        None => errf!("TomlErr", "no concrete return type found"),
+    }
+}
+
 // ── TomlRead (async — handles string, bytes, and stream) ────────
 
 #[derive(Debug)]
@@ -149,16 +184,7 @@ impl EvalCachedAsync for TomlReadEv {
         _from: &mut [Node<R, E>],
         phase: TypecheckPhase<'_>,
     ) -> Result<()> {
-        match phase {
-            TypecheckPhase::Lambda => Ok(()),
-            TypecheckPhase::CallSite(resolved) => {
-                self.cast_typ = extract_cast_type(Some(resolved));
-                if self.cast_typ.is_none() {
-                    bail!("toml::read requires a concrete return type")
-                }
-                Ok(())
-            }
-        }
+        typecheck_cast(&mut self.cast_typ, phase, "toml::read")
     }
 
     fn map_value<R: Rt, E: UserEvent>(
@@ -166,10 +192,7 @@ impl EvalCachedAsync for TomlReadEv {
         ctx: &mut ExecCtx<R, E>,
         v: Value,
     ) -> Option<Value> {
-        match &self.cast_typ {
-            Some(typ) => Some(typ.cast_value(&ctx.env, v)),
-            None => Some(errf!("TomlErr", "no concrete return type found")),
-        }
+        Some(cast(&self.cast_typ, ctx, v))
     }
 
     fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
@@ -185,20 +208,11 @@ impl EvalCachedAsync for TomlReadEv {
     fn eval(input: Self::Args) -> impl Future<Output = Value> + Send {
         async move {
             match input {
-                ReadInput::Str(s) => match toml::from_str::<toml::Value>(&s) {
-                    Ok(t) => toml_to_value(t),
-                    Err(e) => errf!("TomlErr", "{e}"),
+                ReadInput::Str(s) => parse(&s),
+                ReadInput::Bytes(b) => match std::str::from_utf8(&b) {
+                    Ok(s) => parse(s),
+                    Err(e) => errf!("TomlErr", "invalid UTF-8: {e}"),
                 },
-                ReadInput::Bytes(b) => {
-                    let s = match std::str::from_utf8(&b) {
-                        Ok(s) => s,
-                        Err(e) => return errf!("TomlErr", "invalid UTF-8: {e}"),
-                    };
-                    match toml::from_str::<toml::Value>(s) {
-                        Ok(t) => toml_to_value(t),
-                        Err(e) => errf!("TomlErr", "{e}"),
-                    }
-                }
                 ReadInput::Stream(stream) => {
                     let mut guard = stream.lock().await;
                     let s = match guard.as_mut() {
@@ -209,13 +223,9 @@ impl EvalCachedAsync for TomlReadEv {
                     if let Err(e) = s.read_to_end(&mut buf).await {
                         return errf!("IOErr", "read failed: {e}");
                     }
-                    let text = match std::str::from_utf8(&buf) {
-                        Ok(s) => s,
-                        Err(e) => return errf!("TomlErr", "invalid UTF-8: {e}"),
-                    };
-                    match toml::from_str::<toml::Value>(text) {
-                        Ok(t) => toml_to_value(t),
-                        Err(e) => errf!("TomlErr", "{e}"),
+                    match std::str::from_utf8(&buf) {
+                        Ok(s) => parse(s),
+                        Err(e) => errf!("TomlErr", "invalid UTF-8: {e}"),
                     }
                 }
             }
@@ -225,6 +235,62 @@ impl EvalCachedAsync for TomlReadEv {
 
 type TomlRead = CachedArgsAsync<TomlReadEv>;
 
+// ── TomlLoad (async — reads and parses a file) ───────────────────
+
+#[derive(Debug, Default)]
+struct TomlLoadEv {
+    cast_typ: Option<Type>,
+}
+
+impl EvalCachedAsync for TomlLoadEv {
+    const NAME: &str = "toml_load";
+    const NEEDS_CALLSITE: bool = true;
+    type Args = ArcStr;
+
+    fn init<R: Rt, E: UserEvent>(
+        _ctx: &mut ExecCtx<R, E>,
+        _typ: &FnType,
+        resolved: Option<&FnType>,
+        _scope: &Scope,
+        _from: &[Node<R, E>],
+        _top_id: graphix_compiler::expr::ExprId,
+    ) -> Self {
+        Self { cast_typ: extract_cast_type(resolved) }
+    }
+
+    fn typecheck<R: Rt, E: UserEvent>(
+        &mut self,
+        _ctx: &mut ExecCtx<R, E>,
+        _from: &mut [Node<R, E>],
+        phase: TypecheckPhase<'_>,
+    ) -> Result<()> {
+        typecheck_cast(&mut self.cast_typ, phase, "toml::load")
+    }
+
+    fn map_value<R: Rt, E: UserEvent>(
+        &mut self,
+        ctx: &mut ExecCtx<R, E>,
+        v: Value,
+    ) -> Option<Value> {
+        Some(cast(&self.cast_typ, ctx, v))
+    }
+
+    fn prepare_args(&mut self, cached: &CachedVals) -> Option<Self::Args> {
+        cached.get::<ArcStr>(0)
+    }
+
+    fn eval(path: Self::Args) -> impl Future<Output = Value> + Send {
+        async move {
+            match tokio::fs::read_to_string(&*path).await {
+                Ok(s) => parse(&s),
+                Err(e) => errf!("IOErr", "could not read {path}: {e}"),
+            }
+        }
+    }
+}
+
+type TomlLoad = CachedArgsAsync<TomlLoadEv>;
+
 // ── TomlWriteStr (sync) ──────────────────────────────────────────
 
 #[derive(Debug, Default)]
@@ -336,6 +402,7 @@ type TomlWriteStream = CachedArgsAsync<TomlWriteStreamEv>;
 graphix_derive::defpackage! {
     builtins => [
         TomlRead,
+        TomlLoad,
         TomlWriteStr,
         TomlWriteBytes,
         TomlWriteStream,
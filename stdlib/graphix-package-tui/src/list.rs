@@ -1,23 +1,89 @@
 use super::{into_borrowed_line, HighlightSpacingV, LineV, StyleV, TuiW, TuiWidget};
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use arcstr::ArcStr;
 use async_trait::async_trait;
 use crossterm::event::Event;
+use fxhash::FxHashMap;
 use graphix_compiler::expr::ExprId;
-use graphix_rt::{GXExt, GXHandle, TRef};
-use netidx::publisher::Value;
+use graphix_rt::{GXExt, GXHandle, Ref, TRef};
+use netidx::publisher::{FromValue, Value};
 use ratatui::{
     layout::Rect,
+    text::Line,
     widgets::{List, ListState},
     Frame,
 };
+use std::mem;
 use tokio::try_join;
 
+struct Row {
+    key: Option<ArcStr>,
+    src: Value,
+    line: Line<'static>,
+}
+
+/// The rows of a list, converted from its items. Only items that are new or
+/// have changed are converted when the items update.
+#[derive(Default)]
+struct Rows {
+    rows: Vec<Row>,
+    /// the indexes of the rows that were converted by the last update
+    changed: Vec<usize>,
+}
+
+impl Rows {
+    /// Replace the rows with items. An old row is reused if it has the same
+    /// value and the same key, or for items without a key if it is at the
+    /// same position. Keys let rows be reused when items are inserted,
+    /// removed, or reordered.
+    fn update(&mut self, items: &Value, keys: Option<&[ArcStr]>) -> Result<()> {
+        let items = match items {
+            Value::Array(a) => a,
+            v => bail!("expected an array of lines not {v}"),
+        };
+        let mut old: Vec<Option<Row>> =
+            mem::take(&mut self.rows).into_iter().map(Some).collect();
+        let by_key: FxHashMap<ArcStr, usize> = old
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| Some((r.as_ref()?.key.clone()?, i)))
+            .collect();
+        self.changed.clear();
+        for (i, src) in items.iter().enumerate() {
+            let key = keys.and_then(|k| k.get(i)).cloned();
+            let j = match &key {
+                Some(k) => by_key.get(k).copied(),
+                None => Some(i),
+            };
+            let reused = j.and_then(|j| old.get_mut(j)).and_then(|r| {
+                let same = r.as_ref().map_or(false, |r| r.key == key && &r.src == src);
+                if same {
+                    r.take()
+                } else {
+                    None
+                }
+            });
+            let row = match reused {
+                Some(row) => row,
+                None => {
+                    let line = LineV::from_value(src.clone())?.0;
+                    self.changed.push(i);
+                    Row { key, src: src.clone(), line }
+                }
+            };
+            self.rows.push(row);
+        }
+        Ok(())
+    }
+}
+
 pub(super) struct ListW<X: GXExt> {
     highlight_spacing: TRef<X, Option<HighlightSpacingV>>,
     highlight_style: TRef<X, Option<StyleV>>,
     highlight_symbol: TRef<X, Option<ArcStr>>,
-    items: TRef<X, Vec<LineV>>,
+    items: Ref<X>,
+    keys: TRef<X, Option<Vec<ArcStr>>>,
+    rows: Rows,
     repeat_highlight_symbol: TRef<X, Option<bool>>,
     scroll: TRef<X, Option<u32>>,
     selected: TRef<X, Option<u32>>,
@@ -27,13 +93,14 @@ pub(super) struct ListW<X: GXExt> {
 
 impl<X: GXExt> ListW<X> {
     pub(super) async fn compile(gx: GXHandle<X>, v: Value) -> Result<TuiW> {
-        let [(_, highlight_spacing), (_, highlight_style), (_, highlight_symbol), (_, items), (_, repeat_highlight_symbol), (_, scroll), (_, selected), (_, style)] =
-            v.cast_to::<[(ArcStr, u64); 8]>().context("list fields")?;
+        let [(_, highlight_spacing), (_, highlight_style), (_, highlight_symbol), (_, items), (_, keys), (_, repeat_highlight_symbol), (_, scroll), (_, selected), (_, style)] =
+            v.cast_to::<[(ArcStr, u64); 9]>().context("list fields")?;
         let (
             highlight_spacing,
             highlight_style,
             highlight_symbol,
             items,
+            keys,
             repeat_highlight_symbol,
             scroll,
             selected,
@@ -43,6 +110,7 @@ impl<X: GXExt> ListW<X> {
             gx.compile_ref(highlight_style),
             gx.compile_ref(highlight_symbol),
             gx.compile_ref(items),
+            gx.compile_ref(keys),
             gx.compile_ref(repeat_highlight_symbol),
             gx.compile_ref(scroll),
             gx.compile_ref(selected),
@@ -55,7 +123,9 @@ impl<X: GXExt> ListW<X> {
                 .context("list tref highlight_style")?,
             highlight_symbol: TRef::new(highlight_symbol)
                 .context("list tref highlight_symbol")?,
-            items: TRef::new(items).context("list tref items")?,
+            items,
+            keys: TRef::new(keys).context("list tref keys")?,
+            rows: Rows::default(),
             repeat_highlight_symbol: TRef::new(repeat_highlight_symbol)
                 .context("list tref repeat_highlight_symbol")?,
             scroll: TRef::new(scroll).context("list tref scroll")?,
@@ -63,6 +133,10 @@ impl<X: GXExt> ListW<X> {
             style: TRef::new(style).context("list tref style")?,
             state: ListState::default(),
         };
+        if let Some(v) = &t.items.last {
+            let keys = t.keys.t.as_ref().and_then(|k| k.as_deref());
+            t.rows.update(v, keys).context("list items")?;
+        }
         if let Some(Some(s)) = t.scroll.t {
             t.state = t.state.with_offset(s as usize);
         }
@@ -85,6 +159,8 @@ impl<X: GXExt> TuiWidget for ListW<X> {
             highlight_style,
             highlight_symbol,
             items,
+            keys,
+            rows,
             repeat_highlight_symbol,
             scroll,
             selected,
@@ -94,7 +170,16 @@ impl<X: GXExt> TuiWidget for ListW<X> {
         highlight_spacing.update(id, &v).context("list update highlight_spacing")?;
         highlight_style.update(id, &v).context("list update highlight_style")?;
         highlight_symbol.update(id, &v).context("list update highlight_symbol")?;
-        items.update(id, &v).context("list update items")?;
+        let keys_up = keys.update(id, &v).context("list update keys")?.is_some();
+        if items.id == id {
+            items.last = Some(v.clone());
+        }
+        if (keys_up || items.id == id)
+            && let Some(items) = &items.last
+        {
+            let keys = keys.t.as_ref().and_then(|k| k.as_deref());
+            rows.update(items, keys).context("list update items")?;
+        }
         repeat_highlight_symbol
             .update(id, &v)
             .context("list update repeat_highlight_symbol")?;
@@ -113,15 +198,16 @@ impl<X: GXExt> TuiWidget for ListW<X> {
             highlight_spacing,
             highlight_style,
             highlight_symbol,
-            items,
+            items: _,
+            keys: _,
+            rows,
             repeat_highlight_symbol,
             scroll: _,
             selected: _,
             style,
             state,
         } = self;
-        let mut list =
-            List::new(items.t.iter().flat_map(|l| l).map(|l| into_borrowed_line(&l.0)));
+        let mut list = List::new(rows.rows.iter().map(|r| into_borrowed_line(&r.line)));
         if let Some(Some(hs)) = &highlight_spacing.t {
             list = list.highlight_spacing(hs.0.clone());
         }
@@ -141,3 +227,54 @@ impl<X: GXExt> TuiWidget for ListW<X> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use netidx::protocol::valarray::ValArray;
+
+    fn line(text: &str) -> Value {
+        let style = Value::Array(ValArray::from_iter(
+            ["add_modifier", "bg", "fg", "sub_modifier", "underline_color"]
+                .map(|f| Value::Array(ValArray::from([Value::from(f), Value::Null]))),
+        ));
+        Value::Array(ValArray::from([
+            Value::Array(ValArray::from([Value::from("alignment"), Value::Null])),
+            Value::Array(ValArray::from([Value::from("spans"), Value::from(text)])),
+            Value::Array(ValArray::from([Value::from("style"), style])),
+        ]))
+    }
+
+    fn lines(texts: &[&str]) -> Value {
+        Value::Array(ValArray::from_iter(texts.iter().map(|t| line(t))))
+    }
+
+    #[test]
+    fn one_changed_row() -> Result<()> {
+        let mut rows = Rows::default();
+        rows.update(&lines(&["a", "b", "c", "d"]), None)?;
+        assert_eq!(rows.changed, [0, 1, 2, 3]);
+        rows.update(&lines(&["a", "b", "x", "d"]), None)?;
+        assert_eq!(rows.changed, [2]);
+        assert_eq!(rows.rows[2].line, Line::raw("x"));
+        rows.update(&lines(&["a", "b", "x", "d"]), None)?;
+        assert!(rows.changed.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn keyed_reorder() -> Result<()> {
+        let mut rows = Rows::default();
+        let keys = ["a", "b", "c"].map(ArcStr::from);
+        rows.update(&lines(&["a", "b", "c"]), Some(&keys[..]))?;
+        assert_eq!(rows.changed.len(), 3);
+        // moving rows with keys doesn't convert them again
+        let keys = ["c", "x", "a", "b"].map(ArcStr::from);
+        rows.update(&lines(&["c", "x", "a", "b"]), Some(&keys[..]))?;
+        assert_eq!(rows.changed, [1]);
+        // without keys every row that moved is converted again
+        rows.update(&lines(&["x", "a", "b"]), None)?;
+        assert_eq!(rows.changed, [0, 1, 2]);
+        Ok(())
+    }
+}
@@ -3,9 +3,11 @@
 use crate::init;
 use anyhow::{bail, Result};
 use arcstr::ArcStr;
-use graphix_package_core::run;
+use graphix_compiler::expr::{ExprId, ModPath, Span};
+use graphix_package_core::{run, testing};
 use graphix_rt::GXEvent;
-use netidx::publisher::Value;
+use netidx::{path::Path, publisher::Value};
+use std::time::Duration;
 use tokio::sync::mpsc;
 
 #[tokio::test(flavor = "current_thread")]
@@ -50,6 +52,21 @@ run!(mod0, MOD0, |v: Result<&Value>| match v {
     _ => false,
 });
 
+// decimal arithmetic is exact
+const DECIMAL_ARITH: &str = r#"
+[
+  decimal:0.1 + decimal:0.2 == decimal:0.3,
+  decimal:0.3 - decimal:0.1 == decimal:0.2,
+  decimal:1.1 * decimal:1.1 == decimal:1.21,
+  decimal:1 / decimal:8 == decimal:0.125
+]
+"#;
+
+run!(decimal_arith, DECIMAL_ARITH, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => a.iter().all(|v| v == &Value::Bool(true)),
+    _ => false,
+});
+
 const SCOPE: &str = r#"
 {
   let v = (((1 + 1) * 2) / 2) - 1;
@@ -165,3 +182,576 @@ run!(or_never, OR_NEVER, |v: Result<&Value>| match v {
     Ok(Value::I64(42)) => true,
     _ => false,
 });
+
+// a left deep chain of additions nests one level per term without any
+// parser recursion, so it exercises only the compiler's depth limit
+fn nested_sum(n: usize) -> String {
+    vec!["1"; n].join(" + ")
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn nesting_within_limit() -> Result<()> {
+    let code = nested_sum(100);
+    let (v, ctx) = testing::eval(&code, &crate::TEST_REGISTER).await?;
+    assert_eq!(v, Value::I64(100));
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn nesting_depth_exceeded() -> Result<()> {
+    let code = nested_sum(2000);
+    match testing::eval(&code, &crate::TEST_REGISTER).await {
+        Ok((v, _)) => bail!("expected a depth limit error, got {v}"),
+        Err(e) => {
+            let e = format!("{e:?}");
+            assert!(e.contains("maximum nesting depth"), "unexpected error {e}");
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn nesting_depth_configurable() -> Result<()> {
+    let n = graphix_compiler::DEFAULT_MAX_DEPTH + 300;
+    let code = nested_sum(n);
+    let res = testing::eval_with_setup(&code, &crate::TEST_REGISTER, |ctx| {
+        ctx.set_max_depth(graphix_compiler::DEFAULT_MAX_DEPTH * 2)
+    })
+    .await;
+    let (v, ctx) = res?;
+    assert_eq!(v, Value::I64(n as i64));
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn nesting_depth_lowered() -> Result<()> {
+    let code = nested_sum(100);
+    let res = testing::eval_with_setup(&code, &crate::TEST_REGISTER, |ctx| {
+        ctx.set_max_depth(32)
+    })
+    .await;
+    match res {
+        Ok((v, _)) => bail!("expected a depth limit error, got {v}"),
+        Err(e) => {
+            let e = format!("{e:?}");
+            assert!(e.contains("maximum nesting depth 32"), "unexpected error {e}");
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn cast_max_len_exceeded() -> Result<()> {
+    let code = "cast<Array<i64>>([1, 2, 3, 4])";
+    let res = testing::eval_with_setup(code, &crate::TEST_REGISTER, |ctx| {
+        ctx.set_max_len(Some(3))
+    })
+    .await;
+    let (v, ctx) = res?;
+    match v {
+        Value::Error(e) => {
+            let e = format!("{e}");
+            assert!(e.contains("exceeds the maximum length"), "unexpected error {e}")
+        }
+        v => bail!("expected a cast error, got {v}"),
+    }
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn cast_max_len_ok() -> Result<()> {
+    let code = "cast<Array<i64>>([1, 2, 3, 4])";
+    let res = testing::eval_with_setup(code, &crate::TEST_REGISTER, |ctx| {
+        ctx.set_max_len(Some(4))
+    })
+    .await;
+    let (v, ctx) = res?;
+    match v {
+        Value::Array(a) if a.len() == 4 => (),
+        v => bail!("expected an array of length 4, got {v}"),
+    }
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn array_init_max_len() -> Result<()> {
+    // 4 is over the limit, so the first array produced is of length 3
+    let code = "{ let n = 4; n <- 3; array::init(n, |i| i) }";
+    let res = testing::eval_with_setup(code, &crate::TEST_REGISTER, |ctx| {
+        ctx.set_max_len(Some(3))
+    })
+    .await;
+    let (v, ctx) = res?;
+    match v {
+        Value::Array(a) if a.len() == 3 => (),
+        v => bail!("expected an array of length 3, got {v}"),
+    }
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn compile_dependencies() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let v = gx.compile(ArcStr::from("let dep_v = 42")).await?;
+    let id = v
+        .env
+        .lookup_bind(&ModPath::root(), &ModPath::from(["dep_v"]))
+        .map(|(_, b)| b.id)
+        .ok_or_else(|| anyhow::anyhow!("dep_v is not bound"))?;
+    let e = r#"
+{
+  let s: i64 = sys::net::subscribe("/local/deps")?;
+  dep_v
+}
+"#;
+    let e = gx.compile(ArcStr::from(e)).await?;
+    let eid = e.exprs[0].id;
+    assert!(e.exprs[0].refs.contains(&id));
+    'wait: loop {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, _) = ev
+                        && id == eid
+                    {
+                        break 'wait;
+                    }
+                }
+            }
+        }
+    }
+    let paths = e.exprs[0].subscriptions().await?;
+    assert!(paths.contains(&Path::from("/local/deps")), "{paths:?}");
+    drop(e);
+    drop(v);
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn compile_in_env() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let env = gx.get_env().await?;
+    let v = gx.compile_in(ArcStr::from("let captured_v = 42"), env).await?;
+    let env = v.env.clone();
+    // captured_v is only defined in the captured env
+    assert!(gx.compile(ArcStr::from("captured_v + 1")).await.is_err());
+    let e = gx.compile_in(ArcStr::from("captured_v + 1"), env).await?;
+    let eid = e.exprs[0].id;
+    'wait: loop {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, v) = ev
+                        && id == eid
+                    {
+                        assert_eq!(v, Value::I64(43));
+                        break 'wait;
+                    }
+                }
+            }
+        }
+    }
+    assert!(gx.compile(ArcStr::from("captured_v")).await.is_err());
+    drop(e);
+    drop(v);
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn shared_subscription() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let v = gx.compile(ArcStr::from("let shared_v = 0")).await?;
+    let id = v
+        .env
+        .lookup_bind(&ModPath::root(), &ModPath::from(["shared_v"]))
+        .map(|(_, b)| b.id)
+        .ok_or_else(|| anyhow::anyhow!("shared_v is not bound"))?;
+    let p = gx
+        .compile(ArcStr::from(r#"sys::net::publish("/local/shared", shared_v)"#))
+        .await?;
+    let e = r#"
+{
+  let s: i64 = sys::net::subscribe("/local/shared")?;
+  s
+}
+"#;
+    let e0 = gx.compile(ArcStr::from(e)).await?;
+    let e1 = gx.compile(ArcStr::from(e)).await?;
+    let (eid0, eid1) = (e0.exprs[0].id, e1.exprs[0].id);
+    // both expressions share one subscription, registered once. A second
+    // registration would deliver every update to both expressions twice,
+    // so count the updates until they stop arriving.
+    let mut counts = [0, 0];
+    loop {
+        match tokio::time::timeout(Duration::from_millis(500), rx.recv()).await {
+            Err(_) if counts.iter().all(|n| *n > 0) => break,
+            Err(_) => (),
+            Ok(None) => bail!("runtime died"),
+            Ok(Some(mut batch)) => {
+                for ev in batch.drain(..) {
+                    match ev {
+                        GXEvent::Updated(id, Value::I64(0)) if id == eid0 => {
+                            counts[0] += 1
+                        }
+                        GXEvent::Updated(id, Value::I64(0)) if id == eid1 => {
+                            counts[1] += 1
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+    assert_eq!(counts, [1, 1]);
+    let path = Path::from("/local/shared");
+    assert!(e0.exprs[0].subscriptions().await?.contains(&path));
+    assert!(e1.exprs[0].subscriptions().await?.contains(&path));
+    // dropping one subscriber must not unsubscribe the other
+    drop(e0);
+    gx.set(id, 1)?;
+    let mut count = 0;
+    loop {
+        match tokio::time::timeout(Duration::from_millis(500), rx.recv()).await {
+            Err(_) if count > 0 => break,
+            Err(_) => (),
+            Ok(None) => bail!("runtime died"),
+            Ok(Some(mut batch)) => {
+                for ev in batch.drain(..) {
+                    match ev {
+                        GXEvent::Updated(id, _) if id == eid0 => {
+                            bail!("deleted expression updated")
+                        }
+                        GXEvent::Updated(id, Value::I64(1)) if id == eid1 => count += 1,
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+    assert_eq!(count, 1);
+    assert!(e1.exprs[0].subscriptions().await?.contains(&path));
+    drop(e1);
+    drop(p);
+    drop(v);
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn profile_counts_updates() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = testing::init_with_config(
+        tx,
+        &crate::TEST_REGISTER,
+        vec![],
+        |_| {},
+        |b| b.profile(true),
+    )
+    .await?;
+    let gx = &ctx.rt;
+    let v = gx.compile(ArcStr::from("let prof_v = 0")).await?;
+    let id = v
+        .env
+        .lookup_bind(&ModPath::root(), &ModPath::from(["prof_v"]))
+        .map(|(_, b)| b.id)
+        .ok_or_else(|| anyhow::anyhow!("prof_v is not bound"))?;
+    let e = gx.compile(ArcStr::from("prof_v + 1")).await?;
+    let eid = e.exprs[0].id;
+    // the initial value plus one update per set
+    let (mut n, mut set) = (0, false);
+    while n < 5 {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, _) = ev
+                        && id == eid
+                    {
+                        n += 1
+                    }
+                }
+            }
+        }
+        if n > 0 && !set {
+            set = true;
+            for i in 1..5 {
+                gx.set(id, i as i64)?;
+            }
+        }
+    }
+    let profile = gx.profile().await?;
+    assert!(profile.windows(2).all(|w| w[0].1 >= w[1].1), "{profile:?}");
+    assert_eq!(profile.iter().find(|(id, _)| *id == eid).map(|(_, n)| *n), Some(5));
+    assert_eq!(
+        profile.iter().find(|(id, _)| *id == v.exprs[0].id).map(|(_, n)| *n),
+        Some(1)
+    );
+    drop(e);
+    drop(v);
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn profile_disabled() -> Result<()> {
+    let (tx, _rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    assert!(ctx.rt.profile().await.is_err());
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn delete_by_id() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let e = ctx.rt.compile(ArcStr::from("sys::time::timer(0.01, true)")).await?;
+    let eid = e.exprs[0].id;
+    'wait: loop {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, _) = ev
+                        && id == eid
+                    {
+                        break 'wait;
+                    }
+                }
+            }
+        }
+    }
+    ctx.rt.delete(eid)?;
+    // once the runtime has answered a later request the delete is done
+    ctx.rt.get_env().await?;
+    while rx.try_recv().is_ok() {}
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    while let Ok(mut batch) = rx.try_recv() {
+        for ev in batch.drain(..) {
+            if let GXEvent::Updated(id, _) = ev {
+                assert!(id != eid, "deleted expression updated")
+            }
+        }
+    }
+    // deleting again, and dropping the handle, is harmless
+    ctx.rt.delete(eid)?;
+    drop(e);
+    assert!(ctx.rt.graph_dot(eid).await.is_err());
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn graph_dot() -> Result<()> {
+    let (tx, _rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let e = ctx.rt.compile(ArcStr::from("{ let x = 1; x + 2 }")).await?;
+    let dot = ctx.rt.graph_dot(e.exprs[0].id).await?;
+    assert!(dot.starts_with("digraph {\n"), "{dot}");
+    assert!(dot.ends_with("}\n"), "{dot}");
+    let mut nodes = vec![];
+    let mut edges = vec![];
+    for line in dot.lines().skip(2).take_while(|l| *l != "}") {
+        let line = line.trim().strip_suffix(';').expect(line);
+        match line.split_once(" -> ") {
+            Some((from, to)) => {
+                let to = to.split_whitespace().next().unwrap();
+                edges.push((from.to_string(), to.to_string()))
+            }
+            None => {
+                let (id, attrs) = line.split_once(' ').unwrap();
+                assert!(attrs.starts_with('[') && attrs.ends_with(']'), "{line}");
+                nodes.push((id.to_string(), attrs.to_string()))
+            }
+        }
+    }
+    for (from, to) in &edges {
+        assert!(nodes.iter().any(|(id, _)| id == from), "{from}");
+        assert!(nodes.iter().any(|(id, _)| id == to), "{to}");
+    }
+    let label = |l: &str| {
+        nodes
+            .iter()
+            .find(|(_, a)| a.contains(&format!("label=\"{l}\\n")))
+            .map(|(id, _)| id)
+    };
+    for l in ["Do", "Bind", "Constant 1", "Add", "Ref x", "Constant 2"] {
+        assert!(label(l).is_some(), "missing {l} in {dot}");
+    }
+    // the bind of x flows into the reference of x
+    let (bind, rf) = (label("Bind").unwrap(), label("Ref x").unwrap());
+    assert!(edges.iter().any(|(f, t)| f == bind && t == rf), "{dot}");
+    // the lambda argument x is only in scope in the body of the lambda, the
+    // last x refers to the outer let
+    let code = "{ let x = 1; let f = |x| x + 1; f(x) }";
+    let e2 = ctx.rt.compile(ArcStr::from(code)).await?;
+    let dot = ctx.rt.graph_dot(e2.exprs[0].id).await?;
+    let lambda = dot
+        .lines()
+        .find(|l| l.contains("label=\"Lambda\\n"))
+        .and_then(|l| l.trim().split_whitespace().next())
+        .expect(&dot)
+        .to_string();
+    let from_lambda = dot
+        .lines()
+        .filter(|l| l.trim().starts_with(&format!("{lambda} -> ")))
+        .filter(|l| l.contains("style=dashed"))
+        .count();
+    assert_eq!(from_lambda, 1, "{dot}");
+    assert!(ctx.rt.graph_dot(ExprId::new()).await.is_err());
+    drop(e2);
+    drop(e);
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn pause_resume() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let v = gx.compile(ArcStr::from("let pause_v = 0")).await?;
+    let id = v
+        .env
+        .lookup_bind(&ModPath::root(), &ModPath::from(["pause_v"]))
+        .map(|(_, b)| b.id)
+        .ok_or_else(|| anyhow::anyhow!("pause_v is not bound"))?;
+    let e = gx.compile(ArcStr::from("pause_v + 1")).await?;
+    let eid = e.exprs[0].id;
+    let mut updates = vec![];
+    while updates.is_empty() {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, v) = ev
+                        && id == eid
+                    {
+                        updates.push(v)
+                    }
+                }
+            }
+        }
+    }
+    gx.pause()?;
+    for i in 1..4 {
+        gx.set(id, i as i64)?;
+    }
+    // once the runtime has answered a later request the sets were queued
+    gx.get_env().await?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    while let Ok(mut batch) = rx.try_recv() {
+        for ev in batch.drain(..) {
+            if let GXEvent::Updated(id, _) = ev {
+                assert!(id != eid, "paused runtime delivered an update")
+            }
+        }
+    }
+    gx.resume()?;
+    while updates.len() < 4 {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, v) = ev
+                        && id == eid
+                    {
+                        updates.push(v)
+                    }
+                }
+            }
+        }
+    }
+    let expected = (1..5).map(Value::I64).collect::<Vec<_>>();
+    assert_eq!(updates, expected);
+    drop(e);
+    drop(v);
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn annotations_in_env() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let e = r#"
+#[deprecated]
+#[unit = "m/s"]
+let speed_v = 42;
+#[group = "pair"]
+let (ann_a, ann_b) = (1, 2);
+let ann_c = 3;
+speed_v
+"#;
+    let e = gx.compile(ArcStr::from(e)).await?;
+    let bind = |name: &str| {
+        e.env
+            .lookup_bind(&ModPath::root(), &ModPath::from([name]))
+            .map(|(_, b)| b.clone())
+            .ok_or_else(|| anyhow::anyhow!("{name} is not bound"))
+    };
+    let speed = bind("speed_v")?;
+    assert!(speed.annotation("deprecated").is_some_and(|a| a.value.is_none()));
+    let unit = speed.annotation("unit").and_then(|a| a.value.clone());
+    assert_eq!(unit, Some(Value::from("m/s")));
+    assert!(speed.annotation("missing").is_none());
+    // every variable bound by a pattern gets the annotations
+    for name in ["ann_a", "ann_b"] {
+        let group = bind(name)?.annotation("group").and_then(|a| a.value.clone());
+        assert_eq!(group, Some(Value::from("pair")));
+    }
+    assert!(bind("ann_c")?.annotations.is_empty());
+    // annotations have no effect on the value
+    let eid = e.exprs.last().unwrap().id;
+    'outer: loop {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, v) = ev
+                        && id == eid
+                    {
+                        assert_eq!(v, Value::I64(42));
+                        break 'outer;
+                    }
+                }
+            }
+        }
+    }
+    drop(e);
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn compile_spans() -> Result<()> {
+    let (tx, _rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let e = gx.compile(ArcStr::from("let span_v = 1;\nspan_v +  41")).await?;
+    let lc = |(s, e): &Span| ((s.line, s.column), (e.line, e.column));
+    let spans = e.spans();
+    assert_eq!(lc(&spans[&e.exprs[0].id]), ((1, 1), (1, 15)));
+    assert_eq!(lc(&spans[&e.exprs[1].id]), ((2, 1), (2, 13)));
+    // the literal 41 is a sub expression
+    assert!(spans.values().any(|s| lc(s) == ((2, 11), (2, 13))));
+    drop(e);
+    ctx.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,137 @@
+// Tests for calling graphix functions from rust
+
+use crate::init;
+use anyhow::{bail, Result};
+use arcstr::{literal, ArcStr};
+use graphix_compiler::expr::ExprId;
+use graphix_rt::GXEvent;
+use netidx::{protocol::valarray::ValArray, publisher::Value};
+use poolshark::global::GPooled;
+use tokio::sync::mpsc;
+
+async fn wait_for(
+    rx: &mut mpsc::Receiver<GPooled<Vec<GXEvent>>>,
+    eid: ExprId,
+) -> Result<Value> {
+    loop {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for e in batch.drain(..) {
+                    if let GXEvent::Updated(id, v) = e
+                        && id == eid
+                    {
+                        return Ok(v);
+                    }
+                }
+            }
+        }
+    }
+}
+
+const LABELED: &str = r#"
+{
+  let f = |#x: i64, #y: i64 = 2, z: string| "[z] [x - y]";
+  f
+}
+"#;
+
+#[tokio::test(flavor = "current_thread")]
+async fn callable_params() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let e = gx.compile(ArcStr::from(LABELED)).await?;
+    let f = wait_for(&mut rx, e.exprs[0].id).await?;
+    let f = gx.compile_callable(f).await?;
+    let params = f.params();
+    assert_eq!(params.len(), 3);
+    assert_eq!(params[0].label, Some(literal!("x")));
+    assert!(!params[0].optional);
+    assert!(params[0].default.is_none());
+    assert_eq!(params[0].typ.to_string(), "i64");
+    assert_eq!(params[1].label, Some(literal!("y")));
+    assert!(params[1].optional);
+    assert_eq!(params[1].default.as_ref().map(|e| e.to_string()), Some("2".into()));
+    assert_eq!(params[2].label, None);
+    assert!(!params[2].optional);
+    assert!(params[2].default.is_none());
+    assert_eq!(params[2].typ.to_string(), "string");
+    f.call(ValArray::from_iter_exact(
+        [Value::I64(5), Value::I64(3), Value::String(literal!("r"))].into_iter(),
+    ))
+    .await?;
+    assert_eq!(wait_for(&mut rx, f.expr).await?, Value::String(literal!("r 2")));
+    drop(f);
+    drop(e);
+    ctx.shutdown().await;
+    Ok(())
+}
+
+const ALL_LABELED: &str = r#"
+{
+  let f = |#x: i64, #y: i64 = 2, #z: string| "[z] [x - y]";
+  f
+}
+"#;
+
+#[tokio::test(flavor = "current_thread")]
+async fn callable_call_named() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let e = gx.compile(ArcStr::from(ALL_LABELED)).await?;
+    let f = wait_for(&mut rx, e.exprs[0].id).await?;
+    let f = gx.compile_callable(f).await?;
+    assert_eq!(f.params()[1].default_value, Some(Value::I64(2)));
+    // out of order
+    f.call_named(vec![
+        (literal!("z"), Value::String(literal!("a"))),
+        (literal!("y"), Value::I64(4)),
+        (literal!("x"), Value::I64(10)),
+    ])
+    .await?;
+    assert_eq!(wait_for(&mut rx, f.expr).await?, Value::String(literal!("a 6")));
+    // optional arg omitted
+    f.call_named(vec![
+        (literal!("x"), Value::I64(10)),
+        (literal!("z"), Value::String(literal!("b"))),
+    ])
+    .await?;
+    assert_eq!(wait_for(&mut rx, f.expr).await?, Value::String(literal!("b 8")));
+    drop(f);
+    drop(e);
+    ctx.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn callable_call_named_errors() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let e = gx.compile(ArcStr::from(ALL_LABELED)).await?;
+    let f = wait_for(&mut rx, e.exprs[0].id).await?;
+    let f = gx.compile_callable(f).await?;
+    let r = f
+        .call_named(vec![
+            (literal!("x"), Value::I64(1)),
+            (literal!("w"), Value::I64(1)),
+            (literal!("z"), Value::String(literal!("c"))),
+        ])
+        .await;
+    assert!(r.is_err_and(|e| e.to_string().contains("unknown argument w")));
+    let r = f.call_named(vec![(literal!("z"), Value::String(literal!("c")))]).await;
+    assert!(r.is_err_and(|e| e.to_string().contains("missing required argument x")));
+    let r = f
+        .call_named(vec![
+            (literal!("x"), Value::String(literal!("1"))),
+            (literal!("z"), Value::String(literal!("c"))),
+        ])
+        .await;
+    assert!(r.is_err());
+    drop(f);
+    drop(e);
+    ctx.shutdown().await;
+    Ok(())
+}
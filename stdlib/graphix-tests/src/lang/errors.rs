@@ -1,7 +1,9 @@
 // Tests for try/catch and error handling
 
-use anyhow::Result;
-use graphix_package_core::run;
+use anyhow::{bail, Result};
+use arcstr::ArcStr;
+use graphix_compiler::CFlag;
+use graphix_package_core::{run, testing};
 use netidx::publisher::Value;
 
 // unchecked arithmetic: 2 + 2 works normally
@@ -41,6 +43,58 @@ run!(checked_div0, CHECKED_DIV0, |v: Result<&Value>| match v {
     _ => false,
 });
 
+// decimal division by zero is an error too
+const CHECKED_DECIMAL_DIV0: &str = r#"
+{
+    let res = never();
+    try (decimal:1 /? decimal:0)?
+    catch(e) => select (e.0).error {
+        `ArithError(s) => res <- s
+    };
+    res
+}
+"#;
+
+run!(checked_decimal_div0, CHECKED_DECIMAL_DIV0, |v: Result<&Value>| match v {
+    Ok(Value::String(_)) => true,
+    _ => false,
+});
+
+// integer overflow is an error for the checked operators, it doesn't wrap
+const CHECKED_OVERFLOW: &str = r#"
+[
+  is_err(9223372036854775807 +? 1),
+  is_err((0 - 9223372036854775807) -? 2),
+  is_err(4611686018427387904 *? 2),
+  is_err(u8:0 -? u8:1),
+  is_err(9223372036854775806 +? 1)
+]
+"#;
+
+run!(checked_overflow, CHECKED_OVERFLOW, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => {
+        let t = Value::Bool(true);
+        &a[..] == &[t.clone(), t.clone(), t.clone(), t, Value::Bool(false)]
+    }
+    _ => false,
+});
+
+const CHECKED_OVERFLOW_CATCH: &str = r#"
+{
+    let res = never();
+    try (9223372036854775807 +? 1)?
+    catch(e) => select (e.0).error {
+        `ArithError(s) => res <- s
+    };
+    res
+}
+"#;
+
+run!(checked_overflow_catch, CHECKED_OVERFLOW_CATCH, |v: Result<&Value>| match v {
+    Ok(Value::String(s)) => s == "attempt to add with overflow",
+    _ => false,
+});
+
 // try/catch with array index errors still works
 const CATCH1: &str = r#"
 try
@@ -94,3 +148,107 @@ run!(checked_dollar, CHECKED_DOLLAR, |v: Result<&Value>| match v {
     Ok(Value::I64(4)) => true,
     _ => false,
 });
+
+// user constructed errors have the same representation as builtin errors
+const CUSTOM_ERROR: &str = r#"
+error(`TooBig("42 is too big"))
+"#;
+
+run!(custom_error, CUSTOM_ERROR, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+        Ok((tag, msg)) => tag == "TooBig" && msg == "42 is too big",
+        Err(_) => false,
+    },
+    _ => false,
+});
+
+// user constructed errors can be raised and caught by tag
+const CATCH_CUSTOM: &str = r#"
+{
+    let res = never();
+    let check = |x: i64| select x {
+        x if x > 10 => error(`TooBig("[x] is too big")),
+        x => x
+    };
+    try check(42)?
+    catch(e) => select (e.0).error {
+        `TooBig(s) => res <- s
+    };
+    res
+}
+"#;
+
+run!(catch_custom, CATCH_CUSTOM, |v: Result<&Value>| match v {
+    Ok(Value::String(s)) => s == "42 is too big",
+    _ => false,
+});
+
+// calling a function that throws from outside of any try/catch is an error
+// when unhandled errors are warned about and warnings are errors
+#[tokio::test(flavor = "current_thread")]
+async fn unhandled_throws_warns() -> Result<()> {
+    let code = r#"
+{
+    let f = |a: Array<i64>| a[0]?;
+    f([1, 2])
+}
+"#;
+    let flags = CFlag::WarnUnhandled | CFlag::WarningsAreErrors;
+    match testing::eval_with_flags(code, &crate::TEST_REGISTER, flags).await {
+        Ok((v, _)) => bail!("expected an unhandled error, got {v}"),
+        Err(e) => {
+            let e = format!("{e:?}");
+            assert!(e.contains("will not be caught"), "unexpected error {e}")
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn handled_throws_no_warning() -> Result<()> {
+    let code = r#"
+{
+    let f = |a: Array<i64>| a[0]?;
+    try f([1, 2]) catch(e) => -1
+}
+"#;
+    let flags = CFlag::WarnUnhandled | CFlag::WarningsAreErrors;
+    let (v, ctx) = testing::eval_with_flags(code, &crate::TEST_REGISTER, flags).await?;
+    assert_eq!(v, Value::I64(1));
+    ctx.shutdown().await;
+    Ok(())
+}
+
+// by default float arithmetic follows IEEE 754
+const FLOAT_NAN_INF: &str = r#"
+[0.0 / 0.0, 1.0 / 0.0]
+"#;
+
+run!(float_nan_inf, FLOAT_NAN_INF, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::F64(nan), Value::F64(inf)] => nan.is_nan() && *inf == f64::INFINITY,
+        _ => false,
+    },
+    _ => false,
+});
+
+// with float errors enabled NaN and infinity are arithmetic errors, either
+// for the code compiled with the flag, or for everything in the context
+#[tokio::test(flavor = "current_thread")]
+async fn float_errors() -> Result<()> {
+    let code = r#"
+[is_err(0.0 /? 0.0), is_err(1.0 /? 0.0), is_err(1.0 /? 2.0)]
+"#;
+    let expected = Value::Array([true, true, false].map(Value::Bool).into());
+    let flags = CFlag::FloatErrors.into();
+    let (v, ctx) = testing::eval_with_flags(code, &crate::TEST_REGISTER, flags).await?;
+    assert_eq!(v, expected);
+    ctx.shutdown().await;
+    let (v, ctx) = testing::eval_with_setup(code, &crate::TEST_REGISTER, |ctx| {
+        ctx.set_float_errors(true)
+    })
+    .await?;
+    assert_eq!(v, expected);
+    ctx.shutdown().await;
+    Ok(())
+}
@@ -1,8 +1,11 @@
 // Tests for lambdas, first-class functions, labeled arguments, recursive functions
 
 use anyhow::Result;
-use graphix_package_core::run;
+use arcstr::ArcStr;
+use graphix_compiler::CFlag;
+use graphix_package_core::{run, testing};
 use netidx::publisher::Value;
+use tokio::sync::mpsc;
 
 const LAMBDA: &str = r#"
 {
@@ -189,6 +192,35 @@ run!(recursive_lambda0, RECURSIVE_LAMBDA0, |v: Result<&Value>| match v {
     _ => false,
 });
 
+const MUTUAL_RECURSION0: &str = r#"
+{
+    let rec even = |x: i64| -> bool select x { 0 => true, x => odd(x - 1) };
+    let rec odd = |x: i64| -> bool select x { 0 => false, x => even(x - 1) };
+    [even(10), odd(10), even(7), odd(7)]
+}
+"#;
+
+run!(mutual_recursion0, MUTUAL_RECURSION0, |v: Result<&Value>| match v {
+    Ok(v) => match v.clone().cast_to::<[bool; 4]>() {
+        Ok([true, false, false, true]) => true,
+        Ok(_) | Err(_) => false,
+    },
+    _ => false,
+});
+
+const MUTUAL_RECURSION1: &str = r#"
+{
+    let even = |x: i64| -> bool select x { 0 => true, x => odd(x - 1) };
+    let odd = |x: i64| -> bool select x { 0 => false, x => even(x - 1) };
+    even(10)
+}
+"#;
+
+run!(mutual_recursion1, MUTUAL_RECURSION1, |v: Result<&Value>| match v {
+    Err(_) => true,
+    Ok(_) => false,
+});
+
 const LAMBDAMATCH0: &str = r#"
 {
   type T = { foo: Array<f64>, bar: i64, baz: f64 };
@@ -345,3 +377,83 @@ run!(vargs0, VARGS0, |v: Result<&Value>| match v {
     },
     _ => false,
 });
+
+// with inlining enabled a small lambda called once is replaced by its body,
+// while a recursive lambda is still called
+#[tokio::test(flavor = "current_thread")]
+async fn inline_lambdas() -> Result<()> {
+    let (tx, _rx) = mpsc::channel(10);
+    let flags = CFlag::InlineLambdas.into();
+    let ctx = testing::init_with_flags(tx, &crate::TEST_REGISTER, vec![], flags, |_| {})
+        .await?;
+    let e =
+        ctx.rt.compile(ArcStr::from("{ let y = 2; let f = |x| x > y; f(3) }")).await?;
+    let dot = ctx.rt.graph_dot(e.exprs[0].id).await?;
+    assert!(!dot.contains("label=\"Apply\\n"), "{dot}");
+    assert!(!dot.contains("label=\"Lambda\\n"), "{dot}");
+    let code = "{ let rec f = |x| select x { 0 => 0, x => f(x - 1) }; f(3) }";
+    let e = ctx.rt.compile(ArcStr::from(code)).await?;
+    let dot = ctx.rt.graph_dot(e.exprs[0].id).await?;
+    assert!(dot.contains("label=\"Apply\\n"), "{dot}");
+    drop(e);
+    ctx.shutdown().await;
+    let code = "{ let y = 2; let f = |x| x > y; f(3) }";
+    let (v, ctx) = testing::eval_with_flags(code, &crate::TEST_REGISTER, flags).await?;
+    assert_eq!(v, Value::Bool(true));
+    ctx.shutdown().await;
+    let code = "{ let y = 1; let f = |x| x + y; let y = 10; f(y) }";
+    let (v, ctx) = testing::eval_with_flags(code, &crate::TEST_REGISTER, flags).await?;
+    assert_eq!(v, Value::I64(11));
+    ctx.shutdown().await;
+    Ok(())
+}
+
+// the body of a lambda must return its declared return type, a wider type
+// is an error rather than silently widening the return type
+const RTYPE_MISMATCH: &str = r#"
+{
+  let f = |x: i64| -> i64 select x { 0 => "zero", n => n };
+  f(1)
+}
+"#;
+
+run!(rtype_mismatch, RTYPE_MISMATCH, |v: Result<&Value>| match v {
+    Err(e) => format!("{e:?}").contains("not contained by its return type"),
+    Ok(_) => false,
+});
+
+const RTYPE_MATCH: &str = r#"
+{
+  let f = |x: i64| -> [i64, string] select x { 0 => "zero", n => n };
+  f(1)
+}
+"#;
+
+run!(rtype_match, RTYPE_MATCH, |v: Result<&Value>| match v {
+    Ok(Value::I64(1)) => true,
+    _ => false,
+});
+
+const THROWS_MISMATCH: &str = r#"
+{
+  let f = |a: Array<i64>| -> i64 throws Error<ErrChain<`Foo(string)>> a[0]?;
+  try f([1]) catch(e) => -1
+}
+"#;
+
+run!(throws_mismatch, THROWS_MISMATCH, |v: Result<&Value>| match v {
+    Err(e) => format!("{e:?}").contains("not contained by its throws type"),
+    Ok(_) => false,
+});
+
+const THROWS_MATCH: &str = r#"
+{
+  let f = |a: Array<i64>| -> i64 throws Error<ErrChain<`ArrayIndexError(string)>> a[0]?;
+  try f([1]) catch(e) => -1
+}
+"#;
+
+run!(throws_match, THROWS_MATCH, |v: Result<&Value>| match v {
+    Ok(Value::I64(1)) => true,
+    _ => false,
+});
@@ -3,6 +3,7 @@
 mod arrays;
 mod basics;
 mod byref;
+mod callable;
 mod datetime;
 mod errors;
 mod functions;
@@ -1,8 +1,16 @@
-// Tests for dynamic modules
+// Tests for modules, including dynamic modules
 
-use anyhow::Result;
-use graphix_package_core::run;
+use anyhow::{bail, Result};
+use arcstr::{literal, ArcStr};
+use graphix_compiler::{
+    expr::{parser::parse_type, ModuleResolver, Source},
+    typ::{FnType, Type},
+    ExecCtx, Rt, UserEvent,
+};
+use graphix_package_core::{run, testing, CachedArgs, CachedVals, EvalCached};
+use graphix_rt::GXEvent;
 use netidx::publisher::Value;
+use tokio::sync::mpsc;
 
 const DYNAMIC_MODULE0: &str = r#"
 {
@@ -276,3 +284,183 @@ run!(dynamic_module8, DYNAMIC_MODULE8, |v: Result<&Value>| match v {
     Ok(Value::String(s)) if s == "hello world" => true,
     _ => false,
 });
+
+// a type defined in one module is visible through a module that uses it
+run!(
+    reexported_typedef,
+    |v: Result<&Value>| matches!(v, Ok(Value::I64(42))),
+    "/test.gx" => r#"
+        mod a;
+        mod b;
+        mod c;
+        let result = c::result
+    "#,
+    "/test/a.gx" => r#"
+        type T = { x: i64 }
+    "#,
+    "/test/b.gx" => r#"
+        use a;
+        let zero = 0
+    "#,
+    "/test/c.gx" => r#"
+        let v: b::T = { x: 42 };
+        let result = v.x
+    "#
+);
+
+// but not through a module that uses a module that uses it
+run!(
+    reexported_typedef_single_level,
+    |v: Result<&Value>| v.is_err(),
+    "/test.gx" => r#"
+        mod a;
+        mod b;
+        mod c;
+        mod d;
+        let result = d::result
+    "#,
+    "/test/a.gx" => r#"
+        type T = { x: i64 }
+    "#,
+    "/test/b.gx" => r#"
+        use a;
+        let zero = 0
+    "#,
+    "/test/c.gx" => r#"
+        use b;
+        let one = 1
+    "#,
+    "/test/d.gx" => r#"
+        let v: c::T = { x: 42 };
+        let result = v.x
+    "#
+);
+
+#[derive(Debug, Default)]
+struct DoubleEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for DoubleEv {
+    const NAME: &str = "custom_double";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        Some(Value::I64(from.get::<i64>(0)? * 2))
+    }
+}
+
+#[derive(Debug, Default)]
+struct GreetEv;
+
+impl<R: Rt, E: UserEvent> EvalCached<R, E> for GreetEv {
+    const NAME: &str = "custom_greet";
+    const NEEDS_CALLSITE: bool = false;
+
+    fn eval(&mut self, _ctx: &mut ExecCtx<R, E>, from: &CachedVals) -> Option<Value> {
+        let greeting = from.get::<ArcStr>(0)?;
+        let name = from.get::<ArcStr>(1)?;
+        Some(Value::String(format!("{greeting}, {name}").into()))
+    }
+}
+
+fn fn_type(s: &str) -> Result<FnType> {
+    match parse_type(s)? {
+        Type::Fn(ft) => Ok((*ft).clone()),
+        t => bail!("expected a function type, got {t}"),
+    }
+}
+
+// a module generated from registered builtins binds them and compiles
+#[tokio::test(flavor = "current_thread")]
+async fn generated_builtin_module() -> Result<()> {
+    let builtins = [
+        ("custom_double", fn_type("fn(i64) -> i64")?),
+        ("custom_greet", fn_type("fn(#greeting: string, string) -> string")?),
+    ];
+    let source = graphix_rt::builtin_module_source("custom", &builtins)?;
+    assert_eq!(
+        &*source,
+        "let double = |a0: i64| -> i64 'custom_double;\n\
+         let greet = |#greeting: string, a1: string| -> string 'custom_greet;\n"
+    );
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = testing::init_with_config(
+        tx,
+        &crate::TEST_REGISTER,
+        vec![graphix_rt::builtin_module("custom", &builtins)?],
+        |ctx| {
+            ctx.register_builtin::<CachedArgs<DoubleEv>>().unwrap();
+            ctx.register_builtin::<CachedArgs<GreetEv>>().unwrap();
+        },
+        |b| b,
+    )
+    .await?;
+    let e = ctx
+        .rt
+        .compile(literal!(
+            r#"{ mod custom; (custom::greet(#greeting: "hello", "world"), custom::double(21)) }"#
+        ))
+        .await?;
+    let eid = e.exprs[0].id;
+    loop {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, v) = ev
+                        && id == eid
+                    {
+                        match v {
+                            Value::Array(a) => match &a[..] {
+                                [Value::String(s), Value::I64(42)] => {
+                                    assert_eq!(&**s, "hello, world")
+                                }
+                                _ => panic!("unexpected result {a:?}"),
+                            },
+                            v => panic!("unexpected result {v}"),
+                        }
+                        drop(e);
+                        ctx.shutdown().await;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn load_with_resolvers() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    tokio::fs::write(dir.path().join("adhoc.gx"), "let answer = 42").await?;
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = crate::init(tx).await?;
+    let src = Source::Internal(literal!("{ mod adhoc; adhoc::answer }"));
+    let e = ctx
+        .rt
+        .load_with_resolvers(
+            src.clone(),
+            vec![ModuleResolver::Files(dir.path().to_path_buf())],
+        )
+        .await?;
+    let eid = e.exprs[0].id;
+    'outer: loop {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, v) = ev
+                        && id == eid
+                    {
+                        assert_eq!(v, Value::I64(42));
+                        break 'outer;
+                    }
+                }
+            }
+        }
+    }
+    // the ad hoc resolver was only used by that load
+    assert!(ctx.rt.load(src).await.is_err());
+    drop(e);
+    ctx.shutdown().await;
+    Ok(())
+}
@@ -141,3 +141,65 @@ run!(nestedmatch3, NESTEDMATCH3, |v: Result<&Value>| match v {
     Ok(Value::F64(3.0)) => true,
     _ => false,
 });
+
+const STRUCTREST0: &str = r#"
+{
+  type T = { foo: string, bar: i64, baz: f64 };
+  let x = { foo: "bar", bar: 42, baz: 84.0 };
+  let { bar, ..rest }: T = x;
+  (bar, rest == { foo: "bar", baz: 84.0 }, rest.baz)
+}
+"#;
+
+run!(structrest0, STRUCTREST0, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::I64(42), Value::Bool(true), Value::F64(84.0)] => true,
+        _ => false,
+    },
+    _ => false,
+});
+
+const STRUCTREST1: &str = r#"
+{
+  type T = { foo: string, bar: i64, baz: f64 };
+  let x = { foo: "bar", bar: 42, baz: 84.0 };
+  select x {
+    T as { foo: "foo", ..rest } => "[rest.baz]",
+    T as { foo, ..rest } => "[foo][rest.bar]"
+  }
+}
+"#;
+
+run!(structrest1, STRUCTREST1, |v: Result<&Value>| match v {
+    Ok(Value::String(s)) => &**s == "bar42",
+    _ => false,
+});
+
+const STRUCTREST2: &str = r#"
+{
+  type T = { foo: string, bar: i64, baz: f64 };
+  let x = { foo: "bar", bar: 42, baz: 84.0 };
+  select x {
+    T as { foo: "foo", ..rest } => rest.bar
+  }
+}
+"#;
+
+run!(structrest2, STRUCTREST2, |v: Result<&Value>| match v {
+    Err(_) => true,
+    _ => false,
+});
+
+const STRUCTREST3: &str = r#"
+{
+  type T = { foo: string, bar: i64, baz: f64 };
+  let x = { foo: "bar", bar: 42, baz: 84.0 };
+  let { bar, ..rest }: T = x;
+  rest.bar
+}
+"#;
+
+run!(structrest3, STRUCTREST3, |v: Result<&Value>| match v {
+    Err(_) => true,
+    _ => false,
+});
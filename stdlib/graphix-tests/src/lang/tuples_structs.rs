@@ -1,9 +1,13 @@
 // Tests for tuples and structs
 
-use anyhow::Result;
+use crate::init;
+use anyhow::{bail, Result};
 use arcstr::ArcStr;
-use graphix_package_core::run;
+use graphix_compiler::{expr::print::PrettyDisplay, typ::TVal};
+use graphix_package_core::{run, testing};
+use graphix_rt::GXEvent;
 use netidx::publisher::Value;
+use tokio::sync::mpsc;
 
 const TUPLES0: &str = r#"
 {
@@ -104,6 +108,98 @@ run!(bindstruct, BINDSTRUCT, |v: Result<&Value>| match v {
     _ => false,
 });
 
+// the pattern of a let that can't destructure the value is reported at
+// the position of the let
+async fn bind_pattern_error(code: &str) -> Result<()> {
+    match testing::eval(code, &crate::TEST_REGISTER).await {
+        Ok((v, _)) => bail!("expected a match error, got {v}"),
+        Err(e) => {
+            let e = format!("{e:?}");
+            assert!(e.contains("match error"), "unexpected error {e}");
+            assert!(e.contains("can't be matched by"), "unexpected error {e}");
+            assert!(e.contains("at line: 3, column: 3"), "missing position {e}");
+        }
+    }
+    Ok(())
+}
+
+const BINDTUPLEARITY: &str = r#"
+{
+  let (a, b) = (1, 2, 3);
+  a + b
+}
+"#;
+
+#[tokio::test(flavor = "current_thread")]
+async fn bindtuplearity() -> Result<()> {
+    bind_pattern_error(BINDTUPLEARITY).await
+}
+
+const BINDTUPLEARITY1: &str = r#"
+{
+  let f = |x| (x, x, x);
+  let (a, b) = f(1);
+  a + b
+}
+"#;
+
+#[tokio::test(flavor = "current_thread")]
+async fn bindtuplearity1() -> Result<()> {
+    bind_pattern_error(BINDTUPLEARITY1).await
+}
+
+const BINDTUPLE: &str = r#"
+{
+  let f = |x| (x, x + 1);
+  let (a, b) = f(1);
+  a + b
+}
+"#;
+
+run!(bindtuple, BINDTUPLE, |v: Result<&Value>| match v {
+    Ok(Value::I64(3)) => true,
+    _ => false,
+});
+
+const BINDSTRUCTMISSING: &str = r#"
+{
+  let x = { foo: "bar", bar: 42, baz: 84.0 };
+  let { bar, baz } = x;
+  bar + baz
+}
+"#;
+
+#[tokio::test(flavor = "current_thread")]
+async fn bindstructmissing() -> Result<()> {
+    bind_pattern_error(BINDSTRUCTMISSING).await
+}
+
+const BINDVARIANTTAG: &str = r#"
+{
+  let `Foo(a) = `Bar(1);
+  a
+}
+"#;
+
+#[tokio::test(flavor = "current_thread")]
+async fn bindvarianttag() -> Result<()> {
+    bind_pattern_error(BINDVARIANTTAG).await
+}
+
+const BINDSTRUCTREST: &str = r#"
+{
+  type T = { foo: string, bar: i64, baz: f64 };
+  let x = { foo: "bar", bar: 42, baz: 84.0 };
+  let { bar, baz, .. }: T = x;
+  bar + baz
+}
+"#;
+
+run!(bindstructrest, BINDSTRUCTREST, |v: Result<&Value>| match v {
+    Ok(Value::F64(126.0)) => true,
+    _ => false,
+});
+
 const STRUCTACCESSOR: &str = r#"
 {
   let x = { foo: "bar", bar: 42, baz: 84.0 };
@@ -238,3 +334,42 @@ run!(structwith5, STRUCTWITH5, |v: Result<&Value>| match v {
     },
     _ => false,
 });
+
+#[tokio::test(flavor = "current_thread")]
+async fn pretty_print_nested_struct() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = init(tx).await?;
+    let gx = &ctx.rt;
+    let code = r#"{
+  let s = { name: "point", pos: { x: 1, y: 2 }, tags: ["a", "b"] };
+  s
+}"#;
+    let res = gx.compile(ArcStr::from(code)).await?;
+    let e = &res.exprs[0];
+    let v = 'wait: loop {
+        match rx.recv().await {
+            None => bail!("runtime died"),
+            Some(mut batch) => {
+                for ev in batch.drain(..) {
+                    if let GXEvent::Updated(id, v) = ev
+                        && id == e.id
+                    {
+                        break 'wait v;
+                    }
+                }
+            }
+        }
+    };
+    let tv = TVal { env: &res.env, typ: &e.typ, v: &v };
+    assert_eq!(tv.to_string(), r#"{name: "point", pos: {x: 1, y: 2}, tags: ["a", "b"]}"#);
+    let expected = r#"{
+  name: "point",
+  pos: {x: 1, y: 2},
+  tags: ["a", "b"]
+}
+"#;
+    assert_eq!(&*tv.to_string_pretty(30), expected);
+    drop(res);
+    ctx.shutdown().await;
+    Ok(())
+}
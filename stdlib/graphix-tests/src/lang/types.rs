@@ -1,7 +1,7 @@
 // Tests for type system features: type checking, annotations, type variables
 
-use anyhow::Result;
-use graphix_package_core::run;
+use anyhow::{bail, Result};
+use graphix_package_core::{run, testing};
 use netidx::publisher::Value;
 
 const SIMPLE_TYPECHECK: &str = r#"
@@ -239,3 +239,87 @@ run!(typedef_tvar_ok, TYPEDEF_TVAR_OK, |v: Result<&Value>| match v {
     Ok(Value::I64(0)) => true,
     _ => false,
 });
+
+// omitted type parameters take their defaults
+const TYPEDEF_DEFAULTS: &str = r#"
+{
+  type Cache<'k = string, 'v = Any> = Map<'k, 'v>;
+  let c0: Cache = {"a" => 1};
+  let c1: Cache<string> = {"b" => "foo"};
+  let c2: Cache<i64, i64> = {1 => 2};
+  [map::len(c0), map::len(c1), map::len(c2)]
+}
+"#;
+
+run!(typedef_defaults, TYPEDEF_DEFAULTS, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) =>
+        matches!(&a[..], [Value::I64(1), Value::I64(1), Value::I64(1)]),
+    _ => false,
+});
+
+const TYPEDEF_DEFAULT_APPLIED: &str = r#"
+{
+  type T<'a = i64> = Array<'a>;
+  let x: T = ["foo"];
+  x
+}
+"#;
+
+run!(typedef_default_applied, TYPEDEF_DEFAULT_APPLIED, |v: Result<&Value>| match v {
+    Err(_) => true,
+    _ => false,
+});
+
+const TYPEDEF_DEFAULT_ORDER: &str = r#"
+{
+  type P<'a = i64, 'b> = ('a, 'b);
+  0
+}
+"#;
+
+run!(typedef_default_order, TYPEDEF_DEFAULT_ORDER, |v: Result<&Value>| match v {
+    Err(_) => true,
+    _ => false,
+});
+
+async fn type_ref_error(code: &str, msg: &str) -> Result<()> {
+    match testing::eval(code, &crate::TEST_REGISTER).await {
+        Ok((v, _)) => bail!("expected a type error, got {v}"),
+        Err(e) => {
+            let e = format!("{e:?}");
+            assert!(e.contains(msg), "unexpected error {e}");
+            assert!(e.contains("at line: 3, column: "), "missing position {e}");
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn undefined_type_ref_position() -> Result<()> {
+    let code = r#"{
+  type Known = i64;
+  let v: Unknown = 42;
+  v
+}"#;
+    type_ref_error(code, "undefined type Unknown").await
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn type_ref_arity_position() -> Result<()> {
+    let code = r#"{
+  type Pair<'a, 'b> = ('a, 'b);
+  let v: Pair<i64> = (1, 2);
+  v
+}"#;
+    type_ref_error(code, "Pair expects 2 type parameters").await
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn type_ref_default_arity_position() -> Result<()> {
+    let code = r#"{
+  type Pair<'a, 'b = i64> = ('a, 'b);
+  let v: Pair = (1, 2);
+  v
+}"#;
+    type_ref_error(code, "Pair expects between 1 and 2 type parameters").await
+}
@@ -40,3 +40,65 @@ run!(variants1, VARIANTS1, |v: Result<&Value>| match v {
     Ok(Value::I64(0)) => true,
     _ => false,
 });
+
+// dividing quantities combines their units
+const UNITS0: &str = r#"
+{
+  type Meters = `Meters(Quantity<f64>);
+  type Seconds = `Seconds(Quantity<f64>);
+  let d: Meters = `Meters(10.0);
+  let t: Seconds = `Seconds(4.0);
+  let v: `Meters_per_Seconds(Quantity<f64>) = d / t;
+  v
+}
+"#;
+
+run!(units0, UNITS0, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::String(tag), Value::F64(n)] => tag == "Meters_per_Seconds" && *n == 2.5,
+        _ => false,
+    },
+    _ => false,
+});
+
+// quantities with different units can't be added
+const UNITS1: &str = r#"
+{
+  let d: `Meters(Quantity<f64>) = `Meters(1.0);
+  let t: `Seconds(Quantity<f64>) = `Seconds(2.0);
+  d + t
+}
+"#;
+
+run!(units1, UNITS1, |v: Result<&Value>| match v {
+    Err(e) => format!("{e:?}").contains("the units are different"),
+    Ok(_) => false,
+});
+
+const UNITS2: &str = r#"
+{
+  type Meters = `Meters(Quantity<f64>);
+  let m = |x: f64| -> Meters `Meters(x);
+  let s: `Seconds(Quantity<f64>) = `Seconds(4.0);
+  [
+    m(1.0) + m(2.0) == `Meters(3.0),
+    m(2.0) * 3.0 == `Meters(6.0),
+    m(6.0) / m(2.0) == 3.0,
+    m(2.0) * m(3.0) == `Meters_Meters(6.0),
+    1.0 / s == `Per_Seconds(0.25)
+  ]
+}
+"#;
+
+run!(units2, UNITS2, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => a.iter().all(|v| v == &Value::Bool(true)),
+    _ => false,
+});
+
+// quantities are opt in, an ordinary variant with a number has no
+// arithmetic
+const UNITS3: &str = r#"
+  `Retry_count(1) + 1
+"#;
+
+run!(units3, UNITS3, |v: Result<&Value>| v.is_err());
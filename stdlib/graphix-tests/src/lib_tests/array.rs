@@ -143,6 +143,23 @@ run!(array_find_map, ARRAY_FIND_MAP, |v: Result<&Value>| {
     }
 });
 
+const ARRAY_INDEX_OF: &str = r#"
+{
+  let a = ["foo", "bar", "baz", "bar"];
+  [array::index_of(a, "foo"), array::index_of(a, "bar"), array::index_of(a, "qux")]
+}
+"#;
+
+run!(array_index_of, ARRAY_INDEX_OF, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::I64(0), Value::I64(1), Value::Null] => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
 const ARRAY_ITER: &str = r#"
    filter(array::iter([1, 2, 3, 4]), |x| x == 4)
 "#;
@@ -200,6 +217,71 @@ run!(array_fold1, ARRAY_FOLD1, |v: Result<&Value>| {
     }
 });
 
+const ARRAY_SCAN0: &str = r#"
+{
+  let a = [1, 2, 3, 4, 5];
+  array::scan(a, 0, |acc, x| x + acc)
+}
+"#;
+
+run!(array_scan0, ARRAY_SCAN0, |v: Result<&Value>| {
+    match v.and_then(|v| v.clone().cast_to::<[i64; 5]>()) {
+        Ok([1, 3, 6, 10, 15]) => true,
+        _ => false,
+    }
+});
+
+const ARRAY_SCAN1: &str = r#"
+{
+  let a: Array<i64> = [];
+  array::scan(a, 0, |acc, x| x + acc)
+}
+"#;
+
+run!(array_scan1, ARRAY_SCAN1, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => a.is_empty(),
+        _ => false,
+    }
+});
+
+// the accumulator type doesn't have to be the element type
+const ARRAY_SCAN2: &str = r#"
+{
+  let a = ["a", "bb", "ccc"];
+  array::scan(a, 0, |acc, x| acc + str::len(x))
+}
+"#;
+
+run!(array_scan2, ARRAY_SCAN2, |v: Result<&Value>| {
+    match v.and_then(|v| v.clone().cast_to::<[i64; 3]>()) {
+        Ok([1, 3, 6]) => true,
+        _ => false,
+    }
+});
+
+// when m updates only the last accumulator recomputes, the scan still
+// produces the whole array
+const ARRAY_SCAN3: &str = r#"
+{
+  let m = 0;
+  m <- 100;
+  let a = [1, 2, 5];
+  let s = array::scan(a, 0, |acc, x| select x {
+    x if x > 3 => acc + m,
+    x => acc + x
+  });
+  filter(s, |s| s != [1, 3, 3])
+}
+"#;
+
+run!(array_scan3, ARRAY_SCAN3, |v: Result<&Value>| {
+    match v.and_then(|v| v.clone().cast_to::<[i64; 3]>()) {
+        Ok([1, 3, 103]) => true,
+        _ => false,
+    }
+});
+
 const ARRAY_CONCAT: &str = r#"
   array::concat([1, 2, 3], [4, 5], [6])
 "#;
@@ -533,6 +615,62 @@ run!(array_zip, ARRAY_ZIP, |v: Result<&Value>| {
     }
 });
 
+const ARRAY_ZIP_WITH0: &str = r#"
+  array::zip_with([1, 2, 5, 7], [10, 20, 30], |x, y| x * y)
+"#;
+
+run!(array_zip_with0, ARRAY_ZIP_WITH0, |v: Result<&Value>| {
+    match v {
+        Ok(v) => match v.clone().cast_to::<[i64; 3]>() {
+            Ok([10, 40, 150]) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const ARRAY_ZIP_WITH1: &str = r#"
+  array::zip_with(["a", "b"], [1, 2, 3], |s, n| "[s][n * 2]")
+"#;
+
+run!(array_zip_with1, ARRAY_ZIP_WITH1, |v: Result<&Value>| {
+    match v {
+        Ok(v) => match v.clone().cast_to::<[ArcStr; 2]>() {
+            Ok([a, b]) => a == "a2" && b == "b4",
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const ARRAY_ZIP_WITH2: &str = r#"
+{
+  let a: Array<i64> = [];
+  array::zip_with(a, [1, 2], |x, y| x + y)
+}
+"#;
+
+run!(array_zip_with2, ARRAY_ZIP_WITH2, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => a.is_empty(),
+        _ => false,
+    }
+});
+
+const ARRAY_ZIP_WITH3: &str = r#"
+  array::zip_with([6, 4, 2], [3, 0, 1], |x, y| x /? y)
+"#;
+
+run!(array_zip_with3, ARRAY_ZIP_WITH3, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::I64(2), Value::Error(_), Value::I64(2)] => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
 const ARRAY_UNZIP: &str = r#"
 {
    let a = [(1, 1), (2, 2), (5, 3)];
@@ -549,3 +687,51 @@ run!(array_unzip, ARRAY_UNZIP, |v: Result<&Value>| {
         _ => false,
     }
 });
+
+const ARRAY_REVERSE: &str = r#"
+{
+   let a = [1, 2, 3, 4];
+   (array::reverse(a), array::reverse([42]))
+}
+"#;
+
+run!(array_reverse, ARRAY_REVERSE, |v: Result<&Value>| {
+    match v {
+        Ok(v) => match v.clone().cast_to::<([i64; 4], [i64; 1])>() {
+            Ok(([4, 3, 2, 1], [42])) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const ARRAY_ROTATE: &str = r#"
+{
+   let a = [1, 2, 3, 4, 5];
+   (array::rotate(a, 2), array::rotate(a, 7), array::rotate(a, -1), array::rotate(a, 0))
+}
+"#;
+
+run!(array_rotate, ARRAY_ROTATE, |v: Result<&Value>| {
+    match v {
+        Ok(v) => match v.clone().cast_to::<([i64; 5], [i64; 5], [i64; 5], [i64; 5])>() {
+            Ok(([3, 4, 5, 1, 2], [3, 4, 5, 1, 2], [5, 1, 2, 3, 4], [1, 2, 3, 4, 5])) => {
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const ARRAY_ROTATE_EMPTY: &str = r#"
+{
+   let a: Array<i64> = [];
+   array::len(array::rotate(a, -3))
+}
+"#;
+
+run!(array_rotate_empty, ARRAY_ROTATE_EMPTY, |v: Result<&Value>| match v {
+    Ok(Value::I64(0)) => true,
+    _ => false,
+});
@@ -111,3 +111,114 @@ const SHL_I64: &str = r#"
 run!(shl_i64, SHL_I64, |v: Result<&Value>| {
     matches!(v, Ok(Value::I64(0x100000000)))
 });
+
+const BIT_OPS_U32: &str = r#"
+[
+  bit_and(u32:0xF0F0F0F0, u32:0xFF00FF00),
+  bit_or(u32:0xF0000000, u32:0x0000000F),
+  bit_xor(u32:0xFFFFFFFF, u32:0x0F0F0F0F),
+  bit_not(u32:0)
+]
+"#;
+
+run!(bit_ops_u32, BIT_OPS_U32, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => matches!(
+        &a[..],
+        [
+            Value::U32(0xF000_F000),
+            Value::U32(0xF000_000F),
+            Value::U32(0xF0F0_F0F0),
+            Value::U32(u32::MAX)
+        ]
+    ),
+    _ => false,
+});
+
+const BIT_OPS_U64: &str = r#"
+[
+  bit_and(u64:0xFFFFFFFF00000000, u64:0xFF000000FF000000),
+  bit_or(u64:0x8000000000000000, u64:1),
+  bit_xor(u64:0xFFFFFFFFFFFFFFFF, u64:1),
+  bit_not(u64:0)
+]
+"#;
+
+run!(bit_ops_u64, BIT_OPS_U64, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => matches!(
+        &a[..],
+        [
+            Value::U64(0xFF00_0000_0000_0000),
+            Value::U64(0x8000_0000_0000_0001),
+            Value::U64(0xFFFF_FFFF_FFFF_FFFE),
+            Value::U64(u64::MAX)
+        ]
+    ),
+    _ => false,
+});
+
+const SHIFT_U32: &str = r#"
+[shl(u32:1, u32:31), shr(u32:0x80000000, u32:31)]
+"#;
+
+run!(shift_u32, SHIFT_U32, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => {
+        matches!(&a[..], [Value::U32(0x8000_0000), Value::U32(1)])
+    }
+    _ => false,
+});
+
+const SHIFT_U64: &str = r#"
+[shl(u64:1, u64:63), shr(u64:0x8000000000000000, u64:63)]
+"#;
+
+run!(shift_u64, SHIFT_U64, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => {
+        matches!(&a[..], [Value::U64(0x8000_0000_0000_0000), Value::U64(1)])
+    }
+    _ => false,
+});
+
+// ── signed ───────────────────────────────────────────────────────
+
+const BIT_NOT_SIGNED: &str = r#"
+[bit_not(i32:0), bit_not(i64:7)]
+"#;
+
+run!(bit_not_signed, BIT_NOT_SIGNED, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => matches!(&a[..], [Value::I32(-1), Value::I64(-8)]),
+    _ => false,
+});
+
+// shr on a signed type is an arithmetic shift
+const SHR_SIGNED: &str = r#"
+shr(0 - 16, 2)
+"#;
+
+run!(shr_signed, SHR_SIGNED, |v: Result<&Value>| {
+    matches!(v, Ok(Value::I64(-4)))
+});
+
+// ── out of range shifts ──────────────────────────────────────────
+
+const SHL_OUT_OF_RANGE_U32: &str = r#"
+shl(u32:1, u32:32)
+"#;
+
+run!(shl_out_of_range_u32, SHL_OUT_OF_RANGE_U32, |v: Result<&Value>| {
+    matches!(v, Ok(Value::Error(_)))
+});
+
+const SHR_OUT_OF_RANGE_U64: &str = r#"
+shr(u64:1, u64:64)
+"#;
+
+run!(shr_out_of_range_u64, SHR_OUT_OF_RANGE_U64, |v: Result<&Value>| {
+    matches!(v, Ok(Value::Error(_)))
+});
+
+const SHL_NEGATIVE: &str = r#"
+shl(1, 0 - 1)
+"#;
+
+run!(shl_negative, SHL_NEGATIVE, |v: Result<&Value>| {
+    matches!(v, Ok(Value::Error(_)))
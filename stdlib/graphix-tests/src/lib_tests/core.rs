@@ -1,6 +1,11 @@
 use anyhow::Result;
+use arcstr::ArcStr;
+use graphix_compiler::expr::Source;
 use graphix_package_core::run;
+use graphix_rt::TestOutcome;
 use netidx::subscriber::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
 const IS_ERR: &str = r#"
 {
@@ -39,6 +44,111 @@ run!(error, ERROR, |v: Result<&Value>| match v {
     _ => false,
 });
 
+const OR_ELSE0: &str = r#"
+  or_else(1, error("foo"), 3)
+"#;
+
+run!(or_else0, OR_ELSE0, |v: Result<&Value>| match v {
+    Ok(Value::I64(1)) => true,
+    _ => false,
+});
+
+const OR_ELSE1: &str = r#"
+  or_else(error("foo"), 2, 3)
+"#;
+
+run!(or_else1, OR_ELSE1, |v: Result<&Value>| match v {
+    Ok(Value::I64(2)) => true,
+    _ => false,
+});
+
+const OR_ELSE2: &str = r#"
+  or_else(error("foo"), error("bar"))
+"#;
+
+run!(or_else2, OR_ELSE2, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => matches!(&**e, Value::String(s) if s == "bar"),
+    _ => false,
+});
+
+const SEQUENCE0: &str = r#"
+  sequence([`Ok(1), `Ok(2), `Ok(3)])
+"#;
+
+run!(sequence0, SEQUENCE0, |v: Result<&Value>| is_i64s(v, &[1, 2, 3]));
+
+const SEQUENCE1: &str = r#"
+  sequence([`Ok(1), error(`Bad("first")), `Ok(3), error(`Bad("second"))])
+"#;
+
+run!(sequence1, SEQUENCE1, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+        Ok((tag, msg)) => tag == "Bad" && msg == "first",
+        Err(_) => false,
+    },
+    _ => false,
+});
+
+const SEQUENCE2: &str = r#"
+  sequence([])
+"#;
+
+run!(sequence2, SEQUENCE2, |v: Result<&Value>| is_i64s(v, &[]));
+
+const SEQUENCE3: &str = r#"
+  sequence([`Ok(1), `Err("first"), `Ok(3), `Err("second")])
+"#;
+
+run!(sequence3, SEQUENCE3, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => matches!(&**e, Value::String(s) if s == "first"),
+    _ => false,
+});
+
+const SEQUENCE4: &str = r#"
+  sequence([`Ok(1), `Err(`Bad("first")), error(`Bad("second"))])
+"#;
+
+run!(sequence4, SEQUENCE4, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+        Ok((tag, msg)) => tag == "Bad" && msg == "first",
+        Err(_) => false,
+    },
+    _ => false,
+});
+
+const WRAPPING_ADD: &str = r#"
+[
+  wrapping_add(9223372036854775807, 1),
+  wrapping_add(u8:255, u8:2),
+  wrapping_add(i8:127, i8:1)
+]
+"#;
+
+run!(wrapping_add, WRAPPING_ADD, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::I64(i64::MIN), Value::U8(1), Value::I8(-128)] => true,
+        _ => false,
+    },
+    _ => false,
+});
+
+const SATURATING_ADD: &str = r#"
+[
+  saturating_add(9223372036854775807, 1),
+  saturating_add(u8:255, u8:2),
+  saturating_add(i8:127, i8:1),
+  saturating_add(u8:2, u8:3)
+]
+"#;
+
+run!(saturating_add, SATURATING_ADD, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::I64(i64::MAX), Value::U8(255), Value::I8(127), Value::U8(5)] => true,
+        _ => false,
+    },
+    _ => false,
+});
+
 const ONCE: &str = r#"
 {
   let x = [1, 2, 3, 4, 5, 6];
@@ -211,6 +321,51 @@ run!(max, MAX, |v: Result<&Value>| match v {
     _ => false,
 });
 
+const RUNNING_MIN: &str = r#"
+{
+  let x = array::iter([3, 5, 4, 1, 2, 1, 0]);
+  array::group(running_min(x), |n, _| n == 3)
+}
+"#;
+
+run!(running_min, RUNNING_MIN, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::I64(3), Value::I64(1), Value::I64(0)] => true,
+        _ => false,
+    },
+    _ => false,
+});
+
+const RUNNING_MAX: &str = r#"
+{
+  let x = array::iter([3, 5, 4, 5, 1, 7, 2]);
+  array::group(running_max(x), |n, _| n == 3)
+}
+"#;
+
+run!(running_max, RUNNING_MAX, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::I64(3), Value::I64(5), Value::I64(7)] => true,
+        _ => false,
+    },
+    _ => false,
+});
+
+const RUNNING_MAX_MIXED: &str = r#"
+{
+  let x: Array<Number> = [1, 2.5, 2, u8:3];
+  array::group(running_max(array::iter(x)), |n, _| n == 3)
+}
+"#;
+
+run!(running_max_mixed, RUNNING_MAX_MIXED, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::I64(1), Value::F64(2.5), Value::U8(3)] => true,
+        _ => false,
+    },
+    _ => false,
+});
+
 const AND: &str = r#"
 {
   let x = 1;
@@ -291,6 +446,189 @@ run!(filter1, FILTER1, |v: Result<&Value>| {
     }
 });
 
+fn is_memoized(v: Result<&Value>, expected: &[(i64, i64)]) -> bool {
+    match v {
+        Ok(Value::Array(a)) => {
+            a.len() == expected.len()
+                && a.iter().zip(expected).all(|(v, (r, n))| match v {
+                    Value::Array(t) => match &t[..] {
+                        [Value::I64(r0), Value::I64(n0)] => r0 == r && n0 == n,
+                        _ => false,
+                    },
+                    _ => false,
+                })
+        }
+        _ => false,
+    }
+}
+
+const MEMOIZE0: &str = r#"
+{
+  let a = [1, 2, 1, 2, 3];
+  let r = memoize(array::iter(a), |k| (k * 10, count(k)));
+  array::group(r, |n, _| n == 5)
+}
+"#;
+
+run!(memoize0, MEMOIZE0, |v: Result<&Value>| is_memoized(
+    v,
+    &[(10, 1), (20, 2), (10, 1), (20, 2), (30, 3)]
+));
+
+const MEMOIZE1: &str = r#"
+{
+  let a = [1, 2, 1];
+  let r = memoize(#capacity: 1, array::iter(a), |k| (k * 10, count(k)));
+  array::group(r, |n, _| n == 3)
+}
+"#;
+
+run!(memoize1, MEMOIZE1, |v: Result<&Value>| is_memoized(
+    v,
+    &[(10, 1), (20, 2), (10, 3)]
+));
+
+const SWITCH0: &str = r#"
+{
+  let a = [0, 0, 1, 1, 0];
+  let sel = array::iter(a);
+  let r = switch(sel, {0 => || (0, count(sel)), 1 => || (1, count(sel))});
+  array::group(r, |n, _| n == 5)
+}
+"#;
+
+run!(switch0, SWITCH0, |v: Result<&Value>| is_memoized(
+    v,
+    &[(0, 1), (0, 2), (1, 1), (1, 2), (0, 1)]
+));
+
+const SWITCH1: &str = r#"
+{
+  let a = [0, 2, 1];
+  let sel = array::iter(a);
+  let r = switch(sel, {0 => || (0, count(sel)), 1 => || (1, count(sel))});
+  array::group(r, |n, _| n == 2)
+}
+"#;
+
+run!(switch1, SWITCH1, |v: Result<&Value>| is_memoized(v, &[(0, 1), (1, 1)]));
+
+const RETRY0: &str = r#"
+{
+  let n = 0;
+  let op = || select n {
+    x if x < 2 => { n <- x + 1; error(`Flaky("attempt [x] failed")) },
+    x => x
+  };
+  retry(#backoff: duration:0.01s, op)
+}
+"#;
+
+run!(retry0, RETRY0, |v: Result<&Value>| match v {
+    Ok(Value::I64(2)) => true,
+    _ => false,
+});
+
+const RETRY1: &str = r#"
+{
+  let n = 0;
+  let op = || { n <- n + 1; error(`Down("attempt [n] failed")) };
+  retry(#attempts: u64:4, #backoff: duration:0.01s, op)
+}
+"#;
+
+run!(retry1, RETRY1, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+        Ok((tag, msg)) => tag == "Down" && msg == "attempt 3 failed",
+        Err(_) => false,
+    },
+    _ => false,
+});
+
+const WITH_TIMEOUT0: &str = r#"
+  with_timeout(|| 42, duration:1.s)
+"#;
+
+run!(with_timeout0, WITH_TIMEOUT0, |v: Result<&Value>| match v {
+    Ok(Value::I64(42)) => true,
+    _ => false,
+});
+
+// the timeout is returned first, and the value still passes through when
+// it arrives
+const WITH_TIMEOUT1: &str = r#"
+{
+  let op = || sys::time::timer(duration:0.5s, false) ~ 42;
+  array::group(with_timeout(op, duration:0.05s), |n, _| n == 2)
+}
+"#;
+
+run!(with_timeout1, WITH_TIMEOUT1, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::Error(e), Value::I64(42)] => {
+            match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+                Ok((tag, _)) => tag == "Timeout",
+                Err(_) => false,
+            }
+        }
+        _ => false,
+    },
+    _ => false,
+});
+
+fn is_i64s(v: Result<&Value>, expected: &[i64]) -> bool {
+    match v {
+        Ok(Value::Array(a)) => {
+            a.len() == expected.len()
+                && a.iter().zip(expected).all(|(v, e)| match v {
+                    Value::I64(i) => i == e,
+                    _ => false,
+                })
+        }
+        _ => false,
+    }
+}
+
+const STATEFUL_FOLD0: &str = r#"
+{
+  let a = [1, 2, 3, 4, 5];
+  let r = stateful_fold(#init: 0, array::iter(a), |acc, x| acc * 2 + x);
+  array::group(r, |n, _| n == 5)
+}
+"#;
+
+run!(stateful_fold0, STATEFUL_FOLD0, |v: Result<&Value>| is_i64s(v, &[1, 4, 11, 26, 57]));
+
+const STATEFUL_FOLD1: &str = r#"
+{
+  let f = |acc, x| acc * 2 + x;
+  let first = stateful_fold(#init: 0, array::iter([1, 2, 3]), f);
+  let snapshot = array::group(first, |n, _| n == 3)[2]$;
+  let rest = stateful_fold(#init: snapshot, array::iter(snapshot ~ [4, 5]), f);
+  array::group(rest, |n, _| n == 2)
+}
+"#;
+
+run!(stateful_fold1, STATEFUL_FOLD1, |v: Result<&Value>| is_i64s(v, &[26, 57]));
+
+const DISTINCT_BY0: &str = r#"
+{
+  let a = [11, 12, 13, 21];
+  array::group(distinct_by(array::iter(a), |x| x / 10), |n, _| n == 2)
+}
+"#;
+
+run!(distinct_by0, DISTINCT_BY0, |v: Result<&Value>| is_i64s(v, &[11, 21]));
+
+const DISTINCT_BY1: &str = r#"
+{
+  let a = [11, 21, 22, 31];
+  array::group(distinct_by(array::iter(a), |x| x / 10), |n, _| n == 3)
+}
+"#;
+
+run!(distinct_by1, DISTINCT_BY1, |v: Result<&Value>| is_i64s(v, &[11, 21, 31]));
+
 const QUEUE: &str = r#"
 {
   let a = [1, 2, 3, 4, 5, 6, 7, 8];
@@ -367,6 +705,56 @@ run!(uniq, UNIQ, |v: Result<&Value>| {
     }
 });
 
+const PAIRWISE: &str = r#"
+{
+  let a = [1, 2, 4, 7];
+  array::group(pairwise(array::iter(a)), |n, _| n == 3)
+}
+"#;
+
+run!(pairwise, PAIRWISE, |v: Result<&Value>| {
+    let pair = |v: &Value, p: i64, c: i64| match v {
+        Value::Array(a) => match &a[..] {
+            [Value::I64(p0), Value::I64(c0)] => *p0 == p && *c0 == c,
+            _ => false,
+        },
+        _ => false,
+    };
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [p0, p1, p2] => pair(p0, 1, 2) && pair(p1, 2, 4) && pair(p2, 4, 7),
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const COLLECT_MAP: &str = r#"
+{
+  let ops: Array<(string, Option<i64>)> = [
+    ("a", 1),
+    ("b", 2),
+    ("a", 3),
+    ("c", 4),
+    ("b", null)
+  ];
+  let maps = array::group(collect_map(array::iter(ops)), |n, _| n == 5);
+  [
+    maps[0]? == {"a" => 1},
+    maps[2]? == {"a" => 3, "b" => 2},
+    maps[3]? == {"a" => 3, "b" => 2, "c" => 4},
+    maps[4]? == {"a" => 3, "c" => 4}
+  ]
+}
+"#;
+
+run!(collect_map, COLLECT_MAP, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => a.iter().all(|v| v == &Value::Bool(true)),
+        _ => false,
+    }
+});
+
 const SEQ: &str = r#"
   array::group(seq(0, 4), |n, _| n == 4)
 "#;
@@ -399,6 +787,125 @@ run!(throttle, THROTTLE, |v: Result<&Value>| {
     }
 });
 
+// x settles back on 1, which was already returned
+const DEBOUNCE_DISTINCT0: &str = r#"
+{
+  let x = 1;
+  let d = debounce_distinct(x, duration:0.01s);
+  x <- array::iter(d ~ [2, 1]);
+  let timeout = sys::time::timer(duration:0.2s, false) ~ 0;
+  array::group(any(d, timeout), |n, _| n == 2)
+}
+"#;
+
+run!(debounce_distinct0, DEBOUNCE_DISTINCT0, |v: Result<&Value>| is_i64s(v, &[1, 0]));
+
+// x passes through 2 without settling, and settles on 3
+const DEBOUNCE_DISTINCT1: &str = r#"
+{
+  let x = 1;
+  let d = debounce_distinct(x, duration:0.01s);
+  x <- array::iter(d ~ [2, 3]);
+  array::group(d, |n, _| n == 2)
+}
+"#;
+
+run!(debounce_distinct1, DEBOUNCE_DISTINCT1, |v: Result<&Value>| is_i64s(v, &[1, 3]));
+
+// a burst of 10 updates is counted until it leaves the window, however
+// long the updates take to process
+const RATE_BURST: &str = r#"
+{
+    let r = rate(#window: duration:1.s, array::iter([1, 2, 3, 4, 5, 6, 7, 8, 9, 10]));
+    array::group(r, |n, _| n == 10)
+}
+"#;
+
+run!(rate_burst, RATE_BURST, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match (a.first(), a.last()) {
+            (Some(Value::F64(first)), Some(Value::F64(last))) => {
+                *first == 1. && *last == 10.
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+// once the input stops the rate decays to 0
+const RATE_DECAY: &str = r#"
+{
+    let r = rate(#window: duration:0.2s, array::iter([1, 2, 3, 4, 5]));
+    array::group(r, |_, r| r == 0.0)
+}
+"#;
+
+run!(rate_decay, RATE_DECAY, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match (a.first(), a.last()) {
+            (Some(Value::F64(first)), Some(Value::F64(last))) => {
+                *first > 0. && *last == 0.
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+// the samples of x followed by the 0 from the timeout. However the updates
+// of x fall between the ticks no value is returned twice, the samples are
+// in the order x took them, and the last is the final value of x.
+fn is_sampled(v: Result<&Value>, first: Option<i64>, last: i64) -> bool {
+    let a = match v {
+        Ok(Value::Array(a)) => a,
+        _ => return false,
+    };
+    let mut samples = vec![];
+    for v in a.iter() {
+        match v {
+            Value::I64(i) => samples.push(*i),
+            _ => return false,
+        }
+    }
+    match samples.pop() {
+        Some(0) => (),
+        _ => return false,
+    }
+    samples.windows(2).all(|w| w[0] < w[1])
+        && first.map_or(!samples.is_empty(), |f| samples.first() == Some(&f))
+        && samples.last() == Some(&last)
+}
+
+// a burst is returned at most once per tick, ending with its latest value,
+// and is not returned again on later ticks. The timeout starts once the
+// latest value is returned, so it can only catch a repeated sample.
+const SAMPLE_RATE0: &str = r#"
+{
+    let s = sample_rate(array::iter([1, 2, 3, 4, 5]), 20.);
+    let done = filter(s, |v| v == 5);
+    let timeout = sys::time::timer(done ~ duration:0.2s, false) ~ 0;
+    array::group(any(s, timeout), |_, v| v == 0)
+}
+"#;
+
+run!(sample_rate0, SAMPLE_RATE0, |v: Result<&Value>| is_sampled(v, None, 5));
+
+// 2 and 3 arrive after the tick that returned 1, 3 is the last value
+// returned
+const SAMPLE_RATE1: &str = r#"
+{
+    let x = 1;
+    let s = sample_rate(x, 50.);
+    x <- array::iter(filter(s, |v| v == 1) ~ [2, 3]);
+    let done = filter(s, |v| v == 3);
+    let timeout = sys::time::timer(done ~ duration:0.2s, false) ~ 0;
+    array::group(any(s, timeout), |_, v| v == 0)
+}
+"#;
+
+run!(sample_rate1, SAMPLE_RATE1, |v: Result<&Value>| is_sampled(v, Some(1), 3));
+
 const NEVER: &str = r#"
 {
    let x = never(100);
@@ -427,48 +934,242 @@ run!(mean, MEAN, |v: Result<&Value>| {
     }
 });
 
-const RAND: &str = r#"
-  rand::rand(#clock:null)
+const CLAMP: &str = r#"
+{
+  [clamp(-5, 0, 10.0)?, clamp(5, 0, 10)?, clamp(15.5, 0, 10)?]
+}
 "#;
 
-run!(rand, RAND, |v: Result<&Value>| {
+run!(clamp, CLAMP, |v: Result<&Value>| {
     match v {
-        Ok(Value::F64(v)) if *v >= 0. && *v < 1.0 => true,
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::F64(0.), Value::F64(5.), Value::F64(10.)] => true,
+            _ => false,
+        },
         _ => false,
     }
 });
 
-const RAND_PICK: &str = r#"
-  rand::pick(["Chicken is coming", "Grape", "Pilot!"])
+const CLAMP_INVALID: &str = r#"
+  is_err(clamp(5, 10, 0))
 "#;
 
-run!(rand_pick, RAND_PICK, |v: Result<&Value>| {
+run!(clamp_invalid, CLAMP_INVALID, |v: Result<&Value>| {
     match v {
-        Ok(Value::String(v)) => v == "Chicken is coming" || v == "Grape" || v == "Pilot!",
+        Ok(Value::Bool(true)) => true,
         _ => false,
     }
 });
 
-const RAND_SHUFFLE: &str = r#"
-  rand::shuffle(["Chicken is coming", "Grape", "Pilot!"])
+const CLAMP_NAN: &str = r#"
+  [is_err(clamp(1.0, 0.0 / 0.0, 2.0)), is_err(clamp(1.0, 0.0, 0.0 / 0.0))]
 "#;
 
-run!(rand_shuffle, RAND_SHUFFLE, |v: Result<&Value>| {
+run!(clamp_nan, CLAMP_NAN, |v: Result<&Value>| {
     match v {
-        Ok(Value::Array(a)) if a.len() == 3 => {
-            a.contains(&Value::from("Chicken is coming"))
-                && a.contains(&Value::from("Grape"))
-                && a.contains(&Value::from("Pilot!"))
-        }
+        Ok(Value::Array(a)) => &a[..] == &[Value::Bool(true), Value::Bool(true)],
         _ => false,
     }
 });
 
-const HOLD_BASIC: &str = r#"
-{
-  let clock = 1;
-  let value = 42;
-  hold(#clock, value)
+const LERP: &str = r#"
+  [lerp(2, 10, 0), lerp(2, 10, 1), lerp(2, 10.0, 0.5)]
+"#;
+
+run!(lerp, LERP, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::F64(2.), Value::F64(10.), Value::F64(6.)] => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const ROUND_TO: &str = r#"
+[
+  round_to(decimal:2.345, 2)$ == decimal:2.35,
+  round_to(decimal:-2.345, 2)$ == decimal:-2.35,
+  round_to(#mode: `HalfEven, decimal:2.345, 2)$ == decimal:2.34,
+  round_to(#mode: `HalfEven, decimal:2.355, 2)$ == decimal:2.36,
+  round_to(#mode: `Down, decimal:2.349, 2)$ == decimal:2.34,
+  round_to(decimal:2.5, 0)$ == decimal:3
+]
+"#;
+
+run!(round_to, ROUND_TO, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => a.iter().all(|v| v == &Value::Bool(true)),
+    _ => false,
+});
+
+const ROUND_TO_INVALID: &str = r#"
+  is_err(round_to(decimal:2.345, -1))
+"#;
+
+run!(round_to_invalid, ROUND_TO_INVALID, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Bool(true)) => true,
+        _ => false,
+    }
+});
+
+const MOVING_AVERAGE_INPUT: [i64; 8] = [1, 5, 2, 8, 3, 9, 4, -6];
+
+fn naive_moving_average(n: usize) -> Vec<f64> {
+    (0..MOVING_AVERAGE_INPUT.len())
+        .map(|i| {
+            let w = &MOVING_AVERAGE_INPUT[i.saturating_sub(n - 1)..=i];
+            w.iter().sum::<i64>() as f64 / w.len() as f64
+        })
+        .collect()
+}
+
+fn is_moving_average(v: Result<&Value>, n: usize) -> bool {
+    let expected = naive_moving_average(n);
+    match v {
+        Ok(Value::Array(a)) => {
+            a.len() == expected.len()
+                && a.iter().zip(expected.iter()).all(|(v, e)| match v {
+                    Value::F64(v) => (v - e).abs() < 1e-9,
+                    _ => false,
+                })
+        }
+        _ => false,
+    }
+}
+
+const MOVING_AVERAGE0: &str = r#"
+{
+  let a = [1, 5, 2, 8, 3, 9, 4, -6];
+  let v = moving_average(#n: 3, array::iter(a));
+  array::group(v, |n, _| n == 8)
+}
+"#;
+
+run!(moving_average0, MOVING_AVERAGE0, |v: Result<&Value>| is_moving_average(v, 3));
+
+const MOVING_AVERAGE1: &str = r#"
+{
+  let a = [1, 5, 2, 8, 3, 9, 4, -6];
+  let v = moving_average(#n: 1, array::iter(a));
+  array::group(v, |n, _| n == 8)
+}
+"#;
+
+run!(moving_average1, MOVING_AVERAGE1, |v: Result<&Value>| is_moving_average(v, 1));
+
+fn is_histogram(v: &Value, expected: &[u64]) -> bool {
+    match v {
+        Value::Array(a) => {
+            a.len() == expected.len()
+                && a.iter().zip(expected).all(|(v, e)| match v {
+                    Value::U64(n) => n == e,
+                    _ => false,
+                })
+        }
+        _ => false,
+    }
+}
+
+const HISTOGRAM0: &str = r#"
+{
+  let a = [-5, 0, 3, 10, 15, 20, 25, 7];
+  let h = histogram(array::iter(a), [0, 10, 20]);
+  array::group(h, |n, _| n == 8)
+}
+"#;
+
+run!(histogram0, HISTOGRAM0, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => {
+        a.len() == 8
+            && is_histogram(&a[0], &[1, 0, 0, 0])
+            && is_histogram(&a[5], &[1, 2, 2, 1])
+            && is_histogram(&a[7], &[1, 3, 2, 2])
+    }
+    _ => false,
+});
+
+const HISTOGRAM1: &str = r#"
+{
+  let a = [0.5, 1.5, 2.5];
+  let h = histogram(array::iter(a), [1.0, 2.0]);
+  array::group(h, |n, _| n == 3)
+}
+"#;
+
+run!(histogram1, HISTOGRAM1, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => a.len() == 3 && is_histogram(&a[2], &[1, 1, 1]),
+    _ => false,
+});
+
+const TRY_CAST0: &str = r#"
+{
+  type T = {a: i64, b: string};
+  let r: [`Ok(T), `Err] = try_cast({a: 1, b: "x"});
+  select r { `Ok(t) => t.a, `Err => -1 }
+}
+"#;
+
+run!(try_cast0, TRY_CAST0, |v: Result<&Value>| match v {
+    Ok(Value::I64(1)) => true,
+    _ => false,
+});
+
+const TRY_CAST1: &str = r#"
+{
+  type T = {a: i64, b: string};
+  let r: [`Ok(T), `Err] = try_cast({a: "foo"});
+  select r { `Ok(t) => t.a, `Err => -1 }
+}
+"#;
+
+run!(try_cast1, TRY_CAST1, |v: Result<&Value>| match v {
+    Ok(Value::I64(-1)) => true,
+    _ => false,
+});
+
+const RAND: &str = r#"
+  rand::rand(#clock:null)
+"#;
+
+run!(rand, RAND, |v: Result<&Value>| {
+    match v {
+        Ok(Value::F64(v)) if *v >= 0. && *v < 1.0 => true,
+        _ => false,
+    }
+});
+
+const RAND_PICK: &str = r#"
+  rand::pick(["Chicken is coming", "Grape", "Pilot!"])
+"#;
+
+run!(rand_pick, RAND_PICK, |v: Result<&Value>| {
+    match v {
+        Ok(Value::String(v)) => v == "Chicken is coming" || v == "Grape" || v == "Pilot!",
+        _ => false,
+    }
+});
+
+const RAND_SHUFFLE: &str = r#"
+  rand::shuffle(["Chicken is coming", "Grape", "Pilot!"])
+"#;
+
+run!(rand_shuffle, RAND_SHUFFLE, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) if a.len() == 3 => {
+            a.contains(&Value::from("Chicken is coming"))
+                && a.contains(&Value::from("Grape"))
+                && a.contains(&Value::from("Pilot!"))
+        }
+        _ => false,
+    }
+});
+
+const HOLD_BASIC: &str = r#"
+{
+  let clock = 1;
+  let value = 42;
+  hold(#clock, value)
 }
 "#;
 
@@ -519,9 +1220,634 @@ run!(hold_multiple_values, HOLD_MULTIPLE_VALUES, |v: Result<&Value>| match v {
     _ => false,
 });
 
+fn is_batches(v: Result<&Value>, expected: &[&[i64]]) -> bool {
+    match v {
+        Ok(Value::Array(a)) => {
+            a.len() == expected.len()
+                && a.iter().zip(expected).all(|(v, e)| is_i64s(Ok(v), e))
+        }
+        _ => false,
+    }
+}
+
+// strings count graphemes, "🇯🇵" is one grapheme but two chars
+const LEN: &str = r#"
+[
+  len("é🇯🇵x")?,
+  len([1, 2, 3, 4])?,
+  len({"a" => 1, "b" => 2})?,
+  len((1, "two", 3.0))?,
+  len({a: 1, b: "b", c: null, d: 4})?,
+  len([])?
+]
+"#;
+
+run!(len, LEN, |v: Result<&Value>| is_i64s(v, &[3, 4, 2, 3, 4, 0]));
+
+const LEN_SCALAR: &str = r#"
+len(42)
+"#;
+
+run!(len_scalar, LEN_SCALAR, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+        Ok((tag, _)) => tag == "LenError",
+        Err(_) => false,
+    },
+    _ => false,
+});
+
+// variants are arrays at runtime, but their tag isn't counted
+const LEN_VARIANT: &str = r#"
+{
+  type T = [`Foo(i64, i64), `Bar];
+  let bar: T = `Bar;
+  [len(`Foo(1, 2))?, len(`Bar)?, len(bar)?, len(`Baz("a"))?]
+}
+"#;
+
+run!(len_variant, LEN_VARIANT, |v: Result<&Value>| is_i64s(v, &[2, 0, 0, 1]));
+
+const LEN_MIXED: &str = r#"
+{
+  let x: [`Foo, string] = `Foo;
+  len(x)
+}
+"#;
+
+run!(len_mixed, LEN_MIXED, |v: Result<&Value>| v.is_err());
+
+const TO_STRING_LIMITED_DEPTH: &str = r#"
+to_string_limited(#max_depth:2, (1, (2, (3, (4, 5)))))
+"#;
+
+run!(to_string_limited_depth, TO_STRING_LIMITED_DEPTH, |v: Result<&Value>| {
+    match v {
+        Ok(Value::String(s)) => s == "[1, [2, [...]]]",
+        _ => false,
+    }
+});
+
+const TO_STRING_LIMITED_LEN: &str = r#"
+to_string_limited(#max_len:3, ([1, 2, 3, 4], 5, 6, 7, 8, 9))
+"#;
+
+run!(to_string_limited_len, TO_STRING_LIMITED_LEN, |v: Result<&Value>| {
+    match v {
+        Ok(Value::String(s)) => s == "[[1, 2, 3, ... (1 more)], 5, 6, ... (3 more)]",
+        _ => false,
+    }
+});
+
+const TO_STRING_LIMITED_TOTAL: &str = r#"
+to_string_limited(#max_len:100, #max_total:12, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10])
+"#;
+
+run!(to_string_limited_total, TO_STRING_LIMITED_TOTAL, |v: Result<&Value>| {
+    match v {
+        Ok(Value::String(s)) => s == "[1, 2, 3, 4,...",
+        _ => false,
+    }
+});
+
+const TO_STRING_LIMITED_STRING: &str = r#"
+to_string_limited(#max_total:10, ["abcdefghijklmnopqrstuvwxyz"])
+"#;
+
+run!(to_string_limited_string, TO_STRING_LIMITED_STRING, |v: Result<&Value>| {
+    match v {
+        Ok(Value::String(s)) => {
+            s.len() == 13
+                && s.starts_with("[")
+                && s.ends_with("...")
+                && s.contains("abcdef")
+        }
+        _ => false,
+    }
+});
+
+const BATCH_EXACT: &str = r#"
+{
+  let b = batch(array::iter([1, 2, 3, 4, 5, 6]), 3);
+  array::group(b, |n, _| n == 2)
+}
+"#;
+
+run!(batch_exact, BATCH_EXACT, |v: Result<&Value>| {
+    is_batches(v, &[&[1, 2, 3], &[4, 5, 6]])
+});
+
+const BATCH_FLUSH: &str = r#"
+{
+  let flush_on = sys::time::timer(duration:0.5s, false);
+  let b = batch(#flush_on, array::iter([1, 2, 3, 4, 5]), 3);
+  array::group(b, |n, _| n == 2)
+}
+"#;
+
+run!(batch_flush, BATCH_FLUSH, |v: Result<&Value>| {
+    is_batches(v, &[&[1, 2, 3], &[4, 5]])
+});
+
+const BATCH_ONE: &str = r#"
+{
+  let b = batch(array::iter([1, 2, 3]), 1);
+  array::group(b, |n, _| n == 3)
+}
+"#;
+
+run!(batch_one, BATCH_ONE, |v: Result<&Value>| is_batches(v, &[&[1], &[2], &[3]]));
+
+const GROUP_ADJACENT: &str = r#"
+{
+  let g = group_adjacent(array::iter([1, 2, 11, 12, 13, 21, 3]), |n| n / 10);
+  array::group(g, |n, _| n == 3)
+}
+"#;
+
+run!(group_adjacent, GROUP_ADJACENT, |v: Result<&Value>| {
+    is_batches(v, &[&[1, 2], &[11, 12, 13], &[21]])
+});
+
+const GROUP_ADJACENT_FLUSH: &str = r#"
+{
+  let flush_on = sys::time::timer(duration:0.5s, false);
+  let g = group_adjacent(#flush_on, array::iter([1, 2, 11, 12]), |n| n / 10);
+  array::group(g, |n, _| n == 2)
+}
+"#;
+
+run!(group_adjacent_flush, GROUP_ADJACENT_FLUSH, |v: Result<&Value>| {
+    is_batches(v, &[&[1, 2], &[11, 12]])
+});
+
+const WHEN_SUPPRESS: &str = r#"
+{
+  let cond = array::iter([true, false, true]);
+  let v = when(cond, array::iter([1, 2, 3]));
+  array::group(v, |n, _| n == 2)
+}
+"#;
+
+run!(when_suppress, WHEN_SUPPRESS, |v: Result<&Value>| is_i64s(v, &[1, 3]));
+
+const WHEN_PASSTHROUGH: &str = r#"
+{
+  let cond = true;
+  let v = when(cond, array::iter([1, 2, 3]));
+  array::group(v, |n, _| n == 3)
+}
+"#;
+
+run!(when_passthrough, WHEN_PASSTHROUGH, |v: Result<&Value>| is_i64s(v, &[1, 2, 3]));
+
+const WHEN_REEMIT: &str = r#"
+{
+  let cond = array::iter([false, true]);
+  when(cond, 42)
+}
+"#;
+
+run!(when_reemit, WHEN_REEMIT, |v: Result<&Value>| match v {
+    Ok(Value::I64(42)) => true,
+    _ => false,
+});
+
+const WHEN_TYPE: &str = r#"
+{
+  let x: i64 = when(true, 41);
+  x + 1
+}
+"#;
+
+run!(when_type, WHEN_TYPE, |v: Result<&Value>| match v {
+    Ok(Value::I64(42)) => true,
+    _ => false,
+});
+
+const UNLESS: &str = r#"
+{
+  let cond = array::iter([false, true, false]);
+  let v = unless(cond, array::iter([1, 2, 3]));
+  array::group(v, |n, _| n == 2)
+}
+"#;
+
+run!(unless, UNLESS, |v: Result<&Value>| is_i64s(v, &[1, 3]));
+
+const COMPARE_AND_SET0: &str = r#"
+{
+  let x = 1;
+  let ok = compare_and_set(&x, 1, 2);
+  array::group(x, |n, _| n == 2) ~ ok
+}
+"#;
+
+run!(compare_and_set0, COMPARE_AND_SET0, |v: Result<&Value>| match v {
+    Ok(Value::Bool(true)) => true,
+    _ => false,
+});
+
+const COMPARE_AND_SET1: &str = r#"
+{
+  let x = 1;
+  let a = compare_and_set(&x, 1, 2);
+  let b = compare_and_set(&x, 1, 3);
+  let c = compare_and_set(&x, 5, 4);
+  (a, b, c, array::group(x, |n, _| n == 2))
+}
+"#;
+
+run!(compare_and_set1, COMPARE_AND_SET1, |v: Result<&Value>| match v {
+    // b sees the value a set, and neither b nor c write x
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::Bool(true), Value::Bool(false), Value::Bool(false), x] => {
+            is_i64s(Ok(x), &[1, 2])
+        }
+        _ => false,
+    },
+    _ => false,
+});
+
+const SET_FIELD0: &str = r#"
+{
+  let s = {a: 1, b: "x"};
+  set_field(&s, "a", 2);
+  select s.a { 2 => s, _ => never() }
+}
+"#;
+
+run!(set_field0, SET_FIELD0, |v: Result<&Value>| match v {
+    // a is updated through the ref and b is preserved
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::Array(fa), Value::Array(fb)] => {
+            &fa[..] == &[Value::from("a"), Value::I64(2)]
+                && &fb[..] == &[Value::from("b"), Value::from("x")]
+        }
+        _ => false,
+    },
+    _ => false,
+});
+
+const SET_FIELD1: &str = r#"
+{
+  let s = {a: 1, b: "x"};
+  set_field(&s, "c", 2)
+}
+"#;
+
+run!(set_field1, SET_FIELD1, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+        Ok((tag, msg)) => tag == "SetFieldError" && msg == "no field c",
+        Err(_) => false,
+    },
+    _ => false,
+});
+
+const SET_FIELD2: &str = r#"
+{
+  let s = {a: 1, b: "x"};
+  set_field(&s, "a", "y")
+}
+"#;
+
+run!(set_field2, SET_FIELD2, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+        Ok((tag, _)) => tag == "SetFieldError",
+        Err(_) => false,
+    },
+    _ => false,
+});
+
+const SET_FIELD3: &str = r#"
+{
+  let i = 1;
+  set_field(&i, "a", 2)
+}
+"#;
+
+run!(set_field3, SET_FIELD3, |v: Result<&Value>| match v {
+    Err(_) => true,
+    _ => false,
+});
+
+const STRUCT_TO_MAP: &str = r#"
+  struct_to_map({a: 1, b: "x"})
+"#;
+
+run!(struct_to_map, STRUCT_TO_MAP, |v: Result<&Value>| match v {
+    Ok(Value::Map(m)) => {
+        m.len() == 2
+            && m.get(&Value::from("a")) == Some(&Value::I64(1))
+            && m.get(&Value::from("b")) == Some(&Value::from("x"))
+    }
+    _ => false,
+});
+
+const MAP_TO_STRUCT0: &str = r#"
+{
+  type T = {a: i64, b: string};
+  let r: Result<T, `StructError(string)> = map_to_struct({"a" => 1, "b" => "x"});
+  let t: T = r?;
+  t.b
+}
+"#;
+
+run!(map_to_struct0, MAP_TO_STRUCT0, |v: Result<&Value>| match v {
+    Ok(Value::String(s)) => &**s == "x",
+    _ => false,
+});
+
+const MAP_TO_STRUCT1: &str = r#"
+{
+  type T = {a: i64, b: string};
+  let r: Result<T, `StructError(string)> = map_to_struct(struct_to_map({a: 2, b: "y"}));
+  let t: T = r?;
+  t.a
+}
+"#;
+
+run!(map_to_struct1, MAP_TO_STRUCT1, |v: Result<&Value>| match v {
+    Ok(Value::I64(2)) => true,
+    _ => false,
+});
+
+const MAP_TO_STRUCT2: &str = r#"
+{
+  type T = {a: i64, b: string};
+  let r: Result<T, `StructError(string)> = map_to_struct({"a" => 1});
+  r
+}
+"#;
+
+run!(map_to_struct2, MAP_TO_STRUCT2, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+        Ok((tag, msg)) => tag == "StructError" && msg == "missing field b",
+        Err(_) => false,
+    },
+    _ => false,
+});
+
+const MAP_TO_STRUCT3: &str = r#"
+{
+  type T = {a: i64, b: string};
+  let r: Result<T, `StructError(string)> = map_to_struct({"a" => 1, "b" => "x", "c" => 2});
+  r
+}
+"#;
+
+run!(map_to_struct3, MAP_TO_STRUCT3, |v: Result<&Value>| match v {
+    Ok(Value::Error(e)) => match e.clone().cast_to::<(ArcStr, ArcStr)>() {
+        Ok((tag, msg)) => tag == "StructError" && msg == "unexpected field c",
+        Err(_) => false,
+    },
+    _ => false,
+});
+
+const TAP: &str = r#"
+{
+  let seen = never();
+  let v = tap(array::iter([1, 2, 3]), |x| seen <- x * 10);
+  [array::group(v, |n, _| n == 3), array::group(seen, |n, _| n == 3)]
+}
+"#;
+
+run!(tap, TAP, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [v, seen] => is_i64s(Ok(v), &[1, 2, 3]) && is_i64s(Ok(seen), &[10, 20, 30]),
+        _ => false,
+    },
+    _ => false,
+});
+
 const NOW: &str = r#"sys::time::now(null)"#;
 
 run!(now, NOW, |v: Result<&Value>| match v {
     Ok(Value::DateTime(_)) => true,
     _ => false,
 });
+
+const TYPEOF_PRIMITIVES: &str = r#"
+  [typeof(42), typeof(u8:3), typeof("foo"), typeof(3.14), typeof(null)]
+"#;
+
+run!(typeof_primitives, TYPEOF_PRIMITIVES, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::String(i), Value::String(u), Value::String(s), Value::String(f), Value::String(n)] => {
+                &**i == "i64"
+                    && &**u == "u8"
+                    && &**s == "string"
+                    && &**f == "f64"
+                    && &**n == "null"
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const TYPEOF_ARRAY: &str = r#"
+  [typeof([1, 2, 3]), typeof([])]
+"#;
+
+run!(typeof_array, TYPEOF_ARRAY, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::String(a), Value::String(e)] => {
+                &**a == "Array<i64>" && &**e == "Array<_>"
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const TYPEOF_STRUCT: &str = r#"
+  typeof({a: "foo", b: [1, 2]})
+"#;
+
+run!(typeof_struct, TYPEOF_STRUCT, |v: Result<&Value>| match v {
+    Ok(Value::String(s)) => &**s == "{a: string, b: Array<i64>}",
+    _ => false,
+});
+
+const TYPEOF_ERROR: &str = r#"
+  typeof(error("foo"))
+"#;
+
+run!(typeof_error, TYPEOF_ERROR, |v: Result<&Value>| match v {
+    Ok(Value::String(s)) => &**s == "Error<string>",
+    _ => false,
+});
+
+const SAME_SHARED: &str = r#"
+{
+  let a = [1, 2, 3];
+  let b = a;
+  [same(a, b), a == b]
+}
+"#;
+
+run!(same_shared, SAME_SHARED, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::Bool(true), Value::Bool(true)] => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const SAME_FRESH: &str = r#"
+{
+  let x = 3;
+  let a = [1, 2, x];
+  let b = [1, 2, x];
+  [same(a, b), a == b]
+}
+"#;
+
+run!(same_fresh, SAME_FRESH, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::Bool(false), Value::Bool(true)] => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const SAME_PRIMITIVE: &str = r#"
+  [same(42, 42), same(42, 43)]
+"#;
+
+run!(same_primitive, SAME_PRIMITIVE, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::Bool(true), Value::Bool(false)] => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+// structurally equal values hash equal however they were built
+const HASH_EQUAL: &str = r#"
+{
+  let x = 3;
+  let a = [1, 2, x];
+  let b = array::map([0, 1, 2], |i| i + 1);
+  let m0 = {"a" => 1, "b" => 2};
+  let m1 = {"b" => 2, "a" => 1};
+  let s0 = {x: 1, y: "foo"};
+  let s1 = {y: "foo", x: 1};
+  [hash(a) == hash(b), hash(m0) == hash(m1), hash(s0) == hash(s1), str::len(hash(a))]
+}
+"#;
+
+run!(hash_equal, HASH_EQUAL, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::Bool(true), Value::Bool(true), Value::Bool(true), Value::I64(16)] => {
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const HASH_DIFFERENT: &str = r#"
+[
+  hash([1, 2, 3]) == hash([3, 2, 1]),
+  hash({"a" => 1}) == hash({"a" => 2}),
+  hash("foo") == hash("bar"),
+  hash(42) == hash(43)
+]
+"#;
+
+run!(hash_different, HASH_DIFFERENT, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => a.iter().all(|v| matches!(v, Value::Bool(false))),
+        _ => false,
+    }
+});
+
+const CACHED_SINGLE_ARG: &str = r#"
+{
+  let s = array::iter(["a", "bb", "", "dddd"]);
+  array::group(str::len(s), |n, _| n == 4)
+}
+"#;
+
+run!(cached_single_arg, CACHED_SINGLE_ARG, |v: Result<&Value>| match v {
+    Ok(v) => match v.clone().cast_to::<[i64; 4]>() {
+        Ok([1, 2, 0, 4]) => true,
+        _ => false,
+    },
+    _ => false,
+});
+
+const CACHED_MULTI_ARG: &str = r#"
+{
+  let s = array::iter(["a", "bb", "ccc"]);
+  array::group(str::concat(s, "-", s), |n, _| n == 3)
+}
+"#;
+
+run!(cached_multi_arg, CACHED_MULTI_ARG, |v: Result<&Value>| match v {
+    Ok(v) => match v.clone().cast_to::<[String; 3]>() {
+        Ok([a, b, c]) => a == "a-a" && b == "bb-bb" && c == "ccc-ccc",
+        _ => false,
+    },
+    _ => false,
+});
+
+const TEST_CASE: &str = r#"
+{
+  let passed = test::case("pass", || test::assert_eq(1 + 1, 2));
+  let failed = test::case("fail", || { test::assert(false)?; test::assert(true) });
+  [passed, failed]
+}
+"#;
+
+run!(test_case, TEST_CASE, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => &a[..] == &[Value::Bool(true), Value::Bool(false)],
+    _ => false,
+});
+
+#[tokio::test(flavor = "current_thread")]
+async fn run_tests() -> Result<()> {
+    let (tx, _rx) = mpsc::channel(10);
+    let ctx = crate::init(tx).await?;
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/test/basics.gx");
+    let results =
+        ctx.rt.run_tests(Source::File(path.into()), Duration::from_secs(10)).await?;
+    let names = results.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["addition", "chained", "raises", "returns"]);
+    let passed = results.iter().filter(|r| r.outcome == TestOutcome::Passed).count();
+    assert_eq!(passed, 2);
+    for r in &results[2..] {
+        match &r.outcome {
+            TestOutcome::Failed(e) => assert!(e.contains("AssertionFailed"), "{e}"),
+            o => panic!("expected {} to fail, got {o:?}", r.name),
+        }
+    }
+    ctx.shutdown().await;
+    Ok(())
+}
+
+// a file without any cases is done as soon as it's initialized, it doesn't
+// wait out the timeout
+#[tokio::test(flavor = "current_thread")]
+async fn run_tests_empty() -> Result<()> {
+    let (tx, _rx) = mpsc::channel(10);
+    let ctx = crate::init(tx).await?;
+    let src = Source::Internal(ArcStr::from("let x = 1 + 1"));
+    let st = Instant::now();
+    let results = ctx.rt.run_tests(src, Duration::from_secs(10)).await?;
+    assert!(results.is_empty());
+    assert!(st.elapsed() < Duration::from_secs(5), "{:?}", st.elapsed());
+    ctx.shutdown().await;
+    Ok(())
+}
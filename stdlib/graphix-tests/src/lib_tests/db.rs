@@ -1,5 +1,5 @@
 use anyhow::Result;
-use graphix_package_core::run_with_tempdir;
+use graphix_package_core::{run_with_tempdir, testing};
 use netidx::subscriber::Value;
 
 fn assert_tree_type(v: &Value, expected_key: &str, expected_val: &str) {
@@ -580,8 +580,8 @@ run_with_tempdir!(
     name: db_reserved_tree_name,
     code: r#"{{
         let db = db::open("{}")$;
-        let r1: Result<db::Tree<string, string>, `DbErr(string)> = db::tree(db, "$$__graphix_default__$$");
-        let r2: Result<db::Tree<string, string>, `DbErr(string)> = db::tree(db, r1 ~ "$$__graphix_meta__$$");
+        let r1: Result<db::Tree<string, string>, [`DbErr(string), `Cancelled(string)]> = db::tree(db, "$$__graphix_default__$$");
+        let r2: Result<db::Tree<string, string>, [`DbErr(string), `Cancelled(string)]> = db::tree(db, r1 ~ "$$__graphix_meta__$$");
         (is_err(r1), is_err(r2))
     }}"#,
     setup: |td| {
@@ -602,7 +602,7 @@ run_with_tempdir!(
         let db = db::open("{}")$;
         let t1: db::Tree<string, i64> = db::tree(db, "t")?;
         let ins = db::insert(t1, "x", 1)$;
-        let t2: [db::Tree<i64, string>, Error<`DbErr(string)>] = db::tree(db, ins ~ "t");
+        let t2: [db::Tree<i64, string>, Error<[`DbErr(string), `Cancelled(string)]>] = db::tree(db, ins ~ "t");
         (is_err(t1), is_err(t2))
     }}"#,
     setup: |td| {
@@ -623,7 +623,7 @@ run_with_tempdir!(
         let db = db::open("{}")$;
         let t1: db::Tree<string, i64> = db::tree(db, null)?;
         let ins = db::insert(t1, "x", 1)$;
-        let t2: [db::Tree<i64, string>, Error<`DbErr(string)>] = db::tree(ins ~ db, null);
+        let t2: [db::Tree<i64, string>, Error<[`DbErr(string), `Cancelled(string)]>] = db::tree(ins ~ db, null);
         (is_err(t1), is_err(t2))
     }}"#,
     setup: |td| {
@@ -1005,3 +1005,78 @@ run_with_tempdir!(
         Ok(())
     }
 );
+
+const DB_PERSIST_WRITE: &str = r#"{{
+    let db = db::open("{}")$;
+    let t: db::Tree<string, i64> = db::tree(db, "state")$;
+    let old = db::insert(t, "counter", 42)$;
+    db::flush(old ~ db)
+}}"#;
+
+const DB_PERSIST_READ: &str = r#"{{
+    let db = db::open("{}")$;
+    let t: db::Tree<string, i64> = db::tree(db, "state")$;
+    db::get(t, "counter")
+}}"#;
+
+// a value written by one runtime is visible to a new runtime that reopens
+// the same database
+#[tokio::test(flavor = "current_thread")]
+async fn db_persist_reopen() -> Result<()> {
+    let td = tempfile::tempdir()?;
+    let path = td.path().join("persist.db");
+    let path = testing::escape_path(path.display());
+    let code = format!(DB_PERSIST_WRITE, path);
+    let (v, ctx) = testing::eval(&code, &crate::TEST_REGISTER).await?;
+    assert!(matches!(v, Value::Null), "expected flush to succeed, got: {v:?}");
+    // shutdown returns once the runtime has released the database
+    ctx.rt.shutdown().await?;
+    ctx.shutdown().await;
+    let code = format!(DB_PERSIST_READ, path);
+    let (v, ctx) = testing::eval(&code, &crate::TEST_REGISTER).await?;
+    assert!(matches!(v, Value::I64(42)), "expected I64(42), got: {v:?}");
+    ctx.shutdown().await;
+    Ok(())
+}
+
+run_with_tempdir!(
+    name: kv_set_get_delete,
+    code: r#"{{
+        let path = "{}";
+        let set = db::kv::set(path, "a", 42)$;
+        let v = db::kv::get(set ~ path, "a")$;
+        let deleted = db::kv::delete(v ~ path, "a")$;
+        let gone = db::kv::get(deleted ~ path, "a")$;
+        (v, deleted, gone)
+    }}"#,
+    setup: |td| {
+        td.path().join("kv.db")
+    },
+    expect: |v: Value| -> Result<()> {
+        let arr = match &v { Value::Array(a) => a, _ => panic!("not array: {v:?}") };
+        assert!(matches!(arr[0], Value::I64(42)), "expected I64(42), got: {:?}", arr[0]);
+        assert!(matches!(arr[1], Value::Bool(true)), "expected true, got: {:?}", arr[1]);
+        assert!(matches!(arr[2], Value::Null), "expected null, got: {:?}", arr[2]);
+        Ok(())
+    }
+);
+
+// a value set in one runtime can be read by a new runtime using the same
+// store
+#[tokio::test(flavor = "current_thread")]
+async fn kv_persist_reopen() -> Result<()> {
+    let td = tempfile::tempdir()?;
+    let path = td.path().join("kv_persist.db");
+    let path = testing::escape_path(path.display());
+    let code = format!(r#"db::kv::set("{}", "counter", 42)"#, path);
+    let (v, ctx) = testing::eval(&code, &crate::TEST_REGISTER).await?;
+    assert!(matches!(v, Value::Null), "expected set to succeed, got: {v:?}");
+    // shutdown returns once the runtime has closed the store
+    ctx.rt.shutdown().await?;
+    ctx.shutdown().await;
+    let code = format!(r#"db::kv::get("{}", "counter")"#, path);
+    let (v, ctx) = testing::eval(&code, &crate::TEST_REGISTER).await?;
+    assert!(matches!(v, Value::I64(42)), "expected I64(42), got: {v:?}");
+    ctx.shutdown().await;
+    Ok(())
+}
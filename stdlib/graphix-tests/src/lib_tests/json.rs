@@ -66,7 +66,7 @@ run!(json_pretty, r#"{
 });
 
 run!(json_invalid, r#"{
-    let r: Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string)]> = json::read("not json{{{");
+    let r: Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]> = json::read("not json{{{");
     is_err(r)
 }"#, |v: Result<&Value>| {
     matches!(v, Ok(Value::Bool(true)))
@@ -39,6 +39,43 @@ run!(map_get_absent, MAP_GET_ABSENT, |v: Result<&Value>| match v {
     _ => false,
 });
 
+const MAP_GET_OR_PRESENT: &str = r#"
+{
+  let m = {"a" => 1, "b" => 2, "c" => 3};
+  map::get_or(m, "b", 0)
+}
+"#;
+
+run!(map_get_or_present, MAP_GET_OR_PRESENT, |v: Result<&Value>| match v {
+    Ok(Value::I64(2)) => true,
+    _ => false,
+});
+
+const MAP_GET_OR_ABSENT: &str = r#"
+{
+  let m = {"a" => 1, "b" => 2, "c" => 3};
+  map::get_or(m, "d", 0)
+}
+"#;
+
+run!(map_get_or_absent, MAP_GET_OR_ABSENT, |v: Result<&Value>| match v {
+    Ok(Value::I64(0)) => true,
+    _ => false,
+});
+
+const MAP_GET_OR_LATER: &str = r#"
+{
+  let m = {"a" => 1};
+  m <- once(map::insert(m, "b", 2));
+  array::group(map::get_or(m, "b", 0), |n, _| n == 2)
+}
+"#;
+
+run!(map_get_or_later, MAP_GET_OR_LATER, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => matches!(&a[..], [Value::I64(0), Value::I64(2)]),
+    _ => false,
+});
+
 const MAP_MAP: &str = r#"
 {
   let m = {"a" => 1, "b" => 2, "c" => 3};
@@ -1,6 +1,9 @@
 use anyhow::Result;
+use arcstr::ArcStr;
 use graphix_package_core::run;
-use netidx::subscriber::Value;
+use netidx::{path::Path, subscriber::Value};
+use std::time::Duration;
+use tokio::{sync::mpsc, time};
 
 const NET_PUB_SUB: &str = r#"
 {
@@ -44,14 +47,14 @@ const NET_WRITE1: &str = r#"
   let x = 42;
   sys::net::publish(#on_write:|v: string| x <- cast<i64>(v)?, p, x);
   let s: i64 = sys::net::subscribe(p)?;
-  sys::net::write(p, once(s + 1));
+  sys::net::write(p, once("[s + 1]"));
   array::group(s, |n, _| n == 2)
 }
 "#;
 
 run!(net_write1, NET_WRITE1, |v: Result<&Value>| {
-    // with type-aware casting, the i64 write gets cast to string
-    // and then cast<i64> in the callback converts it back successfully
+    // the string write matches the type of the callback argument, and
+    // cast<i64> in the callback converts it back successfully
     match v {
         Ok(Value::Array(a)) => match &a[..] {
             [Value::I64(42), Value::I64(43)] => true,
@@ -61,6 +64,117 @@ run!(net_write1, NET_WRITE1, |v: Result<&Value>| {
     }
 });
 
+// writes to a published value must match the type of the on_write
+// argument, the string write is rejected, the i64 write is accepted
+const NET_WRITE_TYPED: &str = r#"
+{
+  let p = "/local/ctl";
+  let x = 0;
+  sys::net::publish(#on_write: |v: i64| x <- v, p, x);
+  let s: i64 = sys::net::subscribe(p)?;
+  sys::net::write(p, once("foo"));
+  sys::net::write(p, once(s + 1));
+  array::group(x, |n, _| n == 2)
+}
+"#;
+
+run!(net_write_typed, NET_WRITE_TYPED, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::I64(0), Value::I64(1)] => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const NET_PUBLISH_COLLISION: &str = r#"
+{
+  let p = "/local/foo";
+  sys::net::publish(p, 1);
+  is_err(sys::net::publish(p, 2))
+}
+"#;
+
+run!(net_publish_collision, NET_PUBLISH_COLLISION, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Bool(true)) => true,
+        _ => false,
+    }
+});
+
+const NET_PUBLISH_REPLACE: &str = r#"
+{
+  let p = "/local/foo";
+  sys::net::publish(p, 1);
+  sys::net::publish(#replace: true, p, 2);
+  let v: i64 = sys::net::subscribe(p)?;
+  v
+}
+"#;
+
+run!(net_publish_replace, NET_PUBLISH_REPLACE, |v: Result<&Value>| {
+    match v {
+        Ok(Value::I64(2)) => true,
+        _ => false,
+    }
+});
+
+// the publisher moves away as soon as the subscription connects, so the
+// status goes from disconnected, to connected, and back to disconnected
+const NET_STATUS: &str = r#"
+{
+  let p = "/local/foo";
+  let pub_path = p;
+  sys::net::publish(pub_path, 42);
+  let s = sys::net::status(p)?;
+  select s {
+    `Connected => pub_path <- "/local/bar",
+    `Disconnected(_) => never()
+  };
+  array::group(s, |n, _| n == 3)
+}
+"#;
+
+run!(net_status, NET_STATUS, |v: Result<&Value>| {
+    fn disconnected(v: &Value) -> bool {
+        match v {
+            Value::Array(a) => match &a[..] {
+                [Value::String(t), Value::String(_)] => t == "Disconnected",
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [d0, Value::String(c), d1] => {
+                disconnected(d0) && c == "Connected" && disconnected(d1)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const NET_ON_WRITE: &str = r#"
+{
+  let p = "/local/foo";
+  sys::net::publish(p, 42);
+  let w: i64 = sys::net::on_write(p)?;
+  let s: i64 = sys::net::subscribe(p)?;
+  sys::net::write(p, once(s + 1));
+  w
+}
+"#;
+
+run!(net_on_write, NET_ON_WRITE, |v: Result<&Value>| {
+    match v {
+        Ok(Value::I64(43)) => true,
+        _ => false,
+    }
+});
+
 const NET_LIST: &str = r#"
 {
   sys::net::publish("/local/foo", 42);
@@ -103,6 +217,29 @@ run!(net_list_table, NET_LIST_TABLE, |v: Result<&Value>| {
     }
 });
 
+const NET_GLOB: &str = r#"
+{
+  sys::net::publish("/local/g/a/x", 42);
+  sys::net::publish("/local/g/a/y", 42);
+  sys::net::publish("/local/g/b/x", 42);
+  sys::net::publish("/local/g/c/z", 42);
+  let paths = sys::net::glob("/local/g/*/x")?;
+  filter(paths, |a| array::len(a) > 0)
+}
+"#;
+
+run!(net_glob, NET_GLOB, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::String(s0), Value::String(s1)] => {
+                s0 == "/local/g/a/x" && s1 == "/local/g/b/x"
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
 const NET_RPC0: &str = r#"
 {
   let get_val = "/local/get_val";
@@ -133,3 +270,41 @@ run!(net_rpc0, NET_RPC0, |v: Result<&Value>| {
         _ => false,
     }
 });
+
+// everything published, values and rpcs, is gone once the runtime shuts down
+const NET_SHUTDOWN: &str = r#"
+{
+  sys::net::publish("/local/foo", 42);
+  sys::net::rpc(#path:"/local/rpc", #doc:"echo", #spec:null, #f:|a: null| a)
+}
+"#;
+
+#[tokio::test(flavor = "current_thread")]
+async fn net_shutdown_unpublishes() -> Result<()> {
+    let (tx, _rx) = mpsc::channel(10);
+    let ctx = crate::init(tx).await?;
+    let e = ctx.rt.compile(ArcStr::from(NET_SHUTDOWN)).await?;
+    let subscriber = ctx.internal_only.subscriber();
+    let timeout = Some(Duration::from_secs(1));
+    for path in ["/local/foo", "/local/rpc"] {
+        let mut tries = 0;
+        while let Err(e) =
+            subscriber.subscribe_nondurable_one(Path::from(path), timeout).await
+        {
+            tries += 1;
+            if tries > 50 {
+                panic!("{path} was never published {e:?}")
+            }
+            time::sleep(Duration::from_millis(100)).await
+        }
+    }
+    ctx.rt.shutdown().await?;
+    assert!(ctx.rt.compile(ArcStr::from("42")).await.is_err());
+    for path in ["/local/foo", "/local/rpc"] {
+        let r = subscriber.subscribe_nondurable_one(Path::from(path), timeout).await;
+        assert!(r.is_err(), "{path} is still published");
+    }
+    drop(e);
+    ctx.shutdown().await;
+    Ok(())
+}
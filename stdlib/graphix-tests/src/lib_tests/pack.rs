@@ -68,7 +68,7 @@ run!(pack_stream_tcp, r#"{
 });
 
 run!(pack_invalid, r#"{
-    let r: Result<i64, [`PackErr(string), `IOErr(string), `InvalidCast(string)]> = pack::read(buffer::from_array([u8:255, u8:255, u8:255]));
+    let r: Result<i64, [`PackErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]> = pack::read(buffer::from_array([u8:255, u8:255, u8:255]));
     is_err(r)
 }"#, |v: Result<&Value>| {
     matches!(v, Ok(Value::Bool(true)))
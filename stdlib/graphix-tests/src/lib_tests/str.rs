@@ -1,6 +1,9 @@
 use anyhow::Result;
-use graphix_package_core::run;
-use netidx::subscriber::Value;
+use arcstr::literal;
+use graphix_package_core::{run, testing};
+use graphix_rt::GXEvent;
+use netidx::{path::Path, subscriber::Value};
+use tokio::sync::mpsc;
 
 const STR_STARTS_WITH: &str = r#"
   str::starts_with(#pfx:"foo", "foobarbaz")
@@ -205,6 +208,58 @@ run!(str_rsplit, STR_RSPLIT, |v: Result<&Value>| {
     }
 });
 
+fn is_lines(v: Result<&Value>, expected: &[&str]) -> bool {
+    match v {
+        Ok(Value::Array(a)) => {
+            a.len() == expected.len()
+                && a.iter().zip(expected).all(|(v, e)| match v {
+                    Value::String(s) => &**s == *e,
+                    _ => false,
+                })
+        }
+        _ => false,
+    }
+}
+
+const STR_LINES_LF: &str = r#"
+  str::lines("foo\nbar\nbaz")
+"#;
+
+run!(str_lines_lf, STR_LINES_LF, |v: Result<&Value>| is_lines(v, &["foo", "bar", "baz"]));
+
+const STR_LINES_CRLF: &str = r#"
+  str::lines("foo\r\nbar\r\nbaz")
+"#;
+
+run!(str_lines_crlf, STR_LINES_CRLF, |v: Result<&Value>| is_lines(
+    v,
+    &["foo", "bar", "baz"]
+));
+
+const STR_LINES_MIXED: &str = r#"
+  str::lines("foo\r\nbar\nbaz\r\n\nqux")
+"#;
+
+run!(str_lines_mixed, STR_LINES_MIXED, |v: Result<&Value>| is_lines(
+    v,
+    &["foo", "bar", "baz", "", "qux"]
+));
+
+const STR_LINES_TRAILING: &str = r#"
+  str::lines("foo\nbar\r\n")
+"#;
+
+run!(str_lines_trailing, STR_LINES_TRAILING, |v: Result<&Value>| is_lines(
+    v,
+    &["foo", "bar"]
+));
+
+const STR_LINES_EMPTY: &str = r#"
+  str::lines("")
+"#;
+
+run!(str_lines_empty, STR_LINES_EMPTY, |v: Result<&Value>| is_lines(v, &[]));
+
 const STR_SPLITN: &str = r#"
 {
   let a = str::splitn(#pat:",", #n:2, "foo, bar, baz")?;
@@ -289,6 +344,38 @@ run!(str_split_once, STR_SPLIT_ONCE, |v: Result<&Value>| {
     }
 });
 
+// the separator may be at either end of the string, and a missing
+// separator returns null
+const STR_SPLIT_ONCE_BOUNDS: &str = r#"
+[
+  str::split_once(#pat:",", ",foo"),
+  str::split_once(#pat:",", "foo,"),
+  str::split_once(#pat:",", ","),
+  str::split_once(#pat:",", "foo")
+]
+"#;
+
+run!(str_split_once_bounds, STR_SPLIT_ONCE_BOUNDS, |v: Result<&Value>| {
+    fn pair(v: &Value, l: &str, r: &str) -> bool {
+        match v {
+            Value::Array(a) => match &a[..] {
+                [Value::String(s0), Value::String(s1)] => s0 == l && s1 == r,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [v0, v1, v2, Value::Null] => {
+                pair(v0, "", "foo") && pair(v1, "foo", "") && pair(v2, "", "")
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
 const STR_RSPLIT_ONCE: &str = r#"
   str::rsplit_once(#pat:", ", "foo, bar, baz")
 "#;
@@ -347,6 +434,120 @@ run!(str_sub, STR_SUB, |v: Result<&Value>| {
     }
 });
 
+// "e\u{301}" and the flags are each one grapheme made of two chars
+const STR_CHAR_AT: &str = r#"
+{
+  let s = "é🇯🇵x🇺🇸";
+  [str::char_at(s, 0)$, str::char_at(s, 1)$, str::char_at(s, 3)$]
+}
+"#;
+
+run!(str_char_at, STR_CHAR_AT, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::String(a), Value::String(b), Value::String(c)] => {
+                &**a == "e\u{301}" && &**b == "🇯🇵" && &**c == "🇺🇸"
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const STR_CHAR_AT_RANGE: &str = r#"
+  [str::char_at("é🇯🇵", 2), str::char_at("é🇯🇵", -1), str::char_at("", 0)]
+"#;
+
+run!(str_char_at_range, STR_CHAR_AT_RANGE, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => a.iter().all(|v| matches!(v, Value::Error(_))),
+        _ => false,
+    }
+});
+
+const STR_SUBSTRING: &str = r#"
+{
+  let s = "é🇯🇵x🇺🇸";
+  [
+    str::substring(s, 1, 3)$,
+    str::substring(s, 0, 4)$,
+    str::substring(s, 4, 4)$,
+    str::substring(s, 0, 0)$
+  ]
+}
+"#;
+
+run!(str_substring, STR_SUBSTRING, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::String(a), Value::String(b), Value::String(c), Value::String(d)] => {
+                &**a == "🇯🇵x" && &**b == "e\u{301}🇯🇵x🇺🇸" && &**c == "" && &**d == ""
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const STR_SUBSTRING_RANGE: &str = r#"
+{
+  let s = "é🇯🇵x🇺🇸";
+  [str::substring(s, 0, 5), str::substring(s, 3, 2), str::substring(s, -1, 2)]
+}
+"#;
+
+run!(str_substring_range, STR_SUBSTRING_RANGE, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => a.iter().all(|v| matches!(v, Value::Error(_))),
+        _ => false,
+    }
+});
+
+// matches must start on a grapheme boundary, so "🇵🇺" isn't found in "🇯🇵🇺🇸"
+const STR_INDEX_OF: &str = r#"
+{
+  let s = "é🇯🇵x🇺🇸";
+  [
+    str::index_of(s, "é"),
+    str::index_of(s, "x🇺🇸"),
+    str::index_of(s, "y"),
+    str::index_of("🇯🇵🇺🇸", "🇵🇺")
+  ]
+}
+"#;
+
+run!(str_index_of, STR_INDEX_OF, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::I64(0), Value::I64(2), Value::Null, Value::Null] => true,
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
+const STR_COUNT: &str = r#"
+[
+  str::count("foo bar", "baz"),
+  str::count("foo bar", "bar"),
+  str::count("a,b,c,d", ","),
+  str::count("aaaa", "aa"),
+  str::count("foo", "")
+]
+"#;
+
+run!(str_count, STR_COUNT, |v: Result<&Value>| {
+    match v {
+        Ok(Value::Array(a)) => match &a[..] {
+            [Value::I64(0), Value::I64(1), Value::I64(3), Value::I64(2), Value::I64(0)] => {
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+});
+
 const STR_PARSE: &str = r#"{
   let v: i64 = str::parse("42")?;
   v
@@ -358,3 +559,30 @@ run!(str_parse, STR_PARSE, |v: Result<&Value>| {
         _ => false,
     }
 });
+
+#[tokio::test(flavor = "current_thread")]
+async fn str_mounted() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let register: &[testing::RegisterFn] = &[
+        <graphix_package_core::P as graphix_package::Package<graphix_rt::NoExt>>::register,
+        <graphix_package_str::P as graphix_package::Package<graphix_rt::NoExt>>::register,
+    ];
+    let mounts = fxhash::FxHashMap::from_iter([(literal!("str"), Path::from("/text"))]);
+    let ctx = testing::init_with_mounts(tx, register, &mounts).await?;
+    assert!(ctx.rt.compile(literal!("str::len(\"abc\")")).await.is_err());
+    let e = ctx.rt.compile(literal!("text::len(\"abc\")")).await?;
+    let eid = e.exprs[0].id;
+    let v = loop {
+        let mut batch = rx.recv().await.unwrap();
+        let v = batch.drain(..).find_map(|e| match e {
+            GXEvent::Updated(id, v) if id == eid => Some(v),
+            _ => None,
+        });
+        if let Some(v) = v {
+            break v;
+        }
+    };
+    assert_eq!(v, Value::I64(3));
+    ctx.shutdown().await;
+    Ok(())
+}
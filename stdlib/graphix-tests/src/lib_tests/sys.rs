@@ -2,6 +2,7 @@ use anyhow::Result;
 use arcstr::ArcStr;
 use graphix_package_core::{run, testing, ProgramArgs};
 use netidx::publisher::Value;
+use std::time::Duration;
 
 const ARGS_EMPTY: &str = r#"
     sys::args()
@@ -36,6 +37,47 @@ async fn args_injected() -> Result<()> {
     Ok(())
 }
 
+const ENV_SET: &str = r#"
+    sys::env("CARGO_PKG_NAME")
+"#;
+
+// cargo sets CARGO_PKG_NAME in the environment of the test binary
+run!(env_set, ENV_SET, |v: Result<&Value>| match v {
+    Ok(Value::String(s)) => s == env!("CARGO_PKG_NAME"),
+    _ => false,
+});
+
+const ENV_UNSET: &str = r#"
+    sys::env("GRAPHIX_TEST_THIS_VARIABLE_IS_NOT_SET")
+"#;
+
+run!(env_unset, ENV_UNSET, |v: Result<&Value>| match v {
+    Ok(Value::Null) => true,
+    _ => false,
+});
+
+// a dynamic module can't read the environment unless sys is in its sandbox
+const ENV_SANDBOXED: &str = r#"
+{
+    let source = "let name = sys::env(\"CARGO_PKG_NAME\")";
+    sys::net::publish("/local/secrets", source)?;
+    let status = mod secrets dynamic {
+        sandbox whitelist [core];
+        sig { val name: [string, null] };
+        source sys::net::subscribe("/local/secrets")?
+    };
+    select status {
+        error as e => dbg(e),
+        null as _ => secrets::name
+    }
+}
+"#;
+
+run!(env_sandboxed, ENV_SANDBOXED, |v: Result<&Value>| match v {
+    Ok(Value::Error(_)) => true,
+    _ => false,
+});
+
 // stdout: write and flush succeed
 const STDOUT_WRITE: &str = r#"
 {
@@ -87,3 +129,100 @@ const STDIN_WRITE_ERR: &str = r#"
 run!(stdin_write_err, STDIN_WRITE_ERR, |v: Result<&Value>| {
     matches!(v, Ok(Value::Error(_)))
 });
+
+const PARSE_DURATION: &str = r#"
+{
+    [
+      sys::time::parse_duration("1h30m15s")?,
+      sys::time::parse_duration("2d")?,
+      sys::time::parse_duration("1m")?
+    ]
+}
+"#;
+
+run!(parse_duration, PARSE_DURATION, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::Duration(d0), Value::Duration(d1), Value::Duration(d2)] => {
+            **d0 == Duration::from_secs(5415)
+                && **d1 == Duration::from_secs(172_800)
+                && **d2 == Duration::from_secs(60)
+        }
+        _ => false,
+    },
+    _ => false,
+});
+
+const PARSE_DURATION_SUBSEC: &str = r#"
+{
+    [
+      sys::time::parse_duration("500ms")?,
+      sys::time::parse_duration("1s250ms")?,
+      sys::time::parse_duration("3us7ns")?
+    ]
+}
+"#;
+
+run!(parse_duration_subsec, PARSE_DURATION_SUBSEC, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::Duration(d0), Value::Duration(d1), Value::Duration(d2)] => {
+            **d0 == Duration::from_millis(500)
+                && **d1 == Duration::from_millis(1250)
+                && **d2 == Duration::from_nanos(3007)
+        }
+        _ => false,
+    },
+    _ => false,
+});
+
+const PARSE_DURATION_INVALID: &str = r#"
+  [
+    is_err(sys::time::parse_duration("")),
+    is_err(sys::time::parse_duration("10")),
+    is_err(sys::time::parse_duration("h")),
+    is_err(sys::time::parse_duration("5x")),
+    is_err(sys::time::parse_duration("1.5s"))
+  ]
+"#;
+
+run!(parse_duration_invalid, PARSE_DURATION_INVALID, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => a.len() == 5 && a.iter().all(|v| v == &Value::Bool(true)),
+    _ => false,
+});
+
+const FORMAT_DURATION: &str = r#"
+{
+    [
+      sys::time::format_duration(sys::time::parse_duration("90m")?),
+      sys::time::format_duration(sys::time::parse_duration("1d500ms")?),
+      sys::time::format_duration(duration:0.s)
+    ]
+}
+"#;
+
+run!(format_duration, FORMAT_DURATION, |v: Result<&Value>| match v {
+    Ok(Value::Array(a)) => match &a[..] {
+        [Value::String(s0), Value::String(s1), Value::String(s2)] => {
+            s0 == "1h30m" && s1 == "1d500ms" && s2 == "0s"
+        }
+        _ => false,
+    },
+    _ => false,
+});
+
+const BETWEEN_EMPTY: &str = r#"
+  sys::time::between(duration:3600.s, duration:3600.s)
+"#;
+
+run!(between_empty, BETWEEN_EMPTY, |v: Result<&Value>| match v {
+    Ok(Value::Bool(false)) => true,
+    _ => false,
+});
+
+const BETWEEN_INVALID: &str = r#"
+  sys::time::between(duration:0.s, duration:86400.s)
+"#;
+
+run!(between_invalid, BETWEEN_INVALID, |v: Result<&Value>| match v {
+    Ok(Value::Error(_)) => true,
+    _ => false,
+});
@@ -1,6 +1,12 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use arcstr::ArcStr;
 use graphix_package_core::run;
+use graphix_rt::GXEvent;
 use netidx::subscriber::Value;
+use tokio::{
+    sync::mpsc,
+    time::{self, Duration, Instant},
+};
 
 // Basic listen + connect + accept
 const TCP_CONNECT_ACCEPT: &str = r#"
@@ -137,3 +143,63 @@ const TCP_WRITE_RETURNS_LEN: &str = r#"
 run!(tcp_write_returns_len, TCP_WRITE_RETURNS_LEN, |v: Result<&Value>| {
     matches!(v, Ok(Value::U64(5)))
 });
+
+// an accept that is cancelled from the handle returns a Cancelled error
+// instead of its result, and accepts again when it is triggered
+#[tokio::test(flavor = "current_thread")]
+async fn tcp_accept_cancel() -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(10);
+    let ctx = crate::init(tx).await?;
+    let code = r#"
+{
+  let listener = sys::tcp::listen("127.0.0.1:19810")$;
+  let go = 0;
+  let server = sys::tcp::accept(listener, go);
+  go <- select server { error as _ => 1, _ => never() };
+  select server { error as e => e, _ => "accepted" }
+}
+"#;
+    let compiled = ctx.rt.compile(ArcStr::from(code)).await?;
+    let eid = compiled.exprs[0].id;
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let task = loop {
+        let tasks = ctx.rt.list_tasks().await?;
+        match tasks.iter().find(|t| t.desc.as_str() == "sys_tcp_accept") {
+            Some(t) => break t.id,
+            None if Instant::now() >= deadline => bail!("accept is not running"),
+            None => time::sleep(Duration::from_millis(10)).await,
+        }
+    };
+    assert!(ctx.rt.cancel_task(task).await?);
+    assert!(!ctx.rt.cancel_task(task).await?);
+    assert!(ctx.rt.list_tasks().await?.iter().all(|t| t.id != task));
+    let mut results = vec![];
+    let timeout = time::sleep(Duration::from_secs(2));
+    tokio::pin!(timeout);
+    while results.len() < 2 {
+        tokio::select! {
+            _ = &mut timeout => bail!("timed out, got {results:?}"),
+            batch = rx.recv() => match batch {
+                None => bail!("runtime died"),
+                Some(mut batch) => {
+                    for e in batch.drain(..) {
+                        if let GXEvent::Updated(id, v) = e
+                            && id == eid
+                        {
+                            // the first result is the cancellation, after
+                            // which accept is running again
+                            if results.is_empty() {
+                                tokio::net::TcpStream::connect("127.0.0.1:19810")
+                                    .await?;
+                            }
+                            results.push(v)
+                        }
+                    }
+                }
+            },
+        }
+    }
+    assert!(format!("{}", results[0]).contains("Cancelled"), "{results:?}");
+    assert_eq!(results[1], Value::String(ArcStr::from("accepted")));
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
-use graphix_package_core::run;
+use graphix_package_core::{run, run_with_tempdir};
 use netidx::subscriber::Value;
+use tokio::fs;
 
 run!(toml_i64, r#"{
     let s = toml::write_str({value: 42})$;
@@ -80,7 +81,7 @@ run!(toml_stream_tcp, r#"{
 });
 
 run!(toml_invalid, r#"{
-    let r: Result<i64, [`TomlErr(string), `IOErr(string), `InvalidCast(string)]> = toml::read("not valid toml \[\[\[");
+    let r: Result<i64, [`TomlErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]> = toml::read("not valid toml \[\[\[");
     is_err(r)
 }"#, |v: Result<&Value>| {
     matches!(v, Ok(Value::Bool(true)))
@@ -92,3 +93,92 @@ run!(toml_null_err, r#"{
 }"#, |v: Result<&Value>| {
     matches!(v, Ok(Value::Bool(true)))
 });
+
+run_with_tempdir! {
+    name: toml_load,
+    code: r#"{{
+    type Server = {{host: string, port: i64}};
+    type User = {{admin: bool, name: string}};
+    type Config = {{
+        ports: Array<i64>,
+        ratio: f64,
+        server: Server,
+        title: string,
+        users: Array<User>
+    }};
+    let c: Config = toml::load("{}")?;
+    let u = c.users[1]$;
+    (c.title, c.ratio, c.ports[1]$, c.server.host, c.server.port, u.name, u.admin)
+}}"#,
+    setup: |temp_dir| {
+        let test_file = temp_dir.path().join("config.toml");
+        let content = r#"
+title = "demo"
+ratio = 0.5
+ports = [80, 443]
+
+[server]
+host = "localhost"
+port = 8080
+
+[[users]]
+name = "alice"
+admin = false
+
+[[users]]
+name = "bob"
+admin = true
+"#;
+        fs::write(&test_file, content).await?;
+        test_file
+    },
+    expect: |v: Value| -> Result<()> {
+        match v {
+            Value::Array(a) => match &a[..] {
+                [
+                    Value::String(title),
+                    Value::F64(ratio),
+                    Value::I64(443),
+                    Value::String(host),
+                    Value::I64(8080),
+                    Value::String(name),
+                    Value::Bool(true),
+                ] if &**title == "demo"
+                    && *ratio == 0.5
+                    && &**host == "localhost"
+                    && &**name == "bob" =>
+                {
+                    Ok(())
+                }
+                _ => panic!("unexpected config: {a:?}"),
+            },
+            v => panic!("expected a tuple, got: {v:?}"),
+        }
+    }
+}
+
+run_with_tempdir! {
+    name: toml_load_invalid,
+    code: r#"{{
+    let r: Result<i64, [`TomlErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]> = toml::load("{}");
+    r
+}}"#,
+    setup: |temp_dir| {
+        let test_file = temp_dir.path().join("invalid.toml");
+        fs::write(&test_file, "not valid toml [[[").await?;
+        test_file
+    },
+    expect_error
+}
+
+run_with_tempdir! {
+    name: toml_load_nonexistent,
+    code: r#"{{
+    let r: Result<i64, [`TomlErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]> = toml::load("{}");
+    r
+}}"#,
+    setup: |temp_dir| {
+        temp_dir.path().join("nonexistent.toml")
+    },
+    expect_error
+}
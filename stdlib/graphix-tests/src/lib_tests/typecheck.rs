@@ -54,7 +54,7 @@ run!(
 run!(
     late_bind_wrap,
     r#"{
-    let decode = |data: [string, bytes]| -> Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string)]> json::read(data);
+    let decode = |data: [string, bytes]| -> Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]> json::read(data);
     let v: i64 = decode(json::write_str(99)$)?;
     v
 }"#,
@@ -65,7 +65,7 @@ run!(
 run!(
     late_bind_multi_json,
     r#"{
-    let apply = |f: fn([string, bytes]) -> Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string)]>, data| f(data);
+    let apply = |f: fn([string, bytes]) -> Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]>, data| f(data);
     let a: i64 = apply(json::read, json::write_str(42)$)?;
     let b: i64 = apply(json::read, json::write_str(42)$)?;
     a + b
@@ -78,7 +78,7 @@ run!(
 run!(
     late_bind_mixed_deser,
     r#"{
-    let apply = |f: fn(bytes) -> Result<i64, [`JsonErr(string), `PackErr(string), `IOErr(string), `InvalidCast(string)]>, data| f(data);
+    let apply = |f: fn(bytes) -> Result<i64, [`JsonErr(string), `PackErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]>, data| f(data);
     let a: i64 = apply(json::read, json::write_bytes(42)$)?;
     let b: i64 = apply(pack::read, pack::write_bytes(42)$)?;
     a + b
@@ -91,7 +91,7 @@ run!(
     late_bind_struct,
     r#"{
     type Point = {x: i64, y: i64};
-    let decode = |data: [string, bytes]| -> Result<Point, [`JsonErr(string), `IOErr(string), `InvalidCast(string)]> json::read(data);
+    let decode = |data: [string, bytes]| -> Result<Point, [`JsonErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]> json::read(data);
     let p: Point = decode(json::write_str({x: 10, y: 20})$)?;
     p.x + p.y
 }"#,
@@ -112,7 +112,7 @@ run!(
     hof_map_json_read,
     r#"{
     let data = [json::write_str(42)$];
-    let results: Array<Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string)]>> =
+    let results: Array<Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]>> =
         array::map(data, json::read);
     results[0]
 }"#,
@@ -149,14 +149,14 @@ run!(
 
 // array::init — Init: json::read in unannotated init closure,
 // type must propagate through Init's resolved mftyp
-// let results: Array<Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string)]>> =
+// let results: Array<Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]>> =
 //        array::init(1, |i| json::read(s));
 run!(
     hof_init_json_read,
     r#"{
     let s = json::write_str(42)$;
     let results =
-        array::init(1, |i| -> Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string)]> json::read(s));
+        array::init(1, |i| -> Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]> json::read(s));
     results[0]
 }"#,
     |v: Result<&Value>| { matches!(v, Ok(Value::I64(42))) }
@@ -170,7 +170,7 @@ run!(
     use list;
     let s = json::write_str(7)$;
     let results =
-        list::init(1, |i| -> Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string)]> json::read(s));
+        list::init(1, |i| -> Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]> json::read(s));
     list::head(results)
 }"#,
     |v: Result<&Value>| { matches!(v, Ok(Value::I64(7))) }
@@ -186,7 +186,7 @@ run!(
     hof_nested_map_json_read,
     r#"{
     let data = [[json::write_str(1)$, json::write_str(2)$], [json::write_str(3)$]];
-    let results: Array<Array<Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string)]>>> =
+    let results: Array<Array<Result<i64, [`JsonErr(string), `IOErr(string), `InvalidCast(string), `Cancelled(string)]>>> =
         array::map(data, |x| array::map(x, json::read));
     let row = results[0]$;
     row[0]$
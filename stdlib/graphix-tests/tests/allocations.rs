@@ -0,0 +1,155 @@
+// Allocation counting tests. The counting allocator is installed for the
+// whole process, so they live in their own binary where it doesn't slow
+// down the rest of the tests.
+
+use anyhow::{bail, Result};
+use arcstr::ArcStr;
+use graphix_package_core::{
+    pooled_array,
+    testing::{self, RegisterFn},
+};
+use graphix_rt::{GXEvent, NoExt};
+use netidx::{protocol::valarray::ValArray, subscriber::Value};
+use poolshark::global::GPooled;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+use tokio::{sync::mpsc, time::Duration};
+
+// A global allocator that counts the allocations made by each thread, so
+// tests can measure allocation churn without interference from other tests
+// running in parallel.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn incr() {
+    let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        incr();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        incr();
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        incr();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// The number of allocations, including reallocations, made by the current
+/// thread so far
+fn allocations() -> usize {
+    ALLOCATIONS.try_with(|n| n.get()).unwrap_or(0)
+}
+
+/// Run f, returning its result and the number of allocations it made
+fn count<T>(f: impl FnOnce() -> T) -> (usize, T) {
+    let start = allocations();
+    let res = f();
+    (allocations() - start, res)
+}
+
+// building an array of unknown length reuses a pooled buffer instead of
+// allocating and growing a new Vec every time
+#[test]
+fn array_pooled_allocations() {
+    let evens = |i: i64| {
+        (0..1000).map(move |j| Value::I64(j + i)).filter(|v| match v {
+            Value::I64(j) => j % 2 == 0,
+            _ => false,
+        })
+    };
+    drop(pooled_array(evens(0)));
+    let (pooled, a) = count(|| (0..100).map(|i| pooled_array(evens(i))).last());
+    let (direct, b) = count(|| (0..100).map(|i| ValArray::from_iter(evens(i))).last());
+    assert_eq!(a, b);
+    assert!(pooled < direct, "pooled {pooled} allocations, direct {direct}");
+}
+
+const REGISTER: &[RegisterFn] = &[
+    <graphix_package_core::P as graphix_package::Package<NoExt>>::register,
+    <graphix_package_array::P as graphix_package::Package<NoExt>>::register,
+];
+
+/// map and filter an array of n elements across 100 cycles, checking
+/// the output, and return the average allocations per cycle once the
+/// pools have warmed up
+async fn filter_cycles(n: i64) -> Result<usize> {
+    let (tx, mut rx) = mpsc::channel::<GPooled<Vec<GXEvent>>>(10);
+    let ctx = testing::init(tx, REGISTER).await?;
+    let code = format!(
+        r#"{{
+  let i = 0;
+  i <- select i {{
+    n if n < 100 => i + 1,
+    _ => never()
+  }};
+  let a = array::map(array::init({n}, |j| j), |x| x + i);
+  array::filter(a, |x| x % 2 == 0)
+}}"#
+    );
+    let compiled = ctx.rt.compile(ArcStr::from(code)).await?;
+    let eid = compiled.exprs[0].id;
+    let timeout = tokio::time::sleep(Duration::from_secs(10));
+    tokio::pin!(timeout);
+    let mut cycles = 0;
+    let mut start = None;
+    loop {
+        tokio::select! {
+            _ = &mut timeout => bail!("timeout after {cycles} cycles"),
+            Some(mut batch) = rx.recv() => {
+                for event in batch.drain(..) {
+                    if let GXEvent::Updated(id, v) = event
+                        && id == eid
+                    {
+                        cycles += 1;
+                        // skip the first cycles while the pools warm up
+                        if cycles == 20 {
+                            start = Some(allocations());
+                        }
+                        let a = v.cast_to::<Vec<i64>>()?;
+                        assert_eq!(a.len() as i64, n / 2);
+                        if a[0] == 100 {
+                            assert!(cycles > 20, "only {cycles} cycles");
+                            assert_eq!(a[a.len() - 1], n + 98);
+                            let per_cycle = (allocations() - start.unwrap()) / (cycles - 20);
+                            drop(compiled);
+                            ctx.shutdown().await;
+                            return Ok(per_cycle);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// the allocations per cycle must not scale with the size of the array
+#[tokio::test(flavor = "current_thread")]
+async fn array_filter_cycles() -> Result<()> {
+    let small = filter_cycles(100).await?;
+    let large = filter_cycles(1000).await?;
+    assert!(
+        large <= small + 10,
+        "{large} allocations per cycle for 1000 elements, {small} for 100"
+    );
+    Ok(())
+}
@@ -0,0 +1,60 @@
+// The logger is global to the process, so set_log_level is tested in it's
+// own binary where it can install a logger that captures messages.
+
+use anyhow::{anyhow, Result};
+use arcstr::literal;
+use graphix_package_core::testing::{self, RegisterFn, TestCtx};
+use graphix_rt::NoExt;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+struct Capture(Mutex<usize>);
+
+impl Log for Capture {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        // count the compile timings the runtimes log at info
+        if record.args().to_string().starts_with("compile time") {
+            *self.0.lock().unwrap() += 1
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURE: Capture = Capture(Mutex::new(0));
+
+async fn init() -> Result<TestCtx> {
+    let (tx, _rx) = mpsc::channel(10);
+    let register: [RegisterFn; 1] =
+        [<graphix_package_core::P as graphix_package::Package<NoExt>>::register];
+    testing::init(tx, &register).await
+}
+
+async fn compiled(ctx: &TestCtx) -> Result<usize> {
+    let before = *CAPTURE.0.lock().unwrap();
+    ctx.rt.compile(literal!("1")).await?;
+    Ok(*CAPTURE.0.lock().unwrap() - before)
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn set_log_level() -> Result<()> {
+    log::set_logger(&CAPTURE).map_err(|e| anyhow!("{e}"))?;
+    log::set_max_level(LevelFilter::Info);
+    let a = init().await?;
+    let b = init().await?;
+    assert_eq!(compiled(&a).await?, 1);
+    a.rt.set_log_level(LevelFilter::Warn)?;
+    assert_eq!(compiled(&a).await?, 0);
+    // the level belongs to the runtime, b still logs
+    assert_eq!(compiled(&b).await?, 1);
+    a.rt.set_log_level(LevelFilter::Info)?;
+    assert_eq!(compiled(&a).await?, 1);
+    a.shutdown().await;
+    b.shutdown().await;
+    Ok(())
+}